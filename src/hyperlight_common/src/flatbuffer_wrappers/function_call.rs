@@ -22,13 +22,10 @@ use flatbuffers::{size_prefixed_root, WIPOffset};
 #[cfg(feature = "tracing")]
 use tracing::{instrument, Span};
 
-use super::function_types::{ParameterValue, ReturnType};
+use super::function_types::{create_parameter, ParameterValue, ReturnType};
 use crate::flatbuffers::hyperlight::generated::{
-    hlbool, hlboolArgs, hldouble, hldoubleArgs, hlfloat, hlfloatArgs, hlint, hlintArgs, hllong,
-    hllongArgs, hlstring, hlstringArgs, hluint, hluintArgs, hlulong, hlulongArgs, hlvecbytes,
-    hlvecbytesArgs, FunctionCall as FbFunctionCall, FunctionCallArgs as FbFunctionCallArgs,
-    FunctionCallType as FbFunctionCallType, Parameter, ParameterArgs,
-    ParameterValue as FbParameterValue,
+    FunctionCall as FbFunctionCall, FunctionCallArgs as FbFunctionCallArgs,
+    FunctionCallType as FbFunctionCallType, Parameter,
 };
 
 /// The type of function call.
@@ -146,129 +143,11 @@ impl TryFrom<FunctionCall> for Vec<u8> {
 
         let expected_return_type = value.expected_return_type.into();
 
-        let parameters = match &value.parameters {
-            Some(p) => {
-                let num_items = p.len();
-                let mut parameters: Vec<WIPOffset<Parameter>> = Vec::with_capacity(num_items);
-
-                for param in p {
-                    match param {
-                        ParameterValue::Int(i) => {
-                            let hlint = hlint::create(&mut builder, &hlintArgs { value: *i });
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hlint,
-                                    value: Some(hlint.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::UInt(ui) => {
-                            let hluint = hluint::create(&mut builder, &hluintArgs { value: *ui });
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hluint,
-                                    value: Some(hluint.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::Long(l) => {
-                            let hllong = hllong::create(&mut builder, &hllongArgs { value: *l });
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hllong,
-                                    value: Some(hllong.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::ULong(ul) => {
-                            let hlulong =
-                                hlulong::create(&mut builder, &hlulongArgs { value: *ul });
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hlulong,
-                                    value: Some(hlulong.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::Float(f) => {
-                            let hlfloat = hlfloat::create(&mut builder, &hlfloatArgs { value: *f });
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hlfloat,
-                                    value: Some(hlfloat.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::Double(d) => {
-                            let hldouble =
-                                hldouble::create(&mut builder, &hldoubleArgs { value: *d });
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hldouble,
-                                    value: Some(hldouble.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::Bool(b) => {
-                            let hlbool: WIPOffset<hlbool<'_>> =
-                                hlbool::create(&mut builder, &hlboolArgs { value: *b });
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hlbool,
-                                    value: Some(hlbool.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::String(s) => {
-                            let hlstring = {
-                                let val = builder.create_string(s.as_str());
-                                hlstring::create(&mut builder, &hlstringArgs { value: Some(val) })
-                            };
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hlstring,
-                                    value: Some(hlstring.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                        ParameterValue::VecBytes(v) => {
-                            let vec_bytes = builder.create_vector(v);
-
-                            let hlvecbytes = hlvecbytes::create(
-                                &mut builder,
-                                &hlvecbytesArgs {
-                                    value: Some(vec_bytes),
-                                },
-                            );
-                            let parameter = Parameter::create(
-                                &mut builder,
-                                &ParameterArgs {
-                                    value_type: FbParameterValue::hlvecbytes,
-                                    value: Some(hlvecbytes.as_union_value()),
-                                },
-                            );
-                            parameters.push(parameter);
-                        }
-                    }
-                }
-                parameters
-            }
+        let parameters: Vec<WIPOffset<Parameter>> = match &value.parameters {
+            Some(p) => p
+                .iter()
+                .map(|param| create_parameter(&mut builder, param))
+                .collect(),
             None => Vec::new(),
         };
 
@@ -296,6 +175,8 @@ impl TryFrom<FunctionCall> for Vec<u8> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::boxed::Box;
+    use alloc::collections::BTreeMap;
     use alloc::vec;
 
     use super::*;
@@ -349,4 +230,63 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn read_compound_parameters_from_flatbuffer() -> Result<()> {
+        let map = BTreeMap::from([
+            ("a".to_string(), ParameterValue::Int(1)),
+            (
+                "b".to_string(),
+                ParameterValue::List(vec![ParameterValue::String("x".to_string())]),
+            ),
+        ]);
+        let record = vec![
+            ("name".to_string(), ParameterValue::String("n".to_string())),
+            (
+                "inner".to_string(),
+                ParameterValue::Record(vec![("id".to_string(), ParameterValue::ULong(7))]),
+            ),
+            ("empty".to_string(), ParameterValue::List(vec![])),
+        ];
+        let expected_parameters = vec![
+            ParameterValue::List(vec![
+                ParameterValue::Int(1),
+                ParameterValue::Int(2),
+                ParameterValue::Int(3),
+            ]),
+            ParameterValue::Optional(None),
+            ParameterValue::Optional(Some(Box::new(ParameterValue::Double(0.5)))),
+            ParameterValue::Map(map),
+            ParameterValue::Record(record),
+        ];
+
+        let test_data: Vec<u8> = FunctionCall::new(
+            "Compound".to_string(),
+            Some(expected_parameters.clone()),
+            FunctionCallType::Guest,
+            ReturnType::Record,
+        )
+        .try_into()
+        .unwrap();
+
+        let function_call = FunctionCall::try_from(test_data.as_slice())?;
+        assert_eq!(function_call.expected_return_type, ReturnType::Record);
+        let parameters = function_call.parameters.unwrap();
+        assert_eq!(parameters, expected_parameters);
+
+        let list: Vec<i32> = parameters[0].clone().try_into()?;
+        assert_eq!(list, vec![1, 2, 3]);
+        assert_eq!(parameters[1].as_optional(), Some(None));
+        assert_eq!(
+            parameters[3].as_map().unwrap().get("a"),
+            Some(&ParameterValue::Int(1))
+        );
+        assert_eq!(
+            parameters[4].field("inner").unwrap().field("id"),
+            Some(&ParameterValue::ULong(7))
+        );
+        assert_eq!(parameters[4].field("missing"), None);
+
+        Ok(())
+    }
 }
@@ -14,20 +14,23 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
 use anyhow::{anyhow, bail, Error, Result};
-use flatbuffers::size_prefixed_root;
+use flatbuffers::{size_prefixed_root, FlatBufferBuilder, ForwardsUOffset, Vector, WIPOffset};
 #[cfg(feature = "tracing")]
 use tracing::{instrument, Span};
 
 use crate::flatbuffers::hyperlight::generated::{
-    hlbool, hlboolArgs, hldouble, hldoubleArgs, hlfloat, hlfloatArgs, hlint, hlintArgs, hllong,
-    hllongArgs, hlsizeprefixedbuffer, hlsizeprefixedbufferArgs, hlstring, hlstringArgs, hluint,
-    hluintArgs, hlulong, hlulongArgs, hlvoid, hlvoidArgs,
+    hlbool, hlboolArgs, hldouble, hldoubleArgs, hlentry, hlentryArgs, hlfloat, hlfloatArgs, hlint,
+    hlintArgs, hllist, hllistArgs, hllong, hllongArgs, hlmap, hlmapArgs, hloption, hloptionArgs,
+    hlrecord, hlrecordArgs, hlsizeprefixedbuffer, hlsizeprefixedbufferArgs, hlstring, hlstringArgs,
+    hluint, hluintArgs, hlulong, hlulongArgs, hlvecbytes, hlvecbytesArgs, hlvoid, hlvoidArgs,
     FunctionCallResult as FbFunctionCallResult, FunctionCallResultArgs as FbFunctionCallResultArgs,
-    Parameter, ParameterType as FbParameterType, ParameterValue as FbParameterValue,
+    Parameter, ParameterArgs, ParameterType as FbParameterType, ParameterValue as FbParameterValue,
     ReturnType as FbReturnType, ReturnValue as FbReturnValue,
 };
 
@@ -52,6 +55,14 @@ pub enum ParameterValue {
     Bool(bool),
     /// Vec<u8>
     VecBytes(Vec<u8>),
+    /// A list of values, which are not required to be of the same type
+    List(Vec<ParameterValue>),
+    /// An optional value
+    Optional(Option<Box<ParameterValue>>),
+    /// A map of values keyed by string
+    Map(BTreeMap<String, ParameterValue>),
+    /// A record of named fields, in declaration order
+    Record(Vec<(String, ParameterValue)>),
 }
 
 /// Supported parameter types for function calling.
@@ -76,6 +87,14 @@ pub enum ParameterType {
     Bool,
     /// Vec<u8>
     VecBytes,
+    /// Vec<ParameterValue>
+    List,
+    /// Option<ParameterValue>
+    Optional,
+    /// BTreeMap<String, ParameterValue>
+    Map,
+    /// Vec<(String, ParameterValue)>
+    Record,
 }
 
 /// Supported return types with values from function calling.
@@ -101,6 +120,14 @@ pub enum ReturnValue {
     Void,
     /// Vec<u8>
     VecBytes(Vec<u8>),
+    /// A list of values, which are not required to be of the same type
+    List(Vec<ParameterValue>),
+    /// An optional value
+    Optional(Option<Box<ParameterValue>>),
+    /// A map of values keyed by string
+    Map(BTreeMap<String, ParameterValue>),
+    /// A record of named fields, in declaration order
+    Record(Vec<(String, ParameterValue)>),
 }
 
 /// Supported return types from function calling.
@@ -128,6 +155,57 @@ pub enum ReturnType {
     Void,
     /// Vec<u8>
     VecBytes,
+    /// Vec<ParameterValue>
+    List,
+    /// Option<ParameterValue>
+    Optional,
+    /// BTreeMap<String, ParameterValue>
+    Map,
+    /// Vec<(String, ParameterValue)>
+    Record,
+}
+
+impl ParameterValue {
+    /// Returns the elements of this value if it is a `List`
+    pub fn as_list(&self) -> Option<&[ParameterValue]> {
+        match self {
+            ParameterValue::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner value of this value if it is an `Optional`
+    pub fn as_optional(&self) -> Option<Option<&ParameterValue>> {
+        match self {
+            ParameterValue::Optional(v) => Some(v.as_deref()),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries of this value if it is a `Map`
+    pub fn as_map(&self) -> Option<&BTreeMap<String, ParameterValue>> {
+        match self {
+            ParameterValue::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Returns the fields of this value if it is a `Record`
+    pub fn as_record(&self) -> Option<&[(String, ParameterValue)]> {
+        match self {
+            ParameterValue::Record(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the field called `name` if this value is a
+    /// `Record` that has such a field
+    pub fn field(&self, name: &str) -> Option<&ParameterValue> {
+        self.as_record()?
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, value)| value)
+    }
 }
 
 impl From<&ParameterValue> for ParameterType {
@@ -143,6 +221,10 @@ impl From<&ParameterValue> for ParameterType {
             ParameterValue::String(_) => ParameterType::String,
             ParameterValue::Bool(_) => ParameterType::Bool,
             ParameterValue::VecBytes(_) => ParameterType::VecBytes,
+            ParameterValue::List(_) => ParameterType::List,
+            ParameterValue::Optional(_) => ParameterType::Optional,
+            ParameterValue::Map(_) => ParameterType::Map,
+            ParameterValue::Record(_) => ParameterType::Record,
         }
     }
 }
@@ -181,6 +263,30 @@ impl TryFrom<Parameter<'_>> for ParameterValue {
             FbParameterValue::hlvecbytes => param.value_as_hlvecbytes().map(|hlvecbytes| {
                 ParameterValue::VecBytes(hlvecbytes.value().unwrap_or_default().iter().collect())
             }),
+            FbParameterValue::hllist => {
+                let hllist = param
+                    .value_as_hllist()
+                    .ok_or_else(|| anyhow!("Failed to get hllist from parameter value"))?;
+                Some(ParameterValue::List(list_from_flatbuffer(hllist)?))
+            }
+            FbParameterValue::hloption => {
+                let hloption = param
+                    .value_as_hloption()
+                    .ok_or_else(|| anyhow!("Failed to get hloption from parameter value"))?;
+                Some(ParameterValue::Optional(option_from_flatbuffer(hloption)?))
+            }
+            FbParameterValue::hlmap => {
+                let hlmap = param
+                    .value_as_hlmap()
+                    .ok_or_else(|| anyhow!("Failed to get hlmap from parameter value"))?;
+                Some(ParameterValue::Map(map_from_flatbuffer(hlmap)?))
+            }
+            FbParameterValue::hlrecord => {
+                let hlrecord = param
+                    .value_as_hlrecord()
+                    .ok_or_else(|| anyhow!("Failed to get hlrecord from parameter value"))?;
+                Some(ParameterValue::Record(record_from_flatbuffer(hlrecord)?))
+            }
             other => {
                 bail!("Unexpected flatbuffer parameter value type: {:?}", other);
             }
@@ -189,6 +295,173 @@ impl TryFrom<Parameter<'_>> for ParameterValue {
     }
 }
 
+/// Serialise a `ParameterValue` into a flatbuffer `Parameter`, including the
+/// values nested inside a compound value.
+pub(crate) fn create_parameter<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    param: &ParameterValue,
+) -> WIPOffset<Parameter<'a>> {
+    let (value_type, value) = match param {
+        ParameterValue::Int(i) => (
+            FbParameterValue::hlint,
+            hlint::create(builder, &hlintArgs { value: *i }).as_union_value(),
+        ),
+        ParameterValue::UInt(ui) => (
+            FbParameterValue::hluint,
+            hluint::create(builder, &hluintArgs { value: *ui }).as_union_value(),
+        ),
+        ParameterValue::Long(l) => (
+            FbParameterValue::hllong,
+            hllong::create(builder, &hllongArgs { value: *l }).as_union_value(),
+        ),
+        ParameterValue::ULong(ul) => (
+            FbParameterValue::hlulong,
+            hlulong::create(builder, &hlulongArgs { value: *ul }).as_union_value(),
+        ),
+        ParameterValue::Float(f) => (
+            FbParameterValue::hlfloat,
+            hlfloat::create(builder, &hlfloatArgs { value: *f }).as_union_value(),
+        ),
+        ParameterValue::Double(d) => (
+            FbParameterValue::hldouble,
+            hldouble::create(builder, &hldoubleArgs { value: *d }).as_union_value(),
+        ),
+        ParameterValue::Bool(b) => (
+            FbParameterValue::hlbool,
+            hlbool::create(builder, &hlboolArgs { value: *b }).as_union_value(),
+        ),
+        ParameterValue::String(s) => {
+            let val = builder.create_string(s.as_str());
+            (
+                FbParameterValue::hlstring,
+                hlstring::create(builder, &hlstringArgs { value: Some(val) }).as_union_value(),
+            )
+        }
+        ParameterValue::VecBytes(v) => {
+            let val = builder.create_vector(v);
+            (
+                FbParameterValue::hlvecbytes,
+                hlvecbytes::create(builder, &hlvecbytesArgs { value: Some(val) }).as_union_value(),
+            )
+        }
+        ParameterValue::List(v) => (
+            FbParameterValue::hllist,
+            create_hllist(builder, v).as_union_value(),
+        ),
+        ParameterValue::Optional(v) => (
+            FbParameterValue::hloption,
+            create_hloption(builder, v.as_deref()).as_union_value(),
+        ),
+        ParameterValue::Map(m) => (
+            FbParameterValue::hlmap,
+            create_hlmap(builder, m).as_union_value(),
+        ),
+        ParameterValue::Record(r) => (
+            FbParameterValue::hlrecord,
+            create_hlrecord(builder, r).as_union_value(),
+        ),
+    };
+
+    Parameter::create(
+        builder,
+        &ParameterArgs {
+            value_type,
+            value: Some(value),
+        },
+    )
+}
+
+pub(crate) fn create_hllist<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    values: &[ParameterValue],
+) -> WIPOffset<hllist<'a>> {
+    let values: Vec<_> = values
+        .iter()
+        .map(|value| create_parameter(builder, value))
+        .collect();
+    let value = builder.create_vector(&values);
+    hllist::create(builder, &hllistArgs { value: Some(value) })
+}
+
+pub(crate) fn create_hloption<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    value: Option<&ParameterValue>,
+) -> WIPOffset<hloption<'a>> {
+    let value = value.map(|value| create_parameter(builder, value));
+    hloption::create(builder, &hloptionArgs { value })
+}
+
+pub(crate) fn create_hlmap<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    entries: &BTreeMap<String, ParameterValue>,
+) -> WIPOffset<hlmap<'a>> {
+    let value = create_hlentries(builder, entries.iter());
+    hlmap::create(builder, &hlmapArgs { value: Some(value) })
+}
+
+pub(crate) fn create_hlrecord<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    fields: &[(String, ParameterValue)],
+) -> WIPOffset<hlrecord<'a>> {
+    let value = create_hlentries(builder, fields.iter().map(|(name, value)| (name, value)));
+    hlrecord::create(builder, &hlrecordArgs { value: Some(value) })
+}
+
+fn create_hlentries<'a, 'b>(
+    builder: &mut FlatBufferBuilder<'a>,
+    entries: impl Iterator<Item = (&'b String, &'b ParameterValue)>,
+) -> WIPOffset<Vector<'a, ForwardsUOffset<hlentry<'a>>>> {
+    let entries: Vec<_> = entries
+        .map(|(key, value)| {
+            let key = builder.create_string(key);
+            let value = create_parameter(builder, value);
+            hlentry::create(
+                builder,
+                &hlentryArgs {
+                    key: Some(key),
+                    value: Some(value),
+                },
+            )
+        })
+        .collect();
+    builder.create_vector(&entries)
+}
+
+fn list_from_flatbuffer(hllist: hllist<'_>) -> Result<Vec<ParameterValue>> {
+    match hllist.value() {
+        Some(values) => values.iter().map(ParameterValue::try_from).collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn option_from_flatbuffer(hloption: hloption<'_>) -> Result<Option<Box<ParameterValue>>> {
+    hloption
+        .value()
+        .map(|value| ParameterValue::try_from(value).map(Box::new))
+        .transpose()
+}
+
+fn map_from_flatbuffer(hlmap: hlmap<'_>) -> Result<BTreeMap<String, ParameterValue>> {
+    let mut map = BTreeMap::new();
+    for entry in hlmap.value().iter().flatten() {
+        let key = entry.key().to_string();
+        if map.contains_key(&key) {
+            bail!("Duplicate key in flatbuffer map: {:?}", key);
+        }
+        map.insert(key, entry.value().try_into()?);
+    }
+    Ok(map)
+}
+
+fn record_from_flatbuffer(hlrecord: hlrecord<'_>) -> Result<Vec<(String, ParameterValue)>> {
+    hlrecord
+        .value()
+        .iter()
+        .flatten()
+        .map(|entry| Ok((entry.key().to_string(), entry.value().try_into()?)))
+        .collect()
+}
+
 impl From<ParameterType> for FbParameterType {
     #[cfg_attr(feature = "tracing", instrument(skip_all, parent = Span::current(), level= "Trace"))]
     fn from(value: ParameterType) -> Self {
@@ -202,6 +475,10 @@ impl From<ParameterType> for FbParameterType {
             ParameterType::String => FbParameterType::hlstring,
             ParameterType::Bool => FbParameterType::hlbool,
             ParameterType::VecBytes => FbParameterType::hlvecbytes,
+            ParameterType::List => FbParameterType::hllist,
+            ParameterType::Optional => FbParameterType::hloption,
+            ParameterType::Map => FbParameterType::hlmap,
+            ParameterType::Record => FbParameterType::hlrecord,
         }
     }
 }
@@ -220,6 +497,10 @@ impl From<ReturnType> for FbReturnType {
             ReturnType::Bool => FbReturnType::hlbool,
             ReturnType::Void => FbReturnType::hlvoid,
             ReturnType::VecBytes => FbReturnType::hlsizeprefixedbuffer,
+            ReturnType::List => FbReturnType::hllist,
+            ReturnType::Optional => FbReturnType::hloption,
+            ReturnType::Map => FbReturnType::hlmap,
+            ReturnType::Record => FbReturnType::hlrecord,
         }
     }
 }
@@ -238,6 +519,10 @@ impl TryFrom<FbParameterType> for ParameterType {
             FbParameterType::hlstring => Ok(ParameterType::String),
             FbParameterType::hlbool => Ok(ParameterType::Bool),
             FbParameterType::hlvecbytes => Ok(ParameterType::VecBytes),
+            FbParameterType::hllist => Ok(ParameterType::List),
+            FbParameterType::hloption => Ok(ParameterType::Optional),
+            FbParameterType::hlmap => Ok(ParameterType::Map),
+            FbParameterType::hlrecord => Ok(ParameterType::Record),
             _ => {
                 bail!("Unexpected flatbuffer parameter type: {:?}", value)
             }
@@ -260,6 +545,10 @@ impl TryFrom<FbReturnType> for ReturnType {
             FbReturnType::hlbool => Ok(ReturnType::Bool),
             FbReturnType::hlvoid => Ok(ReturnType::Void),
             FbReturnType::hlsizeprefixedbuffer => Ok(ReturnType::VecBytes),
+            FbReturnType::hllist => Ok(ReturnType::List),
+            FbReturnType::hloption => Ok(ReturnType::Optional),
+            FbReturnType::hlmap => Ok(ReturnType::Map),
+            FbReturnType::hlrecord => Ok(ReturnType::Record),
             _ => {
                 bail!("Unexpected flatbuffer return type: {:?}", value)
             }
@@ -514,6 +803,40 @@ impl TryFrom<ReturnValue> for () {
     }
 }
 
+macro_rules! impl_try_from_list {
+    ($($ty:ty),+) => {
+        $(
+            impl TryFrom<ParameterValue> for Vec<$ty> {
+                type Error = Error;
+                #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
+                fn try_from(value: ParameterValue) -> Result<Self> {
+                    match value {
+                        ParameterValue::List(v) => v.into_iter().map(<$ty>::try_from).collect(),
+                        _ => {
+                            bail!("Unexpected parameter value type: {:?}", value)
+                        }
+                    }
+                }
+            }
+
+            impl TryFrom<ReturnValue> for Vec<$ty> {
+                type Error = Error;
+                #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
+                fn try_from(value: ReturnValue) -> Result<Self> {
+                    match value {
+                        ReturnValue::List(v) => v.into_iter().map(<$ty>::try_from).collect(),
+                        _ => {
+                            bail!("Unexpected return value type: {:?}", value)
+                        }
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_try_from_list!(i32, u32, i64, u64, f32, f64, String, bool);
+
 impl TryFrom<FbFunctionCallResult<'_>> for ReturnValue {
     type Error = Error;
     #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
@@ -579,6 +902,30 @@ impl TryFrom<FbFunctionCallResult<'_>> for ReturnValue {
                     };
                 Ok(ReturnValue::VecBytes(hlvecbytes.unwrap_or(Vec::new())))
             }
+            FbReturnValue::hllist => {
+                let hllist = function_call_result_fb
+                    .return_value_as_hllist()
+                    .ok_or_else(|| anyhow!("Failed to get hllist from return value"))?;
+                Ok(ReturnValue::List(list_from_flatbuffer(hllist)?))
+            }
+            FbReturnValue::hloption => {
+                let hloption = function_call_result_fb
+                    .return_value_as_hloption()
+                    .ok_or_else(|| anyhow!("Failed to get hloption from return value"))?;
+                Ok(ReturnValue::Optional(option_from_flatbuffer(hloption)?))
+            }
+            FbReturnValue::hlmap => {
+                let hlmap = function_call_result_fb
+                    .return_value_as_hlmap()
+                    .ok_or_else(|| anyhow!("Failed to get hlmap from return value"))?;
+                Ok(ReturnValue::Map(map_from_flatbuffer(hlmap)?))
+            }
+            FbReturnValue::hlrecord => {
+                let hlrecord = function_call_result_fb
+                    .return_value_as_hlrecord()
+                    .ok_or_else(|| anyhow!("Failed to get hlrecord from return value"))?;
+                Ok(ReturnValue::Record(record_from_flatbuffer(hlrecord)?))
+            }
             other => {
                 bail!("Unexpected flatbuffer return value type: {:?}", other)
             }
@@ -734,8 +1081,97 @@ impl TryFrom<&ReturnValue> for Vec<u8> {
                 builder.finish_size_prefixed(function_call_result, None);
                 builder.finished_data().to_vec()
             }
+            ReturnValue::List(v) => {
+                let hllist = create_hllist(&mut builder, v);
+                let function_call_result = FbFunctionCallResult::create(
+                    &mut builder,
+                    &FbFunctionCallResultArgs {
+                        return_value: Some(hllist.as_union_value()),
+                        return_value_type: FbReturnValue::hllist,
+                    },
+                );
+                builder.finish_size_prefixed(function_call_result, None);
+                builder.finished_data().to_vec()
+            }
+            ReturnValue::Optional(v) => {
+                let hloption = create_hloption(&mut builder, v.as_deref());
+                let function_call_result = FbFunctionCallResult::create(
+                    &mut builder,
+                    &FbFunctionCallResultArgs {
+                        return_value: Some(hloption.as_union_value()),
+                        return_value_type: FbReturnValue::hloption,
+                    },
+                );
+                builder.finish_size_prefixed(function_call_result, None);
+                builder.finished_data().to_vec()
+            }
+            ReturnValue::Map(m) => {
+                let hlmap = create_hlmap(&mut builder, m);
+                let function_call_result = FbFunctionCallResult::create(
+                    &mut builder,
+                    &FbFunctionCallResultArgs {
+                        return_value: Some(hlmap.as_union_value()),
+                        return_value_type: FbReturnValue::hlmap,
+                    },
+                );
+                builder.finish_size_prefixed(function_call_result, None);
+                builder.finished_data().to_vec()
+            }
+            ReturnValue::Record(r) => {
+                let hlrecord = create_hlrecord(&mut builder, r);
+                let function_call_result = FbFunctionCallResult::create(
+                    &mut builder,
+                    &FbFunctionCallResultArgs {
+                        return_value: Some(hlrecord.as_union_value()),
+                        return_value_type: FbReturnValue::hlrecord,
+                    },
+                );
+                builder.finish_size_prefixed(function_call_result, None);
+                builder.finished_data().to_vec()
+            }
         };
 
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn compound_return_values_round_trip() -> Result<()> {
+        let values = vec![
+            ReturnValue::List(vec![
+                ParameterValue::String("a".to_string()),
+                ParameterValue::String("b".to_string()),
+            ]),
+            ReturnValue::Optional(None),
+            ReturnValue::Optional(Some(Box::new(ParameterValue::Bool(true)))),
+            ReturnValue::Map(BTreeMap::from([(
+                "k".to_string(),
+                ParameterValue::Optional(Some(Box::new(ParameterValue::Long(-1)))),
+            )])),
+            ReturnValue::Record(vec![
+                ("z".to_string(), ParameterValue::UInt(1)),
+                ("a".to_string(), ParameterValue::VecBytes(vec![1, 2])),
+            ]),
+        ];
+
+        for value in values {
+            let bytes: Vec<u8> = (&value).try_into()?;
+            let round_tripped = ReturnValue::try_from(bytes.as_slice())?;
+            assert_eq!(round_tripped, value);
+        }
+
+        let strings: Vec<String> =
+            ReturnValue::List(vec![ParameterValue::String("a".to_string())]).try_into()?;
+        assert_eq!(strings, vec!["a".to_string()]);
+        let res: Result<Vec<String>> = ReturnValue::List(vec![ParameterValue::Int(1)]).try_into();
+        assert!(res.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, Error, Result};
+use flatbuffers::{FlatBufferBuilder, WIPOffset};
+#[cfg(feature = "tracing")]
+use tracing::{instrument, Span};
+
+use super::function_types::{ParameterType, ReturnType};
+use crate::flatbuffers::hyperlight::generated::{
+    GuestFunctionDefinition as FbGuestFunctionDefinition,
+    GuestFunctionDefinitionArgs as FbGuestFunctionDefinitionArgs, ParameterType as FbParameterType,
+};
+
+/// The signature of a function exposed from the guest to the host
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GuestFunctionDefinition {
+    /// The function name
+    pub function_name: String,
+    /// The type of the parameter values for the guest function call.
+    pub parameter_types: Vec<ParameterType>,
+    /// The type of the return value from the guest function call
+    pub return_type: ReturnType,
+}
+
+impl GuestFunctionDefinition {
+    /// Create a new `GuestFunctionDefinition`.
+    #[cfg_attr(feature = "tracing", instrument(skip_all, parent = Span::current(), level= "Trace"))]
+    pub fn new(
+        function_name: String,
+        parameter_types: Vec<ParameterType>,
+        return_type: ReturnType,
+    ) -> Self {
+        Self {
+            function_name,
+            parameter_types,
+            return_type,
+        }
+    }
+
+    /// Convert this `GuestFunctionDefinition` into a `WIPOffset<FbGuestFunctionDefinition>`.
+    #[cfg_attr(feature = "tracing", instrument(skip_all, parent = Span::current(), level= "Trace"))]
+    pub(crate) fn convert_to_flatbuffer_def<'a>(
+        &self,
+        builder: &mut FlatBufferBuilder<'a>,
+    ) -> WIPOffset<FbGuestFunctionDefinition<'a>> {
+        let function_name = builder.create_string(&self.function_name);
+        let parameters: Vec<FbParameterType> = self
+            .parameter_types
+            .iter()
+            .map(|pt| pt.clone().into())
+            .collect();
+        let parameters = builder.create_vector(&parameters);
+
+        FbGuestFunctionDefinition::create(
+            builder,
+            &FbGuestFunctionDefinitionArgs {
+                function_name: Some(function_name),
+                parameters: Some(parameters),
+                return_type: self.return_type.into(),
+            },
+        )
+    }
+}
+
+impl TryFrom<&FbGuestFunctionDefinition<'_>> for GuestFunctionDefinition {
+    type Error = Error;
+    #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
+    fn try_from(value: &FbGuestFunctionDefinition) -> Result<Self> {
+        let function_name = value.function_name().to_string();
+        let return_type = value.return_type().try_into().map_err(|_| {
+            anyhow!(
+                "Failed to convert return type for function {}",
+                function_name
+            )
+        })?;
+        let parameter_types = value
+            .parameters()
+            .iter()
+            .flatten()
+            .map(|fb_pt| {
+                fb_pt.try_into().map_err(|_| {
+                    anyhow!(
+                        "Failed to convert parameter type for function {}",
+                        function_name
+                    )
+                })
+            })
+            .collect::<Result<Vec<ParameterType>>>()?;
+
+        Ok(Self::new(function_name, parameter_types, return_type))
+    }
+}
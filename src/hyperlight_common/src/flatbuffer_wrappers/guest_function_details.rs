@@ -0,0 +1,136 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use alloc::vec::Vec;
+
+use anyhow::{Error, Result};
+use flatbuffers::{size_prefixed_root, WIPOffset};
+#[cfg(feature = "tracing")]
+use tracing::{instrument, Span};
+
+use super::guest_function_definition::GuestFunctionDefinition;
+use crate::flatbuffers::hyperlight::generated::{
+    GuestFunctionDefinition as FbGuestFunctionDefinition,
+    GuestFunctionDetails as FbGuestFunctionDetails,
+    GuestFunctionDetailsArgs as FbGuestFunctionDetailsArgs,
+};
+
+/// `GuestFunctionDetails` represents the set of functions that the guest exposes to the host.
+///
+/// The guest writes these to its output buffer once it has finished initialising, so
+/// that the host can check guest function calls before making them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GuestFunctionDetails {
+    /// The guest functions, sorted by name.
+    pub guest_functions: Vec<GuestFunctionDefinition>,
+}
+
+impl GuestFunctionDetails {
+    /// Create a new `GuestFunctionDetails`.
+    #[cfg_attr(feature = "tracing", instrument(skip_all, parent = Span::current(), level= "Trace"))]
+    pub fn new(mut guest_functions: Vec<GuestFunctionDefinition>) -> Self {
+        guest_functions.sort_by(|a, b| a.function_name.cmp(&b.function_name));
+        Self { guest_functions }
+    }
+
+    /// Find a guest function by name.
+    #[cfg_attr(feature = "tracing", instrument(skip_all, parent = Span::current(), level= "Trace"))]
+    pub fn find_by_function_name(&self, function_name: &str) -> Option<&GuestFunctionDefinition> {
+        self.guest_functions
+            .binary_search_by(|gfd| gfd.function_name.as_str().cmp(function_name))
+            .ok()
+            .map(|i| &self.guest_functions[i])
+    }
+}
+
+impl TryFrom<&[u8]> for GuestFunctionDetails {
+    type Error = Error;
+    #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
+    fn try_from(value: &[u8]) -> Result<Self> {
+        let guest_function_details_fb = size_prefixed_root::<FbGuestFunctionDetails>(value)
+            .map_err(|e| anyhow::anyhow!("Error while reading GuestFunctionDetails: {:?}", e))?;
+
+        let guest_functions = guest_function_details_fb
+            .functions()
+            .iter()
+            .flatten()
+            .map(|gfd| GuestFunctionDefinition::try_from(&gfd))
+            .collect::<Result<Vec<GuestFunctionDefinition>>>()?;
+
+        Ok(Self::new(guest_functions))
+    }
+}
+
+impl TryFrom<&GuestFunctionDetails> for Vec<u8> {
+    type Error = Error;
+    #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
+    fn try_from(value: &GuestFunctionDetails) -> Result<Vec<u8>> {
+        let mut builder = flatbuffers::FlatBufferBuilder::new();
+        let guest_function_definitions: Vec<WIPOffset<FbGuestFunctionDefinition>> = value
+            .guest_functions
+            .iter()
+            .map(|gfd| gfd.convert_to_flatbuffer_def(&mut builder))
+            .collect();
+        let functions = builder.create_vector(&guest_function_definitions);
+
+        let guest_function_details = FbGuestFunctionDetails::create(
+            &mut builder,
+            &FbGuestFunctionDetailsArgs {
+                functions: Some(functions),
+            },
+        );
+        builder.finish_size_prefixed(guest_function_details, None);
+        let res = builder.finished_data().to_vec();
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    use super::*;
+    use crate::flatbuffer_wrappers::function_types::{ParameterType, ReturnType};
+
+    #[test]
+    fn round_trip_guest_function_details() -> Result<()> {
+        let details = GuestFunctionDetails::new(vec![
+            GuestFunctionDefinition::new(
+                "Echo".to_string(),
+                vec![ParameterType::String],
+                ReturnType::String,
+            ),
+            GuestFunctionDefinition::new(
+                "Add".to_string(),
+                vec![ParameterType::Int, ParameterType::Int],
+                ReturnType::Int,
+            ),
+            GuestFunctionDefinition::new("Nop".to_string(), vec![], ReturnType::Void),
+        ]);
+
+        let bytes: Vec<u8> = (&details).try_into()?;
+        let round_tripped = GuestFunctionDetails::try_from(bytes.as_slice())?;
+        assert_eq!(round_tripped, details);
+
+        let add = round_tripped.find_by_function_name("Add").unwrap();
+        assert_eq!(add.parameter_types.len(), 2);
+        assert!(round_tripped.find_by_function_name("add").is_none());
+
+        Ok(())
+    }
+}
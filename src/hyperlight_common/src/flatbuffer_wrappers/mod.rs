@@ -18,6 +18,10 @@ pub mod function_call;
 pub mod function_types;
 pub mod guest_error;
 /// cbindgen:ignore
+pub mod guest_function_definition;
+/// cbindgen:ignore
+pub mod guest_function_details;
+/// cbindgen:ignore
 pub mod guest_log_data;
 /// cbindgen:ignore
 pub mod guest_log_level;
@@ -14,10 +14,15 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use flatbuffers::{FlatBufferBuilder, UnionWIPOffset, WIPOffset};
 
+use super::function_types::{
+    create_hllist, create_hlmap, create_hloption, create_hlrecord, ParameterValue,
+};
 use crate::flatbuffers::hyperlight::generated::{
     hldouble as Fbhldouble, hldoubleArgs as FbhldoubleArgs, hlfloat as Fbhlfloat,
     hlfloatArgs as FbhlfloatArgs, hlint as Fbhlint, hlintArgs as FbhlintArgs, hllong as Fbhllong,
@@ -137,6 +142,46 @@ pub fn get_flatbuffer_result_from_vec(data: &[u8]) -> Vec<u8> {
     get_flatbuffer_result(&mut builder, rt, rv)
 }
 
+pub fn get_flatbuffer_result_from_list(values: &[ParameterValue]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+    let hllist = create_hllist(&mut builder, values);
+
+    let rt = FbReturnValue::hllist;
+    let rv: Option<WIPOffset<UnionWIPOffset>> = Some(hllist.as_union_value());
+
+    get_flatbuffer_result(&mut builder, rt, rv)
+}
+
+pub fn get_flatbuffer_result_from_optional(value: Option<&ParameterValue>) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+    let hloption = create_hloption(&mut builder, value);
+
+    let rt = FbReturnValue::hloption;
+    let rv: Option<WIPOffset<UnionWIPOffset>> = Some(hloption.as_union_value());
+
+    get_flatbuffer_result(&mut builder, rt, rv)
+}
+
+pub fn get_flatbuffer_result_from_map(entries: &BTreeMap<String, ParameterValue>) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+    let hlmap = create_hlmap(&mut builder, entries);
+
+    let rt = FbReturnValue::hlmap;
+    let rv: Option<WIPOffset<UnionWIPOffset>> = Some(hlmap.as_union_value());
+
+    get_flatbuffer_result(&mut builder, rt, rv)
+}
+
+pub fn get_flatbuffer_result_from_record(fields: &[(String, ParameterValue)]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+    let hlrecord = create_hlrecord(&mut builder, fields);
+
+    let rt = FbReturnValue::hlrecord;
+    let rv: Option<WIPOffset<UnionWIPOffset>> = Some(hlrecord.as_union_value());
+
+    get_flatbuffer_result(&mut builder, rt, rv)
+}
+
 fn get_flatbuffer_result(
     builder: &mut FlatBufferBuilder,
     return_value_type: FbReturnValue,
@@ -215,6 +215,62 @@ impl<'a> FunctionCallResult<'a> {
             None
         }
     }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn return_value_as_hllist(&self) -> Option<hllist<'a>> {
+        if self.return_value_type() == ReturnValue::hllist {
+            let u = self.return_value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hllist::init_from_table(u) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn return_value_as_hloption(&self) -> Option<hloption<'a>> {
+        if self.return_value_type() == ReturnValue::hloption {
+            let u = self.return_value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hloption::init_from_table(u) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn return_value_as_hlmap(&self) -> Option<hlmap<'a>> {
+        if self.return_value_type() == ReturnValue::hlmap {
+            let u = self.return_value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hlmap::init_from_table(u) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn return_value_as_hlrecord(&self) -> Option<hlrecord<'a>> {
+        if self.return_value_type() == ReturnValue::hlrecord {
+            let u = self.return_value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hlrecord::init_from_table(u) })
+        } else {
+            None
+        }
+    }
 }
 
 impl flatbuffers::Verifiable for FunctionCallResult<'_> {
@@ -282,6 +338,26 @@ impl flatbuffers::Verifiable for FunctionCallResult<'_> {
                             "ReturnValue::hlsizeprefixedbuffer",
                             pos,
                         ),
+                    ReturnValue::hllist => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hllist>>(
+                            "ReturnValue::hllist",
+                            pos,
+                        ),
+                    ReturnValue::hloption => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hloption>>(
+                            "ReturnValue::hloption",
+                            pos,
+                        ),
+                    ReturnValue::hlmap => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hlmap>>(
+                            "ReturnValue::hlmap",
+                            pos,
+                        ),
+                    ReturnValue::hlrecord => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hlrecord>>(
+                            "ReturnValue::hlrecord",
+                            pos,
+                        ),
                     _ => Ok(()),
                 },
             )?
@@ -450,6 +526,46 @@ impl core::fmt::Debug for FunctionCallResult<'_> {
                     )
                 }
             }
+            ReturnValue::hllist => {
+                if let Some(x) = self.return_value_as_hllist() {
+                    ds.field("return_value", &x)
+                } else {
+                    ds.field(
+                        "return_value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
+            ReturnValue::hloption => {
+                if let Some(x) = self.return_value_as_hloption() {
+                    ds.field("return_value", &x)
+                } else {
+                    ds.field(
+                        "return_value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
+            ReturnValue::hlmap => {
+                if let Some(x) = self.return_value_as_hlmap() {
+                    ds.field("return_value", &x)
+                } else {
+                    ds.field(
+                        "return_value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
+            ReturnValue::hlrecord => {
+                if let Some(x) = self.return_value_as_hlrecord() {
+                    ds.field("return_value", &x)
+                } else {
+                    ds.field(
+                        "return_value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
             _ => {
                 let x: Option<()> = None;
                 ds.field("return_value", &x)
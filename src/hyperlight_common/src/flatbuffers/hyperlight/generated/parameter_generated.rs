@@ -198,6 +198,62 @@ impl<'a> Parameter<'a> {
             None
         }
     }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn value_as_hllist(&self) -> Option<hllist<'a>> {
+        if self.value_type() == ParameterValue::hllist {
+            let u = self.value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hllist::init_from_table(u) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn value_as_hloption(&self) -> Option<hloption<'a>> {
+        if self.value_type() == ParameterValue::hloption {
+            let u = self.value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hloption::init_from_table(u) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn value_as_hlmap(&self) -> Option<hlmap<'a>> {
+        if self.value_type() == ParameterValue::hlmap {
+            let u = self.value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hlmap::init_from_table(u) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn value_as_hlrecord(&self) -> Option<hlrecord<'a>> {
+        if self.value_type() == ParameterValue::hlrecord {
+            let u = self.value();
+            // Safety:
+            // Created from a valid Table for this object
+            // Which contains a valid union in this slot
+            Some(unsafe { hlrecord::init_from_table(u) })
+        } else {
+            None
+        }
+    }
 }
 
 impl flatbuffers::Verifiable for Parameter<'_> {
@@ -260,6 +316,26 @@ impl flatbuffers::Verifiable for Parameter<'_> {
                             "ParameterValue::hlvecbytes",
                             pos,
                         ),
+                    ParameterValue::hllist => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hllist>>(
+                            "ParameterValue::hllist",
+                            pos,
+                        ),
+                    ParameterValue::hloption => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hloption>>(
+                            "ParameterValue::hloption",
+                            pos,
+                        ),
+                    ParameterValue::hlmap => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hlmap>>(
+                            "ParameterValue::hlmap",
+                            pos,
+                        ),
+                    ParameterValue::hlrecord => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<hlrecord>>(
+                            "ParameterValue::hlrecord",
+                            pos,
+                        ),
                     _ => Ok(()),
                 },
             )?
@@ -410,6 +486,46 @@ impl core::fmt::Debug for Parameter<'_> {
                     )
                 }
             }
+            ParameterValue::hllist => {
+                if let Some(x) = self.value_as_hllist() {
+                    ds.field("value", &x)
+                } else {
+                    ds.field(
+                        "value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
+            ParameterValue::hloption => {
+                if let Some(x) = self.value_as_hloption() {
+                    ds.field("value", &x)
+                } else {
+                    ds.field(
+                        "value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
+            ParameterValue::hlmap => {
+                if let Some(x) = self.value_as_hlmap() {
+                    ds.field("value", &x)
+                } else {
+                    ds.field(
+                        "value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
+            ParameterValue::hlrecord => {
+                if let Some(x) = self.value_as_hlrecord() {
+                    ds.field("value", &x)
+                } else {
+                    ds.field(
+                        "value",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
             _ => {
                 let x: Option<()> = None;
                 ds.field("value", &x)
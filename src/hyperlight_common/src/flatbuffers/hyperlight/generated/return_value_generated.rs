@@ -19,13 +19,13 @@ pub const ENUM_MIN_RETURN_VALUE: u8 = 0;
     since = "2.0.0",
     note = "Use associated constants instead. This will no longer be generated in 2021."
 )]
-pub const ENUM_MAX_RETURN_VALUE: u8 = 10;
+pub const ENUM_MAX_RETURN_VALUE: u8 = 14;
 #[deprecated(
     since = "2.0.0",
     note = "Use associated constants instead. This will no longer be generated in 2021."
 )]
 #[allow(non_camel_case_types)]
-pub const ENUM_VALUES_RETURN_VALUE: [ReturnValue; 11] = [
+pub const ENUM_VALUES_RETURN_VALUE: [ReturnValue; 15] = [
     ReturnValue::NONE,
     ReturnValue::hlint,
     ReturnValue::hluint,
@@ -37,6 +37,10 @@ pub const ENUM_VALUES_RETURN_VALUE: [ReturnValue; 11] = [
     ReturnValue::hlbool,
     ReturnValue::hlvoid,
     ReturnValue::hlsizeprefixedbuffer,
+    ReturnValue::hllist,
+    ReturnValue::hloption,
+    ReturnValue::hlmap,
+    ReturnValue::hlrecord,
 ];
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -55,9 +59,13 @@ impl ReturnValue {
     pub const hlbool: Self = Self(8);
     pub const hlvoid: Self = Self(9);
     pub const hlsizeprefixedbuffer: Self = Self(10);
+    pub const hllist: Self = Self(11);
+    pub const hloption: Self = Self(12);
+    pub const hlmap: Self = Self(13);
+    pub const hlrecord: Self = Self(14);
 
     pub const ENUM_MIN: u8 = 0;
-    pub const ENUM_MAX: u8 = 10;
+    pub const ENUM_MAX: u8 = 14;
     pub const ENUM_VALUES: &'static [Self] = &[
         Self::NONE,
         Self::hlint,
@@ -70,6 +78,10 @@ impl ReturnValue {
         Self::hlbool,
         Self::hlvoid,
         Self::hlsizeprefixedbuffer,
+        Self::hllist,
+        Self::hloption,
+        Self::hlmap,
+        Self::hlrecord,
     ];
     /// Returns the variant's name or "" if unknown.
     pub fn variant_name(self) -> Option<&'static str> {
@@ -85,6 +97,10 @@ impl ReturnValue {
             Self::hlbool => Some("hlbool"),
             Self::hlvoid => Some("hlvoid"),
             Self::hlsizeprefixedbuffer => Some("hlsizeprefixedbuffer"),
+            Self::hllist => Some("hllist"),
+            Self::hloption => Some("hloption"),
+            Self::hlmap => Some("hlmap"),
+            Self::hlrecord => Some("hlrecord"),
             _ => None,
         }
     }
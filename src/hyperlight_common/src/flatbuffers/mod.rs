@@ -32,10 +32,20 @@ pub mod hyperlight {
         pub use self::hlvecbytes_generated::*;
         mod hlvoid_generated;
         pub use self::hlvoid_generated::*;
-        mod function_call_result_generated;
-        pub use self::function_call_result_generated::*;
         mod parameter_generated;
         pub use self::parameter_generated::*;
+        mod hllist_generated;
+        pub use self::hllist_generated::*;
+        mod hloption_generated;
+        pub use self::hloption_generated::*;
+        mod hlentry_generated;
+        pub use self::hlentry_generated::*;
+        mod hlmap_generated;
+        pub use self::hlmap_generated::*;
+        mod hlrecord_generated;
+        pub use self::hlrecord_generated::*;
+        mod function_call_result_generated;
+        pub use self::function_call_result_generated::*;
         mod function_call_generated;
         pub use self::function_call_generated::*;
         mod function_call_type_generated;
@@ -48,6 +58,10 @@ pub mod hyperlight {
         pub use self::host_function_definition_generated::*;
         mod host_function_details_generated;
         pub use self::host_function_details_generated::*;
+        mod guest_function_definition_generated;
+        pub use self::guest_function_definition_generated::*;
+        mod guest_function_details_generated;
+        pub use self::guest_function_details_generated::*;
         mod hlsizeprefixedbuffer_generated;
         pub use self::hlsizeprefixedbuffer_generated::*;
         mod log_level_generated;
@@ -31,3 +31,5 @@ pub mod flatbuffer_wrappers;
 mod flatbuffers;
 /// cbindgen:ignore
 pub mod mem;
+/// Chunks of the streams that guest functions read and write
+pub mod stream;
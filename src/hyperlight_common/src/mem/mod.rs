@@ -79,6 +79,32 @@ pub struct GuestStackData {
     pub bootStackAddress: u64,
 }
 
+#[repr(C)]
+pub struct GuestHeapStats {
+    /// The number of bytes allocated from the heap
+    pub heapUsedSize: u64,
+    /// The most bytes that have been allocated from the heap at once
+    pub heapHighWaterMark: u64,
+    /// The number of free blocks the heap is split into
+    pub heapFreeFragments: u64,
+    /// Set by the host to non-zero to have the guest count
+    /// `heapFreeFragments` at the end of every guest function call, rather
+    /// than only at the end of its initialisation
+    pub heapFreeFragmentsCounted: u64,
+}
+
+#[repr(C)]
+pub struct GuestHeapGrowth {
+    /// The lowest address of the memory reserved for the heap to grow into
+    pub heapGrowthBuffer: *mut c_void,
+    /// The most bytes the heap can grow by, or 0 if it can't grow
+    pub heapGrowthMaxSize: u64,
+    /// The number of bytes the heap has grown by, which is set by the host
+    pub heapGrownSize: u64,
+    /// The number of bytes the guest is asking the host to grow the heap by
+    pub heapGrowthRequestSize: u64,
+}
+
 #[repr(C)]
 pub struct GuestPanicContextData {
     pub guestPanicContextDataSize: u64,
@@ -101,4 +127,6 @@ pub struct HyperlightPEB {
     pub guestPanicContextData: GuestPanicContextData,
     pub guestheapData: GuestHeapData,
     pub gueststackData: GuestStackData,
+    pub guestheapStats: GuestHeapStats,
+    pub guestheapGrowth: GuestHeapGrowth,
 }
@@ -0,0 +1,102 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use alloc::vec::Vec;
+
+use anyhow::{bail, Error, Result};
+#[cfg(feature = "tracing")]
+use tracing::{instrument, Span};
+
+/// The number of bytes taken up by the length prefix of a `StreamChunk`.
+pub const STREAM_CHUNK_PREFIX_SIZE: usize = 4;
+
+/// `StreamChunk` is a piece of a stream that is passed between the host and
+/// the guest through the input and output data buffers.
+///
+/// This lets a guest function read and write streams that are larger than
+/// those buffers, one chunk at a time. An empty chunk marks the end of a
+/// stream.
+///
+/// A chunk is encoded as its length, as a little-endian `u32`, followed by
+/// its bytes. This is the same size prefix that the flatbuffers in the input
+/// and output data buffers have, so chunks can be pushed to and popped from
+/// those buffers in the same way.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StreamChunk(pub Vec<u8>);
+
+impl StreamChunk {
+    /// The number of bytes that `self` takes up once encoded.
+    pub fn encoded_size(&self) -> usize {
+        STREAM_CHUNK_PREFIX_SIZE + self.0.len()
+    }
+}
+
+impl TryFrom<&[u8]> for StreamChunk {
+    type Error = Error;
+    #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
+    fn try_from(value: &[u8]) -> Result<Self> {
+        let Some(prefix) = value.get(..STREAM_CHUNK_PREFIX_SIZE) else {
+            bail!("Stream chunk is too short to contain its length");
+        };
+        let len = u32::from_le_bytes(prefix.try_into()?) as usize;
+        let Some(data) = value.get(STREAM_CHUNK_PREFIX_SIZE..STREAM_CHUNK_PREFIX_SIZE + len) else {
+            bail!(
+                "Stream chunk of length {} does not fit in a buffer of {} bytes",
+                len,
+                value.len()
+            );
+        };
+        Ok(Self(data.to_vec()))
+    }
+}
+
+impl TryFrom<&StreamChunk> for Vec<u8> {
+    type Error = Error;
+    #[cfg_attr(feature = "tracing", instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace"))]
+    fn try_from(value: &StreamChunk) -> Result<Vec<u8>> {
+        let len = u32::try_from(value.0.len())?;
+        let mut res = Vec::with_capacity(value.encoded_size());
+        res.extend_from_slice(&len.to_le_bytes());
+        res.extend_from_slice(&value.0);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn round_trip_stream_chunk() -> Result<()> {
+        for chunk in [StreamChunk(vec![]), StreamChunk(vec![1, 2, 3])] {
+            let mut bytes: Vec<u8> = (&chunk).try_into()?;
+            assert_eq!(bytes.len(), chunk.encoded_size());
+            // the guest decodes chunks from the rest of its input buffer,
+            // so anything after the chunk must be ignored
+            bytes.extend_from_slice(&[0; 8]);
+            assert_eq!(StreamChunk::try_from(bytes.as_slice())?, chunk);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_stream_chunk() {
+        assert!(StreamChunk::try_from([1_u8, 0].as_slice()).is_err());
+        assert!(StreamChunk::try_from([4_u8, 0, 0, 0, 1, 2].as_slice()).is_err());
+    }
+}
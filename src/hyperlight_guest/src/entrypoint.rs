@@ -24,7 +24,9 @@ use spin::Once;
 
 use crate::guest_error::reset_error;
 use crate::guest_function_call::dispatch_function;
+use crate::guest_function_register::write_guest_function_details;
 use crate::guest_logger::init_logger;
+use crate::heap::write_heap_stats;
 use crate::host_function_call::{outb, OutBAction};
 use crate::{
     __security_cookie, HEAP_ALLOCATOR, MIN_STACK_ADDRESS, OS_PAGE_SIZE, OUTB_PTR,
@@ -143,6 +145,10 @@ pub extern "win64" fn entrypoint(peb_address: u64, seed: u64, ops: u64, max_log_
             reset_error();
 
             hyperlight_main();
+
+            write_guest_function_details();
+
+            write_heap_stats(true);
         }
     });
 
@@ -0,0 +1,190 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hyperlight_common::flatbuffer_wrappers::function_call::FunctionCall;
+use hyperlight_common::flatbuffer_wrappers::function_types::{
+    ParameterType, ParameterValue, ReturnType, ReturnValue,
+};
+use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
+
+use crate::error::{HyperlightGuestError, Result};
+
+/// A type that can be a parameter of a guest function, or of a host
+/// function called by the guest.
+pub trait SupportedParameterType: Sized {
+    /// Get the `ParameterType` that corresponds to this type
+    fn get_hyperlight_type() -> ParameterType;
+    /// Get the `ParameterValue` that holds a copy of `self`
+    fn get_hyperlight_value(&self) -> ParameterValue;
+    /// Get the value of this type held in `value`
+    fn get_inner(value: ParameterValue) -> Result<Self>;
+}
+
+/// A type that can be returned from a guest function, or from a host
+/// function called by the guest.
+pub trait SupportedReturnType: Sized {
+    /// Get the `ReturnType` that corresponds to this type
+    fn get_hyperlight_type() -> ReturnType;
+    /// Get the `ReturnValue` that holds a copy of `self`
+    fn get_hyperlight_value(&self) -> ReturnValue;
+    /// Get the value of this type held in `value`
+    fn get_inner(value: ReturnValue) -> Result<Self>;
+}
+
+fn parameter_type_mismatch(error: anyhow::Error) -> HyperlightGuestError {
+    HyperlightGuestError::new(
+        ErrorCode::GuestFunctionParameterTypeMismatch,
+        format!("{:?}", error),
+    )
+}
+
+macro_rules! impl_supported_type {
+    ($ty:ty, $variant:ident) => {
+        impl SupportedParameterType for $ty {
+            fn get_hyperlight_type() -> ParameterType {
+                ParameterType::$variant
+            }
+
+            fn get_hyperlight_value(&self) -> ParameterValue {
+                ParameterValue::$variant(<$ty as Clone>::clone(self))
+            }
+
+            fn get_inner(value: ParameterValue) -> Result<Self> {
+                <$ty>::try_from(value).map_err(parameter_type_mismatch)
+            }
+        }
+
+        impl SupportedReturnType for $ty {
+            fn get_hyperlight_type() -> ReturnType {
+                ReturnType::$variant
+            }
+
+            fn get_hyperlight_value(&self) -> ReturnValue {
+                ReturnValue::$variant(<$ty as Clone>::clone(self))
+            }
+
+            fn get_inner(value: ReturnValue) -> Result<Self> {
+                Ok(<$ty>::try_from(value)?)
+            }
+        }
+    };
+}
+
+impl_supported_type!(i32, Int);
+impl_supported_type!(u32, UInt);
+impl_supported_type!(i64, Long);
+impl_supported_type!(u64, ULong);
+impl_supported_type!(f32, Float);
+impl_supported_type!(f64, Double);
+impl_supported_type!(String, String);
+impl_supported_type!(bool, Bool);
+impl_supported_type!(Vec<u8>, VecBytes);
+
+macro_rules! impl_supported_list_type {
+    ($($ty:ty),+) => {
+        $(
+            impl SupportedParameterType for Vec<$ty> {
+                fn get_hyperlight_type() -> ParameterType {
+                    ParameterType::List
+                }
+
+                fn get_hyperlight_value(&self) -> ParameterValue {
+                    ParameterValue::List(
+                        self.iter()
+                            .map(SupportedParameterType::get_hyperlight_value)
+                            .collect(),
+                    )
+                }
+
+                fn get_inner(value: ParameterValue) -> Result<Self> {
+                    Vec::<$ty>::try_from(value).map_err(parameter_type_mismatch)
+                }
+            }
+
+            impl SupportedReturnType for Vec<$ty> {
+                fn get_hyperlight_type() -> ReturnType {
+                    ReturnType::List
+                }
+
+                fn get_hyperlight_value(&self) -> ReturnValue {
+                    ReturnValue::List(
+                        self.iter()
+                            .map(SupportedParameterType::get_hyperlight_value)
+                            .collect(),
+                    )
+                }
+
+                fn get_inner(value: ReturnValue) -> Result<Self> {
+                    Ok(Vec::<$ty>::try_from(value)?)
+                }
+            }
+        )+
+    };
+}
+
+impl_supported_list_type!(i32, u32, i64, u64, f32, f64, String, bool);
+
+impl SupportedReturnType for () {
+    fn get_hyperlight_type() -> ReturnType {
+        ReturnType::Void
+    }
+
+    fn get_hyperlight_value(&self) -> ReturnValue {
+        ReturnValue::Void
+    }
+
+    fn get_inner(value: ReturnValue) -> Result<Self> {
+        Ok(<()>::try_from(value)?)
+    }
+}
+
+/// Get the parameter at `index` of `function_call` as a `T`.
+///
+/// This is used by the code that `#[guest_function]` generates to decode the
+/// arguments of a guest function call.
+pub fn get_parameter<T: SupportedParameterType>(
+    function_call: &FunctionCall,
+    index: usize,
+) -> Result<T> {
+    let value = function_call
+        .parameters
+        .as_ref()
+        .and_then(|parameters| parameters.get(index))
+        .cloned()
+        .ok_or_else(|| {
+            HyperlightGuestError::new(
+                ErrorCode::GuestFunctionIncorrecNoOfParameters,
+                format!(
+                    "Function {} has no parameter at index {}",
+                    function_call.function_name, index
+                ),
+            )
+        })?;
+    T::get_inner(value)
+}
+
+/// Serialize `value` so that it can be returned to the host as the result of
+/// a guest function call.
+///
+/// This is used by the code that `#[guest_function]` generates to encode the
+/// return value of a guest function.
+pub fn get_flatbuffer_result<T: SupportedReturnType>(value: T) -> Result<Vec<u8>> {
+    Ok(Vec::<u8>::try_from(&value.get_hyperlight_value())?)
+}
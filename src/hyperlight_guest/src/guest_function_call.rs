@@ -24,11 +24,13 @@ use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
 use crate::entrypoint::halt;
 use crate::error::{HyperlightGuestError, Result};
 use crate::guest_error::{reset_error, set_error};
+use crate::heap::write_heap_stats;
 use crate::shared_input_data::try_pop_shared_input_data_into;
 use crate::shared_output_data::push_shared_output_data;
 use crate::REGISTERED_GUEST_FUNCTIONS;
 
-type GuestFunc = fn(&FunctionCall) -> Result<Vec<u8>>;
+/// The type of the functions that can be registered as guest functions.
+pub type GuestFunc = fn(&FunctionCall) -> Result<Vec<u8>>;
 
 pub(crate) fn call_guest_function(function_call: FunctionCall) -> Result<Vec<u8>> {
     // Validate this is a Guest Function Call
@@ -101,5 +103,6 @@ fn internal_dispatch_function() -> Result<()> {
 // when running in the hypervisor.
 pub(crate) extern "win64" fn dispatch_function() {
     let _ = internal_dispatch_function();
+    write_heap_stats(false);
     halt();
 }
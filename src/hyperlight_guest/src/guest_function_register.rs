@@ -16,8 +16,13 @@ limitations under the License.
 
 use alloc::collections::BTreeMap;
 use alloc::string::String;
+use alloc::vec::Vec;
+
+use hyperlight_common::flatbuffer_wrappers::guest_function_definition::GuestFunctionDefinition as GuestFunctionSignature;
+use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
 
 use super::guest_function_definition::GuestFunctionDefinition;
+use super::shared_output_data::push_shared_output_data;
 use crate::REGISTERED_GUEST_FUNCTIONS;
 
 /// Represents the functions that the guest exposes to the host.
@@ -51,6 +56,23 @@ impl GuestFunctionRegister {
     pub fn get(&self, function_name: &str) -> Option<&GuestFunctionDefinition> {
         self.guest_functions.get(function_name)
     }
+
+    /// Gets the signatures of all the registered guest functions, so that
+    /// they can be sent to the host.
+    pub fn guest_function_details(&self) -> GuestFunctionDetails {
+        GuestFunctionDetails::new(
+            self.guest_functions
+                .values()
+                .map(|gfd| {
+                    GuestFunctionSignature::new(
+                        gfd.function_name.clone(),
+                        gfd.parameter_types.clone(),
+                        gfd.return_type,
+                    )
+                })
+                .collect(),
+        )
+    }
 }
 
 pub fn register_function(function_definition: GuestFunctionDefinition) {
@@ -62,3 +84,20 @@ pub fn register_function(function_definition: GuestFunctionDefinition) {
         gfd.register(function_definition);
     }
 }
+
+/// Writes the signatures of the registered guest functions to the shared
+/// output buffer, where the host reads them once the guest has initialised.
+///
+/// If they don't fit, they aren't written, rather than the guest's
+/// initialisation failing. The host then just can't check guest function
+/// calls before making them.
+pub(crate) fn write_guest_function_details() {
+    #[allow(static_mut_refs)]
+    let register = unsafe { &REGISTERED_GUEST_FUNCTIONS };
+
+    let details = register.guest_function_details();
+    if let Ok(bytes) = Vec::<u8>::try_from(&details) {
+        // Checks that they fit before writing anything
+        let _ = push_shared_output_data(bytes);
+    }
+}
@@ -0,0 +1,172 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cmp::max;
+use core::mem::size_of;
+use core::ops::Deref;
+use core::ptr::{addr_of, addr_of_mut, null_mut, NonNull};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use buddy_system_allocator::{Heap, LockedHeap};
+use hyperlight_common::mem::GuestHeapStats;
+
+use crate::host_function_call::{outb_unchecked, OutBAction};
+use crate::{HEAP_ALLOCATOR, P_PEB};
+
+/// A `LockedHeap` that keeps track of the most bytes that have been
+/// allocated from it at once, and which asks the host to grow it when it
+/// runs out of memory, if the host has reserved memory for it to grow into
+pub struct ProfiledLockedHeap<const ORDER: usize> {
+    heap: LockedHeap<ORDER>,
+    high_water_mark: AtomicUsize,
+}
+
+impl<const ORDER: usize> ProfiledLockedHeap<ORDER> {
+    /// Creates an empty heap
+    pub const fn empty() -> Self {
+        Self {
+            heap: LockedHeap::empty(),
+            high_water_mark: AtomicUsize::new(0),
+        }
+    }
+
+    /// The most bytes that have been allocated from the heap at once
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark.load(Ordering::Relaxed)
+    }
+}
+
+impl<const ORDER: usize> Deref for ProfiledLockedHeap<ORDER> {
+    type Target = LockedHeap<ORDER>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.heap
+    }
+}
+
+unsafe impl<const ORDER: usize> GlobalAlloc for ProfiledLockedHeap<ORDER> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.heap.lock();
+        let ptr = loop {
+            match heap.alloc(layout) {
+                Ok(allocation) => break allocation.as_ptr(),
+                Err(()) if grow(&mut heap, &layout) => continue,
+                Err(()) => break null_mut(),
+            }
+        };
+        self.high_water_mark
+            .fetch_max(heap.stats_alloc_actual(), Ordering::Relaxed);
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap.dealloc(ptr, layout)
+    }
+}
+
+/// Ask the host to grow the heap by enough for `layout` to be allocated
+/// from it, and add the memory it grew into to `heap`. Returns whether it
+/// grew.
+fn grow<const ORDER: usize>(heap: &mut Heap<ORDER>, layout: &Layout) -> bool {
+    let Some(peb_ptr) = (unsafe { P_PEB }) else {
+        return false;
+    };
+    // the host writes to these during the outb, so they are accessed
+    // through raw pointers
+    let growth = unsafe { addr_of_mut!((*peb_ptr).guestheapGrowth) };
+    unsafe {
+        let grown_size = addr_of!((*growth).heapGrownSize).read_volatile();
+        if grown_size >= addr_of!((*growth).heapGrowthMaxSize).read_volatile() {
+            return false;
+        }
+
+        // the allocation needs a free block of the next power of two of its
+        // size, aligned to that size, which twice as much memory always has
+        let block_size = max(
+            max(layout.size().next_power_of_two(), layout.align()),
+            size_of::<usize>(),
+        );
+        addr_of_mut!((*growth).heapGrowthRequestSize).write_volatile(2 * block_size as u64);
+        outb_unchecked(OutBAction::GrowHeap as u16, 0);
+
+        let new_grown_size = addr_of!((*growth).heapGrownSize).read_volatile();
+        if new_grown_size <= grown_size {
+            return false;
+        }
+        let growth_buffer = addr_of!((*growth).heapGrowthBuffer).read_volatile() as usize;
+        heap.add_to_heap(
+            growth_buffer + grown_size as usize,
+            growth_buffer + new_grown_size as usize,
+        );
+    }
+    true
+}
+
+/// Count the free blocks in `heap` by allocating each of them, largest
+/// first so that none of them are split, and then freeing them again.
+fn count_free_blocks<const ORDER: usize>(heap: &mut Heap<ORDER>) -> usize {
+    let block_layout = |class: usize| Layout::from_size_align(1 << class, 1 << class).unwrap();
+    let min_class = size_of::<usize>().trailing_zeros() as usize;
+    let mut counts = [0usize; ORDER];
+
+    // the allocated blocks are linked through their first word
+    let mut blocks: *mut usize = null_mut();
+    for class in (min_class..ORDER).rev() {
+        while let Ok(block) = heap.alloc(block_layout(class)) {
+            let block = block.as_ptr() as *mut usize;
+            unsafe { block.write(blocks as usize) };
+            blocks = block;
+            counts[class] += 1;
+        }
+    }
+
+    // the smallest blocks were allocated last, so they are at the head
+    for (class, count) in counts.iter().enumerate().skip(min_class) {
+        for _ in 0..*count {
+            let block = unsafe { NonNull::new_unchecked(blocks as *mut u8) };
+            blocks = unsafe { blocks.read() } as *mut usize;
+            heap.dealloc(block, block_layout(class));
+        }
+    }
+
+    counts.iter().sum()
+}
+
+/// Report how much of the heap is in use to the host, through the PEB.
+///
+/// Counting the free blocks takes a pass over all of them, so they are only
+/// counted if `count_free_fragments` is set, or if the host has asked for
+/// them to be counted every time with `heapFreeFragmentsCounted`.
+pub(crate) fn write_heap_stats(count_free_fragments: bool) {
+    unsafe {
+        let Some(peb_ptr) = P_PEB else {
+            return;
+        };
+        let stats: *mut GuestHeapStats = addr_of_mut!((*peb_ptr).guestheapStats);
+        let count_free_fragments = count_free_fragments
+            || addr_of!((*stats).heapFreeFragmentsCounted).read_volatile() != 0;
+
+        let mut heap = HEAP_ALLOCATOR.lock();
+        addr_of_mut!((*stats).heapUsedSize).write_volatile(heap.stats_alloc_actual() as u64);
+        addr_of_mut!((*stats).heapHighWaterMark)
+            .write_volatile(HEAP_ALLOCATOR.high_water_mark() as u64);
+        if count_free_fragments {
+            addr_of_mut!((*stats).heapFreeFragments)
+                .write_volatile(count_free_blocks(&mut heap) as u64);
+        }
+    }
+}
@@ -14,7 +14,9 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use alloc::string::ToString;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::arch::global_asm;
 
@@ -27,6 +29,7 @@ use hyperlight_common::flatbuffer_wrappers::util::get_flatbuffer_result_from_int
 use hyperlight_common::mem::RunMode;
 
 use crate::error::{HyperlightGuestError, Result};
+use crate::function_types::SupportedReturnType;
 use crate::host_error::check_for_host_error;
 use crate::host_functions::validate_host_function_call;
 use crate::shared_input_data::try_pop_shared_input_data_into;
@@ -37,6 +40,9 @@ pub enum OutBAction {
     Log = 99,
     CallFunction = 101,
     Abort = 102,
+    ReadStream = 103,
+    WriteStream = 104,
+    GrowHeap = 105,
 }
 
 pub fn get_host_value_return_as_void() -> Result<()> {
@@ -112,8 +118,6 @@ pub fn get_host_value_return_as_ulong() -> Result<u64> {
     }
 }
 
-// TODO: Make this generic, return a Result<T, ErrorCode>
-
 pub fn get_host_value_return_as_vecbytes() -> Result<Vec<u8>> {
     let return_value = try_pop_shared_input_data_into::<ReturnValue>()
         .expect("Unable to deserialize return value from host");
@@ -129,8 +133,72 @@ pub fn get_host_value_return_as_vecbytes() -> Result<Vec<u8>> {
     }
 }
 
-// TODO: Make this generic, return a Result<T, ErrorCode> this should allow callers to call this function and get the result type they expect
-// without having to do the conversion themselves
+pub fn get_host_value_return_as_list() -> Result<Vec<ParameterValue>> {
+    let return_value = try_pop_shared_input_data_into::<ReturnValue>()
+        .expect("Unable to deserialize return value from host");
+
+    // check that return value is a List and return
+    if let ReturnValue::List(v) = return_value {
+        Ok(v)
+    } else {
+        Err(HyperlightGuestError::new(
+            ErrorCode::GuestError,
+            "Host return value was not a List as expected".to_string(),
+        ))
+    }
+}
+
+pub fn get_host_value_return_as_optional() -> Result<Option<Box<ParameterValue>>> {
+    let return_value = try_pop_shared_input_data_into::<ReturnValue>()
+        .expect("Unable to deserialize return value from host");
+
+    // check that return value is an Optional and return
+    if let ReturnValue::Optional(v) = return_value {
+        Ok(v)
+    } else {
+        Err(HyperlightGuestError::new(
+            ErrorCode::GuestError,
+            "Host return value was not an Optional as expected".to_string(),
+        ))
+    }
+}
+
+pub fn get_host_value_return_as_map() -> Result<BTreeMap<String, ParameterValue>> {
+    let return_value = try_pop_shared_input_data_into::<ReturnValue>()
+        .expect("Unable to deserialize return value from host");
+
+    // check that return value is a Map and return
+    if let ReturnValue::Map(m) = return_value {
+        Ok(m)
+    } else {
+        Err(HyperlightGuestError::new(
+            ErrorCode::GuestError,
+            "Host return value was not a Map as expected".to_string(),
+        ))
+    }
+}
+
+pub fn get_host_value_return_as_record() -> Result<Vec<(String, ParameterValue)>> {
+    let return_value = try_pop_shared_input_data_into::<ReturnValue>()
+        .expect("Unable to deserialize return value from host");
+
+    // check that return value is a Record and return
+    if let ReturnValue::Record(r) = return_value {
+        Ok(r)
+    } else {
+        Err(HyperlightGuestError::new(
+            ErrorCode::GuestError,
+            "Host return value was not a Record as expected".to_string(),
+        ))
+    }
+}
+
+/// Get the value returned by the last host function call as a `T`.
+pub fn get_host_return_value<T: SupportedReturnType>() -> Result<T> {
+    let return_value = try_pop_shared_input_data_into::<ReturnValue>()
+        .expect("Unable to deserialize return value from host");
+    T::get_inner(return_value)
+}
 
 pub fn call_host_function(
     function_name: &str,
@@ -158,6 +226,13 @@ pub fn call_host_function(
 }
 
 pub fn outb(port: u16, value: u8) {
+    outb_unchecked(port, value);
+    check_for_host_error();
+}
+
+/// Like `outb`, but doesn't check for an error in the host afterwards,
+/// which allocates, so this can be used while the heap is locked.
+pub(crate) fn outb_unchecked(port: u16, value: u8) {
     unsafe {
         match RUNNING_MODE {
             RunMode::Hypervisor => {
@@ -178,8 +253,6 @@ pub fn outb(port: u16, value: u8) {
                 panic!("Tried to call outb in invalid runmode");
             }
         }
-
-        check_for_host_error();
     }
 }
 
@@ -0,0 +1,63 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Accessing host memory that the host maps into the guest for the duration
+//! of a guest function call with
+//! `MultiUseSandbox::call_guest_function_with_mapped_regions`.
+//!
+//! The host passes the address and length of each region as two `u64`
+//! arguments after the guest function's own arguments.
+
+use core::arch::asm;
+use core::slice;
+
+use hyperlight_common::mem::RunMode;
+
+use crate::RUNNING_MODE;
+
+/// The size of the pages that the host maps regions with.
+const HOST_MAPPED_PAGE_SIZE: usize = 0x200000;
+
+/// Get a region of host memory that is mapped into the guest, from the
+/// address and length that the host passed for it.
+///
+/// # Safety
+///
+/// `addr` and `len` must be the arguments that the host passed for a region
+/// in the current guest function call, and the slice must not be used after
+/// the call returns.
+pub unsafe fn host_mapped_region<'a>(addr: u64, len: u64) -> &'a [u8] {
+    slice::from_raw_parts(addr as *const u8, len as usize)
+}
+
+/// Get a region of host memory that is mapped into the guest read-write,
+/// from the address and length that the host passed for it.
+///
+/// An earlier call may have mapped a read-only region at the same address,
+/// so any translations of it that are still cached are invalidated first.
+///
+/// # Safety
+///
+/// As for `host_mapped_region`, and the host must have mapped the region
+/// read-write.
+pub unsafe fn host_mapped_region_mut<'a>(addr: u64, len: u64) -> &'a mut [u8] {
+    if RUNNING_MODE == RunMode::Hypervisor {
+        for page in (addr..addr + len).step_by(HOST_MAPPED_PAGE_SIZE) {
+            asm!("invlpg [{}]", in(reg) page, options(nostack, preserves_flags));
+        }
+    }
+    slice::from_raw_parts_mut(addr as *mut u8, len as usize)
+}
@@ -20,8 +20,8 @@ use alloc::string::ToString;
 use core::hint::unreachable_unchecked;
 use core::ptr::copy_nonoverlapping;
 
-use buddy_system_allocator::LockedHeap;
 use guest_function_register::GuestFunctionRegister;
+use heap::ProfiledLockedHeap;
 use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
 use hyperlight_common::mem::{HyperlightPEB, RunMode};
 
@@ -30,20 +30,25 @@ extern crate alloc;
 
 // Modules
 pub mod entrypoint;
+pub mod host_mapped_region;
 pub mod shared_input_data;
 pub mod shared_output_data;
+pub mod stream;
 
 pub mod guest_error;
 pub mod guest_function_call;
 pub mod guest_function_definition;
 pub mod guest_function_register;
 
+pub mod function_types;
+
 pub mod host_error;
 pub mod host_function_call;
 pub mod host_functions;
 
 pub mod alloca;
 pub(crate) mod guest_logger;
+pub mod heap;
 pub mod memory;
 pub mod print;
 pub(crate) mod security_check;
@@ -84,7 +89,7 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
 
 // Globals
 #[global_allocator]
-pub(crate) static HEAP_ALLOCATOR: LockedHeap<32> = LockedHeap::<32>::empty();
+pub(crate) static HEAP_ALLOCATOR: ProfiledLockedHeap<32> = ProfiledLockedHeap::<32>::empty();
 
 ///cbindgen:ignore
 #[no_mangle]
@@ -84,3 +84,38 @@ pub fn push_shared_output_data(data: Vec<u8>) -> Result<()> {
 
     Ok(())
 }
+
+// Returns the size of the largest element that can be pushed to the shared output data buffer
+pub fn shared_output_data_space_available() -> Result<usize> {
+    let peb_ptr = unsafe { P_PEB.unwrap() };
+    let shared_buffer_size = unsafe { (*peb_ptr).outputdata.outputDataSize as usize };
+    let odb = unsafe {
+        from_raw_parts_mut(
+            (*peb_ptr).outputdata.outputDataBuffer as *mut u8,
+            shared_buffer_size,
+        )
+    };
+
+    if odb.is_empty() {
+        return Err(HyperlightGuestError::new(
+            ErrorCode::GuestError,
+            "Got a 0-size buffer in shared_output_data_space_available".to_string(),
+        ));
+    }
+
+    let stack_ptr_rel: usize =
+        usize::from_le_bytes(odb[..8].try_into().expect("Shared output buffer too small"));
+
+    if stack_ptr_rel > shared_buffer_size || stack_ptr_rel < 8 {
+        return Err(HyperlightGuestError::new(
+            ErrorCode::GuestError,
+            format!(
+                "Invalid stack pointer: {} in shared_output_data_space_available",
+                stack_ptr_rel
+            ),
+        ));
+    }
+
+    // every element is followed by a pointer pointing back to it
+    Ok((shared_buffer_size - stack_ptr_rel).saturating_sub(8))
+}
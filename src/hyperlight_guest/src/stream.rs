@@ -0,0 +1,141 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Reading and writing the streams that the host passes to a guest function
+//! call with `MultiUseSandbox::call_guest_function_with_streams`.
+//!
+//! Streams are not limited by the size of the input and output data
+//! buffers: they are passed between the host and the guest a chunk at a
+//! time, with an exit to the host for every chunk.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
+use hyperlight_common::stream::{StreamChunk, STREAM_CHUNK_PREFIX_SIZE};
+
+use crate::error::{HyperlightGuestError, Result};
+use crate::host_function_call::{outb, OutBAction};
+use crate::shared_input_data::try_pop_shared_input_data_into;
+use crate::shared_output_data::{push_shared_output_data, shared_output_data_space_available};
+
+/// Reads the input stream of the current guest function call.
+///
+/// Each chunk that the host sends is kept until it has been read, so small
+/// reads don't each need an exit to the host.
+#[derive(Debug, Default)]
+pub struct StreamReader {
+    chunk: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl StreamReader {
+    pub const fn new() -> Self {
+        Self {
+            chunk: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Read bytes from the input stream into `buf`, returning how many were
+    /// read. `Ok(0)` is returned at the end of the stream.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.position == self.chunk.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.read_chunk()?;
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+
+    /// Read the rest of the input stream, appending it to `buf`, and return
+    /// how many bytes were read.
+    pub fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        loop {
+            buf.extend_from_slice(&self.chunk[self.position..]);
+            self.position = self.chunk.len();
+            if self.finished {
+                return Ok(buf.len() - start);
+            }
+            self.read_chunk()?;
+        }
+    }
+
+    fn read_chunk(&mut self) -> Result<()> {
+        outb(OutBAction::ReadStream as u16, 0);
+        let chunk = try_pop_shared_input_data_into::<StreamChunk>()?;
+        self.finished = chunk.0.is_empty();
+        self.chunk = chunk.0;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+/// Writes the output stream of the current guest function call.
+///
+/// Writes are not buffered: each call to `write` sends a chunk to the host,
+/// so writing in large pieces needs fewer exits.
+#[derive(Debug, Default)]
+pub struct StreamWriter {}
+
+impl StreamWriter {
+    pub const fn new() -> Self {
+        Self {}
+    }
+
+    /// Write as much of `buf` to the output stream as fits in a single
+    /// chunk, returning how many bytes were written.
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let max_len =
+            shared_output_data_space_available()?.saturating_sub(STREAM_CHUNK_PREFIX_SIZE);
+        if max_len == 0 {
+            return Err(HyperlightGuestError::new(
+                ErrorCode::GuestError,
+                "Not enough space in shared output buffer to write to the output stream"
+                    .to_string(),
+            ));
+        }
+
+        let len = buf.len().min(max_len);
+        let chunk = StreamChunk(buf[..len].to_vec());
+        push_shared_output_data(Vec::<u8>::try_from(&chunk)?)?;
+        outb(OutBAction::WriteStream as u16, 0);
+        Ok(len)
+    }
+
+    /// Write all of `buf` to the output stream.
+    pub fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            let len = self.write(buf)?;
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use alloc::boxed::Box;
+use alloc::ffi::CString;
 use alloc::slice;
 use alloc::vec::Vec;
 use core::ffi::{c_char, CStr};
@@ -8,6 +9,7 @@ use hyperlight_common::flatbuffer_wrappers::function_call::FunctionCall;
 use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterType, ReturnType};
 use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
 use hyperlight_guest::error::{HyperlightGuestError, Result};
+use hyperlight_guest::guest_error::setError;
 use hyperlight_guest::guest_function_definition::GuestFunctionDefinition;
 use hyperlight_guest::guest_function_register::GuestFunctionRegister;
 use hyperlight_guest::host_function_call::call_host_function;
@@ -90,7 +92,15 @@ pub extern "C" fn hl_register_function_definition(
 /// The caller is responsible for freeing the memory associated with given `FfiFunctionCall`.
 #[no_mangle]
 pub extern "C" fn hl_call_host_function(function_call: &FfiFunctionCall) {
-    let parameters = unsafe { function_call.copy_parameters() };
+    let parameters = match unsafe { function_call.copy_parameters() } {
+        Ok(parameters) => parameters,
+        Err(e) => {
+            // `setError` halts the guest, returning the error to the host
+            let message = CString::new(e.message).unwrap_or_default();
+            unsafe { setError(e.kind.into(), message.as_ptr()) };
+            return;
+        }
+    };
     let func_name = unsafe { function_call.copy_function_name() };
     let return_type = unsafe { function_call.copy_return_type() };
     let _ = call_host_function(&func_name, Some(parameters), return_type);
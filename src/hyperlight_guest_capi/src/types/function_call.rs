@@ -31,8 +31,8 @@ impl FfiFunctionCall {
             Some(p) => {
                 let parameters: Vec<FfiParameter> = p
                     .into_iter()
-                    .map(|param| FfiParameter::from_parameter_value(param).unwrap())
-                    .collect();
+                    .map(FfiParameter::from_parameter_value)
+                    .collect::<Result<_>>()?;
                 let boxed = parameters.into_boxed_slice();
                 let parameters_len = boxed.len();
                 let leaked_param_vec = Box::into_raw(boxed);
@@ -49,10 +49,11 @@ impl FfiFunctionCall {
         })
     }
 
-    /// Copies the parameters of `self` into a new `Vec<ParameterValue>`.
+    /// Copies the parameters of `self` into a new `Vec<ParameterValue>`,
+    /// failing if any of them has a type the C API doesn't support.
     /// # Safety
     /// `self` must be an unmodified version of what `from_function_call` returned.
-    pub unsafe fn copy_parameters(&self) -> Vec<ParameterValue> {
+    pub unsafe fn copy_parameters(&self) -> Result<Vec<ParameterValue>> {
         let slice = unsafe { slice::from_raw_parts(self.parameters, self.parameters_len) };
         slice
             .iter()
@@ -1,8 +1,10 @@
 use alloc::ffi::CString;
+use alloc::format;
 use core::ffi::{c_char, CStr};
 
 use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterType, ParameterValue};
-use hyperlight_guest::error::Result;
+use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
+use hyperlight_guest::error::{HyperlightGuestError, Result};
 
 use crate::types::FfiVec;
 
@@ -55,15 +57,25 @@ impl FfiParameter {
                     FfiParameterValue { VecBytes: leaked },
                 )
             }
+            other @ (ParameterValue::List(_)
+            | ParameterValue::Optional(_)
+            | ParameterValue::Map(_)
+            | ParameterValue::Record(_)) => {
+                return Err(HyperlightGuestError::new(
+                    ErrorCode::UnsupportedParameterType,
+                    format!("Unsupported parameter value for the C API: {:?}", other),
+                ));
+            }
         };
         Ok(FfiParameter { tag, value: union })
     }
 
-    /// Copies self into a new `ParameterValue`.
+    /// Copies self into a new `ParameterValue`, failing if `self` has a tag
+    /// that the C API doesn't support.
     /// # Safety
     /// `self` must be an unmodified version of what `from_parameter_value` returned.
-    pub unsafe fn copy_to_parameter_value(&self) -> ParameterValue {
-        match self.tag {
+    pub unsafe fn copy_to_parameter_value(&self) -> Result<ParameterValue> {
+        Ok(match self.tag {
             ParameterType::Int => ParameterValue::Int(unsafe { self.value.Int }),
             ParameterType::UInt => ParameterValue::UInt(unsafe { self.value.UInt }),
             ParameterType::Long => ParameterValue::Long(unsafe { self.value.Long }),
@@ -79,7 +91,18 @@ impl FfiParameter {
             ParameterType::VecBytes => {
                 ParameterValue::VecBytes(unsafe { self.value.VecBytes.copy_to_vec() })
             }
-        }
+            // `from_parameter_value` never creates an `FfiParameter` with these
+            // tags, but a C caller can
+            ParameterType::List
+            | ParameterType::Optional
+            | ParameterType::Map
+            | ParameterType::Record => {
+                return Err(HyperlightGuestError::new(
+                    ErrorCode::UnsupportedParameterType,
+                    format!("Unsupported parameter type for the C API: {:?}", self.tag),
+                ));
+            }
+        })
     }
 }
 
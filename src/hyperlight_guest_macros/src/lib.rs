@@ -0,0 +1,369 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Procedural macros for writing Hyperlight guests in Rust.
+//!
+//! [`macro@guest_function`] exposes a function to the host, and
+//! [`macro@host_import`] declares a typed stub for calling a host function:
+//!
+//! ```ignore
+//! use alloc::string::String;
+//!
+//! use hyperlight_guest::error::Result;
+//! use hyperlight_guest_macros::{guest_function, host_import};
+//!
+//! #[host_import("HostPrint")]
+//! fn host_print(message: String) -> Result<i32>;
+//!
+//! #[guest_function("PrintOutput")]
+//! fn print_output(message: String) -> Result<i32> {
+//!     host_print(message)
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn hyperlight_main() {
+//!     print_output::register();
+//! }
+//! ```
+//!
+//! Parameters and return values can be of any type that implements
+//! `hyperlight_guest::function_types::SupportedParameterType` or
+//! `SupportedReturnType` respectively. The generated code refers to the
+//! `hyperlight_guest` crate, so guests using these macros must depend on it
+//! directly.
+
+#![deny(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, FnArg, ForeignItemFn, GenericArgument, ItemFn, LitStr, Pat, PathArguments,
+    Signature, Type,
+};
+
+/// Exposes a function to the host as a guest function.
+///
+/// The function's parameters must implement `SupportedParameterType`, and it
+/// must return a `Result<T>` where `T` implements `SupportedReturnType`. The
+/// name the host calls it by defaults to the name of the function, and can
+/// be set with `#[guest_function("Name")]`.
+///
+/// Alongside the function, this generates a module of the same name with:
+///
+/// - `definition()`, which returns the function's `GuestFunctionDefinition`,
+///   whose function pointer decodes the arguments of a call, calls the
+///   function, and encodes its return value.
+/// - `register()`, which registers that definition, and is typically called
+///   from `hyperlight_main`.
+#[proc_macro_attribute]
+pub fn guest_function(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let name = parse_macro_input!(attr as FunctionName);
+    let function = parse_macro_input!(item as ItemFn);
+    expand_guest_function(name, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements a function declaration without a body as a call to a host
+/// function.
+///
+/// The function's parameters must implement `SupportedParameterType`, and it
+/// must return a `Result<T>` where `T` implements `SupportedReturnType`. The
+/// name of the host function defaults to the name of the function, and can
+/// be set with `#[host_import("Name")]`.
+#[proc_macro_attribute]
+pub fn host_import(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let name = parse_macro_input!(attr as FunctionName);
+    let function = parse_macro_input!(item as ForeignItemFn);
+    expand_host_import(name, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The optional function name given as the argument of the attributes.
+struct FunctionName(Option<LitStr>);
+
+impl FunctionName {
+    fn value_or(&self, sig: &Signature) -> String {
+        match &self.0 {
+            Some(name) => name.value(),
+            None => sig.ident.to_string(),
+        }
+    }
+}
+
+impl Parse for FunctionName {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            Ok(Self(None))
+        } else {
+            Ok(Self(Some(input.parse()?)))
+        }
+    }
+}
+
+/// Get the parameter types of `sig`, and the `T` in its `Result<T>` return
+/// type, rejecting signatures that can't be called through Hyperlight.
+fn signature_types(sig: &Signature) -> syn::Result<(Vec<&Type>, &Type)> {
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(syn::Error::new(
+            asyncness.span(),
+            "Hyperlight functions cannot be async",
+        ));
+    }
+    if let Some(unsafety) = &sig.unsafety {
+        return Err(syn::Error::new(
+            unsafety.span(),
+            "Hyperlight functions cannot be unsafe",
+        ));
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(syn::Error::new(
+            sig.generics.span(),
+            "Hyperlight functions cannot be generic",
+        ));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(syn::Error::new(
+            variadic.span(),
+            "Hyperlight functions cannot be variadic",
+        ));
+    }
+
+    let param_types = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(pat_type) => Ok(&*pat_type.ty),
+            FnArg::Receiver(receiver) => Err(syn::Error::new(
+                receiver.span(),
+                "Hyperlight functions cannot take `self`",
+            )),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let return_type = match &sig.output {
+        syn::ReturnType::Type(_, ty) => result_ok_type(ty),
+        syn::ReturnType::Default => None,
+    }
+    .ok_or_else(|| {
+        syn::Error::new(
+            sig.output.span(),
+            "Hyperlight functions must return `Result<T>`",
+        )
+    })?;
+
+    Ok((param_types, return_type))
+}
+
+/// Get `T` from a `Result<T>` or `Result<T, E>` type.
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+fn expand_guest_function(name: FunctionName, function: ItemFn) -> syn::Result<TokenStream2> {
+    let (param_types, return_type) = signature_types(&function.sig)?;
+    let vis = &function.vis;
+    let ident = &function.sig.ident;
+    let name = name.value_or(&function.sig);
+    let indices = 0..param_types.len();
+    let doc = format!(
+        "Registration of `{}` as the guest function `{}`.",
+        ident, name
+    );
+
+    Ok(quote! {
+        #function
+
+        #[doc = #doc]
+        #vis mod #ident {
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// Get the definition of the guest function.
+            pub fn definition() -> ::hyperlight_guest::guest_function_definition::GuestFunctionDefinition {
+                let call: ::hyperlight_guest::guest_function_call::GuestFunc = |function_call| {
+                    let result = super::#ident(#(
+                        ::hyperlight_guest::function_types::get_parameter::<#param_types>(
+                            function_call,
+                            #indices,
+                        )?
+                    ),*)?;
+                    ::hyperlight_guest::function_types::get_flatbuffer_result(result)
+                };
+
+                ::hyperlight_guest::guest_function_definition::GuestFunctionDefinition::new(
+                    #name.into(),
+                    [#(
+                        <#param_types as ::hyperlight_guest::function_types::SupportedParameterType>::get_hyperlight_type()
+                    ),*].into(),
+                    <#return_type as ::hyperlight_guest::function_types::SupportedReturnType>::get_hyperlight_type(),
+                    call as usize as i64,
+                )
+            }
+
+            /// Register the guest function, so that the host can call it.
+            pub fn register() {
+                ::hyperlight_guest::guest_function_register::register_function(definition());
+            }
+        }
+    })
+}
+
+fn expand_host_import(name: FunctionName, function: ForeignItemFn) -> syn::Result<TokenStream2> {
+    let (_, return_type) = signature_types(&function.sig)?;
+    let ForeignItemFn {
+        attrs, vis, sig, ..
+    } = &function;
+    let name = name.value_or(sig);
+
+    let args = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident)
+                    if pat_ident.by_ref.is_none() && pat_ident.subpat.is_none() =>
+                {
+                    Ok(&pat_ident.ident)
+                }
+                pat => Err(syn::Error::new(
+                    pat.span(),
+                    "host function parameters must be plain identifiers",
+                )),
+            },
+            // already rejected by `signature_types`
+            FnArg::Receiver(receiver) => Err(syn::Error::new(receiver.span(), "unexpected `self`")),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let parameters = if args.is_empty() {
+        quote!(::core::option::Option::None)
+    } else {
+        quote! {
+            ::core::option::Option::Some([#(
+                ::hyperlight_guest::function_types::SupportedParameterType::get_hyperlight_value(&#args)
+            ),*].into())
+        }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            ::hyperlight_guest::host_function_call::call_host_function(
+                #name,
+                #parameters,
+                <#return_type as ::hyperlight_guest::function_types::SupportedReturnType>::get_hyperlight_type(),
+            )?;
+            ::hyperlight_guest::host_function_call::get_host_return_value::<#return_type>()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{parse_quote, ForeignItemFn, ItemFn};
+
+    use super::{expand_guest_function, expand_host_import, FunctionName};
+
+    #[test]
+    fn guest_function_uses_given_name() {
+        let function: ItemFn = parse_quote! {
+            fn echo(value: String) -> Result<String> {
+                Ok(value)
+            }
+        };
+        let name = FunctionName(Some(parse_quote!("Echo")));
+        let tokens = expand_guest_function(name, function).unwrap().to_string();
+        assert!(tokens.contains("mod echo"), "{}", tokens);
+        assert!(tokens.contains("\"Echo\""), "{}", tokens);
+    }
+
+    #[test]
+    fn guest_function_name_defaults_to_function_name() {
+        let function: ItemFn = parse_quote! {
+            fn add(a: i32, b: i32) -> Result<i32> {
+                Ok(a + b)
+            }
+        };
+        let tokens = expand_guest_function(FunctionName(None), function)
+            .unwrap()
+            .to_string();
+        assert!(tokens.contains("\"add\""), "{}", tokens);
+    }
+
+    #[test]
+    fn guest_function_rejects_unsupported_signatures() {
+        let functions: [ItemFn; 4] = [
+            parse_quote!(
+                fn no_result() -> i32 {
+                    0
+                }
+            ),
+            parse_quote!(
+                fn no_return() {}
+            ),
+            parse_quote!(
+                fn generic<T>(value: T) -> Result<T> {
+                    Ok(value)
+                }
+            ),
+            parse_quote!(
+                async fn asynchronous() -> Result<()> {
+                    Ok(())
+                }
+            ),
+        ];
+        for function in functions {
+            assert!(expand_guest_function(FunctionName(None), function).is_err());
+        }
+    }
+
+    #[test]
+    fn host_import_passes_arguments() {
+        let function: ForeignItemFn = parse_quote! {
+            fn host_print(message: String) -> Result<i32>;
+        };
+        let name = FunctionName(Some(parse_quote!("HostPrint")));
+        let tokens = expand_host_import(name, function).unwrap().to_string();
+        assert!(tokens.contains("\"HostPrint\""), "{}", tokens);
+        assert!(tokens.contains("& message"), "{}", tokens);
+    }
+
+    #[test]
+    fn host_import_rejects_patterns() {
+        let function: ForeignItemFn = parse_quote! {
+            fn host_add((a, b): (i32, i32)) -> Result<i32>;
+        };
+        assert!(expand_host_import(FunctionName(None), function).is_err());
+    }
+}
@@ -44,7 +44,7 @@ fn guest_call_benchmark(c: &mut Criterion) {
 
         b.iter(|| {
             call_ctx
-                .call(
+                .call_guest_function_by_name(
                     "Echo",
                     ReturnType::Int,
                     Some(vec![ParameterValue::String("hello\n".to_string())]),
@@ -89,7 +89,7 @@ fn guest_call_benchmark(c: &mut Criterion) {
 
         b.iter(|| {
             call_ctx
-                .call(
+                .call_guest_function_by_name(
                     "Add",
                     ReturnType::Int,
                     Some(vec![ParameterValue::Int(1), ParameterValue::Int(41)]),
@@ -50,7 +50,7 @@ fn main() {
 fn do_calls(mut ctx: MultiUseGuestCallContext) -> Result<MultiUseSandbox> {
     {
         let res1: i32 = {
-            let rv = ctx.call(
+            let rv = ctx.call_guest_function_by_name(
                 "StackAllocate",
                 ReturnType::Int,
                 Some(vec![ParameterValue::Int(1)]),
@@ -62,7 +62,7 @@ fn do_calls(mut ctx: MultiUseGuestCallContext) -> Result<MultiUseSandbox> {
     }
     {
         let res2: i32 = {
-            let rv = ctx.call(
+            let rv = ctx.call_guest_function_by_name(
                 "CallMalloc",
                 ReturnType::Int,
                 Some(vec![ParameterValue::Int(200)]),
@@ -110,7 +110,7 @@ fn main() -> Result<()> {
     for _ in 0..5 {
         let mut ctx = multiuse_sandbox.new_call_context();
 
-        let result = ctx.call("Spin", ReturnType::Void, None);
+        let result = ctx.call_guest_function_by_name("Spin", ReturnType::Void, None);
         assert!(result.is_err());
         let result = ctx.finish();
         assert!(result.is_ok());
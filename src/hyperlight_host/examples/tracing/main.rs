@@ -141,7 +141,7 @@ fn run_example() -> Result<()> {
         let _entered = span.enter();
         let mut ctx = multiuse_sandbox.new_call_context();
 
-        let result = ctx.call("Spin", ReturnType::Void, None);
+        let result = ctx.call_guest_function_by_name("Spin", ReturnType::Void, None);
         assert!(result.is_err());
         let result = ctx.finish();
         assert!(result.is_ok());
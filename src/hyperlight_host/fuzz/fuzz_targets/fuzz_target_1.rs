@@ -39,7 +39,7 @@ fuzz_target!(|data: &[u8]| {
     let len = msg.len() as i32;
     let mut ctx = mu_sbox.new_call_context();
     let result = ctx
-        .call(
+        .call_guest_function_by_name(
             "PrintOutput",
             ReturnType::Int,
             Some(vec![ParameterValue::String(msg.clone())]),
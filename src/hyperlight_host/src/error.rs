@@ -58,6 +58,10 @@ pub enum HyperlightError {
     #[error("Couldnt add offset to base address. Offset: {0}, Base Address: {1}")]
     CheckedAddOverflow(u64, u64),
 
+    /// No checkpoint with the given name was taken
+    #[error("No checkpoint named {0:?} was found")]
+    CheckpointNotFound(String),
+
     /// Cross beam channel receive error
     #[error("{0:?}")]
     #[cfg(target_os = "windows")]
@@ -125,6 +129,15 @@ pub enum HyperlightError {
     #[error("Guest call is already in progress")]
     GuestFunctionCallAlreadyInProgress(),
 
+    /// A guest function was called by the host but the guest did not register it.
+    #[error("GuestFunction {0} was not found")]
+    GuestFunctionNotFound(String),
+
+    /// A guest function was called with a signature that does not match the
+    /// one the guest registered it with.
+    #[error("GuestFunction {0} has signature {1} but was called as {2}")]
+    GuestFunctionSignatureMismatch(String, String, String),
+
     /// The given type is not supported by the guest interface.
     #[error("Unsupported type: {0}")]
     GuestInterfaceUnsupportedType(String),
@@ -137,6 +150,23 @@ pub enum HyperlightError {
     #[error("HostFunction {0} was not found")]
     HostFunctionNotFound(String),
 
+    /// A host function was registered to run in a helper process without
+    /// first being added to the table of those that can.
+    #[error("Host function {0} was not registered to run in helper processes")]
+    #[cfg(target_os = "linux")]
+    HostFunctionNotRegisteredForHelperProcesses(String),
+
+    /// A host function panicked. The guest that called it gets this as an
+    /// error, rather than the panic tearing down the sandbox.
+    #[error("Host function {0} panicked: {1}")]
+    HostFunctionPanicked(String, String),
+
+    /// The helper process a host function runs in crashed or exited, so the
+    /// host function can't be called any more.
+    #[error("The helper process for host function {0} exited: {1}")]
+    #[cfg(target_os = "linux")]
+    HostFunctionProcessExited(String, String),
+
     /// An attempt to communicate with or from the Hypervisor Handler thread failed
     /// (i.e., usually a failure call to `.send()` or `.recv()` on a message passing
     /// channel)
@@ -165,6 +195,14 @@ pub enum HyperlightError {
     #[error("The flatbuffer is invalid")]
     InvalidFlatBuffer(#[from] InvalidFlatbuffer),
 
+    /// One or more problems were found when validating a sandbox configuration
+    #[error("Invalid sandbox configuration: {}", .0.join("; "))]
+    InvalidSandboxConfiguration(Vec<String>),
+
+    /// A sandbox snapshot file could not be loaded
+    #[error("Invalid snapshot file: {0}")]
+    InvalidSnapshotFile(String),
+
     /// Conversion of str to Json failed
     #[error("Conversion of str data to json failed")]
     JsonConversionFailure(#[from] serde_json::Error),
@@ -211,6 +249,11 @@ pub enum HyperlightError {
     #[cfg(mshv)]
     MSHVError(#[from] mshv_ioctls::MshvError),
 
+    /// A host function called back into the guest more deeply than the
+    /// sandbox allows
+    #[error("Guest function calls from host functions can't be nested more than {0} deep")]
+    NestedGuestCallDepthExceeded(u8),
+
     /// No Hypervisor was found for Sandbox.
     #[error("No Hypervisor was found for Sandbox")]
     NoHypervisorFound(),
@@ -251,6 +294,10 @@ pub enum HyperlightError {
     #[error("Failed To Convert Return Value {0:?} to {1:?}")]
     ReturnValueConversionFailure(ReturnValue, &'static str),
 
+    /// No sandbox became available in a `SandboxPool` before the checkout timed out
+    #[error("Timed out waiting for a sandbox from the pool")]
+    SandboxPoolCheckoutTimedOut(),
+
     /// Stack overflow detected in guest
     #[error("Stack overflow detected")]
     StackOverflow(),
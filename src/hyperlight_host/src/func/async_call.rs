@@ -0,0 +1,359 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Instant;
+
+use hyperlight_common::flatbuffer_wrappers::function_types::{
+    ParameterValue, ReturnType, ReturnValue,
+};
+
+use super::guest_dispatch::{finish_function_call_on_guest, write_guest_function_call};
+use crate::hypervisor::hypervisor_handler::{HypervisorHandler, HypervisorHandlerAction};
+use crate::sandbox::WrapperGetter;
+use crate::{HyperlightError, MultiUseSandbox, Result};
+
+/// A `Future` that resolves to the result of a guest function call made with
+/// `MultiUseSandbox::call_guest_function_async`.
+///
+/// The guest function runs on the sandbox's Hypervisor Handler thread, so
+/// polling this future never blocks waiting for the guest. The future is
+/// woken when the Hypervisor Handler finishes the call, or when the
+/// sandbox's `max_execution_time` elapses, in which case the guest execution
+/// is cancelled exactly as it would be for a synchronous call.
+///
+/// If the future is dropped before it completes, the running guest function
+/// is cancelled with `terminate_execution` and the sandbox's state is
+/// restored, so the `MultiUseSandbox` can be used again.
+#[must_use = "futures do nothing unless polled"]
+pub struct GuestFunctionCallFuture<'a> {
+    sandbox: &'a mut MultiUseSandbox,
+    state: CallState,
+}
+
+enum CallState {
+    NotStarted {
+        function_name: String,
+        return_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+    },
+    Running {
+        deadline: Instant,
+        timer: Option<DeadlineTimer>,
+    },
+    Done,
+}
+
+/// A thread that wakes the task waiting on a guest function call once the
+/// call's deadline has passed, so that the call can be cancelled.
+struct DeadlineTimer {
+    cancelled: Arc<AtomicBool>,
+    thread: thread::Thread,
+}
+
+impl DeadlineTimer {
+    fn start(hv_handler: HypervisorHandler, deadline: Instant) -> Result<Self> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancelled_clone = cancelled.clone();
+        let join_handle = thread::Builder::new()
+            .name("Guest Call Deadline Timer".to_string())
+            .spawn(move || {
+                loop {
+                    if cancelled_clone.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+                hv_handler.wake();
+            })?;
+
+        Ok(Self {
+            cancelled,
+            thread: join_handle.thread().clone(),
+        })
+    }
+}
+
+impl Drop for DeadlineTimer {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.thread.unpark();
+    }
+}
+
+impl<'a> GuestFunctionCallFuture<'a> {
+    pub(crate) fn new(
+        sandbox: &'a mut MultiUseSandbox,
+        function_name: &str,
+        return_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+    ) -> Self {
+        Self {
+            sandbox,
+            state: CallState::NotStarted {
+                function_name: function_name.to_string(),
+                return_type,
+                args,
+            },
+        }
+    }
+
+    /// Write the function call into the guest's memory and hand it to the
+    /// Hypervisor Handler, without waiting for it to finish.
+    fn start(
+        &mut self,
+        function_name: &str,
+        return_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+    ) -> Result<Instant> {
+        write_guest_function_call(self.sandbox, function_name, return_type, args)?;
+
+        let mut hv_handler = self.sandbox.get_hv_handler().clone();
+        hv_handler.send_hypervisor_handler_action(
+            HypervisorHandlerAction::DispatchCallFromHost(function_name.to_string()),
+        )?;
+
+        Ok(Instant::now() + hv_handler.max_exec_time())
+    }
+
+    /// Read the result of a finished (or timed out) guest function call and
+    /// reset the sandbox's state, as `call_guest_function_by_name` does.
+    fn finish(&mut self, dispatch_res: Result<()>) -> Result<ReturnValue> {
+        self.state = CallState::Done;
+        let res = finish_function_call_on_guest(self.sandbox, dispatch_res)?;
+        self.sandbox.restore_state()?;
+        Ok(res)
+    }
+}
+
+impl Future for GuestFunctionCallFuture<'_> {
+    type Output = Result<ReturnValue>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let CallState::NotStarted { .. } = this.state {
+            let CallState::NotStarted {
+                function_name,
+                return_type,
+                args,
+            } = std::mem::replace(&mut this.state, CallState::Done)
+            else {
+                unreachable!()
+            };
+            match this.start(&function_name, return_type, args) {
+                Ok(deadline) => {
+                    this.state = CallState::Running {
+                        deadline,
+                        timer: None,
+                    }
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+
+        let CallState::Running { deadline, timer } = &mut this.state else {
+            return Poll::Ready(Err(HyperlightError::Error(
+                "GuestFunctionCallFuture polled after completion".to_string(),
+            )));
+        };
+
+        let hv_handler = this.sandbox.get_hv_handler().clone();
+        if let Some(dispatch_res) = hv_handler.poll_handler_msg(cx.waker()) {
+            return Poll::Ready(this.finish(dispatch_res));
+        }
+
+        if hv_handler.is_interrupted_while_running() {
+            hv_handler.clear_waker();
+            return Poll::Ready(this.finish(Err(
+                HyperlightError::HypervisorHandlerMessageReceiveTimedout(),
+            )));
+        }
+
+        if Instant::now() >= *deadline {
+            hv_handler.clear_waker();
+            return Poll::Ready(this.finish(Err(
+                HyperlightError::HypervisorHandlerMessageReceiveTimedout(),
+            )));
+        }
+
+        if timer.is_none() {
+            match DeadlineTimer::start(hv_handler, *deadline) {
+                Ok(t) => *timer = Some(t),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for GuestFunctionCallFuture<'_> {
+    fn drop(&mut self) {
+        if !matches!(self.state, CallState::Running { .. }) {
+            return;
+        }
+        // stop the deadline timer before cancelling
+        self.state = CallState::Done;
+
+        let mut hv_handler = self.sandbox.get_hv_handler().clone();
+        hv_handler.clear_waker();
+        match hv_handler.terminate_hypervisor_handler_execution_and_reinitialise(
+            self.sandbox.get_mgr_wrapper_mut().unwrap_mgr_mut(),
+        ) {
+            Ok(HyperlightError::HypervisorHandlerExecutionCancelAttemptOnFinishedExecution()) => {
+                // The call finished before it could be cancelled, so its
+                // result is still waiting to be received.
+                let _ = hv_handler.try_receive_handler_msg();
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Failed to cancel guest function call: {:?}", e);
+            }
+        }
+
+        if let Err(e) = self.sandbox.restore_state() {
+            log::error!(
+                "Failed to restore sandbox state after cancelling guest function call: {:?}",
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hyperlight_common::flatbuffer_wrappers::function_types::{
+        ParameterValue, ReturnType, ReturnValue,
+    };
+    use hyperlight_testing::simple_guest_as_string;
+
+    use crate::sandbox::SandboxConfiguration;
+    use crate::sandbox_state::sandbox::EvolvableSandbox;
+    use crate::sandbox_state::transition::Noop;
+    use crate::{GuestBinary, HyperlightError, MultiUseSandbox, UninitializedSandbox};
+
+    fn new_sandbox(max_execution_time: Option<Duration>) -> MultiUseSandbox {
+        let mut cfg = SandboxConfiguration::default();
+        if let Some(max_execution_time) = max_execution_time {
+            cfg.set_max_execution_time(max_execution_time);
+        }
+        let u_sbox = UninitializedSandbox::new(
+            GuestBinary::FilePath(simple_guest_as_string().unwrap()),
+            Some(cfg),
+            None,
+            None,
+        )
+        .unwrap();
+        u_sbox.evolve(Noop::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn call_guest_function_async() {
+        let mut sbox = new_sandbox(None);
+
+        for i in 0..10 {
+            let res = sbox
+                .call_guest_function_async(
+                    "Echo",
+                    ReturnType::String,
+                    Some(vec![ParameterValue::String(format!("hello {}", i))]),
+                )
+                .await
+                .unwrap();
+            assert_eq!(res, ReturnValue::String(format!("hello {}", i)));
+        }
+    }
+
+    #[tokio::test]
+    async fn call_guest_function_async_times_out() {
+        let mut sbox = new_sandbox(None);
+
+        let res = sbox
+            .call_guest_function_async("Spin", ReturnType::Int, None)
+            .await;
+        assert!(
+            matches!(res, Err(HyperlightError::ExecutionCanceledByHost())),
+            "{:?}",
+            res
+        );
+
+        // the sandbox is still usable after the cancelled call
+        let res = sbox
+            .call_guest_function_async(
+                "Echo",
+                ReturnType::String,
+                Some(vec![ParameterValue::String("hello".to_string())]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res, ReturnValue::String("hello".to_string()));
+    }
+
+    #[tokio::test]
+    async fn dropping_future_cancels_guest_call() {
+        let mut sbox = new_sandbox(Some(Duration::from_secs(30)));
+
+        let res = tokio::time::timeout(
+            Duration::from_millis(100),
+            sbox.call_guest_function_async("Spin", ReturnType::Int, None),
+        )
+        .await;
+        assert!(res.is_err());
+
+        let res = sbox
+            .call_guest_function_async(
+                "Echo",
+                ReturnType::String,
+                Some(vec![ParameterValue::String("hello".to_string())]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res, ReturnValue::String("hello".to_string()));
+    }
+
+    #[tokio::test]
+    async fn interrupting_async_guest_call() {
+        let mut sbox = new_sandbox(Some(Duration::from_secs(30)));
+
+        let interrupt_handle = sbox.interrupt_handle();
+        let killer = std::thread::spawn(move || {
+            while !interrupt_handle.kill() {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let res = sbox
+            .call_guest_function_async("Spin", ReturnType::Int, None)
+            .await;
+        killer.join().unwrap();
+        assert!(
+            matches!(res, Err(HyperlightError::ExecutionCanceledByHost())),
+            "{:?}",
+            res
+        );
+    }
+}
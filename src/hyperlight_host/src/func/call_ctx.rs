@@ -20,6 +20,7 @@ use hyperlight_common::flatbuffer_wrappers::function_types::{
 use tracing::{instrument, Span};
 
 use super::guest_dispatch::call_function_on_guest;
+use super::{CallTimeout, SupportedParameterTuple, SupportedReturnType};
 use crate::{MultiUseSandbox, Result, SingleUseSandbox};
 /// A context for calling guest functions.
 ///
@@ -61,7 +62,7 @@ impl MultiUseGuestCallContext {
     /// If you want  to reset state, call `finish()` on this `MultiUseGuestCallContext`
     /// and get a new one from the resulting `MultiUseSandbox`
     #[instrument(err(Debug),skip(self, args),parent = Span::current())]
-    pub fn call(
+    pub fn call_guest_function_by_name(
         &mut self,
         func_name: &str,
         func_ret_type: ReturnType,
@@ -72,17 +73,112 @@ impl MultiUseGuestCallContext {
         // !Send (and !Sync), we also don't need to worry about
         // synchronization
 
-        call_function_on_guest(&mut self.sbox, func_name, func_ret_type, args)
+        call_function_on_guest(&mut self.sbox, func_name, func_ret_type, args, None)
+    }
+
+    /// Call the guest function called `func_name` with the given arguments
+    /// `args`, and expect the return value have the same type as
+    /// `func_ret_type`, cancelling it once `timeout` is reached instead of
+    /// once it has run for the sandbox's `max_execution_time`.
+    ///
+    /// As with `call_guest_function_by_name`, guest state is retained across
+    /// calls made through this context.
+    #[instrument(err(Debug), skip(self, args, timeout), parent = Span::current())]
+    pub fn call_guest_function_by_name_with_timeout(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        timeout: impl Into<CallTimeout>,
+    ) -> Result<ReturnValue> {
+        call_function_on_guest(
+            &mut self.sbox,
+            func_name,
+            func_ret_type,
+            args,
+            Some(timeout.into()),
+        )
+    }
+
+    /// Call the guest function called `func_name` with the arguments
+    /// `args`, and convert its return value to `R`.
+    ///
+    /// This is a typed equivalent of `call_guest_function_by_name`: `Args`
+    /// is a tuple of `SupportedParameterType`s (use `()` for a function that
+    /// takes no arguments) and `R` is a `SupportedReturnType`. For example:
+    ///
+    /// `let sum: i32 = ctx.call("Add", (1_i32, 41_i32))?;`
+    ///
+    /// As with `call_guest_function_by_name`, guest state is retained across
+    /// calls made through this context.
+    #[instrument(err(Debug), skip(self, args), parent = Span::current())]
+    pub fn call<Args: SupportedParameterTuple, R: SupportedReturnType<R>>(
+        &mut self,
+        func_name: &str,
+        args: Args,
+    ) -> Result<R> {
+        let args = args.get_hyperlight_values();
+        self.sbox
+            .check_guest_function_signature(func_name, R::get_hyperlight_type(), &args)?;
+        let ret = self.call_guest_function_by_name(func_name, R::get_hyperlight_type(), args)?;
+        R::get_inner(ret)
+    }
+
+    /// Take a checkpoint named `name` of the guest's current state, which
+    /// can later be rolled back to with `rollback`, replacing any checkpoint
+    /// with the same name.
+    ///
+    /// Checkpoints are kept after the context is finished or committed, so
+    /// contexts created later from the same sandbox can roll back to them
+    /// too. See `MultiUseSandbox::checkpoint`.
+    #[instrument(err(Debug), skip(self, name), parent = Span::current())]
+    pub fn checkpoint(&mut self, name: impl Into<String>) -> Result<()> {
+        self.sbox.checkpoint(name)
+    }
+
+    /// Roll the guest's state back to the checkpoint named `name`, so that
+    /// further calls through this context start from that state. The
+    /// checkpoints taken after it are discarded.
+    ///
+    /// Returns `HyperlightError::CheckpointNotFound` if there is no such
+    /// checkpoint.
+    #[instrument(err(Debug), skip(self), parent = Span::current())]
+    pub fn rollback(&mut self, name: &str) -> Result<()> {
+        self.sbox
+            .mem_mgr
+            .unwrap_mgr_mut()
+            .rollback_to_checkpoint(name)
     }
 
     /// Close out the context and get back the internally-stored
     /// `MultiUseSandbox`. Future contexts opened by the returned sandbox
     /// will have guest state restored.
+    ///
+    /// Together with `commit`, this gives calls made through the context
+    /// transactional semantics: on failure, `finish` discards all the changes
+    /// they made to the guest's state, and on success, `commit` keeps them.
     #[instrument(err(Debug), skip(self), parent = Span::current())]
     pub fn finish(mut self) -> Result<MultiUseSandbox> {
         self.sbox.restore_state()?;
         Ok(self.sbox)
     }
+
+    /// Close out the context and get back the internally-stored
+    /// `MultiUseSandbox`, with the guest's current state as the new state
+    /// that its guest function calls, and the contexts opened from it, start
+    /// from.
+    ///
+    /// The committed state replaces the one the sandbox had before, so
+    /// committing repeatedly doesn't keep a copy of the sandbox's memory per
+    /// commit, and devolving the returned sandbox restores the state it had
+    /// before it was last evolved. To make a checkpoint the new state
+    /// instead, `rollback` to it before committing.
+    #[instrument(err(Debug), skip(self), parent = Span::current())]
+    pub fn commit(self) -> Result<MultiUseSandbox> {
+        let mut sbox = self.finish_no_reset();
+        sbox.mem_mgr.unwrap_mgr_mut().replace_state()?;
+        Ok(sbox)
+    }
     /// Close out the context and get back the internally-stored
     /// `MultiUseSandbox`.
     ///
@@ -131,7 +227,20 @@ impl SingleUseGuestCallContext {
         func_ret_type: ReturnType,
         args: Option<Vec<ParameterValue>>,
     ) -> Result<ReturnValue> {
-        self.call_internal(func_name, func_ret_type, args)
+        self.call_internal(func_name, func_ret_type, args, None)
+    }
+
+    /// Like `call`, but cancels the guest function once `timeout` is reached
+    /// instead of once it has run for the sandbox's `max_execution_time`.
+    #[instrument(err(Debug), skip(self, args), parent = Span::current())]
+    pub(crate) fn call_with_timeout(
+        mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        timeout: CallTimeout,
+    ) -> Result<ReturnValue> {
+        self.call_internal(func_name, func_ret_type, args, Some(timeout))
     }
 
     // Internal call function that takes a mutable reference to self
@@ -143,13 +252,14 @@ impl SingleUseGuestCallContext {
         func_name: &str,
         func_ret_type: ReturnType,
         args: Option<Vec<ParameterValue>>,
+        timeout: Option<CallTimeout>,
     ) -> Result<ReturnValue> {
         // We are guaranteed to be holding a lock now, since `self` can't
         // exist without doing so. since GuestCallContext is effectively
         // !Send (and !Sync), we also don't need to worry about
         // synchronization
 
-        call_function_on_guest(&mut self.sbox, func_name, func_ret_type, args)
+        call_function_on_guest(&mut self.sbox, func_name, func_ret_type, args, timeout)
     }
 
     /// This function allows for a `SingleUseSandbox` to be used to make multiple calls to guest functions before it is dropped.
@@ -189,7 +299,21 @@ impl SingleUseMultiGuestCallContext {
         args: Option<Vec<ParameterValue>>,
     ) -> Result<ReturnValue> {
         self.call_context
-            .call_internal(func_name, func_ret_type, args)
+            .call_internal(func_name, func_ret_type, args, None)
+    }
+
+    /// Call the guest function called `func_name` with the given arguments,
+    /// cancelling it once `timeout` is reached instead of once it has run
+    /// for the sandbox's `max_execution_time`
+    pub fn call_with_timeout(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        timeout: impl Into<CallTimeout>,
+    ) -> Result<ReturnValue> {
+        self.call_context
+            .call_internal(func_name, func_ret_type, args, Some(timeout.into()))
     }
 }
 
@@ -309,7 +433,11 @@ mod tests {
                 let mut ctx = sbox.new_call_context();
                 for call in calls {
                     let res = ctx
-                        .call(call.func_name.as_str(), call.ret_type, call.params)
+                        .call_guest_function_by_name(
+                            call.func_name.as_str(),
+                            call.ret_type,
+                            call.params,
+                        )
                         .unwrap();
                     assert_eq!(call.expected_ret, res);
                 }
@@ -364,7 +492,7 @@ mod tests {
             let mut ctx = self.sandbox.new_call_context();
             let mut sum: i32 = 0;
             for n in 0..i {
-                let result = ctx.call(
+                let result = ctx.call_guest_function_by_name(
                     "AddToStatic",
                     ReturnType::Int,
                     Some(vec![ParameterValue::Int(n)]),
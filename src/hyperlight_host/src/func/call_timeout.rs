@@ -0,0 +1,63 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::time::{Duration, Instant};
+
+/// How long a single guest function call may run before it is cancelled,
+/// overriding the sandbox's `max_execution_time` for that call.
+///
+/// Unlike `max_execution_time`, which is at most `u16::MAX` milliseconds,
+/// the timeout of a single call can be of any length. Calls that time out
+/// fail with `HyperlightError::ExecutionCanceledByHost`.
+///
+/// A `Duration` or an `Instant` can be used wherever an
+/// `impl Into<CallTimeout>` is expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallTimeout {
+    /// Cancel the call if it runs for longer than the given duration
+    After(Duration),
+    /// Cancel the call if it is still running at the given instant. A call
+    /// whose deadline has already passed is cancelled straight away.
+    Deadline(Instant),
+    /// Never cancel the call, however long it runs. This should only be
+    /// used for trusted guests, since a guest that never returns will block
+    /// the calling thread forever.
+    Never,
+}
+
+impl CallTimeout {
+    /// Get how long the call may still run for, which is `Duration::MAX` if
+    /// it may run forever
+    pub(crate) fn remaining(&self) -> Duration {
+        match self {
+            CallTimeout::After(duration) => *duration,
+            CallTimeout::Deadline(deadline) => deadline.saturating_duration_since(Instant::now()),
+            CallTimeout::Never => Duration::MAX,
+        }
+    }
+}
+
+impl From<Duration> for CallTimeout {
+    fn from(duration: Duration) -> Self {
+        CallTimeout::After(duration)
+    }
+}
+
+impl From<Instant> for CallTimeout {
+    fn from(deadline: Instant) -> Self {
+        CallTimeout::Deadline(deadline)
+    }
+}
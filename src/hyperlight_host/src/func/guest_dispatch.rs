@@ -20,6 +20,7 @@ use hyperlight_common::flatbuffer_wrappers::function_types::{
 };
 use tracing::{instrument, Span};
 
+use super::call_timeout::CallTimeout;
 use super::guest_err::check_for_guest_error;
 use crate::hypervisor::hypervisor_handler::HypervisorHandlerAction;
 use crate::sandbox::WrapperGetter;
@@ -27,6 +28,9 @@ use crate::HyperlightError::GuestExecutionHungOnHostFunctionCall;
 use crate::{HyperlightError, Result};
 
 /// Call a guest function by name, using the given `wrapper_getter`.
+///
+/// The call is cancelled once `timeout` is reached, or, if it is `None`,
+/// once it has run for the sandbox's `max_execution_time`.
 #[instrument(
     err(Debug),
     skip(wrapper_getter, args),
@@ -38,9 +42,30 @@ pub(crate) fn call_function_on_guest<WrapperGetterT: WrapperGetter>(
     function_name: &str,
     return_type: ReturnType,
     args: Option<Vec<ParameterValue>>,
+    timeout: Option<CallTimeout>,
 ) -> Result<ReturnValue> {
-    let mut timedout = false;
+    write_guest_function_call(wrapper_getter, function_name, return_type, args)?;
+
+    let mut hv_handler = wrapper_getter.get_hv_handler().clone();
+    let action = HypervisorHandlerAction::DispatchCallFromHost(function_name.to_string());
+    let dispatch_res = match timeout {
+        Some(timeout) => {
+            hv_handler.execute_hypervisor_handler_action_with_timeout(action, timeout.remaining())
+        }
+        None => hv_handler.execute_hypervisor_handler_action(action),
+    };
+
+    finish_function_call_on_guest(wrapper_getter, dispatch_res)
+}
 
+/// Serialize a call to the guest function `function_name` and write it
+/// into the guest's input buffer, ready to be dispatched.
+pub(crate) fn write_guest_function_call<WrapperGetterT: WrapperGetter>(
+    wrapper_getter: &mut WrapperGetterT,
+    function_name: &str,
+    return_type: ReturnType,
+    args: Option<Vec<ParameterValue>>,
+) -> Result<()> {
     let fc = FunctionCall::new(
         function_name.to_string(),
         args,
@@ -52,19 +77,25 @@ pub(crate) fn call_function_on_guest<WrapperGetterT: WrapperGetter>(
         .try_into()
         .map_err(|_| HyperlightError::Error("Failed to serialize FunctionCall".to_string()))?;
 
-    {
-        let mem_mgr = wrapper_getter.get_mgr_wrapper_mut();
-        mem_mgr.as_mut().write_guest_function_call(&buffer)?;
-    }
+    let mem_mgr = wrapper_getter.get_mgr_wrapper_mut();
+    mem_mgr.as_mut().write_guest_function_call(&buffer)
+}
 
-    let mut hv_handler = wrapper_getter.get_hv_handler().clone();
-    match hv_handler.execute_hypervisor_handler_action(
-        HypervisorHandlerAction::DispatchCallFromHost(function_name.to_string()),
-    ) {
+/// Given the result of dispatching a guest function call to the Hypervisor
+/// Handler, cancel the execution if it timed out, check for guest errors and
+/// read the guest function's return value.
+pub(crate) fn finish_function_call_on_guest<WrapperGetterT: WrapperGetter>(
+    wrapper_getter: &mut WrapperGetterT,
+    dispatch_res: Result<()>,
+) -> Result<ReturnValue> {
+    let mut timedout = false;
+
+    match dispatch_res {
         Ok(()) => {}
         Err(e) => match e {
             HyperlightError::HypervisorHandlerMessageReceiveTimedout() => {
                 timedout = true;
+                let mut hv_handler = wrapper_getter.get_hv_handler().clone();
                 match hv_handler.terminate_hypervisor_handler_execution_and_reinitialise(
                     wrapper_getter.get_mgr_wrapper_mut().unwrap_mgr_mut(),
                 )? {
@@ -75,12 +106,37 @@ pub(crate) fn call_function_on_guest<WrapperGetterT: WrapperGetter>(
                     e => return Err(e),
                 }
             }
+            HyperlightError::ExecutionCanceledByHost() => {
+                // The call was interrupted before the guest started running
+                // it, so only the call written into its memory is undone.
+                wrapper_getter
+                    .get_mgr_wrapper_mut()
+                    .unwrap_mgr_mut()
+                    .restore_state_from_last_snapshot()?;
+                return Err(HyperlightError::ExecutionCanceledByHost());
+            }
+            e @ HyperlightError::GuestAborted(..) => {
+                // The guest aborts when a host function it called panicked,
+                // which leaves the sandbox usable once its memory is
+                // restored, so the host function's error is returned instead
+                let mem_mgr = wrapper_getter.get_mgr_wrapper_mut();
+                if let Err(host_err @ HyperlightError::OutBHandlingError(..)) =
+                    check_for_guest_error(mem_mgr)
+                {
+                    mem_mgr
+                        .unwrap_mgr_mut()
+                        .restore_state_from_last_snapshot()?;
+                    return Err(host_err);
+                }
+                return Err(e);
+            }
             e => return Err(e),
         },
     };
 
     let mem_mgr = wrapper_getter.get_mgr_wrapper_mut();
     mem_mgr.check_stack_guard()?; // <- wrapper around mem_mgr `check_for_stack_guard`
+    mem_mgr.record_memory_stats()?;
     check_for_guest_error(mem_mgr)?;
 
     mem_mgr
@@ -221,6 +277,214 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    fn test_audit_seccomp_filters() -> Result<()> {
+        fn make_get_pid_syscall() -> Result<u64> {
+            let pid = unsafe { libc::syscall(libc::SYS_getpid) };
+            Ok(pid as u64)
+        }
+        let make_get_pid_syscall_func = Arc::new(Mutex::new(make_get_pid_syscall));
+
+        let mut cfg = crate::sandbox::SandboxConfiguration::default();
+        cfg.set_seccomp_audit_mode(true);
+        let mut usbox = UninitializedSandbox::new(
+            GuestBinary::FilePath(simple_guest_as_string().expect("Guest Binary Missing")),
+            Some(cfg),
+            None,
+            None,
+        )?;
+        make_get_pid_syscall_func.register(&mut usbox, "MakeGetpidSyscall")?;
+        let mut sbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
+
+        // The disallowed syscall is made, rather than stopping the host function
+        let res =
+            sbox.call_guest_function_by_name("ViolateSeccompFilters", ReturnType::ULong, None)?;
+        assert_eq!(res, ReturnValue::ULong(std::process::id() as u64));
+
+        let audited = sbox.audited_syscalls()?;
+        let syscalls = audited
+            .get("MakeGetpidSyscall")
+            .expect("MakeGetpidSyscall made no disallowed syscalls");
+        assert!(syscalls
+            .iter()
+            .any(|audited| audited.syscall == libc::SYS_getpid));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    fn test_hypervisor_handler_seccomp_filter() -> Result<()> {
+        fn make_get_pid_syscall() -> Result<u64> {
+            let pid = unsafe { libc::syscall(libc::SYS_getpid) };
+            Ok(pid as u64)
+        }
+        let make_get_pid_syscall_func = Arc::new(Mutex::new(make_get_pid_syscall));
+
+        let mut cfg = crate::sandbox::SandboxConfiguration::default();
+        cfg.set_hypervisor_handler_seccomp_filter(true);
+        let mut usbox = UninitializedSandbox::new(
+            GuestBinary::FilePath(simple_guest_as_string().expect("Guest Binary Missing")),
+            Some(cfg),
+            None,
+            None,
+        )?;
+        make_get_pid_syscall_func.register_with_extra_allowed_syscalls(
+            &mut usbox,
+            "MakeGetpidSyscall",
+            vec![libc::SYS_getpid],
+        )?;
+        let mut sbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
+
+        let res = sbox.call_guest_function_by_name(
+            "Echo",
+            ReturnType::String,
+            Some(vec![ParameterValue::String("hello".to_string())]),
+        )?;
+        assert_eq!(res, ReturnValue::String("hello".to_string()));
+
+        // Host functions run on threads the confined thread spawns, and may
+        // still make the syscalls they are allowed to
+        sbox.call_guest_function_by_name(
+            "PrintOutput",
+            ReturnType::Int,
+            Some(vec![ParameterValue::String("hello\n".to_string())]),
+        )?;
+        let res =
+            sbox.call_guest_function_by_name("ViolateSeccompFilters", ReturnType::ULong, None)?;
+        assert_eq!(res, ReturnValue::ULong(std::process::id() as u64));
+
+        // Forks are confined too
+        let mut fork = sbox.fork()?;
+        let res = fork.call_guest_function_by_name(
+            "Echo",
+            ReturnType::String,
+            Some(vec![ParameterValue::String("fork".to_string())]),
+        )?;
+        assert_eq!(res, ReturnValue::String("fork".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    fn test_host_function_filesystem_access() -> Result<()> {
+        use crate::sandbox::FilesystemAccess;
+
+        let allowed_dir = tempfile::tempdir()?;
+        let denied_dir = tempfile::tempdir()?;
+        let allowed_file = allowed_dir.path().join("config");
+        let denied_file = denied_dir.path().join("secret");
+        std::fs::write(&allowed_file, "config")?;
+        std::fs::write(&denied_file, "secret")?;
+
+        // Reports whether the host function could read the file it was
+        // allowed to, and only that one
+        let read_files = {
+            let allowed_file = allowed_file.clone();
+            let denied_file = denied_file.clone();
+            move || -> Result<u64> {
+                let allowed = std::fs::read_to_string(&allowed_file)?;
+                let denied = std::fs::read_to_string(&denied_file);
+                match denied {
+                    Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                        Ok(allowed.len() as u64)
+                    }
+                    _ => Err(new_error!(
+                        "Expected reading {:?} to be denied",
+                        denied_file
+                    )),
+                }
+            }
+        };
+        let read_files_func = Arc::new(Mutex::new(read_files));
+        let syscalls_to_read_files = [
+            libc::SYS_openat,
+            libc::SYS_read,
+            libc::SYS_close,
+            libc::SYS_statx,
+            libc::SYS_fstat,
+            libc::SYS_newfstatat,
+            libc::SYS_lseek,
+        ];
+
+        // With and without the Hypervisor Handler thread's filter, which the
+        // host function worker thread must not inherit
+        for hypervisor_handler_seccomp_filter in [false, true] {
+            let mut cfg = crate::sandbox::SandboxConfiguration::default();
+            cfg.set_hypervisor_handler_seccomp_filter(hypervisor_handler_seccomp_filter);
+            let mut usbox = UninitializedSandbox::new(
+                GuestBinary::FilePath(simple_guest_as_string().expect("Guest Binary Missing")),
+                Some(cfg),
+                None,
+                None,
+            )?;
+            read_files_func.register_with_filesystem_access(
+                &mut usbox,
+                "ReadFiles",
+                syscalls_to_read_files.to_vec(),
+                FilesystemAccess::new().allow_read(allowed_dir.path()),
+            )?;
+            let mut sbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
+
+            let res = sbox.call_guest_function_by_name(
+                "CallHostFunctionByName",
+                ReturnType::ULong,
+                Some(vec![ParameterValue::String("ReadFiles".to_string())]),
+            )?;
+            assert_eq!(res, ReturnValue::ULong("config".len() as u64));
+
+            // The ruleset only applies to the host function
+            assert_eq!(std::fs::read_to_string(&denied_file)?, "secret");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_function_panic() -> Result<()> {
+        fn panic_in_host() -> Result<u64> {
+            panic!("host function failed");
+        }
+        let panic_in_host_func = Arc::new(Mutex::new(panic_in_host));
+
+        let mut usbox = UninitializedSandbox::new(
+            GuestBinary::FilePath(simple_guest_as_string().expect("Guest Binary Missing")),
+            None,
+            None,
+            None,
+        )?;
+        panic_in_host_func.register(&mut usbox, "PanicInHost")?;
+        let mut sbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
+
+        let res = sbox.call_guest_function_by_name(
+            "CallHostFunctionByName",
+            ReturnType::ULong,
+            Some(vec![ParameterValue::String("PanicInHost".to_string())]),
+        );
+        match res {
+            Err(HyperlightError::OutBHandlingError(name, message)) => {
+                assert_eq!(name, "PanicInHost");
+                assert_eq!(
+                    message,
+                    "Host function PanicInHost panicked: host function failed"
+                );
+            }
+            other => panic!("Expected OutBHandlingError error, got {:?}", other),
+        }
+
+        // The sandbox is still usable after the panic
+        let res = sbox.call_guest_function_by_name(
+            "Echo",
+            ReturnType::String,
+            Some(vec![ParameterValue::String("hello".to_string())]),
+        )?;
+        assert_eq!(res, ReturnValue::String("hello".to_string()));
+
+        Ok(())
+    }
+
     #[test]
     fn test_execute_in_host() {
         let uninitialized_sandbox = || {
@@ -325,7 +589,7 @@ mod tests {
         let len = msg.len() as i32;
         let mut ctx = mu_sbox.new_call_context();
         let result = ctx
-            .call(
+            .call_guest_function_by_name(
                 "PrintOutput",
                 ReturnType::Int,
                 Some(vec![ParameterValue::String(msg.clone())]),
@@ -399,7 +663,7 @@ mod tests {
         )?;
         let sandbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
         let mut ctx = sandbox.new_call_context();
-        let result = ctx.call("Spin", ReturnType::Void, None);
+        let result = ctx.call_guest_function_by_name("Spin", ReturnType::Void, None);
 
         assert!(result.is_err());
         match result.unwrap_err() {
@@ -471,7 +735,7 @@ mod tests {
 
         let sandbox: MultiUseSandbox = usbox.evolve(Noop::default()).unwrap();
         let mut ctx = sandbox.new_call_context();
-        let result = ctx.call("CallHostSpin", ReturnType::Void, None);
+        let result = ctx.call_guest_function_by_name("CallHostSpin", ReturnType::Void, None);
 
         assert!(result.is_err());
         match result.unwrap_err() {
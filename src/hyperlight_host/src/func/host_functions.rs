@@ -23,16 +23,44 @@ use paste::paste;
 use tracing::{instrument, Span};
 
 use super::{HyperlightFunction, SupportedParameterType, SupportedReturnType};
-use crate::sandbox::{ExtraAllowedSyscall, UninitializedSandbox};
+#[cfg(target_os = "linux")]
+use crate::sandbox::helper_process::add_helper_process_function;
+use crate::sandbox::{ExtraAllowedSyscall, FilesystemAccess, UninitializedSandbox};
 use crate::HyperlightError::UnexpectedNoOfArguments;
 use crate::{log_then_return, new_error, Result};
 
+/// A host function of any supported arity that can be registered with a
+/// sandbox.
+///
+/// This is implemented for every `Arc<Mutex<T>>` that implements one of the
+/// `HostFunction0` to `HostFunction10` traits, where `Args` is the tuple of
+/// parameter types the function takes. It allows host functions of
+/// different arities to be handled uniformly, for example by
+/// `SandboxBuilder`.
+pub trait RegisterableHostFunction<Args, R> {
+    /// Register the host function with the given name in the sandbox,
+    /// optionally allowing extra syscalls and restricting the files it may
+    /// access.
+    ///
+    /// Passing `Some` for `extra_allowed_syscalls` or `filesystem_access` is
+    /// an error unless running on Linux with the `seccomp` feature enabled.
+    fn register_with_sandbox(
+        &self,
+        sandbox: &mut UninitializedSandbox,
+        name: &str,
+        extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+        filesystem_access: Option<FilesystemAccess>,
+    ) -> Result<()>;
+}
+
 macro_rules! host_function {
     // Special case for zero parameters
     (0) => {
         paste! {
             /// Trait for registering a host function with zero parameters.
-            pub trait HostFunction0<'a, R: SupportedReturnType<R>> {
+            pub trait HostFunction0<'a, R: SupportedReturnType<R>>:
+                RegisterableHostFunction<(), R>
+            {
                 /// Register the host function with the given name in the sandbox.
                 fn register(
                     &self,
@@ -40,14 +68,66 @@ macro_rules! host_function {
                     name: &str,
                 ) -> Result<()>;
 
-                /// Register the host function with the given name in the sandbox, allowing extra syscalls.
+                /// Register the host function with the given name in the sandbox, allowing extra syscalls,
+                /// which may be given as bare syscall numbers or as `ExtraAllowedSyscall`s with rules.
                 #[cfg(all(feature = "seccomp", target_os = "linux"))]
                 fn register_with_extra_allowed_syscalls(
                     &self,
                     sandbox: &mut UninitializedSandbox,
                     name: &str,
-                    extra_allowed_syscalls: Vec<ExtraAllowedSyscall>,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                ) -> Result<()>
+                where
+                    Self: Sized;
+
+                /// Register the host function with the given name in the sandbox, allowing extra syscalls
+                /// and only allowing access to the files `filesystem_access` allows.
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                fn register_with_filesystem_access(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                    filesystem_access: FilesystemAccess,
+                ) -> Result<()>
+                where
+                    Self: Sized;
+
+                /// Register the host function with the given name in the sandbox, running it in a
+                /// helper process rather than in the host process.
+                ///
+                /// The helper process is started now, as a new instance of the host program, and
+                /// calls the instance of the host function that instance added to the table of
+                /// those that can run in helper processes as `name`, with
+                /// `register_for_helper_processes`. It is an error if this instance didn't add one.
+                #[cfg(target_os = "linux")]
+                fn register_in_helper_process(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
                 ) -> Result<()>;
+
+                /// Add the host function to the table of those that can run in helper processes,
+                /// as `name`.
+                ///
+                /// A helper process is a new instance of the host program, so the program must
+                /// add the same host functions in every instance, before it calls
+                /// `run_helper_process_if_requested` at the start of `main`. The instance in each
+                /// helper process keeps its own state between the calls made to it.
+                #[cfg(target_os = "linux")]
+                fn register_for_helper_processes(&self, name: &str) -> Result<()>;
+
+                /// Register the host function with the given name in the sandbox, running it in a
+                /// helper process that is allowed extra syscalls.
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                fn register_in_helper_process_with_extra_allowed_syscalls(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                ) -> Result<()>
+                where
+                    Self: Sized;
             }
 
             impl<'a, T, R> HostFunction0<'a, R> for Arc<Mutex<T>>
@@ -63,7 +143,7 @@ macro_rules! host_function {
                     sandbox: &mut UninitializedSandbox,
                     name: &str,
                 ) -> Result<()> {
-                    register_host_function_0(self.clone(), sandbox, name, None)
+                    register_host_function_0(self.clone(), sandbox, name, None, None, false)
                 }
 
                 #[cfg(all(feature = "seccomp", target_os = "linux"))]
@@ -75,33 +155,133 @@ macro_rules! host_function {
                     &self,
                     sandbox: &mut UninitializedSandbox,
                     name: &str,
-                    extra_allowed_syscalls: Vec<ExtraAllowedSyscall>,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                ) -> Result<()> {
+                    let extra_allowed_syscalls = extra_allowed_syscalls.into_iter().map(Into::into).collect();
+                    register_host_function_0(self.clone(), sandbox, name, Some(extra_allowed_syscalls), None, false)
+                }
+
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                #[instrument(
+                    err(Debug), skip(self, sandbox, extra_allowed_syscalls, filesystem_access),
+                    parent = Span::current(), level = "Trace"
+                )]
+                fn register_with_filesystem_access(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                    filesystem_access: FilesystemAccess,
+                ) -> Result<()> {
+                    let extra_allowed_syscalls = extra_allowed_syscalls.into_iter().map(Into::into).collect();
+                    register_host_function_0(
+                        self.clone(),
+                        sandbox,
+                        name,
+                        Some(extra_allowed_syscalls),
+                        Some(filesystem_access),
+                        false,
+                    )
+                }
+
+                #[cfg(target_os = "linux")]
+                #[instrument(
+                    err(Debug), skip(self, sandbox), parent = Span::current(), level = "Trace"
+                )]
+                fn register_in_helper_process(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                ) -> Result<()> {
+                    register_host_function_0(self.clone(), sandbox, name, None, None, true)
+                }
+
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                #[instrument(
+                    err(Debug), skip(self, sandbox, extra_allowed_syscalls),
+                    parent = Span::current(), level = "Trace"
+                )]
+                fn register_in_helper_process_with_extra_allowed_syscalls(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
                 ) -> Result<()> {
-                    register_host_function_0(self.clone(), sandbox, name, Some(extra_allowed_syscalls))
+                    let extra_allowed_syscalls = extra_allowed_syscalls.into_iter().map(Into::into).collect();
+                    register_host_function_0(self.clone(), sandbox, name, Some(extra_allowed_syscalls), None, true)
+                }
+
+                #[cfg(target_os = "linux")]
+                #[instrument(err(Debug), skip(self), parent = Span::current(), level = "Trace")]
+                fn register_for_helper_processes(&self, name: &str) -> Result<()> {
+                    add_helper_process_function(name, host_function_0(self.clone()))
                 }
             }
 
+            impl<T, R> RegisterableHostFunction<(), R> for Arc<Mutex<T>>
+            where
+                T: FnMut() -> Result<R> + Send + 'static,
+                R: SupportedReturnType<R>,
+            {
+                fn register_with_sandbox(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+                    filesystem_access: Option<FilesystemAccess>,
+                ) -> Result<()> {
+                    register_host_function_0(self.clone(), sandbox, name, extra_allowed_syscalls, filesystem_access, false)
+                }
+            }
+
+            fn host_function_0<T, R>(self_: Arc<Mutex<T>>) -> HyperlightFunction
+            where
+                T: FnMut() -> Result<R> + Send + 'static,
+                R: SupportedReturnType<R>,
+            {
+                HyperlightFunction::new(move |_: Vec<ParameterValue>| {
+                    let result = self_
+                        .lock()
+                        .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?()?;
+                    Ok(result.get_hyperlight_value())
+                })
+            }
+
             fn register_host_function_0<T, R>(
                 self_: Arc<Mutex<T>>,
                 sandbox: &mut UninitializedSandbox,
                 name: &str,
                 extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+                filesystem_access: Option<FilesystemAccess>,
+                in_helper_process: bool,
             ) -> Result<()>
             where
                 T: FnMut() -> Result<R> + Send + 'static,
                 R: SupportedReturnType<R>,
             {
-                let cloned = self_.clone();
-                let func = Box::new(move |_: Vec<ParameterValue>| {
-                    let result = cloned
-                        .try_lock()
-                        .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?()?;
-                    Ok(result.get_hyperlight_value())
-                });
+                if in_helper_process {
+                    #[cfg(target_os = "linux")]
+                    {
+                        return sandbox
+                            .host_funcs
+                            .try_lock()
+                            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+                            .register_host_function_in_helper_process(
+                                sandbox.mgr.as_mut(),
+                                &HostFunctionDefinition::new(name.to_string(), None, R::get_hyperlight_type()),
+                                #[cfg(feature = "seccomp")]
+                                extra_allowed_syscalls,
+                            );
+                    }
+                    #[cfg(not(target_os = "linux"))]
+                    log_then_return!("Running host functions in helper processes is only supported on Linux");
+                }
 
-                if let Some(_eas) = extra_allowed_syscalls {
+                let func = host_function_0(self_);
+
+                if extra_allowed_syscalls.is_some() || filesystem_access.is_some() {
                     if cfg!(all(feature = "seccomp", target_os = "linux")) {
-                        // Register with extra allowed syscalls
+                        // Register with extra allowed syscalls and restricted filesystem access
                         #[cfg(all(feature = "seccomp", target_os = "linux"))]
                         {
                             sandbox
@@ -111,16 +291,17 @@ macro_rules! host_function {
                                 .register_host_function_with_syscalls(
                                     sandbox.mgr.as_mut(),
                                     &HostFunctionDefinition::new(name.to_string(), None, R::get_hyperlight_type()),
-                                    HyperlightFunction::new(func),
-                                    _eas,
+                                    func,
+                                    extra_allowed_syscalls.unwrap_or_default(),
+                                    filesystem_access,
                                 )?;
                         }
                     } else {
                         // Log and return an error
-                        log_then_return!("Extra allowed syscalls are only supported on Linux with seccomp enabled");
+                        log_then_return!("Extra allowed syscalls and restricted filesystem access are only supported on Linux with seccomp enabled");
                     }
                 } else {
-                    // Register without extra allowed syscalls
+                    // Register without extra allowed syscalls or restricted filesystem access
                     sandbox
                         .host_funcs
                         .try_lock()
@@ -128,7 +309,7 @@ macro_rules! host_function {
                         .register_host_function(
                             sandbox.mgr.as_mut(),
                             &HostFunctionDefinition::new(name.to_string(), None, R::get_hyperlight_type()),
-                            HyperlightFunction::new(func),
+                            func,
                         )?;
                 }
 
@@ -140,7 +321,8 @@ macro_rules! host_function {
     ($N:expr, $($P:ident),+) => {
         paste! {
             /// Trait for registering a host function with $N parameters.
-            pub trait [<HostFunction $N>]<'a, $($P,)* R>
+            pub trait [<HostFunction $N>]<'a, $($P,)* R>:
+                RegisterableHostFunction<($($P,)*), R>
             where
                 $($P: SupportedParameterType<$P> + Clone + 'a,)*
                 R: SupportedReturnType<R>,
@@ -152,14 +334,66 @@ macro_rules! host_function {
                     name: &str,
                 ) -> Result<()>;
 
-                /// Register the host function with the given name in the sandbox, allowing extra syscalls.
+                /// Register the host function with the given name in the sandbox, allowing extra syscalls,
+                /// which may be given as bare syscall numbers or as `ExtraAllowedSyscall`s with rules.
                 #[cfg(all(feature = "seccomp", target_os = "linux"))]
                 fn register_with_extra_allowed_syscalls(
                     &self,
                     sandbox: &mut UninitializedSandbox,
                     name: &str,
-                    extra_allowed_syscalls: Vec<ExtraAllowedSyscall>,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                ) -> Result<()>
+                where
+                    Self: Sized;
+
+                /// Register the host function with the given name in the sandbox, allowing extra syscalls
+                /// and only allowing access to the files `filesystem_access` allows.
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                fn register_with_filesystem_access(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                    filesystem_access: FilesystemAccess,
+                ) -> Result<()>
+                where
+                    Self: Sized;
+
+                /// Register the host function with the given name in the sandbox, running it in a
+                /// helper process rather than in the host process.
+                ///
+                /// The helper process is started now, as a new instance of the host program, and
+                /// calls the instance of the host function that instance added to the table of
+                /// those that can run in helper processes as `name`, with
+                /// `register_for_helper_processes`. It is an error if this instance didn't add one.
+                #[cfg(target_os = "linux")]
+                fn register_in_helper_process(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
                 ) -> Result<()>;
+
+                /// Add the host function to the table of those that can run in helper processes,
+                /// as `name`.
+                ///
+                /// A helper process is a new instance of the host program, so the program must
+                /// add the same host functions in every instance, before it calls
+                /// `run_helper_process_if_requested` at the start of `main`. The instance in each
+                /// helper process keeps its own state between the calls made to it.
+                #[cfg(target_os = "linux")]
+                fn register_for_helper_processes(&self, name: &str) -> Result<()>;
+
+                /// Register the host function with the given name in the sandbox, running it in a
+                /// helper process that is allowed extra syscalls.
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                fn register_in_helper_process_with_extra_allowed_syscalls(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                ) -> Result<()>
+                where
+                    Self: Sized;
             }
 
             impl<'a, T, $($P,)* R> [<HostFunction $N>]<'a, $($P,)* R> for Arc<Mutex<T>>
@@ -176,7 +410,7 @@ macro_rules! host_function {
                     sandbox: &mut UninitializedSandbox,
                     name: &str,
                 ) -> Result<()> {
-                    [<register_host_function_ $N>](self.clone(), sandbox, name, None)
+                    [<register_host_function_ $N>](self.clone(), sandbox, name, None, None, false)
                 }
 
                 #[cfg(all(feature = "seccomp", target_os = "linux"))]
@@ -188,25 +422,93 @@ macro_rules! host_function {
                     &self,
                     sandbox: &mut UninitializedSandbox,
                     name: &str,
-                    extra_allowed_syscalls: Vec<ExtraAllowedSyscall>,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                ) -> Result<()> {
+                    let extra_allowed_syscalls = extra_allowed_syscalls.into_iter().map(Into::into).collect();
+                    [<register_host_function_ $N>](self.clone(), sandbox, name, Some(extra_allowed_syscalls), None, false)
+                }
+
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                #[instrument(
+                    err(Debug), skip(self, sandbox, extra_allowed_syscalls, filesystem_access),
+                    parent = Span::current(), level = "Trace"
+                )]
+                fn register_with_filesystem_access(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
+                    filesystem_access: FilesystemAccess,
+                ) -> Result<()> {
+                    let extra_allowed_syscalls = extra_allowed_syscalls.into_iter().map(Into::into).collect();
+                    [<register_host_function_ $N>](
+                        self.clone(),
+                        sandbox,
+                        name,
+                        Some(extra_allowed_syscalls),
+                        Some(filesystem_access),
+                        false,
+                    )
+                }
+
+                #[cfg(target_os = "linux")]
+                #[instrument(
+                    err(Debug), skip(self, sandbox), parent = Span::current(), level = "Trace"
+                )]
+                fn register_in_helper_process(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                ) -> Result<()> {
+                    [<register_host_function_ $N>](self.clone(), sandbox, name, None, None, true)
+                }
+
+                #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                #[instrument(
+                    err(Debug), skip(self, sandbox, extra_allowed_syscalls),
+                    parent = Span::current(), level = "Trace"
+                )]
+                fn register_in_helper_process_with_extra_allowed_syscalls(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Vec<impl Into<ExtraAllowedSyscall>>,
                 ) -> Result<()> {
-                    [<register_host_function_ $N>](self.clone(), sandbox, name, Some(extra_allowed_syscalls))
+                    let extra_allowed_syscalls = extra_allowed_syscalls.into_iter().map(Into::into).collect();
+                    [<register_host_function_ $N>](self.clone(), sandbox, name, Some(extra_allowed_syscalls), None, true)
+                }
+
+                #[cfg(target_os = "linux")]
+                #[instrument(err(Debug), skip(self), parent = Span::current(), level = "Trace")]
+                fn register_for_helper_processes(&self, name: &str) -> Result<()> {
+                    add_helper_process_function(name, [<host_function_ $N>](self.clone()))
                 }
             }
 
-            fn [<register_host_function_ $N>]<'a, T, $($P,)* R>(
-                self_: Arc<Mutex<T>>,
-                sandbox: &mut UninitializedSandbox,
-                name: &str,
-                extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
-            ) -> Result<()>
+            impl<T, $($P,)* R> RegisterableHostFunction<($($P,)*), R> for Arc<Mutex<T>>
+            where
+                T: FnMut($($P),*) -> Result<R> + Send + 'static,
+                $($P: SupportedParameterType<$P> + Clone,)*
+                R: SupportedReturnType<R>,
+            {
+                fn register_with_sandbox(
+                    &self,
+                    sandbox: &mut UninitializedSandbox,
+                    name: &str,
+                    extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+                    filesystem_access: Option<FilesystemAccess>,
+                ) -> Result<()> {
+                    [<register_host_function_ $N>](self.clone(), sandbox, name, extra_allowed_syscalls, filesystem_access, false)
+                }
+            }
+
+            fn [<host_function_ $N>]<'a, T, $($P,)* R>(self_: Arc<Mutex<T>>) -> HyperlightFunction
             where
                 T: FnMut($($P),*) -> Result<R> + Send + 'static,
                 $($P: SupportedParameterType<$P> + Clone + 'a,)*
                 R: SupportedReturnType<R>,
             {
-                let cloned = self_.clone();
-                let func = Box::new(move |args: Vec<ParameterValue>| {
+                HyperlightFunction::new(move |args: Vec<ParameterValue>| {
                     if args.len() != $N {
                         log_then_return!(UnexpectedNoOfArguments(args.len(), $N));
                     }
@@ -216,19 +518,57 @@ macro_rules! host_function {
                         let $P = $P::get_inner(args_iter.next().unwrap())?;
                     )*
 
-                    let result = cloned
-                        .try_lock()
+                    let result = self_
+                        .lock()
                         .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?(
                             $($P),*
                         )?;
                     Ok(result.get_hyperlight_value())
-                });
+                })
+            }
 
+            fn [<register_host_function_ $N>]<'a, T, $($P,)* R>(
+                self_: Arc<Mutex<T>>,
+                sandbox: &mut UninitializedSandbox,
+                name: &str,
+                extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+                filesystem_access: Option<FilesystemAccess>,
+                in_helper_process: bool,
+            ) -> Result<()>
+            where
+                T: FnMut($($P),*) -> Result<R> + Send + 'static,
+                $($P: SupportedParameterType<$P> + Clone + 'a,)*
+                R: SupportedReturnType<R>,
+            {
                 let parameter_types = Some(vec![$($P::get_hyperlight_type()),*]);
 
-                if let Some(_eas) = extra_allowed_syscalls {
+                if in_helper_process {
+                    #[cfg(target_os = "linux")]
+                    {
+                        return sandbox
+                            .host_funcs
+                            .try_lock()
+                            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+                            .register_host_function_in_helper_process(
+                                sandbox.mgr.as_mut(),
+                                &HostFunctionDefinition::new(
+                                    name.to_string(),
+                                    parameter_types,
+                                    R::get_hyperlight_type(),
+                                ),
+                                #[cfg(feature = "seccomp")]
+                                extra_allowed_syscalls,
+                            );
+                    }
+                    #[cfg(not(target_os = "linux"))]
+                    log_then_return!("Running host functions in helper processes is only supported on Linux");
+                }
+
+                let func = [<host_function_ $N>](self_);
+
+                if extra_allowed_syscalls.is_some() || filesystem_access.is_some() {
                     if cfg!(all(feature = "seccomp", target_os = "linux")) {
-                        // Register with extra allowed syscalls
+                        // Register with extra allowed syscalls and restricted filesystem access
                         #[cfg(all(feature = "seccomp", target_os = "linux"))]
                         {
                             sandbox
@@ -242,16 +582,17 @@ macro_rules! host_function {
                                         parameter_types,
                                         R::get_hyperlight_type(),
                                     ),
-                                    HyperlightFunction::new(func),
-                                    _eas,
+                                    func,
+                                    extra_allowed_syscalls.unwrap_or_default(),
+                                    filesystem_access,
                                 )?;
                         }
                     } else {
                         // Log and return an error
-                        log_then_return!("Extra allowed syscalls are only supported on Linux with seccomp enabled");
+                        log_then_return!("Extra allowed syscalls and restricted filesystem access are only supported on Linux with seccomp enabled");
                     }
                 } else {
-                    // Register without extra allowed syscalls
+                    // Register without extra allowed syscalls or restricted filesystem access
                     sandbox
                         .host_funcs
                         .try_lock()
@@ -263,7 +604,7 @@ macro_rules! host_function {
                                 parameter_types,
                                 R::get_hyperlight_type(),
                             ),
-                            HyperlightFunction::new(func),
+                            func,
                         )?;
                 }
 
@@ -15,10 +15,16 @@ limitations under the License.
 */
 
 use crate::{new_error, Result};
+/// A `Future` for calling guest functions without blocking the calling
+/// thread
+#[cfg(feature = "async")]
+pub mod async_call;
 /// Context structures used to allow the user to call one or more guest
 /// functions on the same Hyperlight sandbox instance, all from within the
 /// same state and mutual exclusion context.
 pub mod call_ctx;
+/// Per-call timeouts for guest function calls
+pub(crate) mod call_timeout;
 /// Functionality to dispatch a call from the host to the guest
 pub(crate) mod guest_dispatch;
 /// Functionality to check for errors after a guest call
@@ -35,6 +41,8 @@ pub(crate) mod guest_err;
 /// - Dynamically dispatching a call from the guest to the appropriate
 ///   host function
 pub mod host_functions;
+/// Calling guest functions from inside host functions
+pub(crate) mod nested_call;
 /// Definitions and functionality for supported parameter types
 pub(crate) mod param_type;
 /// Definitions and functionality for supported return types
@@ -42,13 +50,24 @@ pub mod ret_type;
 
 use std::sync::{Arc, Mutex};
 
+/// Re-export for `GuestFunctionCallFuture` type
+#[cfg(feature = "async")]
+pub use async_call::GuestFunctionCallFuture;
+/// Re-export for `CallTimeout` enum
+pub use call_timeout::CallTimeout;
 /// Re-export for `ParameterValue` enum
 pub use hyperlight_common::flatbuffer_wrappers::function_types::ParameterValue;
 /// Re-export for `ReturnType` enum
 pub use hyperlight_common::flatbuffer_wrappers::function_types::ReturnType;
 /// Re-export for `ReturnType` enum
 pub use hyperlight_common::flatbuffer_wrappers::function_types::ReturnValue;
-pub use param_type::SupportedParameterType;
+/// Re-export for `GuestFunctionDefinition` struct
+pub use hyperlight_common::flatbuffer_wrappers::guest_function_definition::GuestFunctionDefinition;
+/// Re-export for `GuestFunctionDetails` struct
+pub use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
+/// Re-export for `call_guest_function_from_host_function` function
+pub use nested_call::call_guest_function_from_host_function;
+pub use param_type::{SupportedParameterTuple, SupportedParameterType};
 pub use ret_type::SupportedReturnType;
 use tracing::{instrument, Span};
 
@@ -69,9 +88,11 @@ impl HyperlightFunction {
 
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
     pub(crate) fn call(&self, args: Vec<ParameterValue>) -> Result<ReturnValue> {
+        // Sandboxes forked from the same sandbox share its host functions, so
+        // a call waits for the same function to return in another sandbox
         let mut f = self
             .0
-            .try_lock()
+            .lock()
             .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
         f(args)
     }
@@ -99,3 +120,5 @@ pub use host_functions::HostFunction7;
 pub use host_functions::HostFunction8;
 /// Re-export for `HostFunction9` trait
 pub use host_functions::HostFunction9;
+/// Re-export for `RegisterableHostFunction` trait
+pub use host_functions::RegisterableHostFunction;
@@ -0,0 +1,390 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+use crossbeam_channel::Sender;
+use hyperlight_common::flatbuffer_wrappers::function_call::{FunctionCall, FunctionCallType};
+use hyperlight_common::flatbuffer_wrappers::function_types::{
+    ParameterValue, ReturnType, ReturnValue,
+};
+use tracing::{instrument, Span};
+
+use super::guest_err::check_for_guest_error;
+use crate::hypervisor::handlers::MemAccessHandlerWrapper;
+use crate::hypervisor::hypervisor_handler::{HostFunctionTimer, HypervisorHandler};
+use crate::hypervisor::Hypervisor;
+use crate::mem::shared_mem::HostSharedMemory;
+use crate::sandbox::host_funcs::HostFuncsWrapper;
+use crate::sandbox::mem_mgr::MemMgrWrapper;
+use crate::sandbox::outb::outb_handler_wrapper;
+use crate::HyperlightError::NestedGuestCallDepthExceeded;
+use crate::{new_error, HyperlightError, Result};
+
+thread_local! {
+    /// The vCPU running the guest on this thread, while the guest waits for
+    /// an outb to be handled
+    static VCPU: RefCell<Option<VcpuContext>> = const { RefCell::new(None) };
+    /// What a guest function call from the host function running on this
+    /// thread needs
+    static HOST_CALL: RefCell<Option<HostCallContext>> = const { RefCell::new(None) };
+    /// On a host function worker thread, where to send guest function calls
+    /// so that they're made on the thread running the vCPU
+    static VCPU_THREAD: RefCell<Option<Sender<NestedCallRequest>>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone)]
+struct VcpuContext {
+    hv: *mut dyn Hypervisor,
+    hv_handler: Option<HypervisorHandler>,
+    mem_access_fn: MemAccessHandlerWrapper,
+    /// How many guest function calls from host functions the guest is in
+    depth: u8,
+}
+
+#[derive(Clone)]
+struct HostCallContext {
+    mem_mgr: MemMgrWrapper<HostSharedMemory>,
+    host_funcs: Arc<Mutex<HostFuncsWrapper>>,
+    host_function_timer: HostFunctionTimer,
+    /// The host functions the guest is waiting for, outermost first
+    running: Vec<String>,
+}
+
+/// Makes `hv` available to guest function calls from host functions on this
+/// thread until dropped.
+pub(crate) struct VcpuGuard(Option<VcpuContext>);
+
+impl VcpuGuard {
+    /// `hv` must only be used through this guard until it's dropped, which
+    /// is the case while it's waiting for an outb to be handled.
+    pub(crate) fn enter(
+        hv: &mut dyn Hypervisor,
+        hv_handler: Option<HypervisorHandler>,
+        mem_access_fn: MemAccessHandlerWrapper,
+    ) -> Self {
+        // SAFETY: only the lifetime of the pointer is changed, and it's
+        // removed from the thread local when the guard is dropped, before
+        // the borrow it came from ends
+        let hv: *mut dyn Hypervisor = unsafe { std::mem::transmute(hv as *mut dyn Hypervisor) };
+        let previous = VCPU.with(|vcpu| vcpu.borrow().clone());
+        let depth = previous.as_ref().map_or(0, |vcpu| vcpu.depth + 1);
+        VCPU.with(|vcpu| {
+            *vcpu.borrow_mut() = Some(VcpuContext {
+                hv,
+                hv_handler,
+                mem_access_fn,
+                depth,
+            })
+        });
+        Self(previous)
+    }
+}
+
+impl Drop for VcpuGuard {
+    fn drop(&mut self) {
+        VCPU.with(|vcpu| *vcpu.borrow_mut() = self.0.take());
+    }
+}
+
+/// Lets the host function called on this thread call back into the guest
+/// until dropped.
+pub(crate) struct HostCallGuard(Option<HostCallContext>);
+
+impl HostCallGuard {
+    /// Fails if the guest is already waiting for `function_name` to return,
+    /// as calling it again would wait forever for the call that is running.
+    pub(crate) fn enter(
+        mem_mgr: MemMgrWrapper<HostSharedMemory>,
+        host_funcs: Arc<Mutex<HostFuncsWrapper>>,
+        host_function_timer: HostFunctionTimer,
+        function_name: &str,
+    ) -> Result<Self> {
+        let mut running = HOST_CALL
+            .with(|host_call| host_call.borrow().as_ref().map(|c| c.running.clone()))
+            .unwrap_or_default();
+        if running.iter().any(|name| name == function_name) {
+            return Err(new_error!(
+                "Host function {} can't be called from a guest function it called",
+                function_name
+            ));
+        }
+        running.push(function_name.to_string());
+        let previous = HOST_CALL.with(|host_call| {
+            host_call.borrow_mut().replace(HostCallContext {
+                mem_mgr,
+                host_funcs,
+                host_function_timer,
+                running,
+            })
+        });
+        Ok(Self(previous))
+    }
+}
+
+impl Drop for HostCallGuard {
+    fn drop(&mut self) {
+        HOST_CALL.with(|host_call| *host_call.borrow_mut() = self.0.take());
+    }
+}
+
+/// A guest function call from a host function running on a worker thread,
+/// to be made on the thread running the vCPU
+pub(crate) struct NestedCallRequest {
+    function_name: String,
+    return_type: ReturnType,
+    args: Option<Vec<ParameterValue>>,
+    result_tx: Sender<Result<ReturnValue>>,
+}
+
+impl NestedCallRequest {
+    /// Make the call on this thread, which must be the one running the vCPU,
+    /// and send its result back to the host function that requested it
+    pub(crate) fn handle(self) {
+        let res = call_guest_function_on_vcpu(&self.function_name, self.return_type, self.args);
+        let _ = self.result_tx.send(res);
+    }
+}
+
+/// Forwards guest function calls from the host function running on this
+/// worker thread to the thread running the vCPU until dropped.
+pub(crate) struct VcpuThreadGuard;
+
+impl VcpuThreadGuard {
+    /// The thread running the vCPU handles the calls sent to `request_tx`
+    pub(crate) fn enter(request_tx: Sender<NestedCallRequest>) -> Self {
+        VCPU_THREAD.with(|vcpu_thread| *vcpu_thread.borrow_mut() = Some(request_tx));
+        Self
+    }
+}
+
+impl Drop for VcpuThreadGuard {
+    fn drop(&mut self) {
+        VCPU_THREAD.with(|vcpu_thread| *vcpu_thread.borrow_mut() = None);
+    }
+}
+
+/// Call the guest function `function_name` from inside a host function,
+/// while the guest that called the host function waits for it to return.
+///
+/// This lets host functions take callbacks from the guest, e.g. comparators
+/// or visitors. Calls can be nested, with the guest calling a host function
+/// which calls the guest, and so on, up to the sandbox's
+/// `max_nested_guest_call_depth`.
+///
+/// Returns an error if called from anywhere but a host function that the
+/// guest called.
+#[instrument(err(Debug), skip(args), parent = Span::current(), level = "Trace")]
+pub fn call_guest_function_from_host_function(
+    function_name: &str,
+    return_type: ReturnType,
+    args: Option<Vec<ParameterValue>>,
+) -> Result<ReturnValue> {
+    match VCPU_THREAD.with(|vcpu_thread| vcpu_thread.borrow().clone()) {
+        Some(request_tx) => {
+            let (result_tx, result_rx) = crossbeam_channel::bounded(1);
+            request_tx
+                .send(NestedCallRequest {
+                    function_name: function_name.to_string(),
+                    return_type,
+                    args,
+                    result_tx,
+                })
+                .map_err(|_| new_error!("The guest is no longer waiting for this host function"))?;
+            result_rx
+                .recv()
+                .map_err(|_| new_error!("The guest is no longer waiting for this host function"))?
+        }
+        None => call_guest_function_on_vcpu(function_name, return_type, args),
+    }
+}
+
+fn call_guest_function_on_vcpu(
+    function_name: &str,
+    return_type: ReturnType,
+    args: Option<Vec<ParameterValue>>,
+) -> Result<ReturnValue> {
+    let (vcpu, host_call) = match (
+        VCPU.with(|vcpu| vcpu.borrow().clone()),
+        HOST_CALL.with(|host_call| host_call.borrow().clone()),
+    ) {
+        (Some(vcpu), Some(host_call)) => (vcpu, host_call),
+        _ => {
+            return Err(new_error!(
+                "Guest functions can only be called from host functions the guest has called"
+            ))
+        }
+    };
+    let hv_handler = vcpu.hv_handler.ok_or_else(|| {
+        new_error!("Calling the guest from a host function is not supported here")
+    })?;
+    let max_depth = hv_handler.configuration().max_nested_guest_call_depth;
+    if vcpu.depth >= max_depth {
+        return Err(NestedGuestCallDepthExceeded(max_depth));
+    }
+    let dispatch_func_addr = hv_handler
+        .configuration()
+        .dispatch_function_addr
+        .try_lock()
+        .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+        .clone()
+        .ok_or_else(|| new_error!("Hypervisor not initialized"))?;
+
+    let fc = FunctionCall::new(
+        function_name.to_string(),
+        args,
+        FunctionCallType::Guest,
+        return_type,
+    );
+    let buffer: Vec<u8> = fc
+        .try_into()
+        .map_err(|_| HyperlightError::Error("Failed to serialize FunctionCall".to_string()))?;
+    let mut mem_mgr = host_call.mem_mgr.clone();
+    mem_mgr.as_mut().write_guest_function_call(&buffer)?;
+
+    // The outb handler the guest is waiting on is busy, so the nested call
+    // gets its own
+    let outb_handle_fn = outb_handler_wrapper(
+        host_call.mem_mgr,
+        host_call.host_funcs,
+        host_call.host_function_timer,
+    );
+    // SAFETY: the vCPU's guard was entered on this thread and hasn't been
+    // dropped, so it's waiting for the outb that led to this call, and
+    // nothing else is using it
+    let hv = unsafe { &mut *vcpu.hv };
+    hv.dispatch_nested_call_from_host(
+        dispatch_func_addr,
+        outb_handle_fn,
+        vcpu.mem_access_fn,
+        Some(hv_handler),
+    )?;
+
+    mem_mgr.check_stack_guard()?;
+    if let Err(e) = check_for_guest_error(&mem_mgr) {
+        // the guest waiting for the host function hasn't failed
+        mem_mgr.as_mut().clear_guest_error()?;
+        return Err(e);
+    }
+    mem_mgr.as_mut().get_guest_function_call_result()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use hyperlight_testing::callback_guest_as_string;
+
+    use super::*;
+    use crate::func::HostFunction1;
+    use crate::sandbox::SandboxBuilder;
+    use crate::sandbox_state::sandbox::EvolvableSandbox;
+    use crate::sandbox_state::transition::Noop;
+    use crate::{GuestBinary, MultiUseSandbox};
+
+    /// A host function that calls the guest function `function_name` with
+    /// the message it was called with
+    fn call_guest(function_name: &'static str) -> impl FnMut(String) -> Result<i32> + Send {
+        move |msg: String| -> Result<i32> {
+            let res = call_guest_function_from_host_function(
+                function_name,
+                ReturnType::Int,
+                Some(vec![ParameterValue::String(msg)]),
+            )?;
+            i32::try_from(res).map_err(|e| new_error!("{}", e))
+        }
+    }
+
+    /// A sandbox where calling `GuestMethod` leads to calls nested two deep:
+    /// the guest calls `HostMethod`, which calls `GuestMethod1`, which calls
+    /// `HostMethod1`, which calls `PrintOutput`
+    fn new_sandbox(max_nested_guest_call_depth: u8) -> MultiUseSandbox {
+        let writer = Arc::new(Mutex::new(|msg: String| -> Result<i32> {
+            Ok(msg.len() as i32)
+        }));
+        let mut u_sbox =
+            SandboxBuilder::new(GuestBinary::FilePath(callback_guest_as_string().unwrap()))
+                .with_max_nested_guest_call_depth(max_nested_guest_call_depth)
+                .with_max_execution_time(Duration::from_secs(10))
+                .with_host_print_fn(&writer)
+                .build()
+                .unwrap();
+        Arc::new(Mutex::new(call_guest("GuestMethod1")))
+            .register(&mut u_sbox, "HostMethod")
+            .unwrap();
+        Arc::new(Mutex::new(call_guest("PrintOutput")))
+            .register(&mut u_sbox, "HostMethod1")
+            .unwrap();
+        u_sbox.evolve(Noop::default()).unwrap()
+    }
+
+    #[test]
+    fn nested_calls() {
+        let mut sbox = new_sandbox(2);
+        let call = |sbox: &mut MultiUseSandbox| {
+            sbox.call_guest_function_by_name(
+                "GuestMethod",
+                ReturnType::Int,
+                Some(vec![ParameterValue::String("hello".to_string())]),
+            )
+        };
+        let printed = "Hello from GuestFunction1, Hello from GuestFunction, hello";
+        for _ in 0..2 {
+            assert_eq!(
+                call(&mut sbox).unwrap(),
+                ReturnValue::Int(printed.len() as i32)
+            );
+        }
+
+        let mut sbox = new_sandbox(1);
+        let res = call(&mut sbox);
+        assert!(
+            matches!(&res, Err(e) if e.to_string().contains(&NestedGuestCallDepthExceeded(1).to_string())),
+            "{:?}",
+            res
+        );
+
+        // guest functions can't be called from anywhere else
+        assert!(call_guest_function_from_host_function("Echo", ReturnType::String, None).is_err());
+    }
+
+    #[test]
+    fn reentrant_host_function_call() {
+        // `HostMethod` calls `GuestMethod`, which calls `HostMethod` again
+        let mut u_sbox =
+            SandboxBuilder::new(GuestBinary::FilePath(callback_guest_as_string().unwrap()))
+                .with_max_execution_time(Duration::from_secs(10))
+                .build()
+                .unwrap();
+        Arc::new(Mutex::new(call_guest("GuestMethod")))
+            .register(&mut u_sbox, "HostMethod")
+            .unwrap();
+        let mut sbox: MultiUseSandbox = u_sbox.evolve(Noop::default()).unwrap();
+
+        let res = sbox.call_guest_function_by_name(
+            "GuestMethod",
+            ReturnType::Int,
+            Some(vec![ParameterValue::String("hello".to_string())]),
+        );
+        assert!(
+            matches!(&res, Err(e) if e.to_string().contains("can't be called from a guest function it called")),
+            "{:?}",
+            res
+        );
+    }
+}
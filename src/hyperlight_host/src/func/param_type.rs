@@ -14,6 +14,8 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::collections::BTreeMap;
+
 use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterType, ParameterValue};
 use tracing::{instrument, Span};
 
@@ -147,6 +149,50 @@ impl SupportedParameterType<u64> for u64 {
     }
 }
 
+impl SupportedParameterType<f32> for f32 {
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ParameterType {
+        ParameterType::Float
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ParameterValue {
+        ParameterValue::Float(*self)
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ParameterValue) -> Result<f32> {
+        match a {
+            ParameterValue::Float(f) => Ok(f),
+            other => {
+                log_then_return!(ParameterValueConversionFailure(other.clone(), "f32"));
+            }
+        }
+    }
+}
+
+impl SupportedParameterType<f64> for f64 {
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ParameterType {
+        ParameterType::Double
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ParameterValue {
+        ParameterValue::Double(*self)
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ParameterValue) -> Result<f64> {
+        match a {
+            ParameterValue::Double(d) => Ok(d),
+            other => {
+                log_then_return!(ParameterValueConversionFailure(other.clone(), "f64"));
+            }
+        }
+    }
+}
+
 impl SupportedParameterType<bool> for bool {
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
     fn get_hyperlight_type() -> ParameterType {
@@ -190,3 +236,134 @@ impl SupportedParameterType<Vec<u8>> for Vec<u8> {
         }
     }
 }
+
+macro_rules! impl_supported_parameter_type_for_vec {
+    ($($T:ty),+) => {
+        $(
+            impl SupportedParameterType<Vec<$T>> for Vec<$T> {
+                #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+                fn get_hyperlight_type() -> ParameterType {
+                    ParameterType::List
+                }
+
+                #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+                fn get_hyperlight_value(&self) -> ParameterValue {
+                    ParameterValue::List(self.iter().map(|v| v.get_hyperlight_value()).collect())
+                }
+
+                #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+                fn get_inner(a: ParameterValue) -> Result<Vec<$T>> {
+                    match a {
+                        ParameterValue::List(v) => v.into_iter().map(<$T>::get_inner).collect(),
+                        other => {
+                            log_then_return!(ParameterValueConversionFailure(
+                                other.clone(),
+                                concat!("Vec<", stringify!($T), ">")
+                            ));
+                        }
+                    }
+                }
+            }
+        )+
+    };
+}
+
+// `Vec<u8>` is passed as `VecBytes`, so lists are only supported for these
+// element types rather than for every `SupportedParameterType`
+impl_supported_parameter_type_for_vec!(String, i32, u32, i64, u64, f32, f64, bool);
+
+impl<T: SupportedParameterType<T>> SupportedParameterType<Option<T>> for Option<T> {
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ParameterType {
+        ParameterType::Optional
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ParameterValue {
+        ParameterValue::Optional(self.as_ref().map(|v| Box::new(v.get_hyperlight_value())))
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ParameterValue) -> Result<Option<T>> {
+        match a {
+            ParameterValue::Optional(v) => v.map(|v| T::get_inner(*v)).transpose(),
+            other => {
+                log_then_return!(ParameterValueConversionFailure(other.clone(), "Option<T>"));
+            }
+        }
+    }
+}
+
+impl<T: SupportedParameterType<T>> SupportedParameterType<BTreeMap<String, T>>
+    for BTreeMap<String, T>
+{
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ParameterType {
+        ParameterType::Map
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ParameterValue {
+        ParameterValue::Map(
+            self.iter()
+                .map(|(k, v)| (k.clone(), v.get_hyperlight_value()))
+                .collect(),
+        )
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ParameterValue) -> Result<BTreeMap<String, T>> {
+        match a {
+            ParameterValue::Map(m) => m
+                .into_iter()
+                .map(|(k, v)| Ok((k, T::get_inner(v)?)))
+                .collect(),
+            other => {
+                log_then_return!(ParameterValueConversionFailure(
+                    other.clone(),
+                    "BTreeMap<String, T>"
+                ));
+            }
+        }
+    }
+}
+
+/// A tuple of `SupportedParameterType`s, used as the arguments to a typed
+/// guest function call such as `MultiUseSandbox::call`.
+///
+/// This is implemented for `()` and for tuples of up to 10 elements, to
+/// match the arities supported by `HostFunction0` to `HostFunction10`.
+pub trait SupportedParameterTuple {
+    /// Get the underlying Hyperlight parameter values for the elements of
+    /// this tuple, or `None` if the tuple is empty
+    fn get_hyperlight_values(&self) -> Option<Vec<ParameterValue>>;
+}
+
+impl SupportedParameterTuple for () {
+    fn get_hyperlight_values(&self) -> Option<Vec<ParameterValue>> {
+        None
+    }
+}
+
+macro_rules! impl_supported_parameter_tuple {
+    ($($P:ident),+) => {
+        impl<$($P: SupportedParameterType<$P>),+> SupportedParameterTuple for ($($P,)+) {
+            #[allow(non_snake_case)]
+            fn get_hyperlight_values(&self) -> Option<Vec<ParameterValue>> {
+                let ($($P,)+) = self;
+                Some(vec![$($P.get_hyperlight_value()),+])
+            }
+        }
+    };
+}
+
+impl_supported_parameter_tuple!(P1);
+impl_supported_parameter_tuple!(P1, P2);
+impl_supported_parameter_tuple!(P1, P2, P3);
+impl_supported_parameter_tuple!(P1, P2, P3, P4);
+impl_supported_parameter_tuple!(P1, P2, P3, P4, P5);
+impl_supported_parameter_tuple!(P1, P2, P3, P4, P5, P6);
+impl_supported_parameter_tuple!(P1, P2, P3, P4, P5, P6, P7);
+impl_supported_parameter_tuple!(P1, P2, P3, P4, P5, P6, P7, P8);
+impl_supported_parameter_tuple!(P1, P2, P3, P4, P5, P6, P7, P8, P9);
+impl_supported_parameter_tuple!(P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
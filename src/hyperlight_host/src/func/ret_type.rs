@@ -14,9 +14,12 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::collections::BTreeMap;
+
 use hyperlight_common::flatbuffer_wrappers::function_types::{ReturnType, ReturnValue};
 use tracing::{instrument, Span};
 
+use super::SupportedParameterType;
 use crate::HyperlightError::ReturnValueConversionFailure;
 use crate::{log_then_return, Result};
 
@@ -164,6 +167,50 @@ impl SupportedReturnType<u64> for u64 {
     }
 }
 
+impl SupportedReturnType<f32> for f32 {
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ReturnType {
+        ReturnType::Float
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ReturnValue {
+        ReturnValue::Float(*self)
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ReturnValue) -> Result<f32> {
+        match a {
+            ReturnValue::Float(f) => Ok(f),
+            other => {
+                log_then_return!(ReturnValueConversionFailure(other.clone(), "f32"));
+            }
+        }
+    }
+}
+
+impl SupportedReturnType<f64> for f64 {
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ReturnType {
+        ReturnType::Double
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ReturnValue {
+        ReturnValue::Double(*self)
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ReturnValue) -> Result<f64> {
+        match a {
+            ReturnValue::Double(d) => Ok(d),
+            other => {
+                log_then_return!(ReturnValueConversionFailure(other.clone(), "f64"));
+            }
+        }
+    }
+}
+
 impl SupportedReturnType<bool> for bool {
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
     fn get_hyperlight_type() -> ReturnType {
@@ -207,3 +254,101 @@ impl SupportedReturnType<Vec<u8>> for Vec<u8> {
         }
     }
 }
+
+macro_rules! impl_supported_return_type_for_vec {
+    ($($T:ty),+) => {
+        $(
+            impl SupportedReturnType<Vec<$T>> for Vec<$T> {
+                #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+                fn get_hyperlight_type() -> ReturnType {
+                    ReturnType::List
+                }
+
+                #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+                fn get_hyperlight_value(&self) -> ReturnValue {
+                    ReturnValue::List(
+                        self.iter()
+                            .map(SupportedParameterType::get_hyperlight_value)
+                            .collect(),
+                    )
+                }
+
+                #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+                fn get_inner(a: ReturnValue) -> Result<Vec<$T>> {
+                    match a {
+                        ReturnValue::List(v) => v
+                            .into_iter()
+                            .map(<$T as SupportedParameterType<$T>>::get_inner)
+                            .collect(),
+                        other => {
+                            log_then_return!(ReturnValueConversionFailure(
+                                other.clone(),
+                                concat!("Vec<", stringify!($T), ">")
+                            ));
+                        }
+                    }
+                }
+            }
+        )+
+    };
+}
+
+// `Vec<u8>` is returned as `VecBytes`, so lists are only supported for these
+// element types rather than for every `SupportedParameterType`
+impl_supported_return_type_for_vec!(String, i32, u32, i64, u64, f32, f64, bool);
+
+impl<T: SupportedParameterType<T>> SupportedReturnType<Option<T>> for Option<T> {
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ReturnType {
+        ReturnType::Optional
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ReturnValue {
+        ReturnValue::Optional(self.as_ref().map(|v| Box::new(v.get_hyperlight_value())))
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ReturnValue) -> Result<Option<T>> {
+        match a {
+            ReturnValue::Optional(v) => v.map(|v| T::get_inner(*v)).transpose(),
+            other => {
+                log_then_return!(ReturnValueConversionFailure(other.clone(), "Option<T>"));
+            }
+        }
+    }
+}
+
+impl<T: SupportedParameterType<T>> SupportedReturnType<BTreeMap<String, T>>
+    for BTreeMap<String, T>
+{
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_type() -> ReturnType {
+        ReturnType::Map
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_hyperlight_value(&self) -> ReturnValue {
+        ReturnValue::Map(
+            self.iter()
+                .map(|(k, v)| (k.clone(), v.get_hyperlight_value()))
+                .collect(),
+        )
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_inner(a: ReturnValue) -> Result<BTreeMap<String, T>> {
+        match a {
+            ReturnValue::Map(m) => m
+                .into_iter()
+                .map(|(k, v)| Ok((k, T::get_inner(v)?)))
+                .collect(),
+            other => {
+                log_then_return!(ReturnValueConversionFailure(
+                    other.clone(),
+                    "BTreeMap<String, T>"
+                ));
+            }
+        }
+    }
+}
@@ -16,7 +16,7 @@ limitations under the License.
 
 use std::fmt::{Debug, Formatter};
 
-use log::error;
+use log::{error, LevelFilter};
 use mshv_bindings::{
     hv_message, hv_message_type, hv_message_type_HVMSG_GPA_INTERCEPT,
     hv_message_type_HVMSG_UNMAPPED_GPA, hv_message_type_HVMSG_X64_HALT,
@@ -30,8 +30,8 @@ use tracing::{instrument, Span};
 use super::fpu::{FP_CONTROL_WORD_DEFAULT, FP_TAG_WORD_DEFAULT, MXCSR_DEFAULT};
 use super::handlers::{MemAccessHandlerWrapper, OutBHandlerWrapper};
 use super::{
-    Hypervisor, VirtualCPU, CR0_AM, CR0_ET, CR0_MP, CR0_NE, CR0_PE, CR0_PG, CR0_WP, CR4_OSFXSR,
-    CR4_OSXMMEXCPT, CR4_PAE, EFER_LMA, EFER_LME, EFER_NX, EFER_SCE,
+    nested_call_stack_pointer, Hypervisor, VirtualCPU, CR0_AM, CR0_ET, CR0_MP, CR0_NE, CR0_PE,
+    CR0_PG, CR0_WP, CR4_OSFXSR, CR4_OSXMMEXCPT, CR4_PAE, EFER_LMA, EFER_LME, EFER_NX, EFER_SCE,
 };
 use crate::hypervisor::hypervisor_handler::HypervisorHandler;
 use crate::hypervisor::HyperlightExit;
@@ -65,6 +65,8 @@ pub(super) struct HypervLinuxDriver {
     vcpu_fd: VcpuFd,
     entrypoint: u64,
     mem_regions: Vec<MemoryRegion>,
+    /// Host memory mapped into the guest for the duration of a call
+    mapped_regions: Vec<MemoryRegion>,
     orig_rsp: GuestPtr,
 }
 
@@ -104,6 +106,7 @@ impl HypervLinuxDriver {
             vm_fd,
             vcpu_fd,
             mem_regions,
+            mapped_regions: Vec::new(),
             entrypoint: entrypoint_ptr.absolute()?,
             orig_rsp: rsp_ptr,
         })
@@ -146,6 +149,9 @@ impl Debug for HypervLinuxDriver {
         for region in &self.mem_regions {
             f.field("Memory Region", &region);
         }
+        for region in &self.mapped_regions {
+            f.field("Mapped Memory Region", &region);
+        }
 
         let regs = self.vcpu_fd.get_regs();
 
@@ -173,7 +179,13 @@ impl Hypervisor for HypervLinuxDriver {
         outb_hdl: OutBHandlerWrapper,
         mem_access_hdl: MemAccessHandlerWrapper,
         hv_handler: Option<HypervisorHandler>,
+        max_guest_log_level: Option<LevelFilter>,
     ) -> Result<()> {
+        let max_guest_log_level: u64 = match max_guest_log_level {
+            Some(level) => level as u64,
+            None => self.get_max_log_level().into(),
+        };
+
         let regs = StandardRegisters {
             rip: self.entrypoint,
             rsp: self.orig_rsp.absolute()?,
@@ -183,7 +195,7 @@ impl Hypervisor for HypervLinuxDriver {
             rcx: peb_addr.into(),
             rdx: seed,
             r8: page_size.into(),
-            r9: self.get_max_log_level().into(),
+            r9: max_guest_log_level,
 
             ..Default::default()
         };
@@ -205,6 +217,17 @@ impl Hypervisor for HypervLinuxDriver {
         Ok(())
     }
 
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn initialise_from_snapshot(&mut self) -> Result<()> {
+        // leave the registers as `initialise` would have
+        self.vcpu_fd.set_regs(&StandardRegisters {
+            rsp: self.orig_rsp.absolute()?,
+            rflags: 2, //bit 1 of rlags is required to be set
+            ..Default::default()
+        })?;
+        Ok(())
+    }
+
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     fn dispatch_call_from_host(
         &mut self,
@@ -249,6 +272,45 @@ impl Hypervisor for HypervLinuxDriver {
         Ok(())
     }
 
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn dispatch_nested_call_from_host(
+        &mut self,
+        dispatch_func_addr: RawPtr,
+        outb_handle_fn: OutBHandlerWrapper,
+        mem_access_fn: MemAccessHandlerWrapper,
+        hv_handler: Option<HypervisorHandler>,
+    ) -> Result<()> {
+        // RIP still points at the OUT instruction the guest is waiting on,
+        // and is moved past it by `handle_io` once the host function returns
+        let regs_before = self.vcpu_fd.get_regs()?;
+        let fpu_before = self.vcpu_fd.get_fpu()?;
+
+        self.vcpu_fd.set_regs(&StandardRegisters {
+            rip: dispatch_func_addr.into(),
+            rsp: nested_call_stack_pointer(regs_before.rsp, self.orig_rsp.absolute()?),
+            rflags: 2, //bit 1 of rlags is required to be set
+            ..Default::default()
+        })?;
+        self.vcpu_fd.set_fpu(&FloatingPointUnit {
+            fcw: FP_CONTROL_WORD_DEFAULT,
+            ftwx: FP_TAG_WORD_DEFAULT,
+            mxcsr: MXCSR_DEFAULT,
+            ..Default::default() // zero out the rest
+        })?;
+
+        let res = VirtualCPU::run(
+            self.as_mut_hypervisor(),
+            hv_handler,
+            outb_handle_fn,
+            mem_access_fn,
+        );
+
+        // put the waiting guest back the way it was
+        self.vcpu_fd.set_regs(&regs_before)?;
+        self.vcpu_fd.set_fpu(&fpu_before)?;
+        res
+    }
+
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     fn handle_io(
         &mut self,
@@ -325,11 +387,15 @@ impl Hypervisor for HypervLinuxDriver {
                     );
                     #[cfg(all(debug_assertions, feature = "dump_on_crash"))]
                     self.dump_on_crash(self.mem_regions.clone());
-                    match self.get_memory_access_violation(
-                        gpa as usize,
-                        &self.mem_regions,
-                        access_info,
-                    ) {
+                    match self
+                        .get_memory_access_violation(gpa as usize, &self.mem_regions, access_info)
+                        .or_else(|| {
+                            self.get_memory_access_violation(
+                                gpa as usize,
+                                &self.mapped_regions,
+                                access_info,
+                            )
+                        }) {
                         Some(access_info_violation) => access_info_violation,
                         None => HyperlightExit::Mmio(gpa),
                     }
@@ -356,6 +422,22 @@ impl Hypervisor for HypervLinuxDriver {
         Ok(result)
     }
 
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    unsafe fn map_region(&mut self, region: &MemoryRegion) -> Result<()> {
+        self.vm_fd.map_user_memory(region.to_owned().into())?;
+        self.mapped_regions.push(region.clone());
+        Ok(())
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn unmap_regions(&mut self) -> Result<()> {
+        for region in &self.mapped_regions {
+            self.vm_fd.unmap_user_memory(region.to_owned().into())?;
+        }
+        self.mapped_regions.clear();
+        Ok(())
+    }
+
     #[instrument(skip_all, parent = Span::current(), level = "Trace")]
     fn as_mut_hypervisor(&mut self) -> &mut dyn Hypervisor {
         self as &mut dyn Hypervisor
@@ -365,7 +447,7 @@ impl Hypervisor for HypervLinuxDriver {
 impl Drop for HypervLinuxDriver {
     #[instrument(skip_all, parent = Span::current(), level = "Trace")]
     fn drop(&mut self) {
-        for region in &self.mem_regions {
+        for region in self.mem_regions.iter().chain(&self.mapped_regions) {
             let mshv_region: mshv_user_mem_region = region.to_owned().into();
             match self.vm_fd.unmap_user_memory(mshv_region) {
                 Ok(_) => (),
@@ -16,18 +16,23 @@ limitations under the License.
 
 #[cfg(target_os = "windows")]
 use core::ffi::c_void;
+use std::cmp::min;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use std::task::Waker;
 use std::thread;
 use std::thread::{sleep, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "linux")]
 use crossbeam::atomic::AtomicCell;
+#[cfg(feature = "async")]
+use crossbeam_channel::TryRecvError;
 use crossbeam_channel::{Receiver, Sender};
 #[cfg(target_os = "linux")]
 use libc::{pthread_kill, pthread_self, ESRCH};
-use log::{error, info};
+use log::{error, info, LevelFilter};
 use tracing::{instrument, Span};
 #[cfg(target_os = "linux")]
 use vmm_sys_util::signal::SIGRTMIN;
@@ -37,15 +42,20 @@ use windows::Win32::System::Hypervisor::{WHvCancelRunVirtualProcessor, WHV_PARTI
 #[cfg(feature = "function_call_metrics")]
 use crate::histogram_vec_observe;
 use crate::hypervisor::handlers::{MemAccessHandlerWrapper, OutBHandlerWrapper};
+use crate::hypervisor::interrupt_handle::InterruptHandle;
 use crate::hypervisor::Hypervisor;
 use crate::mem::layout::SandboxMemoryLayout;
+use crate::mem::memory_region::MemoryRegion;
 use crate::mem::mgr::SandboxMemoryManager;
 use crate::mem::ptr::{GuestPtr, RawPtr};
 use crate::mem::ptr_offset::Offset;
 use crate::mem::shared_mem::{GuestSharedMemory, HostSharedMemory, SharedMemory};
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use crate::sandbox::host_funcs::HostFunctionWorkerSpawner;
 use crate::sandbox::hypervisor::{get_available_hypervisor, HypervisorType};
 #[cfg(feature = "function_call_metrics")]
 use crate::sandbox::metrics::SandboxMetric::GuestFunctionCallDurationMicroseconds;
+use crate::sandbox::ExecutionTimeBudget;
 #[cfg(target_os = "linux")]
 use crate::signal_handlers::setup_signal_handlers;
 use crate::HyperlightError::{
@@ -96,6 +106,9 @@ struct HvHandlerExecVars {
     running: Arc<AtomicBool>,
     #[cfg(target_os = "linux")]
     run_cancelled: Arc<crossbeam::atomic::AtomicCell<bool>>,
+    #[cfg(feature = "async")]
+    waker: Arc<Mutex<Option<Waker>>>,
+    interrupt_handle: InterruptHandle,
 }
 
 impl HvHandlerExecVars {
@@ -161,6 +174,14 @@ impl HvHandlerExecVars {
             .try_lock()
             .map_err(|_| new_error!("Failed to get_timeout"))?)
     }
+
+    /// Wakes the task waiting on the result of an async guest function call, if any.
+    #[cfg(feature = "async")]
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().ok().and_then(|mut w| w.take()) {
+            waker.wake();
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -182,6 +203,104 @@ pub(crate) struct HvHandlerConfig {
     pub(crate) outb_handler: OutBHandlerWrapper,
     pub(crate) mem_access_handler: MemAccessHandlerWrapper,
     pub(crate) max_wait_for_cancellation: Duration,
+    pub(crate) max_guest_log_level: Option<LevelFilter>,
+    pub(crate) execution_time_budget: ExecutionTimeBudget,
+    pub(crate) max_host_function_time: Duration,
+    pub(crate) max_nested_guest_call_depth: u8,
+    pub(crate) host_function_timer: HostFunctionTimer,
+    /// The seccomp filter the Hypervisor Handler thread applies to itself
+    /// when it starts, if any, and what spawns its host function worker
+    /// threads instead of it
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub(crate) seccomp_filter: Option<(seccompiler::BpfProgram, HostFunctionWorkerSpawner)>,
+}
+
+/// Keeps track of the time a guest function call spends in host functions,
+/// so that it can be left out of the call's execution time when the
+/// sandbox's execution time budget is `ExecutionTimeBudget::GuestOnly`.
+///
+/// Clones share the same times, so that the outb handler, which runs on the
+/// Hypervisor Handler Thread, can record them for the thread waiting for the
+/// call to finish.
+#[derive(Clone, Default)]
+pub(crate) struct HostFunctionTimer(Arc<Mutex<HostFunctionTimes>>);
+
+#[derive(Default)]
+struct HostFunctionTimes {
+    /// When the guest function call being timed started, or `None` if no
+    /// call is being timed
+    call_started: Option<Instant>,
+    /// When the outermost host function currently being called started, if
+    /// any. Guest function calls that host functions make back into the
+    /// guest count towards the time of the host function that made them.
+    host_call_started: Option<Instant>,
+    /// How many host function calls are in progress, one inside the other
+    host_call_depth: usize,
+    /// The time the call has spent in host functions that have returned
+    host_time: Duration,
+}
+
+impl HostFunctionTimer {
+    fn times(&self) -> std::sync::MutexGuard<'_, HostFunctionTimes> {
+        // the times are always left consistent, so a poisoned lock is fine
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start timing a guest function call
+    fn start_call(&self) {
+        *self.times() = HostFunctionTimes {
+            call_started: Some(Instant::now()),
+            ..Default::default()
+        };
+    }
+
+    /// Stop timing the current guest function call, if any
+    fn stop_call(&self) {
+        self.times().call_started = None;
+    }
+
+    /// Record that the guest has called a host function
+    pub(crate) fn start_host_call(&self) {
+        let mut times = self.times();
+        if times.host_call_depth == 0 {
+            times.host_call_started = Some(Instant::now());
+        }
+        times.host_call_depth += 1;
+    }
+
+    /// Record that the host function the guest called has returned
+    pub(crate) fn stop_host_call(&self) {
+        let mut times = self.times();
+        times.host_call_depth = times.host_call_depth.saturating_sub(1);
+        if times.host_call_depth > 0 {
+            return;
+        }
+        if let Some(started) = times.host_call_started.take() {
+            times.host_time += started.elapsed();
+        }
+    }
+
+    /// Get how much longer to wait for the guest function call being timed
+    /// to finish, given that the guest may run for `timeout` and each host
+    /// function it calls for `max_host_function_time`. Returns `None` if no
+    /// call is being timed.
+    fn remaining(&self, timeout: Duration, max_host_function_time: Duration) -> Option<Duration> {
+        let times = self.times();
+        let call_elapsed = times.call_started?.elapsed();
+        match times.host_call_started {
+            Some(host_call_started) => {
+                let host_call_elapsed = host_call_started.elapsed();
+                let guest_time = call_elapsed.saturating_sub(times.host_time + host_call_elapsed);
+                // the guest can't use up its time until the host function
+                // returns, but it might return at any moment
+                Some(min(
+                    timeout.saturating_sub(guest_time),
+                    max_host_function_time.saturating_sub(host_call_elapsed),
+                ))
+            }
+            None => Some(timeout.saturating_sub(call_elapsed.saturating_sub(times.host_time))),
+        }
+    }
 }
 
 impl HypervisorHandler {
@@ -198,6 +317,13 @@ impl HypervisorHandler {
             from_handler_rx,
         };
 
+        #[cfg(feature = "async")]
+        let waker = Arc::new(Mutex::new(None));
+        #[cfg(feature = "async")]
+        let interrupt_handle = InterruptHandle::new(waker.clone());
+        #[cfg(not(feature = "async"))]
+        let interrupt_handle = InterruptHandle::new();
+
         let execution_variables = HvHandlerExecVars {
             join_handle: Arc::new(Mutex::new(None)),
             shm: Arc::new(Mutex::new(None)),
@@ -208,6 +334,9 @@ impl HypervisorHandler {
             running: Arc::new(AtomicBool::new(false)),
             #[cfg(target_os = "linux")]
             run_cancelled: Arc::new(AtomicCell::new(false)),
+            #[cfg(feature = "async")]
+            waker,
+            interrupt_handle,
             timeout: Arc::new(Mutex::new(configuration.max_init_time)),
         };
 
@@ -295,187 +424,306 @@ impl HypervisorHandler {
             thread::Builder::new()
                 .name("Hypervisor Handler".to_string())
                 .spawn(move || -> Result<()> {
-                    for action in to_handler_rx {
-                        match action {
-                            HypervisorHandlerAction::Initialise => {
-                                #[cfg(target_os = "linux")]
-                                {
-                                    // We cannot use the Killable trait, so we get the `pthread_t` via a libc
-                                    // call.
-                                    execution_variables.set_thread_id(unsafe { pthread_self() })?;
-                                }
-                                execution_variables.running.store(true, Ordering::SeqCst);
+                    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                    let (
+                        configuration_seccomp_filter,
+                        disallowed_syscall_tx,
+                        disallowed_syscall_execution_variables,
+                    ) = (
+                        configuration.seccomp_filter.clone(),
+                        from_handler_tx.clone(),
+                        execution_variables.clone(),
+                    );
+
+                    let handle_actions = move || -> Result<()> {
+                        for action in to_handler_rx {
+                            match action {
+                                HypervisorHandlerAction::Initialise
+                                | HypervisorHandlerAction::InitialiseFromSnapshot => {
+                                    let from_snapshot = matches!(
+                                        action,
+                                        HypervisorHandlerAction::InitialiseFromSnapshot
+                                    );
+
+                                    #[cfg(target_os = "linux")]
+                                    {
+                                        // We cannot use the Killable trait, so we get the `pthread_t` via a libc
+                                        // call.
+                                        execution_variables.set_thread_id(unsafe { pthread_self() })?;
+                                    }
+                                    execution_variables.running.store(true, Ordering::SeqCst);
 
-                                #[cfg(target_os = "linux")]
-                                execution_variables.run_cancelled.store(false);
+                                    #[cfg(target_os = "linux")]
+                                    execution_variables.run_cancelled.store(false);
 
-                                log::info!("Initialising Hypervisor Handler");
+                                    log::info!("Initialising Hypervisor Handler");
 
-                                let mut evar_lock_guard =
-                                    execution_variables.shm.try_lock().map_err(|e| {
-                                        new_error!(
-                                            "Error locking exec var shm lock: {}:{}: {}",
-                                            file!(),
-                                            line!(),
-                                            e
+                                    let mut evar_lock_guard =
+                                        execution_variables.shm.try_lock().map_err(|e| {
+                                            new_error!(
+                                                "Error locking exec var shm lock: {}:{}: {}",
+                                                file!(),
+                                                line!(),
+                                                e
+                                            )
+                                        })?;
+                                    let mem_lock_guard = evar_lock_guard
+                                        .as_mut()
+                                        .ok_or_else(|| {
+                                            new_error!("guest shm lock: {}:{}:", file!(), line!())
+                                        })?
+                                        .shared_mem
+                                        .lock
+                                        .try_read();
+                                    let res = if from_snapshot {
+                                        hv.initialise_from_snapshot()
+                                    } else {
+                                        hv.initialise(
+                                            configuration.peb_addr.clone(),
+                                            configuration.seed,
+                                            configuration.page_size,
+                                            configuration.outb_handler.clone(),
+                                            configuration.mem_access_handler.clone(),
+                                            Some(hv_handler_clone.clone()),
+                                            configuration.max_guest_log_level,
                                         )
-                                    })?;
-                                let mem_lock_guard = evar_lock_guard
-                                    .as_mut()
-                                    .ok_or_else(|| {
-                                        new_error!("guest shm lock: {}:{}:", file!(), line!())
-                                    })?
-                                    .shared_mem
-                                    .lock
-                                    .try_read();
-                                let res = hv.initialise(
-                                    configuration.peb_addr.clone(),
-                                    configuration.seed,
-                                    configuration.page_size,
-                                    configuration.outb_handler.clone(),
-                                    configuration.mem_access_handler.clone(),
-                                    Some(hv_handler_clone.clone()),
-                                );
-                                drop(mem_lock_guard);
-                                drop(evar_lock_guard);
-
-                                execution_variables.running.store(false, Ordering::SeqCst);
-
-                                match res {
-                                    Ok(_) => {
-                                        log::info!("Initialised Hypervisor Handler");
+                                    };
+                                    drop(mem_lock_guard);
+                                    mark_guest_dirty_pages(hv.as_mut(), evar_lock_guard.as_ref())?;
+                                    drop(evar_lock_guard);
+
+                                    execution_variables.running.store(false, Ordering::SeqCst);
+
+                                    match res {
+                                        Ok(_) => {
+                                            log::info!("Initialised Hypervisor Handler");
+                                            from_handler_tx
+                                                .send(HandlerMsg::FinishedHypervisorHandlerAction)
+                                                .map_err(|_| {
+                                                    HyperlightError::HypervisorHandlerCommunicationFailure()
+                                                })?;
+                                        }
+                                        Err(e) => {
+                                            log::info!(
+                                                "Error initialising Hypervisor Handler: {:?}",
+                                                e
+                                            );
+                                            from_handler_tx.send(HandlerMsg::Error(e)).map_err(|_| {
+                                                HyperlightError::HypervisorHandlerCommunicationFailure()
+                                            })?;
+                                        }
+                                    }
+                                }
+                                HypervisorHandlerAction::DispatchCallFromHost(function_name) => {
+                                    // Lock to indicate an action is being performed in the hypervisor
+                                    execution_variables.running.store(true, Ordering::SeqCst);
+
+                                    #[cfg(target_os = "linux")]
+                                    execution_variables.run_cancelled.store(false);
+
+                                    // A call that was interrupted before it started is cancelled
+                                    // here, as the thread waiting for it won't try to cancel it
+                                    // unless it's running.
+                                    if execution_variables.interrupt_handle.is_interrupted() {
+                                        info!("Call from host was interrupted: {}", function_name);
+                                        execution_variables.running.store(false, Ordering::SeqCst);
+                                        #[cfg(target_os = "linux")]
+                                        execution_variables.run_cancelled.store(true);
                                         from_handler_tx
-                                            .send(HandlerMsg::FinishedHypervisorHandlerAction)
+                                            .send(HandlerMsg::Error(
+                                                HyperlightError::ExecutionCanceledByHost(),
+                                            ))
                                             .map_err(|_| {
                                                 HyperlightError::HypervisorHandlerCommunicationFailure()
                                             })?;
+                                        #[cfg(feature = "async")]
+                                        execution_variables.wake();
+                                        continue;
                                     }
-                                    Err(e) => {
-                                        log::info!(
-                                            "Error initialising Hypervisor Handler: {:?}",
-                                            e
-                                        );
-                                        from_handler_tx.send(HandlerMsg::Error(e)).map_err(|_| {
-                                            HyperlightError::HypervisorHandlerCommunicationFailure()
+
+                                    info!("Dispatching call from host: {}", function_name);
+
+                                    let dispatch_function_addr = configuration
+                                        .dispatch_function_addr
+                                        .clone()
+                                        .try_lock()
+                                        .map_err(|e| {
+                                            new_error!(
+                                                "Error locking at {}:{}: {}",
+                                                file!(),
+                                                line!(),
+                                                e
+                                            )
+                                        })?
+                                        .clone()
+                                        .ok_or_else(|| new_error!("Hypervisor not initialized"))?;
+
+                                    let mut evar_lock_guard =
+                                        execution_variables.shm.try_lock().map_err(|e| {
+                                            new_error!(
+                                                "Error locking exec var shm lock: {}:{}: {}",
+                                                file!(),
+                                                line!(),
+                                                e
+                                            )
                                         })?;
-                                    }
-                                }
-                            }
-                            HypervisorHandlerAction::DispatchCallFromHost(function_name) => {
-                                // Lock to indicate an action is being performed in the hypervisor
-                                execution_variables.running.store(true, Ordering::SeqCst);
-
-                                #[cfg(target_os = "linux")]
-                                execution_variables.run_cancelled.store(false);
-
-                                info!("Dispatching call from host: {}", function_name);
-
-                                let dispatch_function_addr = configuration
-                                    .dispatch_function_addr
-                                    .clone()
-                                    .try_lock()
-                                    .map_err(|e| {
-                                        new_error!(
-                                            "Error locking at {}:{}: {}",
-                                            file!(),
-                                            line!(),
-                                            e
-                                        )
-                                    })?
-                                    .clone()
-                                    .ok_or_else(|| new_error!("Hypervisor not initialized"))?;
-
-                                let mut evar_lock_guard =
-                                    execution_variables.shm.try_lock().map_err(|e| {
-                                        new_error!(
-                                            "Error locking exec var shm lock: {}:{}: {}",
-                                            file!(),
-                                            line!(),
-                                            e
-                                        )
-                                    })?;
-                                let mem_lock_guard = evar_lock_guard
-                                    .as_mut()
-                                    .ok_or_else(|| {
-                                        new_error!("guest shm lock {}:{}", file!(), line!())
-                                    })?
-                                    .shared_mem
-                                    .lock
-                                    .try_read();
-
-                                let res = {
-                                    #[cfg(feature = "function_call_metrics")]
-                                    {
-                                        let start = std::time::Instant::now();
-                                        let result = hv.dispatch_call_from_host(
+                                    let mem_lock_guard = evar_lock_guard
+                                        .as_mut()
+                                        .ok_or_else(|| {
+                                            new_error!("guest shm lock {}:{}", file!(), line!())
+                                        })?
+                                        .shared_mem
+                                        .lock
+                                        .try_read();
+
+                                    let res = {
+                                        #[cfg(feature = "function_call_metrics")]
+                                        {
+                                            let start = std::time::Instant::now();
+                                            let result = hv.dispatch_call_from_host(
+                                                dispatch_function_addr,
+                                                configuration.outb_handler.clone(),
+                                                configuration.mem_access_handler.clone(),
+                                                Some(hv_handler_clone.clone()),
+                                            );
+                                            histogram_vec_observe!(
+                                                &GuestFunctionCallDurationMicroseconds,
+                                                &[function_name.as_str()],
+                                                start.elapsed().as_micros() as f64
+                                            );
+                                            result
+                                        }
+
+                                        #[cfg(not(feature = "function_call_metrics"))]
+                                        hv.dispatch_call_from_host(
                                             dispatch_function_addr,
                                             configuration.outb_handler.clone(),
                                             configuration.mem_access_handler.clone(),
                                             Some(hv_handler_clone.clone()),
-                                        );
-                                        histogram_vec_observe!(
-                                            &GuestFunctionCallDurationMicroseconds,
-                                            &[function_name.as_str()],
-                                            start.elapsed().as_micros() as f64
-                                        );
-                                        result
-                                    }
-
-                                    #[cfg(not(feature = "function_call_metrics"))]
-                                    hv.dispatch_call_from_host(
-                                        dispatch_function_addr,
-                                        configuration.outb_handler.clone(),
-                                        configuration.mem_access_handler.clone(),
-                                        Some(hv_handler_clone.clone()),
-                                    )
-                                };
-                                drop(mem_lock_guard);
-                                drop(evar_lock_guard);
-
-                                execution_variables.running.store(false, Ordering::SeqCst);
-
-                                match res {
-                                    Ok(_) => {
-                                        log::info!(
-                                            "Finished dispatching call from host: {}",
-                                            function_name
-                                        );
-                                        from_handler_tx
-                                            .send(HandlerMsg::FinishedHypervisorHandlerAction)
-                                            .map_err(|_| {
+                                        )
+                                    };
+                                    drop(mem_lock_guard);
+                                    mark_guest_dirty_pages(hv.as_mut(), evar_lock_guard.as_ref())?;
+                                    drop(evar_lock_guard);
+
+                                    execution_variables.running.store(false, Ordering::SeqCst);
+
+                                    match res {
+                                        Ok(_) => {
+                                            log::info!(
+                                                "Finished dispatching call from host: {}",
+                                                function_name
+                                            );
+                                            from_handler_tx
+                                                .send(HandlerMsg::FinishedHypervisorHandlerAction)
+                                                .map_err(|_| {
+                                                    HyperlightError::HypervisorHandlerCommunicationFailure()
+                                                })?;
+                                        }
+                                        Err(e) => {
+                                            log::info!(
+                                                "Error dispatching call from host: {}: {:?}",
+                                                function_name,
+                                                e
+                                            );
+                                            from_handler_tx.send(HandlerMsg::Error(e)).map_err(|_| {
                                                 HyperlightError::HypervisorHandlerCommunicationFailure()
                                             })?;
+                                        }
                                     }
-                                    Err(e) => {
-                                        log::info!(
-                                            "Error dispatching call from host: {}: {:?}",
-                                            function_name,
-                                            e
-                                        );
-                                        from_handler_tx.send(HandlerMsg::Error(e)).map_err(|_| {
-                                            HyperlightError::HypervisorHandlerCommunicationFailure()
-                                        })?;
-                                    }
+
+                                    #[cfg(feature = "async")]
+                                    execution_variables.wake();
+                                }
+                                HypervisorHandlerAction::MapRegion(region) => {
+                                    // SAFETY: the sender of this action keeps the region's host
+                                    // memory valid until it sends `UnmapRegions`.
+                                    let msg = match unsafe { hv.map_region(&region) } {
+                                        Ok(()) => HandlerMsg::FinishedHypervisorHandlerAction,
+                                        Err(e) => HandlerMsg::Error(e),
+                                    };
+                                    from_handler_tx.send(msg).map_err(|_| {
+                                        HyperlightError::HypervisorHandlerCommunicationFailure()
+                                    })?;
+                                }
+                                HypervisorHandlerAction::UnmapRegions => {
+                                    let msg = match hv.unmap_regions() {
+                                        Ok(()) => HandlerMsg::FinishedHypervisorHandlerAction,
+                                        Err(e) => HandlerMsg::Error(e),
+                                    };
+                                    from_handler_tx.send(msg).map_err(|_| {
+                                        HyperlightError::HypervisorHandlerCommunicationFailure()
+                                    })?;
+                                }
+                                HypervisorHandlerAction::TerminateHandlerThread => {
+                                    info!("Terminating Hypervisor Handler Thread");
+                                    break;
                                 }
-                            }
-                            HypervisorHandlerAction::TerminateHandlerThread => {
-                                info!("Terminating Hypervisor Handler Thread");
-                                break;
                             }
                         }
-                    }
 
-                    // If we make it here, it means the main thread issued a `TerminateHandlerThread` action,
-                    // and we are now exiting the handler thread.
+                        // If we make it here, it means the main thread issued a `TerminateHandlerThread` action,
+                        // and we are now exiting the handler thread.
+                        {
+                            from_handler_tx
+                                .send(HandlerMsg::FinishedHypervisorHandlerAction)
+                                .map_err(|_| {
+                                    HyperlightError::HypervisorHandlerCommunicationFailure()
+                                })?;
+                        }
+
+                        Ok(())
+                    };
+
+                    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+                    if let Some((seccomp_filter, host_function_worker_spawner)) =
+                        &configuration_seccomp_filter
                     {
-                        from_handler_tx
-                            .send(HandlerMsg::FinishedHypervisorHandlerAction)
-                            .map_err(|_| {
+                        // The host function worker threads only have their own filters applied,
+                        // rather than inheriting this one
+                        host_function_worker_spawner.clone().spawn_for_this_thread()?;
+                        // A disallowed syscall is reported as the result of the action being
+                        // handled instead
+                        crate::signal_handlers::report_panics_on_this_thread();
+                        if let Err(e) = seccompiler::apply_filter(seccomp_filter) {
+                            // this is received by the thread waiting for the first action
+                            let e = HyperlightError::from(e);
+                            log::error!("Error applying Hypervisor Handler seccomp filter: {}", e);
+                            disallowed_syscall_tx.send(HandlerMsg::Error(e)).map_err(|_| {
                                 HyperlightError::HypervisorHandlerCommunicationFailure()
                             })?;
+                            return Ok(());
+                        }
+
+                        // A disallowed syscall panics out of the code that made it, as returning
+                        // to it could be unsafe. The sandbox's state may be inconsistent then, so
+                        // the action being handled fails and the thread exits, closing the VM.
+                        return std::panic::catch_unwind(std::panic::AssertUnwindSafe(
+                            handle_actions,
+                        ))
+                        .unwrap_or_else(|payload| {
+                            if !matches!(
+                                payload.downcast_ref::<HyperlightError>(),
+                                Some(HyperlightError::DisallowedSyscall)
+                            ) {
+                                std::panic::resume_unwind(payload);
+                            }
+                            log::error!("Hypervisor Handler thread made a disallowed syscall");
+                            disallowed_syscall_execution_variables
+                                .running
+                                .store(false, Ordering::SeqCst);
+                            disallowed_syscall_tx
+                                .send(HandlerMsg::Error(HyperlightError::DisallowedSyscall))
+                                .map_err(|_| {
+                                    HyperlightError::HypervisorHandlerCommunicationFailure()
+                                })?;
+                            #[cfg(feature = "async")]
+                            disallowed_syscall_execution_variables.wake();
+                            Ok(())
+                        });
                     }
 
-                    Ok(())
+                    handle_actions()
                 })
         };
 
@@ -526,7 +774,20 @@ impl HypervisorHandler {
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     pub(crate) fn kill_hypervisor_handler_thread(&mut self) -> Result<()> {
         log::debug!("Killing Hypervisor Handler Thread");
-        self.execute_hypervisor_handler_action(HypervisorHandlerAction::TerminateHandlerThread)?;
+        // A thread that exited after a disallowed syscall can't be told to
+        // terminate, and only needs joining
+        let finished = self
+            .execution_variables
+            .join_handle
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished);
+        if !finished {
+            self.execute_hypervisor_handler_action(
+                HypervisorHandlerAction::TerminateHandlerThread,
+            )?;
+        }
 
         self.try_join_hypervisor_handler_thread()
     }
@@ -539,34 +800,91 @@ impl HypervisorHandler {
         &mut self,
         hypervisor_handler_action: HypervisorHandlerAction,
     ) -> Result<()> {
-        log::debug!(
-            "Sending Hypervisor Handler Action: {:?}",
-            hypervisor_handler_action
-        );
+        let timeout = self.default_timeout(&hypervisor_handler_action);
+        self.execute_hypervisor_handler_action_with_timeout(hypervisor_handler_action, timeout)
+    }
+
+    /// Send a message to the Hypervisor Handler and wait up to `timeout` for
+    /// a response, instead of the timeout configured for the action.
+    pub(crate) fn execute_hypervisor_handler_action_with_timeout(
+        &mut self,
+        hypervisor_handler_action: HypervisorHandlerAction,
+        timeout: Duration,
+    ) -> Result<()> {
+        // Async calls are only ever timed by the wall clock, so host function
+        // time is only left out of the time of calls that are waited for here.
+        if matches!(
+            hypervisor_handler_action,
+            HypervisorHandlerAction::DispatchCallFromHost(_)
+        ) && self.configuration.execution_time_budget == ExecutionTimeBudget::GuestOnly
+            && timeout != Duration::MAX
+        {
+            self.configuration.host_function_timer.start_call();
+        }
+
+        self.send_hypervisor_handler_action_with_timeout(hypervisor_handler_action, timeout)?;
+
+        log::debug!("Waiting for Hypervisor Handler Response");
+
+        self.try_receive_handler_msg()
+    }
+
+    /// Send a message to the Hypervisor Handler without waiting for a response.
+    ///
+    /// The response must later be received with `try_receive_handler_msg` (or,
+    /// for async calls, `poll_handler_msg`).
+    #[cfg(feature = "async")]
+    pub(crate) fn send_hypervisor_handler_action(
+        &mut self,
+        hypervisor_handler_action: HypervisorHandlerAction,
+    ) -> Result<()> {
+        let timeout = self.default_timeout(&hypervisor_handler_action);
+        self.send_hypervisor_handler_action_with_timeout(hypervisor_handler_action, timeout)
+    }
 
+    /// Get the timeout configured for `hypervisor_handler_action`.
+    fn default_timeout(&self, hypervisor_handler_action: &HypervisorHandlerAction) -> Duration {
         match hypervisor_handler_action {
-            HypervisorHandlerAction::Initialise => self
-                .execution_variables
-                .set_timeout(self.configuration.max_init_time)?,
-            HypervisorHandlerAction::DispatchCallFromHost(_) => self
-                .execution_variables
-                .set_timeout(self.configuration.max_exec_time)?,
-            HypervisorHandlerAction::TerminateHandlerThread => self
-                .execution_variables
-                .set_timeout(self.configuration.max_init_time)?,
+            HypervisorHandlerAction::Initialise
+            | HypervisorHandlerAction::InitialiseFromSnapshot => self.configuration.max_init_time,
+            HypervisorHandlerAction::DispatchCallFromHost(_) => self.configuration.max_exec_time,
+            HypervisorHandlerAction::MapRegion(_) | HypervisorHandlerAction::UnmapRegions => {
+                self.configuration.max_init_time
+            }
+            HypervisorHandlerAction::TerminateHandlerThread => self.configuration.max_init_time,
             // note: terminate can never hang, so setting the timeout for it is just
             // for completion of the match statement, and it is not really needed for
             // `TerminateHandlerThread`.
         }
+    }
+
+    /// Send a message to the Hypervisor Handler without waiting for a
+    /// response, which must be received within `timeout` instead of the
+    /// timeout configured for the action. A `timeout` of `Duration::MAX`
+    /// waits for the response forever.
+    pub(crate) fn send_hypervisor_handler_action_with_timeout(
+        &mut self,
+        hypervisor_handler_action: HypervisorHandlerAction,
+        timeout: Duration,
+    ) -> Result<()> {
+        log::debug!(
+            "Sending Hypervisor Handler Action: {:?}",
+            hypervisor_handler_action
+        );
+
+        self.execution_variables.set_timeout(timeout)?;
+
+        if matches!(
+            hypervisor_handler_action,
+            HypervisorHandlerAction::DispatchCallFromHost(_)
+        ) {
+            self.execution_variables.interrupt_handle.start_call();
+        }
 
         self.communication_channels
             .to_handler_tx
             .send(hypervisor_handler_action)
-            .map_err(|_| HyperlightError::HypervisorHandlerCommunicationFailure())?;
-
-        log::debug!("Waiting for Hypervisor Handler Response");
-
-        self.try_receive_handler_msg()
+            .map_err(|_| HyperlightError::HypervisorHandlerCommunicationFailure())
     }
 
     /// Try to receive a `HandlerMsg` from the Hypervisor Handler Thread.
@@ -577,20 +895,124 @@ impl HypervisorHandler {
     /// This function is only useful when we time out, handle a timeout,
     /// and still have to receive after sorting that out without sending
     /// an extra message.
+    ///
+    /// If the time spent in host functions is being left out of the call
+    /// being waited for, this waits until the guest has run for the timeout
+    /// or a host function it called has run for `max_host_function_time`.
+    ///
+    /// If the call being waited for is interrupted with an `InterruptHandle`
+    /// while it's running, this returns as if it had timed out.
     pub(crate) fn try_receive_handler_msg(&self) -> Result<()> {
-        match self
-            .communication_channels
-            .from_handler_rx
-            .recv_timeout(self.execution_variables.get_timeout()?)
-        {
-            Ok(msg) => match msg {
-                HandlerMsg::Error(e) => Err(e),
-                HandlerMsg::FinishedHypervisorHandlerAction => Ok(()),
-            },
-            Err(_) => Err(HyperlightError::HypervisorHandlerMessageReceiveTimedout()),
+        let timeout = self.execution_variables.get_timeout()?;
+        let timer = &self.configuration.host_function_timer;
+        let interrupt_handle = &self.execution_variables.interrupt_handle;
+        let started = Instant::now();
+        let remaining = || {
+            timer
+                .remaining(timeout, self.configuration.max_host_function_time)
+                .unwrap_or_else(|| timeout.saturating_sub(started.elapsed()))
+        };
+
+        loop {
+            crossbeam_channel::select! {
+                recv(self.communication_channels.from_handler_rx) -> msg => {
+                    timer.stop_call();
+                    interrupt_handle.finish_call();
+                    return match msg {
+                        Ok(HandlerMsg::Error(e)) => Err(e),
+                        Ok(HandlerMsg::FinishedHypervisorHandlerAction) => Ok(()),
+                        Err(_) => Err(HyperlightError::HypervisorHandlerMessageReceiveTimedout()),
+                    };
+                }
+                recv(interrupt_handle.wake_rx()) -> _ => {
+                    // a call that hasn't started yet is cancelled by the
+                    // Hypervisor Handler Thread, and one that has finished
+                    // sends its result as usual
+                    if self.is_interrupted_while_running() {
+                        timer.stop_call();
+                        interrupt_handle.finish_call();
+                        return Err(HyperlightError::HypervisorHandlerMessageReceiveTimedout());
+                    }
+                }
+                // part of the timeout may have been spent in host functions
+                // or waiting for an interrupted call
+                default(remaining()) => {
+                    if remaining().is_zero() {
+                        timer.stop_call();
+                        interrupt_handle.finish_call();
+                        return Err(HyperlightError::HypervisorHandlerMessageReceiveTimedout());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get a handle that can be used to interrupt the guest function calls
+    /// this Hypervisor Handler runs.
+    pub(crate) fn interrupt_handle(&self) -> InterruptHandle {
+        self.execution_variables.interrupt_handle.clone()
+    }
+
+    /// Whether the guest function call in flight has been interrupted while
+    /// the vCPU is running it, in which case it must be cancelled as if it
+    /// had timed out.
+    pub(crate) fn is_interrupted_while_running(&self) -> bool {
+        self.execution_variables.interrupt_handle.is_interrupted()
+            && self.execution_variables.running.load(Ordering::SeqCst)
+    }
+
+    /// Check, without blocking, whether the Hypervisor Handler Thread has
+    /// sent a `HandlerMsg`.
+    ///
+    /// Returns `None` if no message has been received yet, in which case
+    /// `waker` is woken when the current `DispatchCallFromHost` action
+    /// finishes.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_handler_msg(&self, waker: &Waker) -> Option<Result<()>> {
+        // Register the waker before checking the channel, so that a message
+        // sent between the check and the registration can't be missed.
+        if let Ok(mut w) = self.execution_variables.waker.lock() {
+            *w = Some(waker.clone());
+        }
+
+        match self.communication_channels.from_handler_rx.try_recv() {
+            Ok(msg) => {
+                self.clear_waker();
+                self.execution_variables.interrupt_handle.finish_call();
+                Some(match msg {
+                    HandlerMsg::Error(e) => Err(e),
+                    HandlerMsg::FinishedHypervisorHandlerAction => Ok(()),
+                })
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.clear_waker();
+                Some(Err(HyperlightError::HypervisorHandlerCommunicationFailure()))
+            }
+        }
+    }
+
+    /// Wake the task registered with `poll_handler_msg`, if any, so that it
+    /// is polled again.
+    #[cfg(feature = "async")]
+    pub(crate) fn wake(&self) {
+        self.execution_variables.wake();
+    }
+
+    /// Forget the waker registered with `poll_handler_msg`, if any.
+    #[cfg(feature = "async")]
+    pub(crate) fn clear_waker(&self) {
+        if let Ok(mut w) = self.execution_variables.waker.lock() {
+            *w = None;
         }
     }
 
+    /// Get the maximum execution time for a guest function call.
+    #[cfg(feature = "async")]
+    pub(crate) fn max_exec_time(&self) -> Duration {
+        self.configuration.max_exec_time
+    }
+
     /// Terminate the execution of the hypervisor handler
     ///
     /// This function is intended to be called after a guest function called has
@@ -664,6 +1086,11 @@ impl HypervisorHandler {
         res
     }
 
+    /// Get the configuration this handler was created with
+    pub(crate) fn configuration(&self) -> &HvHandlerConfig {
+        &self.configuration
+    }
+
     pub(crate) fn set_dispatch_function_addr(
         &mut self,
         dispatch_function_addr: RawPtr,
@@ -751,8 +1178,15 @@ impl HypervisorHandler {
 pub enum HypervisorHandlerAction {
     /// Initialise the vCPU
     Initialise,
+    /// Initialise the vCPU to call into a guest whose memory has been
+    /// restored from a snapshot, without running the guest's entrypoint
+    InitialiseFromSnapshot,
     /// Execute a function call (String = name) from the host
     DispatchCallFromHost(String),
+    /// Map host memory into the guest until `UnmapRegions`
+    MapRegion(MemoryRegion),
+    /// Unmap all host memory mapped with `MapRegion`
+    UnmapRegions,
     /// Terminate hypervisor handler thread
     TerminateHandlerThread,
 }
@@ -763,7 +1197,12 @@ impl std::fmt::Debug for HypervisorHandlerAction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             HypervisorHandlerAction::Initialise => write!(f, "Initialise"),
+            HypervisorHandlerAction::InitialiseFromSnapshot => {
+                write!(f, "InitialiseFromSnapshot")
+            }
             HypervisorHandlerAction::DispatchCallFromHost(_) => write!(f, "DispatchCallFromHost"),
+            HypervisorHandlerAction::MapRegion(_) => write!(f, "MapRegion"),
+            HypervisorHandlerAction::UnmapRegions => write!(f, "UnmapRegions"),
             HypervisorHandlerAction::TerminateHandlerThread => write!(f, "TerminateHandlerThread"),
         }
     }
@@ -778,6 +1217,21 @@ pub enum HandlerMsg {
     Error(HyperlightError),
 }
 
+/// Mark the pages of `mgr`'s memory that the guest has written to since
+/// this was last called as dirty, so that restoring the sandbox's memory
+/// from a snapshot only needs to copy those.
+fn mark_guest_dirty_pages(
+    hv: &mut dyn Hypervisor,
+    mgr: Option<&SandboxMemoryManager<GuestSharedMemory>>,
+) -> Result<()> {
+    let mgr = mgr.ok_or_else(|| new_error!("guest shm lock: {}:{}:", file!(), line!()))?;
+    let dirty_pages = hv.get_dirty_pages().unwrap_or_else(|e| {
+        log::error!("Error getting the pages written to by the guest: {:?}", e);
+        None
+    });
+    mgr.shared_mem.mark_guest_dirty_pages(dirty_pages)
+}
+
 fn set_up_hypervisor_partition(
     mgr: &mut SandboxMemoryManager<GuestSharedMemory>,
     #[allow(unused_variables)] // parameter only used for in-process mode
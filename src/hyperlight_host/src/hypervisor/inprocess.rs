@@ -71,6 +71,7 @@ impl<'a> Hypervisor for InprocessDriver<'a> {
         _outb_handle_fn: super::handlers::OutBHandlerWrapper,
         _mem_access_fn: super::handlers::MemAccessHandlerWrapper,
         _hv_handler: Option<super::hypervisor_handler::HypervisorHandler>,
+        max_guest_log_level: Option<log::LevelFilter>,
     ) -> crate::Result<()> {
         let entrypoint_fn: extern "win64" fn(u64, u64, u64, u64) =
             unsafe { std::mem::transmute(self.args.entrypoint_raw as *const c_void) };
@@ -79,7 +80,7 @@ impl<'a> Hypervisor for InprocessDriver<'a> {
             self.args.peb_ptr_raw,
             seed,
             page_size as u64,
-            log::max_level() as u64,
+            max_guest_log_level.unwrap_or(log::max_level()) as u64,
         );
 
         Ok(())
@@ -0,0 +1,117 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::sync::Mutex;
+#[cfg(feature = "async")]
+use std::task::Waker;
+
+use crossbeam_channel::{Receiver, Sender};
+
+/// A handle that can be used from any thread to cancel the guest function
+/// call a sandbox is running.
+///
+/// Get one from `MultiUseSandbox::interrupt_handle` before making the call.
+/// All clones of the handle refer to the same sandbox, and stay valid for as
+/// long as it, or any sandbox it's evolved into, exists.
+///
+/// A call that is interrupted fails with
+/// `HyperlightError::ExecutionCanceledByHost`, and the sandbox's state is
+/// reset to what it was before the call, as when a call times out.
+#[derive(Clone)]
+pub struct InterruptHandle(Arc<InterruptState>);
+
+struct InterruptState {
+    /// Whether a guest function call is in flight
+    call_in_flight: AtomicBool,
+    /// Whether the call in flight has been interrupted
+    interrupted: AtomicBool,
+    /// Wakes the thread waiting for the call in flight to finish
+    wake_tx: Sender<()>,
+    wake_rx: Receiver<()>,
+    /// Wakes the task waiting for the async call in flight to finish
+    #[cfg(feature = "async")]
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl InterruptHandle {
+    pub(crate) fn new(#[cfg(feature = "async")] waker: Arc<Mutex<Option<Waker>>>) -> Self {
+        let (wake_tx, wake_rx) = crossbeam_channel::unbounded();
+        Self(Arc::new(InterruptState {
+            call_in_flight: AtomicBool::new(false),
+            interrupted: AtomicBool::new(false),
+            wake_tx,
+            wake_rx,
+            #[cfg(feature = "async")]
+            waker,
+        }))
+    }
+
+    /// Cancel the guest function call the sandbox is running, if any.
+    ///
+    /// This doesn't wait for the call to be cancelled. Returns `false` if no
+    /// call was in flight, in which case nothing is cancelled, not even the
+    /// next call the sandbox makes.
+    pub fn kill(&self) -> bool {
+        if !self.0.call_in_flight.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.0.interrupted.store(true, Ordering::SeqCst);
+        let _ = self.0.wake_tx.send(());
+        #[cfg(feature = "async")]
+        if let Some(waker) = self.0.waker.lock().ok().and_then(|mut w| w.take()) {
+            waker.wake();
+        }
+        true
+    }
+
+    /// Record that a guest function call is about to be dispatched, which
+    /// clears any interrupt of an earlier call.
+    pub(crate) fn start_call(&self) {
+        while self.0.wake_rx.try_recv().is_ok() {}
+        self.0.interrupted.store(false, Ordering::SeqCst);
+        self.0.call_in_flight.store(true, Ordering::SeqCst);
+    }
+
+    /// Record that the guest function call in flight, if any, has finished
+    /// or been cancelled.
+    pub(crate) fn finish_call(&self) {
+        self.0.call_in_flight.store(false, Ordering::SeqCst);
+        self.0.interrupted.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether the guest function call in flight has been interrupted
+    pub(crate) fn is_interrupted(&self) -> bool {
+        self.0.interrupted.load(Ordering::SeqCst)
+    }
+
+    /// Get a receiver that receives a message whenever the call in flight is
+    /// interrupted
+    pub(crate) fn wake_rx(&self) -> &Receiver<()> {
+        &self.0.wake_rx
+    }
+}
+
+impl std::fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterruptHandle")
+            .field("call_in_flight", &self.0.call_in_flight)
+            .field("interrupted", &self.0.interrupted)
+            .finish()
+    }
+}
@@ -18,16 +18,21 @@ use std::convert::TryFrom;
 use std::fmt::Debug;
 
 use cfg_if::cfg_if;
-use kvm_bindings::{kvm_fpu, kvm_regs, kvm_userspace_memory_region, KVM_MEM_READONLY};
+use hyperlight_common::mem::PAGE_SIZE_USIZE;
+use kvm_bindings::{
+    kvm_fpu, kvm_regs, kvm_userspace_memory_region, KVM_MEM_LOG_DIRTY_PAGES, KVM_MEM_READONLY,
+};
 use kvm_ioctls::Cap::UserMemory;
 use kvm_ioctls::{Kvm, VcpuExit, VcpuFd, VmFd};
+use log::LevelFilter;
 use tracing::{instrument, Span};
 
 use super::fpu::{FP_CONTROL_WORD_DEFAULT, FP_TAG_WORD_DEFAULT, MXCSR_DEFAULT};
 use super::handlers::{MemAccessHandlerWrapper, OutBHandlerWrapper};
 use super::{
-    HyperlightExit, Hypervisor, VirtualCPU, CR0_AM, CR0_ET, CR0_MP, CR0_NE, CR0_PE, CR0_PG, CR0_WP,
-    CR4_OSFXSR, CR4_OSXMMEXCPT, CR4_PAE, EFER_LMA, EFER_LME, EFER_NX, EFER_SCE,
+    nested_call_stack_pointer, HyperlightExit, Hypervisor, VirtualCPU, CR0_AM, CR0_ET, CR0_MP,
+    CR0_NE, CR0_PE, CR0_PG, CR0_WP, CR4_OSFXSR, CR4_OSXMMEXCPT, CR4_PAE, EFER_LMA, EFER_LME,
+    EFER_NX, EFER_SCE,
 };
 use crate::hypervisor::hypervisor_handler::HypervisorHandler;
 use crate::mem::memory_region::{MemoryRegion, MemoryRegionFlags};
@@ -59,11 +64,14 @@ pub(crate) fn is_hypervisor_present() -> bool {
 /// A Hypervisor driver for KVM on Linux
 pub(super) struct KVMDriver {
     _kvm: Kvm,
-    _vm_fd: VmFd,
+    vm_fd: VmFd,
     vcpu_fd: VcpuFd,
     entrypoint: u64,
     orig_rsp: GuestPtr,
     mem_regions: Vec<MemoryRegion>,
+    /// Host memory mapped into the guest for the duration of a call, in
+    /// the memory slots after those of `mem_regions`
+    mapped_regions: Vec<MemoryRegion>,
 }
 
 impl KVMDriver {
@@ -84,21 +92,13 @@ impl KVMDriver {
 
         let vm_fd = kvm.create_vm_with_type(0)?;
 
-        let perm_flags =
-            MemoryRegionFlags::READ | MemoryRegionFlags::WRITE | MemoryRegionFlags::EXECUTE;
-
         mem_regions.iter().enumerate().try_for_each(|(i, region)| {
-            let perm_flags = perm_flags.intersection(region.flags);
-            let kvm_region = kvm_userspace_memory_region {
-                slot: i as u32,
-                guest_phys_addr: region.guest_region.start as u64,
-                memory_size: (region.guest_region.end - region.guest_region.start) as u64,
-                userspace_addr: region.host_region.start as u64,
-                flags: match perm_flags {
-                    MemoryRegionFlags::READ => KVM_MEM_READONLY,
-                    _ => 0, // normal, RWX
-                },
-            };
+            let mut kvm_region = Self::kvm_memory_region(i as u32, region);
+            // Log the guest's writes to the sandbox's memory, so that only
+            // the pages it writes to need to be restored from a snapshot
+            if kvm_region.flags & KVM_MEM_READONLY == 0 {
+                kvm_region.flags |= KVM_MEM_LOG_DIRTY_PAGES;
+            }
             unsafe { vm_fd.set_user_memory_region(kvm_region) }
         })?;
 
@@ -108,14 +108,32 @@ impl KVMDriver {
         let rsp_gp = GuestPtr::try_from(RawPtr::from(rsp))?;
         Ok(Self {
             _kvm: kvm,
-            _vm_fd: vm_fd,
+            vm_fd,
             vcpu_fd,
             entrypoint,
             orig_rsp: rsp_gp,
             mem_regions,
+            mapped_regions: Vec::new(),
         })
     }
 
+    /// Describe `region` as the KVM memory slot `slot`
+    fn kvm_memory_region(slot: u32, region: &MemoryRegion) -> kvm_userspace_memory_region {
+        let perm_flags =
+            MemoryRegionFlags::READ | MemoryRegionFlags::WRITE | MemoryRegionFlags::EXECUTE;
+        let perm_flags = perm_flags.intersection(region.flags);
+        kvm_userspace_memory_region {
+            slot,
+            guest_phys_addr: region.guest_region.start as u64,
+            memory_size: (region.guest_region.end - region.guest_region.start) as u64,
+            userspace_addr: region.host_region.start as u64,
+            flags: match perm_flags {
+                MemoryRegionFlags::READ => KVM_MEM_READONLY,
+                _ => 0, // normal, RWX
+            },
+        }
+    }
+
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     fn setup_inital_sregs(vcpu_fd: &mut VcpuFd, pml4_addr: u64) -> Result<()> {
         // setup paging and IA-32e (64-bit) mode
@@ -138,6 +156,9 @@ impl Debug for KVMDriver {
         for region in &self.mem_regions {
             f.field("Memory Region", &region);
         }
+        for region in &self.mapped_regions {
+            f.field("Mapped Memory Region", &region);
+        }
         let regs = self.vcpu_fd.get_regs();
         // check that regs is OK and then set field in debug struct
 
@@ -168,7 +189,13 @@ impl Hypervisor for KVMDriver {
         outb_hdl: OutBHandlerWrapper,
         mem_access_hdl: MemAccessHandlerWrapper,
         hv_handler: Option<HypervisorHandler>,
+        max_guest_log_level: Option<LevelFilter>,
     ) -> Result<()> {
+        let max_guest_log_level: u64 = match max_guest_log_level {
+            Some(level) => level as u64,
+            None => self.get_max_log_level().into(),
+        };
+
         let regs = kvm_regs {
             rip: self.entrypoint,
             rsp: self.orig_rsp.absolute()?,
@@ -177,7 +204,7 @@ impl Hypervisor for KVMDriver {
             rcx: peb_addr.into(),
             rdx: seed,
             r8: page_size.into(),
-            r9: self.get_max_log_level().into(),
+            r9: max_guest_log_level,
 
             ..Default::default()
         };
@@ -198,6 +225,16 @@ impl Hypervisor for KVMDriver {
         Ok(())
     }
 
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn initialise_from_snapshot(&mut self) -> Result<()> {
+        // leave the registers as `initialise` would have
+        self.vcpu_fd.set_regs(&kvm_regs {
+            rsp: self.orig_rsp.absolute()?,
+            ..Default::default()
+        })?;
+        Ok(())
+    }
+
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     fn dispatch_call_from_host(
         &mut self,
@@ -240,6 +277,58 @@ impl Hypervisor for KVMDriver {
         Ok(())
     }
 
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn dispatch_nested_call_from_host(
+        &mut self,
+        dispatch_func_addr: RawPtr,
+        outb_handle_fn: OutBHandlerWrapper,
+        mem_access_fn: MemAccessHandlerWrapper,
+        hv_handler: Option<HypervisorHandler>,
+    ) -> Result<()> {
+        // KVM only moves RIP past the OUT instruction the guest is waiting on
+        // when the vCPU is next run, so complete it without running any
+        // further guest instructions before saving the registers
+        self.vcpu_fd.set_kvm_immediate_exit(1);
+        let completed = self.vcpu_fd.run().map(|exit| format!("{:?}", exit));
+        self.vcpu_fd.set_kvm_immediate_exit(0);
+        match completed {
+            Err(e) if e.errno() == libc::EINTR => {}
+            Err(e) => {
+                log_then_return!("Error completing IO before nested call {:?}", e);
+            }
+            Ok(exit) => {
+                log_then_return!("Unexpected KVM exit before nested call {}", exit);
+            }
+        }
+
+        let regs_before = self.vcpu_fd.get_regs()?;
+        let fpu_before = self.vcpu_fd.get_fpu()?;
+
+        self.vcpu_fd.set_regs(&kvm_regs {
+            rip: dispatch_func_addr.into(),
+            rsp: nested_call_stack_pointer(regs_before.rsp, self.orig_rsp.absolute()?),
+            ..Default::default()
+        })?;
+        self.vcpu_fd.set_fpu(&kvm_fpu {
+            fcw: FP_CONTROL_WORD_DEFAULT,
+            ftwx: FP_TAG_WORD_DEFAULT,
+            mxcsr: MXCSR_DEFAULT,
+            ..Default::default() // zero out the rest
+        })?;
+
+        let res = VirtualCPU::run(
+            self.as_mut_hypervisor(),
+            hv_handler,
+            outb_handle_fn,
+            mem_access_fn,
+        );
+
+        // put the waiting guest back the way it was
+        self.vcpu_fd.set_regs(&regs_before)?;
+        self.vcpu_fd.set_fpu(&fpu_before)?;
+        res
+    }
+
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     fn handle_io(
         &mut self,
@@ -267,6 +356,52 @@ impl Hypervisor for KVMDriver {
         Ok(())
     }
 
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    unsafe fn map_region(&mut self, region: &MemoryRegion) -> Result<()> {
+        let slot = (self.mem_regions.len() + self.mapped_regions.len()) as u32;
+        let kvm_region = Self::kvm_memory_region(slot, region);
+        unsafe { self.vm_fd.set_user_memory_region(kvm_region) }?;
+        self.mapped_regions.push(region.clone());
+        Ok(())
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn unmap_regions(&mut self) -> Result<()> {
+        for (i, region) in self.mapped_regions.iter().enumerate() {
+            let slot = (self.mem_regions.len() + i) as u32;
+            // A memory slot is deleted by setting its size to 0
+            let kvm_region = kvm_userspace_memory_region {
+                memory_size: 0,
+                ..Self::kvm_memory_region(slot, region)
+            };
+            unsafe { self.vm_fd.set_user_memory_region(kvm_region) }?;
+        }
+        self.mapped_regions.clear();
+        Ok(())
+    }
+
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn get_dirty_pages(&mut self) -> Result<Option<Vec<usize>>> {
+        let mut dirty_pages = Vec::new();
+        for (slot, region) in self.mem_regions.iter().enumerate() {
+            if Self::kvm_memory_region(slot as u32, region).flags & KVM_MEM_READONLY != 0 {
+                continue;
+            }
+            let bitmap = self
+                .vm_fd
+                .get_dirty_log(slot as u32, region.guest_region.len())?;
+            for (i, word) in bitmap.into_iter().enumerate() {
+                let mut bits = word;
+                while bits != 0 {
+                    let page = i * 64 + bits.trailing_zeros() as usize;
+                    dirty_pages.push(region.host_region.start + page * PAGE_SIZE_USIZE);
+                    bits &= bits - 1;
+                }
+            }
+        }
+        Ok(Some(dirty_pages))
+    }
+
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     fn run(&mut self) -> Result<HyperlightExit> {
         let exit_reason = self.vcpu_fd.run();
@@ -286,11 +421,15 @@ impl Hypervisor for KVMDriver {
                 #[cfg(all(debug_assertions, feature = "dump_on_crash"))]
                 self.dump_on_crash(self.mem_regions.clone());
                 let gpa = addr as usize;
-                match self.get_memory_access_violation(
-                    gpa,
-                    &self.mem_regions,
-                    MemoryRegionFlags::READ,
-                ) {
+                match self
+                    .get_memory_access_violation(gpa, &self.mem_regions, MemoryRegionFlags::READ)
+                    .or_else(|| {
+                        self.get_memory_access_violation(
+                            gpa,
+                            &self.mapped_regions,
+                            MemoryRegionFlags::READ,
+                        )
+                    }) {
                     Some(access_violation_exit) => access_violation_exit,
                     None => HyperlightExit::Mmio(addr),
                 }
@@ -300,11 +439,15 @@ impl Hypervisor for KVMDriver {
                 #[cfg(all(debug_assertions, feature = "dump_on_crash"))]
                 self.dump_on_crash(self.mem_regions.clone());
                 let gpa = addr as usize;
-                match self.get_memory_access_violation(
-                    gpa,
-                    &self.mem_regions,
-                    MemoryRegionFlags::WRITE,
-                ) {
+                match self
+                    .get_memory_access_violation(gpa, &self.mem_regions, MemoryRegionFlags::WRITE)
+                    .or_else(|| {
+                        self.get_memory_access_violation(
+                            gpa,
+                            &self.mapped_regions,
+                            MemoryRegionFlags::WRITE,
+                        )
+                    }) {
                     Some(access_violation_exit) => access_violation_exit,
                     None => HyperlightExit::Mmio(addr),
                 }
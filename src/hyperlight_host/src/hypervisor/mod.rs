@@ -14,6 +14,7 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use log::LevelFilter;
 use tracing::{instrument, Span};
 
 use crate::error::HyperlightError::ExecutionCanceledByHost;
@@ -33,6 +34,8 @@ pub mod hyperv_linux;
 /// Hyperv-on-windows functionality
 pub(crate) mod hyperv_windows;
 pub(crate) mod hypervisor_handler;
+/// Cancelling guest function calls from other threads
+pub(crate) mod interrupt_handle;
 
 /// Driver for running in process instead of using hypervisor
 #[cfg(inprocess)]
@@ -58,9 +61,13 @@ pub(crate) mod wrappers;
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
 
+/// Re-export for the `InterruptHandle` type
+pub use interrupt_handle::InterruptHandle;
+
 use self::handlers::{
     MemAccessHandlerCaller, MemAccessHandlerWrapper, OutBHandlerCaller, OutBHandlerWrapper,
 };
+use crate::func::nested_call::VcpuGuard;
 use crate::hypervisor::hypervisor_handler::HypervisorHandler;
 use crate::mem::ptr::RawPtr;
 
@@ -79,6 +86,21 @@ pub(crate) const EFER_LMA: u64 = 1 << 10;
 pub(crate) const EFER_SCE: u64 = 1;
 pub(crate) const EFER_NX: u64 = 1 << 11;
 
+/// The size of the area below its stack pointer that guest code may use
+/// without moving the stack pointer
+#[cfg(any(kvm, mshv))]
+const RED_ZONE_SIZE: u64 = 128;
+
+/// Get the stack pointer to dispatch a nested call from the host with, given
+/// the stack pointer `rsp` of the guest waiting for a host function, and the
+/// stack pointer `orig_rsp` that calls from the host are normally dispatched
+/// with. The nested call's stack starts below anything the waiting guest may
+/// be using, with the same alignment as any other call's.
+#[cfg(any(kvm, mshv))]
+pub(crate) fn nested_call_stack_pointer(rsp: u64, orig_rsp: u64) -> u64 {
+    ((rsp - RED_ZONE_SIZE) & !0xf) - 0x10 + (orig_rsp & 0xf)
+}
+
 /// These are the generic exit reasons that we can handle from a Hypervisor the Hypervisors run method is responsible for mapping from
 /// the hypervisor specific exit reasons to these generic ones
 pub enum HyperlightExit {
@@ -106,6 +128,9 @@ pub enum HyperlightExit {
 pub(crate) trait Hypervisor: Debug + Sync + Send {
     /// Initialise the internally stored vCPU with the given PEB address and
     /// random number seed, then run it until a HLT instruction.
+    ///
+    /// If `max_guest_log_level` is `None`, the host's current max log level
+    /// is passed to the guest.
     #[allow(clippy::too_many_arguments)]
     fn initialise(
         &mut self,
@@ -115,8 +140,16 @@ pub(crate) trait Hypervisor: Debug + Sync + Send {
         outb_handle_fn: OutBHandlerWrapper,
         mem_access_fn: MemAccessHandlerWrapper,
         hv_handler: Option<HypervisorHandler>,
+        max_guest_log_level: Option<LevelFilter>,
     ) -> Result<()>;
 
+    /// Set up the internally stored vCPU to dispatch calls to a guest whose
+    /// memory has been restored from a snapshot of an initialised sandbox,
+    /// without running the guest's entrypoint again.
+    fn initialise_from_snapshot(&mut self) -> Result<()> {
+        log_then_return!("Creating a sandbox from a snapshot is not supported by this hypervisor");
+    }
+
     /// Dispatch a call from the host to the guest using the given pointer
     /// to the dispatch function _in the guest's address space_.
     ///
@@ -132,6 +165,27 @@ pub(crate) trait Hypervisor: Debug + Sync + Send {
         hv_handler: Option<HypervisorHandler>,
     ) -> Result<()>;
 
+    /// Dispatch a call from the host to the guest while the guest is
+    /// waiting for a host function it called to return, using the given
+    /// pointer to the dispatch function _in the guest's address space_.
+    ///
+    /// The call runs on the stack below the waiting guest's, and the vCPU's
+    /// registers are restored once it has finished, so that the guest
+    /// carries on where it left off when the host function returns.
+    ///
+    /// Returns `Ok` if the call succeeded, and an `Err` if it failed
+    fn dispatch_nested_call_from_host(
+        &mut self,
+        _dispatch_func_addr: RawPtr,
+        _outb_handle_fn: OutBHandlerWrapper,
+        _mem_access_fn: MemAccessHandlerWrapper,
+        _hv_handler: Option<HypervisorHandler>,
+    ) -> Result<()> {
+        log_then_return!(
+            "Calling the guest from a host function is not supported by this hypervisor"
+        );
+    }
+
     /// Handle an IO exit from the internally stored vCPU.
     fn handle_io(
         &mut self,
@@ -145,6 +199,29 @@ pub(crate) trait Hypervisor: Debug + Sync + Send {
     /// Run the vCPU
     fn run(&mut self) -> Result<HyperlightExit>;
 
+    /// Map `region`, which is host memory outside of the sandbox's own
+    /// memory, into the guest's physical address space until
+    /// `unmap_regions` is called.
+    ///
+    /// # Safety
+    ///
+    /// The host memory in `region` must stay valid until it is unmapped.
+    unsafe fn map_region(&mut self, _region: &MemoryRegion) -> Result<()> {
+        log_then_return!("Mapping host memory into the guest is not supported by this hypervisor");
+    }
+
+    /// Unmap all the regions that were mapped with `map_region`.
+    fn unmap_regions(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Get the host addresses of the pages of the sandbox's memory that the
+    /// guest has written to since this was last called, or `None` if this
+    /// hypervisor doesn't track them.
+    fn get_dirty_pages(&mut self) -> Result<Option<Vec<usize>>> {
+        Ok(None)
+    }
+
     /// Returns a Some(HyperlightExit::AccessViolation(..)) if the given gpa doesn't have
     /// access its corresponding region. Returns None otherwise, or if the region is not found.
     fn get_memory_access_violation(
@@ -268,6 +345,9 @@ impl VirtualCPU {
                     break;
                 }
                 HyperlightExit::IoOut(port, data, rip, instruction_length) => {
+                    // let host functions called through the outb handler call back
+                    // into the guest on this vCPU while it waits for them
+                    let _vcpu = VcpuGuard::enter(hv, hv_handler.clone(), mem_access_fn.clone());
                     hv.handle_io(port, data, rip, instruction_length, outb_handle_fn.clone())?
                 }
                 HyperlightExit::Mmio(addr) => {
@@ -322,11 +402,11 @@ pub(crate) mod tests {
 
     use super::handlers::{MemAccessHandlerWrapper, OutBHandlerWrapper};
     use crate::hypervisor::hypervisor_handler::{
-        HvHandlerConfig, HypervisorHandler, HypervisorHandlerAction,
+        HostFunctionTimer, HvHandlerConfig, HypervisorHandler, HypervisorHandlerAction,
     };
     use crate::mem::ptr::RawPtr;
     use crate::sandbox::uninitialized::GuestBinary;
-    use crate::sandbox::{SandboxConfiguration, UninitializedSandbox};
+    use crate::sandbox::{ExecutionTimeBudget, SandboxConfiguration, UninitializedSandbox};
     use crate::{new_error, Result};
 
     pub(crate) fn test_initialise(
@@ -362,6 +442,15 @@ pub(crate) mod tests {
             max_wait_for_cancellation: Duration::from_millis(
                 SandboxConfiguration::DEFAULT_MAX_WAIT_FOR_CANCELLATION as u64,
             ),
+            max_guest_log_level: None,
+            execution_time_budget: ExecutionTimeBudget::default(),
+            max_host_function_time: Duration::from_millis(
+                SandboxConfiguration::DEFAULT_MAX_HOST_FUNCTION_TIME as u64,
+            ),
+            max_nested_guest_call_depth: SandboxConfiguration::DEFAULT_MAX_NESTED_GUEST_CALL_DEPTH,
+            host_function_timer: HostFunctionTimer::default(),
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            seccomp_filter: None,
         };
 
         let mut hv_handler = HypervisorHandler::new(hv_handler_config);
@@ -88,8 +88,15 @@ pub(crate) mod testing;
 
 /// The re-export for the `HyperlightError` type
 pub use error::HyperlightError;
+/// The re-export for the `InterruptHandle` type
+pub use hypervisor::InterruptHandle;
+/// The re-export for the `HostMappedRegion` type
+pub use mem::host_mapped_region::HostMappedRegion;
 /// The re-export for the set_registry function
 pub use metrics::set_metrics_registry;
+/// The re-export for the `run_helper_process_if_requested` function
+#[cfg(target_os = "linux")]
+pub use sandbox::helper_process::run_helper_process_if_requested;
 /// The re-export for the `is_hypervisor_present` type
 pub use sandbox::is_hypervisor_present;
 /// The re-export for the `GuestBinary` type
@@ -99,6 +106,8 @@ pub use sandbox::uninitialized::GuestBinary;
 /// A sandbox that can call be used to make multiple calls to guest functions,
 /// and otherwise reused multiple times
 pub use sandbox::MultiUseSandbox;
+/// The re-export for the `SandboxBuilder` type
+pub use sandbox::SandboxBuilder;
 /// The re-export for the `SandboxRunOptions` type
 pub use sandbox::SandboxRunOptions;
 /// A sandbox that can be used at most once to call a guest function, and
@@ -0,0 +1,108 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use hyperlight_common::mem::PAGE_SIZE_USIZE;
+use tracing::{instrument, Span};
+
+/// The pages of a sandbox's shared memory that have been written to, by
+/// either the host or the guest, since it was last snapshotted or restored
+/// from a snapshot.
+#[derive(Debug)]
+pub(crate) struct DirtyPages {
+    /// One bit per page, set if the page has been written to
+    bitmap: Vec<u64>,
+    /// Whether some pages may have been written to without being marked,
+    /// in which case all pages must be treated as dirty
+    all: bool,
+}
+
+impl DirtyPages {
+    /// Create a `DirtyPages` for `mem_size` bytes of memory, all of which
+    /// are dirty until the first time they are taken.
+    pub(crate) fn new(mem_size: usize) -> Self {
+        let num_pages = mem_size.div_ceil(PAGE_SIZE_USIZE);
+        Self {
+            bitmap: vec![0; num_pages.div_ceil(64)],
+            all: true,
+        }
+    }
+
+    /// Mark the pages containing `[offset, offset + len)` as dirty
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn mark(&mut self, offset: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let first = offset / PAGE_SIZE_USIZE;
+        let last = (offset + len - 1) / PAGE_SIZE_USIZE;
+        for page in first..=last {
+            if let Some(word) = self.bitmap.get_mut(page / 64) {
+                *word |= 1 << (page % 64);
+            }
+        }
+    }
+
+    /// Mark all pages as dirty
+    pub(crate) fn mark_all(&mut self) {
+        self.all = true;
+    }
+
+    /// Get the indices of the dirty pages, or `None` if all pages are
+    /// dirty, then mark all pages as clean.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn take(&mut self) -> Option<Vec<usize>> {
+        let all = std::mem::replace(&mut self.all, false);
+        let mut pages = Vec::new();
+        for (i, word) in self.bitmap.iter_mut().enumerate() {
+            let mut bits = std::mem::take(word);
+            while bits != 0 {
+                pages.push(i * 64 + bits.trailing_zeros() as usize);
+                bits &= bits - 1;
+            }
+        }
+        if all {
+            None
+        } else {
+            Some(pages)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyperlight_common::mem::PAGE_SIZE_USIZE;
+
+    use super::DirtyPages;
+
+    #[test]
+    fn mark_and_take() {
+        let mut dirty = DirtyPages::new(100 * PAGE_SIZE_USIZE);
+        // all pages are dirty until they are first taken
+        assert_eq!(dirty.take(), None);
+        assert_eq!(dirty.take(), Some(vec![]));
+
+        dirty.mark(PAGE_SIZE_USIZE - 1, 2);
+        dirty.mark(70 * PAGE_SIZE_USIZE, PAGE_SIZE_USIZE);
+        dirty.mark(99 * PAGE_SIZE_USIZE + 8, 0);
+        assert_eq!(dirty.take(), Some(vec![0, 1, 70]));
+        assert_eq!(dirty.take(), Some(vec![]));
+
+        dirty.mark(0, 1);
+        dirty.mark_all();
+        assert_eq!(dirty.take(), None);
+        assert_eq!(dirty.take(), Some(vec![]));
+    }
+}
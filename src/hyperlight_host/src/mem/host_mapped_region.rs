@@ -0,0 +1,168 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::marker::PhantomData;
+
+use hyperlight_common::mem::PAGE_SIZE_USIZE;
+use tracing::{instrument, Span};
+
+use super::layout::SandboxMemoryLayout;
+use super::memory_region::{MemoryRegion, MemoryRegionFlags, MemoryRegionType};
+use super::mgr::AMOUNT_OF_MEMORY_PER_PT;
+use crate::{log_then_return, Result};
+
+/// Host memory, such as a memfd or a memory mapped file, that is mapped
+/// into the guest for the duration of a guest function call instead of
+/// being copied into the sandbox.
+///
+/// The memory is borrowed for as long as the `HostMappedRegion` exists, so
+/// the host cannot access it while the guest can.
+#[derive(Debug)]
+pub struct HostMappedRegion<'a> {
+    host_addr: usize,
+    len: usize,
+    writable: bool,
+    _data: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> HostMappedRegion<'a> {
+    /// Map `data` into the guest read-only.
+    ///
+    /// Since the guest accesses memory a page at a time, `data` must start
+    /// on a page boundary, and its length must be a non-zero multiple of the
+    /// page size.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    pub fn read_only(data: &'a [u8]) -> Result<Self> {
+        Self::new(data.as_ptr() as usize, data.len(), false)
+    }
+
+    /// Map `data` into the guest read-write.
+    ///
+    /// `data` has the same alignment requirements as for `read_only`.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    pub fn read_write(data: &'a mut [u8]) -> Result<Self> {
+        Self::new(data.as_mut_ptr() as usize, data.len(), true)
+    }
+
+    fn new(host_addr: usize, len: usize, writable: bool) -> Result<Self> {
+        if len == 0 || host_addr % PAGE_SIZE_USIZE != 0 || len % PAGE_SIZE_USIZE != 0 {
+            log_then_return!(
+                "Host memory mapped into the guest must be whole pages, but got {} bytes at {:#x}",
+                len,
+                host_addr
+            );
+        }
+        Ok(Self {
+            host_addr,
+            len,
+            writable,
+            _data: PhantomData,
+        })
+    }
+}
+
+/// Lay out `regions` in the guest's address space from `guest_addr`, each
+/// starting on a 2MB boundary so that it can be mapped with 2MB pages.
+#[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+pub(crate) fn layout_host_mapped_regions(
+    regions: &[HostMappedRegion<'_>],
+    mut guest_addr: usize,
+) -> Result<Vec<MemoryRegion>> {
+    let mut memory_regions = Vec::with_capacity(regions.len());
+    for region in regions {
+        let guest_end = guest_addr + region.len;
+        if guest_end > SandboxMemoryLayout::MAX_GUEST_ADDRESS {
+            log_then_return!(
+                "Host memory mapped into the guest must end before {:#x}, but would end at {:#x}",
+                SandboxMemoryLayout::MAX_GUEST_ADDRESS,
+                guest_end
+            );
+        }
+        let flags = if region.writable {
+            MemoryRegionFlags::READ | MemoryRegionFlags::WRITE
+        } else {
+            MemoryRegionFlags::READ
+        };
+        memory_regions.push(MemoryRegion {
+            guest_region: guest_addr..guest_end,
+            host_region: region.host_addr..region.host_addr + region.len,
+            flags,
+            region_type: MemoryRegionType::HostMapped,
+        });
+        guest_addr = guest_end.next_multiple_of(AMOUNT_OF_MEMORY_PER_PT);
+    }
+    Ok(memory_regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use hyperlight_common::mem::PAGE_SIZE_USIZE;
+
+    use super::{layout_host_mapped_regions, HostMappedRegion};
+    use crate::mem::layout::SandboxMemoryLayout;
+    use crate::mem::memory_region::MemoryRegionFlags;
+    use crate::mem::mgr::AMOUNT_OF_MEMORY_PER_PT;
+
+    #[repr(C, align(4096))]
+    struct Pages([u8; 2 * PAGE_SIZE_USIZE]);
+
+    #[test]
+    fn regions_must_be_whole_pages() {
+        let mut pages = Pages([0; 2 * PAGE_SIZE_USIZE]);
+        assert!(HostMappedRegion::read_only(&pages.0[..0]).is_err());
+        assert!(HostMappedRegion::read_only(&pages.0[1..PAGE_SIZE_USIZE + 1]).is_err());
+        assert!(HostMappedRegion::read_write(&mut pages.0[..PAGE_SIZE_USIZE + 1]).is_err());
+        assert!(HostMappedRegion::read_write(&mut pages.0[PAGE_SIZE_USIZE..]).is_ok());
+        assert!(HostMappedRegion::read_only(&pages.0).is_ok());
+    }
+
+    #[test]
+    fn regions_are_laid_out_on_2mb_boundaries() {
+        let mut first = Pages([0; 2 * PAGE_SIZE_USIZE]);
+        let second = Pages([0; 2 * PAGE_SIZE_USIZE]);
+        let first_addr = first.0.as_ptr() as usize;
+        let regions = [
+            HostMappedRegion::read_write(&mut first.0).unwrap(),
+            HostMappedRegion::read_only(&second.0).unwrap(),
+        ];
+
+        let base = 4 * AMOUNT_OF_MEMORY_PER_PT;
+        let memory_regions = layout_host_mapped_regions(&regions, base).unwrap();
+        assert_eq!(memory_regions.len(), 2);
+        assert_eq!(
+            memory_regions[0].guest_region,
+            base..base + 2 * PAGE_SIZE_USIZE
+        );
+        assert_eq!(
+            memory_regions[0].host_region,
+            first_addr..first_addr + 2 * PAGE_SIZE_USIZE
+        );
+        assert_eq!(
+            memory_regions[0].flags,
+            MemoryRegionFlags::READ | MemoryRegionFlags::WRITE
+        );
+        let second_base = base + AMOUNT_OF_MEMORY_PER_PT;
+        assert_eq!(
+            memory_regions[1].guest_region,
+            second_base..second_base + 2 * PAGE_SIZE_USIZE
+        );
+        assert_eq!(memory_regions[1].flags, MemoryRegionFlags::READ);
+
+        let base = SandboxMemoryLayout::MAX_GUEST_ADDRESS - AMOUNT_OF_MEMORY_PER_PT;
+        assert!(layout_host_mapped_regions(&regions, base).is_err());
+        assert!(layout_host_mapped_regions(&regions[..1], base).is_ok());
+    }
+}
@@ -17,15 +17,18 @@ limitations under the License.
 use std::fmt::Debug;
 use std::mem::{offset_of, size_of};
 
-use hyperlight_common::mem::{GuestStackData, HyperlightPEB, RunMode, PAGE_SIZE_USIZE};
+use hyperlight_common::mem::{
+    GuestHeapGrowth, GuestHeapStats, HyperlightPEB, RunMode, PAGE_SIZE_USIZE,
+};
 use paste::paste;
 use rand::rngs::OsRng;
 use rand::RngCore;
 use tracing::{instrument, Span};
 
 use super::memory_region::MemoryRegionType::{
-    BootStack, Code, GuardPage, GuestErrorData, Heap, HostExceptionData, HostFunctionDefinitions,
-    InputData, KernelStack, OutputData, PageTables, PanicContext, Peb, Stack,
+    BootStack, Code, GuardPage, GuestErrorData, Heap, HeapGrowth, HostExceptionData,
+    HostFunctionDefinitions, InputData, KernelStack, OutputData, PageTables, PanicContext, Peb,
+    Stack,
 };
 use super::memory_region::{MemoryRegion, MemoryRegionFlags, MemoryRegionVecBuilder};
 use super::mgr::AMOUNT_OF_MEMORY_PER_PT;
@@ -47,6 +50,8 @@ use crate::{log_then_return, new_error, Result};
 // +-------------------------------------------+
 // |             Guard Page (4KiB)             |
 // +-------------------------------------------+
+// |         Guest Heap Growth                 |
+// +-------------------------------------------+
 // |             Guest Heap                    |
 // +-------------------------------------------+
 // |         Guest Panic Context               |
@@ -61,7 +66,7 @@ use crate::{log_then_return, new_error, Result};
 // +-------------------------------------------+
 // |        Host Function Definitions          |
 // +-------------------------------------------+
-// |                PEB Struct (0x98)          |
+// |                PEB Struct (0xF8)          |
 // +-------------------------------------------+
 // |               Guest Code                  |
 // +-------------------------------------------+
@@ -99,6 +104,11 @@ use crate::{log_then_return, new_error, Result};
 /// - `GuestHeap` - this is a buffer that is used for heap data in the guest. the length
 ///   of this field is returned by the `heap_size()` method of this struct
 ///
+/// - `GuestHeapGrowth` - this is memory reserved for the guest heap to grow into,
+///   which isn't mapped into the guest until it asks the host for more heap memory.
+///   the length of this field is the `MaxHeapSize` from `SandboxConfiguration` less
+///   the heap size, or 0 if that is not bigger.
+///
 /// - `GuestStack` - this is a buffer that is used for stack data in the guest. the length
 ///   of this field is returned by the `stack_size()` method of this struct. in reality,
 ///   the stack might be slightly bigger or smaller than this value since total memory
@@ -121,6 +131,8 @@ pub(crate) struct SandboxMemoryLayout {
     pub(super) stack_size: usize,
     /// The heap size of this sandbox.
     pub(super) heap_size: usize,
+    /// The most bytes the heap of this sandbox can grow by.
+    pub(super) heap_growth_size: usize,
 
     /// The following fields are offsets to the actual PEB struct fields.
     /// They are used when writing the PEB struct itself
@@ -137,6 +149,8 @@ pub(crate) struct SandboxMemoryLayout {
     peb_guest_panic_context_offset: usize,
     peb_heap_data_offset: usize,
     peb_guest_stack_data_offset: usize,
+    peb_guest_heap_stats_offset: usize,
+    peb_guest_heap_growth_offset: usize,
 
     // The following are the actual values
     // that are written to the PEB struct
@@ -147,6 +161,7 @@ pub(crate) struct SandboxMemoryLayout {
     pub(super) output_data_buffer_offset: usize,
     guest_panic_context_buffer_offset: usize,
     guest_heap_buffer_offset: usize,
+    heap_growth_buffer_offset: usize,
     guard_page_offset: usize,
     guest_user_stack_buffer_offset: usize, // the lowest address of the user stack
     user_stack_guard_page_offset: usize,
@@ -158,7 +173,7 @@ pub(crate) struct SandboxMemoryLayout {
 
     // other
     pub(crate) peb_address: usize,
-    code_size: usize,
+    pub(super) code_size: usize,
     // The total size of the page tables
     total_page_table_size: usize,
     // The offset in the sandbox memory where the code starts
@@ -174,6 +189,10 @@ impl Debug for SandboxMemoryLayout {
             )
             .field("Stack Size", &format_args!("{:#x}", self.stack_size))
             .field("Heap Size", &format_args!("{:#x}", self.heap_size))
+            .field(
+                "Heap Growth Size",
+                &format_args!("{:#x}", self.heap_growth_size),
+            )
             .field("PEB Address", &format_args!("{:#x}", self.peb_address))
             .field("PEB Offset", &format_args!("{:#x}", self.peb_offset))
             .field("Code Size", &format_args!("{:#x}", self.code_size))
@@ -221,6 +240,14 @@ impl Debug for SandboxMemoryLayout {
                 "Guest Stack Offset",
                 &format_args!("{:#x}", self.peb_guest_stack_data_offset),
             )
+            .field(
+                "Guest Heap Stats Offset",
+                &format_args!("{:#x}", self.peb_guest_heap_stats_offset),
+            )
+            .field(
+                "Guest Heap Growth Offset",
+                &format_args!("{:#x}", self.peb_guest_heap_growth_offset),
+            )
             .field(
                 "Host Function Definitions Buffer Offset",
                 &format_args!("{:#x}", self.host_function_definitions_buffer_offset),
@@ -249,6 +276,10 @@ impl Debug for SandboxMemoryLayout {
                 "Guest Heap Buffer Offset",
                 &format_args!("{:#x}", self.guest_heap_buffer_offset),
             )
+            .field(
+                "Heap Growth Buffer Offset",
+                &format_args!("{:#x}", self.heap_growth_buffer_offset),
+            )
             .field(
                 "Guard Page Offset",
                 &format_args!("{:#x}", self.guard_page_offset),
@@ -308,6 +339,9 @@ impl SandboxMemoryLayout {
     /// The addressable virtual memory with current paging setup is virtual address 0x0 - 0x40000000 (excl.),
     /// However, the memory up to Self::BASE_ADDRESS is not used.
     const MAX_MEMORY_SIZE: usize = 0x40000000 - Self::BASE_ADDRESS;
+    /// The end (excl.) of the addressable virtual memory, which host memory
+    /// mapped into the guest can't extend past.
+    pub(super) const MAX_GUEST_ADDRESS: usize = Self::BASE_ADDRESS + Self::MAX_MEMORY_SIZE;
 
     /// The base address of the sandbox's memory.
     pub(crate) const BASE_ADDRESS: usize = 0x0200000;
@@ -345,13 +379,15 @@ impl SandboxMemoryLayout {
             peb_offset + offset_of!(HyperlightPEB, guestPanicContextData);
         let peb_heap_data_offset = peb_offset + offset_of!(HyperlightPEB, guestheapData);
         let peb_guest_stack_data_offset = peb_offset + offset_of!(HyperlightPEB, gueststackData);
+        let peb_guest_heap_stats_offset = peb_offset + offset_of!(HyperlightPEB, guestheapStats);
+        let peb_guest_heap_growth_offset = peb_offset + offset_of!(HyperlightPEB, guestheapGrowth);
 
         // The following offsets are the actual values that relate to memory layout,
         // which are written to PEB struct
         let peb_address = Self::BASE_ADDRESS + peb_offset;
         // make sure host function definitions buffer starts at 4K boundary
         let host_function_definitions_buffer_offset = round_up_to(
-            peb_guest_stack_data_offset + size_of::<GuestStackData>(),
+            peb_guest_heap_growth_offset + size_of::<GuestHeapGrowth>(),
             PAGE_SIZE_USIZE,
         );
         // make sure host exception buffer starts at 4K boundary
@@ -380,8 +416,12 @@ impl SandboxMemoryLayout {
             guest_panic_context_buffer_offset + cfg.get_guest_panic_context_buffer_size(),
             PAGE_SIZE_USIZE,
         );
+        // the memory the heap can grow into follows it
+        let heap_growth_buffer_offset =
+            round_up_to(guest_heap_buffer_offset + heap_size, PAGE_SIZE_USIZE);
+        let heap_growth_size = Self::get_heap_growth_size(cfg, heap_size);
         // make sure guard page starts at 4K boundary
-        let guard_page_offset = round_up_to(guest_heap_buffer_offset + heap_size, PAGE_SIZE_USIZE);
+        let guard_page_offset = heap_growth_buffer_offset + heap_growth_size;
         let guest_user_stack_buffer_offset = guard_page_offset + PAGE_SIZE_USIZE;
         // round up stack size to page size. This is needed for MemoryRegion
         let stack_size_rounded = round_up_to(stack_size, PAGE_SIZE_USIZE);
@@ -396,6 +436,7 @@ impl SandboxMemoryLayout {
             peb_offset,
             stack_size: stack_size_rounded,
             heap_size,
+            heap_growth_size,
             peb_security_cookie_seed_offset,
             peb_guest_dispatch_function_ptr_offset,
             peb_host_function_definitions_offset,
@@ -408,6 +449,8 @@ impl SandboxMemoryLayout {
             peb_guest_panic_context_offset,
             peb_heap_data_offset,
             peb_guest_stack_data_offset,
+            peb_guest_heap_stats_offset,
+            peb_guest_heap_growth_offset,
             guest_error_buffer_offset,
             sandbox_memory_config: cfg,
             code_size,
@@ -416,6 +459,7 @@ impl SandboxMemoryLayout {
             input_data_buffer_offset,
             output_data_buffer_offset,
             guest_heap_buffer_offset,
+            heap_growth_buffer_offset,
             guest_user_stack_buffer_offset,
             peb_address,
             guest_panic_context_buffer_offset,
@@ -490,6 +534,27 @@ impl SandboxMemoryLayout {
         self.stack_size
     }
 
+    /// Get the offset in guest memory to the heap statistics the guest
+    /// reports after each call into it.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(super) fn get_guest_heap_stats_offset(&self) -> usize {
+        self.peb_guest_heap_stats_offset
+    }
+
+    /// Get the offset in guest memory to the `GuestHeapGrowth` struct in
+    /// the PEB, through which the guest asks for the heap to grow.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(super) fn get_guest_heap_growth_offset(&self) -> usize {
+        self.peb_guest_heap_growth_offset
+    }
+
+    /// Get the offset in guest memory to the memory reserved for the heap
+    /// to grow into
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(super) fn get_heap_growth_buffer_offset(&self) -> usize {
+        self.heap_growth_buffer_offset
+    }
+
     /// Get the offset in guest memory to the start of host errors
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
     pub(super) fn get_host_exception_offset(&self) -> usize {
@@ -690,6 +755,15 @@ impl SandboxMemoryLayout {
         self.boot_stack_buffer_offset
     }
 
+    /// Get the most bytes the heap can grow by, which is the amount the
+    /// configured maximum heap size exceeds `heap_size` by, rounded up to
+    /// the page size
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    fn get_heap_growth_size(cfg: SandboxConfiguration, heap_size: usize) -> usize {
+        let max_heap_size = usize::try_from(cfg.get_max_heap_size()).unwrap_or(usize::MAX);
+        round_up_to(max_heap_size.saturating_sub(heap_size), PAGE_SIZE_USIZE)
+    }
+
     #[cfg(test)]
     /// Get the page table size
     fn get_page_table_size(&self) -> usize {
@@ -720,6 +794,7 @@ impl SandboxMemoryLayout {
         let mut total_mapped_memory_size: usize = round_up_to(code_size, PAGE_SIZE_USIZE);
         total_mapped_memory_size += round_up_to(stack_size, PAGE_SIZE_USIZE);
         total_mapped_memory_size += round_up_to(heap_size, PAGE_SIZE_USIZE);
+        total_mapped_memory_size += Self::get_heap_growth_size(cfg, heap_size);
         total_mapped_memory_size += round_up_to(cfg.get_host_exception_size(), PAGE_SIZE_USIZE);
         total_mapped_memory_size +=
             round_up_to(cfg.get_host_function_definition_size(), PAGE_SIZE_USIZE);
@@ -770,6 +845,17 @@ impl SandboxMemoryLayout {
         }
     }
 
+    /// Get the guest address from which host memory can be mapped into the
+    /// guest for the duration of a call. This is the first 2MB boundary
+    /// after the sandbox's memory, so that it can be mapped with 2MB pages.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_host_mapped_regions_address(&self) -> Result<usize> {
+        Ok(round_up_to(
+            Self::BASE_ADDRESS + self.get_memory_size()?,
+            AMOUNT_OF_MEMORY_PER_PT,
+        ))
+    }
+
     /// Returns the memory regions associated with this memory layout,
     /// suitable for passing to a hypervisor for mapping into memory
     pub fn get_memory_regions(&self, shared_mem: &GuestSharedMemory) -> Result<Vec<MemoryRegion>> {
@@ -935,17 +1021,30 @@ impl SandboxMemoryLayout {
 
         // heap
         #[cfg(feature = "executable_heap")]
-        let guard_page_offset = builder.push_page_aligned(
-            self.heap_size,
-            MemoryRegionFlags::READ | MemoryRegionFlags::WRITE | MemoryRegionFlags::EXECUTE,
-            Heap,
-        );
+        let heap_flags =
+            MemoryRegionFlags::READ | MemoryRegionFlags::WRITE | MemoryRegionFlags::EXECUTE;
         #[cfg(not(feature = "executable_heap"))]
-        let guard_page_offset = builder.push_page_aligned(
-            self.heap_size,
-            MemoryRegionFlags::READ | MemoryRegionFlags::WRITE,
-            Heap,
-        );
+        let heap_flags = MemoryRegionFlags::READ | MemoryRegionFlags::WRITE;
+        let heap_growth_offset = builder.push_page_aligned(self.heap_size, heap_flags, Heap);
+
+        let expected_heap_growth_offset =
+            TryInto::<usize>::try_into(self.heap_growth_buffer_offset)?;
+
+        if heap_growth_offset != expected_heap_growth_offset {
+            return Err(new_error!(
+                "Heap Growth offset does not match expected Heap Growth offset expected:  {}, actual:  {}",
+                expected_heap_growth_offset,
+                heap_growth_offset
+            ));
+        }
+
+        // heap growth, which is only mapped into the guest's page tables as
+        // the heap grows
+        let guard_page_offset = if self.heap_growth_size > 0 {
+            builder.push_page_aligned(self.heap_growth_size, heap_flags, HeapGrowth)
+        } else {
+            heap_growth_offset
+        };
 
         let expected_guard_page_offset = TryInto::<usize>::try_into(self.guard_page_offset)?;
 
@@ -1186,6 +1285,27 @@ impl SandboxMemoryLayout {
         shared_mem.write_u64(self.get_heap_size_offset(), self.heap_size.try_into()?)?;
         shared_mem.write_u64(self.get_heap_pointer_offset(), addr)?;
 
+        // Set up the memory the heap can grow into, none of which it has
+        // grown into yet
+        let addr = get_address!(heap_growth_buffer);
+        let heap_growth_offset = self.get_guest_heap_growth_offset();
+        shared_mem.write_u64(
+            heap_growth_offset + offset_of!(GuestHeapGrowth, heapGrowthBuffer),
+            addr,
+        )?;
+        shared_mem.write_u64(
+            heap_growth_offset + offset_of!(GuestHeapGrowth, heapGrowthMaxSize),
+            self.heap_growth_size.try_into()?,
+        )?;
+
+        // Have the guest count the free blocks in its heap after every guest
+        // function call if the sandbox's memory statistics are collected
+        shared_mem.write_u64(
+            self.get_guest_heap_stats_offset()
+                + offset_of!(GuestHeapStats, heapFreeFragmentsCounted),
+            self.sandbox_memory_config.get_collect_memory_stats() as u64,
+        )?;
+
         // Set up user stack pointers
 
         // Set up Min Guest User Stack Address
@@ -1307,6 +1427,8 @@ mod tests {
 
         expected_size += round_up_to(layout.heap_size, PAGE_SIZE_USIZE);
 
+        expected_size += layout.heap_growth_size;
+
         expected_size += PAGE_SIZE_USIZE; // guard page
 
         expected_size += round_up_to(layout.stack_size, PAGE_SIZE_USIZE);
@@ -1331,4 +1453,21 @@ mod tests {
             get_expected_memory_size(&sbox_mem_layout)
         );
     }
+
+    #[test]
+    fn test_get_memory_size_with_heap_growth() {
+        let mut sbox_cfg = SandboxConfiguration::default();
+        sbox_cfg.set_max_heap_size(0x10000);
+        let sbox_mem_layout = SandboxMemoryLayout::new(sbox_cfg, 4096, 2048, 4096).unwrap();
+        assert_eq!(0x10000 - 4096, sbox_mem_layout.heap_growth_size);
+        assert_eq!(
+            sbox_mem_layout.get_memory_size().unwrap(),
+            get_expected_memory_size(&sbox_mem_layout)
+        );
+
+        // the heap can't grow if its maximum size isn't more than its size
+        sbox_cfg.set_max_heap_size(2048);
+        let sbox_mem_layout = SandboxMemoryLayout::new(sbox_cfg, 4096, 2048, 4096).unwrap();
+        assert_eq!(0, sbox_mem_layout.heap_growth_size);
+    }
 }
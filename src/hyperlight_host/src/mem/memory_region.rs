@@ -130,6 +130,8 @@ pub enum MemoryRegionType {
     PanicContext,
     /// The region contains the Heap
     Heap,
+    /// The region is reserved for the Heap to grow into
+    HeapGrowth,
     /// The region contains the Guard Page
     GuardPage,
     /// The region contains the Stack
@@ -138,6 +140,9 @@ pub enum MemoryRegionType {
     KernelStack,
     /// The region contains the Boot Stack
     BootStack,
+    /// The region is host memory mapped into the guest for the duration of
+    /// a guest function call
+    HostMapped,
 }
 
 /// represents a single memory region inside the guest. All memory within a region has
@@ -14,8 +14,8 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use core::mem::size_of;
-use std::cmp::Ordering;
+use core::mem::{offset_of, size_of};
+use std::cmp::{min, Ordering};
 use std::str::from_utf8;
 use std::sync::{Arc, Mutex};
 
@@ -24,8 +24,11 @@ use hyperlight_common::flatbuffer_wrappers::function_call::{
 };
 use hyperlight_common::flatbuffer_wrappers::function_types::ReturnValue;
 use hyperlight_common::flatbuffer_wrappers::guest_error::{ErrorCode, GuestError};
+use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
 use hyperlight_common::flatbuffer_wrappers::guest_log_data::GuestLogData;
 use hyperlight_common::flatbuffer_wrappers::host_function_details::HostFunctionDetails;
+use hyperlight_common::mem::{GuestHeapGrowth, GuestHeapStats, PAGE_SIZE_USIZE};
+use hyperlight_common::stream::{StreamChunk, STREAM_CHUNK_PREFIX_SIZE};
 use serde_json::from_str;
 use tracing::{instrument, Span};
 
@@ -33,18 +36,20 @@ use super::exe::ExeInfo;
 use super::layout::SandboxMemoryLayout;
 #[cfg(target_os = "windows")]
 use super::loaded_lib::LoadedLib;
-use super::memory_region::{MemoryRegion, MemoryRegionType};
+use super::memory_region::{MemoryRegion, MemoryRegionFlags, MemoryRegionType};
 use super::ptr::{GuestPtr, RawPtr};
 use super::ptr_offset::Offset;
 use super::shared_mem::{ExclusiveSharedMemory, GuestSharedMemory, HostSharedMemory, SharedMemory};
 use super::shared_mem_snapshot::SharedMemorySnapshot;
+use super::snapshot_file::{SnapshotFile, SnapshotHeader};
 use crate::error::HyperlightError::{
-    ExceptionDataLengthIncorrect, ExceptionMessageTooBig, JsonConversionFailure, NoMemorySnapshot,
-    UTF8SliceConversionFailure,
+    CheckpointNotFound, ExceptionDataLengthIncorrect, ExceptionMessageTooBig, InvalidSnapshotFile,
+    JsonConversionFailure, NoMemorySnapshot, UTF8SliceConversionFailure,
 };
 use crate::error::HyperlightHostError;
-use crate::sandbox::SandboxConfiguration;
-use crate::{log_then_return, new_error, HyperlightError, Result};
+use crate::sandbox::metrics::SandboxMetric::RestoredSnapshotPages;
+use crate::sandbox::{MemoryStats, SandboxConfiguration};
+use crate::{histogram_observe, log_then_return, new_error, HyperlightError, Result};
 
 /// Paging Flags
 ///
@@ -55,6 +60,7 @@ use crate::{log_then_return, new_error, HyperlightError, Result};
 const PAGE_PRESENT: u64 = 1; // Page is Present
 const PAGE_RW: u64 = 1 << 1; // Page is Read/Write (if not set page is read only so long as the WP bit in CR0 is set to 1 - which it is in Hyperlight)
 const PAGE_USER: u64 = 1 << 2; // User/Supervisor (if this bit is set then the page is accessible by user mode code)
+const PAGE_PS: u64 = 1 << 7; // Page Size (if this bit is set in a PDE then it maps a 2MB page rather than pointing to a PT)
 const PAGE_NX: u64 = 1 << 63; // Execute Disable (if this bit is set then data in the page cannot be executed)
 
 // The amount of memory that can be mapped per page table
@@ -63,6 +69,10 @@ pub(super) const AMOUNT_OF_MEMORY_PER_PT: usize = 0x200000;
 /// The page size for the 64-bit PDE
 /// The size of stack guard cookies
 pub(crate) const STACK_COOKIE_LEN: usize = 16;
+/// The byte the guest's stack is filled with before the guest first runs,
+/// so that how much of it has been used can be found by looking for where
+/// the pattern has been overwritten
+const STACK_PATTERN: u8 = 0xcd;
 
 /// A struct that is responsible for laying out and managing the memory
 /// for a given `Sandbox`.
@@ -81,6 +91,9 @@ pub(crate) struct SandboxMemoryManager<S> {
     /// A vector of memory snapshots that can be used to save and  restore the state of the memory
     /// This is used by the Rust Sandbox implementation (rather than the mem_snapshot field above which only exists to support current C API)
     snapshots: Arc<Mutex<Vec<SharedMemorySnapshot>>>,
+    /// Named snapshots of the memory, in the order they were taken, that it
+    /// can be rolled back to without affecting `snapshots`
+    checkpoints: Arc<Mutex<Vec<(String, SharedMemorySnapshot)>>>,
     /// This field must be present, even though it's not read,
     /// so that its underlying resources are properly dropped at
     /// the right time.
@@ -109,6 +122,7 @@ where
             load_addr,
             entrypoint_offset,
             snapshots: Arc::new(Mutex::new(Vec::new())),
+            checkpoints: Arc::new(Mutex::new(Vec::new())),
             #[cfg(target_os = "windows")]
             _lib: lib,
         }
@@ -124,16 +138,10 @@ where
         &mut self.shared_mem
     }
 
-    /// Set up the hypervisor partition in the given `SharedMemory` parameter
-    /// `shared_mem`, with the given memory size `mem_size`
-    // TODO: This should perhaps happen earlier and use an
-    // ExclusiveSharedMemory from the beginning.
-    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
-    pub(crate) fn set_up_shared_memory(
-        &mut self,
-        mem_size: u64,
-        regions: &mut [MemoryRegion],
-    ) -> Result<u64> {
+    /// Get the stack pointer the guest starts with, and which is restored
+    /// after each call into the guest.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_initial_stack_pointer(&self) -> u64 {
         // Add 0x200000 because that's the start of mapped memory
         // For MSVC, move rsp down by 0x28.  This gives the called 'main'
         // function the appearance that rsp was 16 byte aligned before
@@ -147,10 +155,23 @@ where
         //
         // NOTE: We do this also for GCC freestanding binaries because we
         // specify __attribute__((ms_abi)) on the start method
-        let rsp: u64 = self.layout.get_top_of_user_stack_offset() as u64
+        self.layout.get_top_of_user_stack_offset() as u64
             + SandboxMemoryLayout::BASE_ADDRESS as u64
             + self.layout.stack_size as u64
-            - 0x28;
+            - 0x28
+    }
+
+    /// Set up the hypervisor partition in the given `SharedMemory` parameter
+    /// `shared_mem`, with the given memory size `mem_size`
+    // TODO: This should perhaps happen earlier and use an
+    // ExclusiveSharedMemory from the beginning.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn set_up_shared_memory(
+        &mut self,
+        mem_size: u64,
+        regions: &mut [MemoryRegion],
+    ) -> Result<u64> {
+        let rsp = self.get_initial_stack_pointer();
 
         self.shared_mem.with_exclusivity(|shared_mem| {
             // Create PDL4 table with only 1 PML4E
@@ -206,6 +227,9 @@ where
                                 MemoryRegionType::Heap => {
                                     PAGE_PRESENT | PAGE_RW | PAGE_USER | PAGE_NX
                                 }
+                                // The heap is only mapped into the memory reserved for it to
+                                // grow into as it grows, by `grow_heap`
+                                MemoryRegionType::HeapGrowth => 0,
                                 // The guard page is marked RW and User so that if it gets written to we can detect it in the host
                                 // If/When we implement an interupt handler for page faults in the guest then we can remove this access and handle things properly there
                                 MemoryRegionType::GuardPage => {
@@ -225,6 +249,9 @@ where
                                 MemoryRegionType::PageTables => PAGE_PRESENT | PAGE_RW | PAGE_NX,
                                 MemoryRegionType::KernelStack => PAGE_PRESENT | PAGE_RW | PAGE_NX,
                                 MemoryRegionType::BootStack => PAGE_PRESENT | PAGE_RW | PAGE_NX,
+                                // Host mapped regions are outside of the sandbox's memory, and are
+                                // mapped with 2MB pages by `map_host_region_pages`
+                                MemoryRegionType::HostMapped => 0,
                             },
                             // If there is an error then the address isn't mapped so mark it as not present
                             Err(_) => 0,
@@ -287,6 +314,25 @@ where
         Ok(())
     }
 
+    /// this function replaces the last snapshot on the stack of snapshots with a snapshot of the current state of
+    /// the memory, or pushes one if the stack is empty
+    /// It should be used when you want the current state of the memory to become the state that
+    /// `restore_state_from_last_snapshot` restores, without keeping the state it replaces, for example when
+    /// committing a call context
+    pub(crate) fn replace_state(&mut self) -> Result<()> {
+        let mut snapshots = self
+            .snapshots
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
+        match snapshots.last_mut() {
+            Some(last) => last.replace_snapshot(&mut self.shared_mem),
+            None => {
+                snapshots.push(SharedMemorySnapshot::new(&mut self.shared_mem)?);
+                Ok(())
+            }
+        }
+    }
+
     /// this function restores a memory snapshot from the last snapshot in the list but does not pop the snapshot
     /// off the stack
     /// It should be used when you want to restore the state of the memory to a previous state but still want to
@@ -301,7 +347,9 @@ where
             log_then_return!(NoMemorySnapshot);
         }
         let snapshot = last.unwrap();
-        snapshot.restore_from_snapshot(&mut self.shared_mem)
+        let restored_pages = snapshot.restore_from_snapshot(&mut self.shared_mem)?;
+        histogram_observe!(&RestoredSnapshotPages, restored_pages as f64);
+        Ok(())
     }
 
     /// this function pops the last snapshot off the stack and restores the memory to the previous state
@@ -316,9 +364,72 @@ where
         if last.is_none() {
             log_then_return!(NoMemorySnapshot);
         }
+        // only the pages written to since the popped snapshot are known, not
+        // those that differ between it and the one being restored
+        self.shared_mem.mark_all_dirty()?;
         self.restore_state_from_last_snapshot()
     }
 
+    /// Take a snapshot of the memory named `name`, which it can be rolled
+    /// back to with `rollback_to_checkpoint`, replacing any checkpoint with
+    /// the same name. Unlike `push_state`, this does not change the state
+    /// that `restore_state_from_last_snapshot` restores.
+    pub(crate) fn checkpoint(&mut self, name: String) -> Result<()> {
+        let snapshot = SharedMemorySnapshot::new(&mut self.shared_mem)?;
+        // the pages written to since the last snapshot are no longer known
+        self.shared_mem.mark_all_dirty()?;
+        let mut checkpoints = self
+            .checkpoints
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
+        checkpoints.retain(|(n, _)| *n != name);
+        checkpoints.push((name, snapshot));
+        Ok(())
+    }
+
+    /// Restore the memory to the checkpoint named `name`, and discard the
+    /// checkpoints taken after it
+    pub(crate) fn rollback_to_checkpoint(&mut self, name: &str) -> Result<()> {
+        let mut checkpoints = self
+            .checkpoints
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
+        let Some(i) = checkpoints.iter().position(|(n, _)| n == name) else {
+            log_then_return!(CheckpointNotFound(name.to_string()));
+        };
+        checkpoints.truncate(i + 1);
+        // only the pages written to since the last snapshot are known, not
+        // those that differ between it and the checkpoint
+        self.shared_mem.mark_all_dirty()?;
+        let restored_pages = checkpoints[i]
+            .1
+            .restore_from_snapshot(&mut self.shared_mem)?;
+        histogram_observe!(&RestoredSnapshotPages, restored_pages as f64);
+        self.shared_mem.mark_all_dirty()
+    }
+
+    /// Discard the checkpoint named `name`, returning whether it existed
+    pub(crate) fn remove_checkpoint(&mut self, name: &str) -> Result<bool> {
+        let mut checkpoints = self
+            .checkpoints
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
+        let len = checkpoints.len();
+        checkpoints.retain(|(n, _)| n != name);
+        Ok(checkpoints.len() != len)
+    }
+
+    /// Get the names of the checkpoints, in the order they were taken
+    pub(crate) fn checkpoint_names(&self) -> Result<Vec<String>> {
+        Ok(self
+            .checkpoints
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect())
+    }
+
     /// Sets `addr` to the correct offset in the memory referenced by
     /// `shared_mem` to indicate the address of the outb pointer and context
     /// for calling outb function
@@ -472,6 +583,59 @@ impl SandboxMemoryManager<ExclusiveSharedMemory> {
         }
     }
 
+    /// Create a new `SandboxMemoryManager` whose memory is restored from
+    /// the snapshot of an initialised sandbox in `file`, after checking
+    /// that the snapshot's memory matches the layout it describes.
+    ///
+    /// Snapshots can't be taken of in-process sandboxes, so the returned
+    /// manager always runs its guest in a VM.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn from_snapshot_file(file: &SnapshotFile) -> Result<Self> {
+        let header = &file.header;
+        let layout = SandboxMemoryLayout::new(
+            header.config,
+            header.code_size,
+            header.stack_size,
+            header.heap_size,
+        )?;
+        let mem_size = layout.get_memory_size()?;
+        if file.memory.len() != mem_size {
+            log_then_return!(InvalidSnapshotFile(format!(
+                "the snapshot has {:#x} bytes of memory, but its layout needs {:#x}",
+                file.memory.len(),
+                mem_size
+            )));
+        }
+        if header.load_addr != layout.get_guest_code_address() as u64 {
+            log_then_return!(InvalidSnapshotFile(format!(
+                "the guest binary was loaded at {:#x}, but its layout puts it at {:#x}",
+                header.load_addr,
+                layout.get_guest_code_address()
+            )));
+        }
+
+        let mut shared_mem = ExclusiveSharedMemory::new(mem_size)?;
+        shared_mem.copy_from_slice(&file.memory, 0)?;
+
+        let mgr = Self::new(
+            layout,
+            shared_mem,
+            false,
+            RawPtr::from(header.load_addr),
+            Offset::from(header.entrypoint_offset),
+            #[cfg(target_os = "windows")]
+            None,
+        );
+        if header.stack_pointer != mgr.get_initial_stack_pointer() {
+            log_then_return!(InvalidSnapshotFile(format!(
+                "the guest's stack pointer is {:#x}, but its layout puts it at {:#x}",
+                header.stack_pointer,
+                mgr.get_initial_stack_pointer()
+            )));
+        }
+        Ok(mgr)
+    }
+
     /// Writes host function details to memory
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
     pub(crate) fn write_buffer_host_function_details(&mut self, buffer: &[u8]) -> Result<()> {
@@ -516,7 +680,18 @@ impl SandboxMemoryManager<ExclusiveSharedMemory> {
         self.shared_mem.copy_from_slice(cookie, stack_offset)
     }
 
-    /// Wraps ExclusiveSharedMemory::build
+    /// Fill the guest's stack with `STACK_PATTERN`, so that how much of it
+    /// the guest uses can be measured by `get_memory_stats`. This must be
+    /// done before the stack guard is set, as it overwrites it.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn write_stack_pattern(&mut self) -> Result<()> {
+        let stack_offset = self.layout.get_top_of_user_stack_offset();
+        self.shared_mem
+            .copy_from_slice(&vec![STACK_PATTERN; self.layout.stack_size], stack_offset)
+    }
+
+    /// Wraps ExclusiveSharedMemory::build. The host side keeps any
+    /// snapshots and checkpoints `self` already has.
     pub fn build(
         self,
     ) -> (
@@ -531,7 +706,8 @@ impl SandboxMemoryManager<ExclusiveSharedMemory> {
                 inprocess: self.inprocess,
                 load_addr: self.load_addr.clone(),
                 entrypoint_offset: self.entrypoint_offset,
-                snapshots: Arc::new(Mutex::new(Vec::new())),
+                snapshots: self.snapshots,
+                checkpoints: self.checkpoints,
                 #[cfg(target_os = "windows")]
                 _lib: self._lib,
             },
@@ -542,6 +718,7 @@ impl SandboxMemoryManager<ExclusiveSharedMemory> {
                 load_addr: self.load_addr.clone(),
                 entrypoint_offset: self.entrypoint_offset,
                 snapshots: Arc::new(Mutex::new(Vec::new())),
+                checkpoints: Arc::new(Mutex::new(Vec::new())),
                 #[cfg(target_os = "windows")]
                 _lib: None,
             },
@@ -570,6 +747,115 @@ impl SandboxMemoryManager<HostSharedMemory> {
         Ok(cmp_res == Ordering::Equal)
     }
 
+    /// Get how much of its memory the guest is using, as of its current
+    /// state. The heap statistics are those the guest reported at the end
+    /// of the last call into it, and the peak stack depth is since its stack
+    /// was last filled with `STACK_PATTERN`.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_memory_stats(&self) -> Result<MemoryStats> {
+        let heap_stats_offset = self.layout.get_guest_heap_stats_offset();
+        let read_heap_stat = |field_offset: usize| -> Result<u64> {
+            self.shared_mem
+                .read::<u64>(heap_stats_offset + field_offset)
+        };
+
+        // the stack guard is at the bottom of the stack, and the stack grows
+        // down towards it, so the untouched part of the stack is read from
+        // the bottom up, a page at a time, until a used byte is found
+        let stack_offset = self.layout.get_top_of_user_stack_offset();
+        let stack_size = self.layout.stack_size;
+        let mut chunk = [0; PAGE_SIZE_USIZE];
+        let mut untouched = 0;
+        while STACK_COOKIE_LEN + untouched < stack_size {
+            let offset = STACK_COOKIE_LEN + untouched;
+            let chunk = &mut chunk[..min(PAGE_SIZE_USIZE, stack_size - offset)];
+            self.shared_mem
+                .copy_to_slice(chunk, stack_offset + offset)?;
+            let chunk_untouched = chunk.iter().take_while(|b| **b == STACK_PATTERN).count();
+            untouched += chunk_untouched;
+            if chunk_untouched < chunk.len() {
+                break;
+            }
+        }
+
+        let heap_grown_size = self.get_heap_grown_size()?;
+
+        Ok(MemoryStats {
+            heap_used: read_heap_stat(offset_of!(GuestHeapStats, heapUsedSize))?,
+            heap_high_water_mark: read_heap_stat(offset_of!(GuestHeapStats, heapHighWaterMark))?,
+            heap_free_fragments: read_heap_stat(offset_of!(GuestHeapStats, heapFreeFragments))?,
+            heap_size: (self.layout.heap_size + heap_grown_size) as u64,
+            stack_peak_depth: (stack_size - STACK_COOKIE_LEN - untouched) as u64,
+            stack_size: stack_size as u64,
+            total_mapped_size: (self.shared_mem.mem_size() - self.layout.heap_growth_size
+                + heap_grown_size) as u64,
+        })
+    }
+
+    /// Describe the sandbox as of the last snapshot of its memory, so that
+    /// it can be written to a file and restored from it later.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn to_snapshot_file(
+        &self,
+        stack_guard: [u8; STACK_COOKIE_LEN],
+        guest_functions: Option<GuestFunctionDetails>,
+    ) -> Result<SnapshotFile> {
+        let snapshots = self
+            .snapshots
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
+        let Some(snapshot) = snapshots.last() else {
+            log_then_return!(NoMemorySnapshot);
+        };
+        Ok(SnapshotFile {
+            header: SnapshotHeader {
+                hyperlight_version: env!("CARGO_PKG_VERSION").to_string(),
+                config: self.layout.sandbox_memory_config,
+                code_size: self.layout.code_size,
+                stack_size: self.layout.stack_size,
+                heap_size: self.layout.heap_size,
+                load_addr: self.load_addr.clone().into(),
+                entrypoint_offset: self.entrypoint_offset.into(),
+                stack_pointer: self.get_initial_stack_pointer(),
+                stack_guard,
+            },
+            guest_functions,
+            memory: snapshot.as_slice().to_vec(),
+        })
+    }
+
+    /// Create a new `SandboxMemoryManager` for a fork of this sandbox,
+    /// whose memory is a copy-on-write mapping of the last snapshot of
+    /// this sandbox's memory, and which starts with the same snapshots.
+    #[cfg(target_os = "linux")]
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn fork(&self) -> Result<SandboxMemoryManager<ExclusiveSharedMemory>> {
+        let mut snapshots = self
+            .snapshots
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
+        let Some(last) = snapshots.last_mut() else {
+            log_then_return!(NoMemorySnapshot);
+        };
+        let memfd = last.memfd()?;
+        let shared_mem =
+            ExclusiveSharedMemory::new_copy_on_write(&memfd, self.shared_mem.mem_size())?;
+        Ok(SandboxMemoryManager {
+            shared_mem,
+            layout: self.layout,
+            inprocess: self.inprocess,
+            load_addr: self.load_addr.clone(),
+            entrypoint_offset: self.entrypoint_offset,
+            snapshots: Arc::new(Mutex::new(snapshots.clone())),
+            checkpoints: Arc::new(Mutex::new(
+                self.checkpoints
+                    .try_lock()
+                    .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+                    .clone(),
+            )),
+        })
+    }
+
     /// Get the address of the dispatch function in memory
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
     pub(crate) fn get_pointer_to_dispatch_function(&self) -> Result<u64> {
@@ -640,6 +926,29 @@ impl SandboxMemoryManager<HostSharedMemory> {
         )
     }
 
+    /// Reads the signatures of the guest's functions from memory.
+    ///
+    /// The guest writes these to its output buffer when it finishes
+    /// initialising. `None` is returned if the output buffer is empty,
+    /// which is the case for guests that don't write them.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn read_guest_function_details(&mut self) -> Result<Option<GuestFunctionDetails>> {
+        let stack_pointer = self
+            .shared_mem
+            .read::<u64>(self.layout.output_data_buffer_offset)?;
+        // the first 8 bytes of the buffer hold the stack pointer itself, so
+        // a stack pointer of 8 or less means the buffer is empty
+        if stack_pointer <= 8 {
+            return Ok(None);
+        }
+        self.shared_mem
+            .try_pop_buffer_into::<GuestFunctionDetails>(
+                self.layout.output_data_buffer_offset,
+                self.layout.sandbox_memory_config.get_output_data_size(),
+            )
+            .map(Some)
+    }
+
     /// Read guest log data from the `SharedMemory` contained within `self`
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
     pub(crate) fn read_guest_log_data(&mut self) -> Result<GuestLogData> {
@@ -649,6 +958,130 @@ impl SandboxMemoryManager<HostSharedMemory> {
         )
     }
 
+    /// Get the size of the largest chunk of the guest's input stream that
+    /// currently fits in the input buffer
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_input_stream_chunk_capacity(&self) -> Result<usize> {
+        let space_available = self.shared_mem.buffer_space_available(
+            self.layout.input_data_buffer_offset,
+            self.layout.sandbox_memory_config.get_input_data_size(),
+        )?;
+        Ok(space_available.saturating_sub(STREAM_CHUNK_PREFIX_SIZE))
+    }
+
+    /// Writes a chunk of the guest's input stream to memory
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn write_input_stream_chunk(&mut self, chunk: &StreamChunk) -> Result<()> {
+        let buffer = Vec::<u8>::try_from(chunk)?;
+        self.shared_mem.push_buffer(
+            self.layout.input_data_buffer_offset,
+            self.layout.sandbox_memory_config.get_input_data_size(),
+            buffer.as_slice(),
+        )
+    }
+
+    /// Reads a chunk of the guest's output stream from memory
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn read_output_stream_chunk(&mut self) -> Result<StreamChunk> {
+        self.shared_mem.try_pop_buffer_into::<StreamChunk>(
+            self.layout.output_data_buffer_offset,
+            self.layout.sandbox_memory_config.get_output_data_size(),
+        )
+    }
+
+    /// Grow the guest's heap by at least as many bytes as the guest asked
+    /// for in its PEB, by mapping more of the memory reserved for the heap
+    /// to grow into into the guest's page tables, and tell the guest how many
+    /// bytes the heap has grown by in total. If that would be more than the
+    /// reserved memory, the heap doesn't grow, which the guest can see.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn grow_heap(&mut self) -> Result<()> {
+        let growth_offset = self.layout.get_guest_heap_growth_offset();
+        let grown_size_offset = growth_offset + offset_of!(GuestHeapGrowth, heapGrownSize);
+        let request_size_offset =
+            growth_offset + offset_of!(GuestHeapGrowth, heapGrowthRequestSize);
+        // the PEB is writable by the guest, so neither of these are trusted
+        // beyond staying within the reserved memory
+        let grown_size = usize::try_from(self.shared_mem.read::<u64>(grown_size_offset)?)?;
+        let request_size = usize::try_from(self.shared_mem.read::<u64>(request_size_offset)?)?;
+        let new_size = match grown_size
+            .checked_add(request_size)
+            .and_then(|size| size.checked_next_multiple_of(PAGE_SIZE_USIZE))
+        {
+            Some(new_size) if new_size <= self.layout.heap_growth_size => new_size,
+            _ => {
+                log::debug!(
+                    "Guest heap can't grow by {:#x} bytes, it has grown by {:#x} of {:#x}",
+                    request_size,
+                    grown_size,
+                    self.layout.heap_growth_size
+                );
+                return Ok(());
+            }
+        };
+
+        #[cfg(feature = "executable_heap")]
+        let flags = PAGE_PRESENT | PAGE_RW | PAGE_USER;
+        #[cfg(not(feature = "executable_heap"))]
+        let flags = PAGE_PRESENT | PAGE_RW | PAGE_USER | PAGE_NX;
+        let growth_buffer_offset = self.layout.get_heap_growth_buffer_offset();
+        for offset in (grown_size / PAGE_SIZE_USIZE * PAGE_SIZE_USIZE..new_size)
+            .step_by(PAGE_SIZE_USIZE)
+            .map(|offset| growth_buffer_offset + offset)
+        {
+            // the PTs map the guest's memory one to one from address 0, and
+            // `set_up_shared_memory` wrote not present PTEs for this page
+            let addr = SandboxMemoryLayout::BASE_ADDRESS + offset;
+            let pte_offset = SandboxMemoryLayout::PT_OFFSET + (addr / PAGE_SIZE_USIZE) * 8;
+            self.shared_mem
+                .write::<u64>(pte_offset, addr as u64 | flags)?;
+        }
+        self.shared_mem
+            .write::<u64>(grown_size_offset, new_size.try_into()?)
+    }
+
+    /// Get how many bytes the guest's heap has grown by
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    fn get_heap_grown_size(&self) -> Result<usize> {
+        let grown_size = self.shared_mem.read::<u64>(
+            self.layout.get_guest_heap_growth_offset() + offset_of!(GuestHeapGrowth, heapGrownSize),
+        )?;
+        Ok(usize::try_from(grown_size)?.min(self.layout.heap_growth_size))
+    }
+
+    /// Map `region`, which the hypervisor has mapped at the same guest
+    /// physical address, into the guest's virtual address space with 2MB
+    /// pages. The region must start on a 2MB boundary.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn map_host_region_pages(&mut self, region: &MemoryRegion) -> Result<()> {
+        let flags = if region.flags.contains(MemoryRegionFlags::WRITE) {
+            PAGE_PRESENT | PAGE_PS | PAGE_RW | PAGE_NX
+        } else {
+            PAGE_PRESENT | PAGE_PS | PAGE_NX
+        };
+        for addr in region.guest_region.clone().step_by(AMOUNT_OF_MEMORY_PER_PT) {
+            let offset = SandboxMemoryLayout::PD_OFFSET + (addr / AMOUNT_OF_MEMORY_PER_PT) * 8;
+            self.shared_mem.write::<u64>(offset, addr as u64 | flags)?;
+        }
+        Ok(())
+    }
+
+    /// Restore the page directory entries that `map_host_region_pages`
+    /// replaced to what `set_up_shared_memory` wrote.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn unmap_host_region_pages(&mut self, region: &MemoryRegion) -> Result<()> {
+        for addr in region.guest_region.clone().step_by(AMOUNT_OF_MEMORY_PER_PT) {
+            let i = addr / AMOUNT_OF_MEMORY_PER_PT;
+            let offset = SandboxMemoryLayout::PD_OFFSET + (i * 8);
+            let val_to_write: u64 = (SandboxMemoryLayout::PT_GUEST_ADDRESS as u64
+                + (i * 4096) as u64)
+                | PAGE_PRESENT
+                | PAGE_RW;
+            self.shared_mem.write::<u64>(offset, val_to_write)?;
+        }
+        Ok(())
+    }
+
     /// Get the length of the host exception
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
     fn get_host_error_length(&self) -> Result<i32> {
@@ -736,10 +1169,23 @@ impl SandboxMemoryManager<HostSharedMemory> {
         })
     }
 
+    /// Clear the guest error data, so that an error from a guest function
+    /// call that a host function made isn't mistaken for an error from the
+    /// guest function call that called the host function
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn clear_guest_error(&mut self) -> Result<()> {
+        let err_buffer_size_offset = self.layout.get_guest_error_buffer_size_offset();
+        let max_err_buffer_size = self.shared_mem.read::<u64>(err_buffer_size_offset)?;
+        self.shared_mem.copy_from_slice(
+            &vec![0; usize::try_from(max_err_buffer_size)?],
+            self.layout.guest_error_buffer_offset,
+        )
+    }
+
     /// This function writes an error to guest memory and is intended to be
     /// used when the host's outb handler code raises an error.
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
-    fn write_outb_error(
+    pub(crate) fn write_outb_error(
         &mut self,
         guest_error_msg: &[u8],
         host_exception_data: &[u8],
@@ -17,10 +17,16 @@ limitations under the License.
 /// Reusable structure to hold data and provide a `Drop` implementation
 #[cfg(inprocess)]
 pub(crate) mod custom_drop;
+/// Tracking of the pages of shared memory written to since the last
+/// snapshot or restore
+pub(crate) mod dirty_pages;
 /// A simple ELF loader
 pub(crate) mod elf;
 /// A generic wrapper for executable files (PE, ELF, etc)
 pub(crate) mod exe;
+/// Host memory mapped into the guest for the duration of a guest function
+/// call
+pub mod host_mapped_region;
 /// Functionality to establish a sandbox's memory layout.
 pub mod layout;
 /// Safe wrapper around an HINSTANCE created by the windows
@@ -50,3 +56,5 @@ pub mod shared_mem_snapshot;
 /// Utilities for writing shared memory tests
 #[cfg(test)]
 pub(crate) mod shared_mem_tests;
+/// A file format for persisting snapshots of initialised sandboxes
+pub(crate) mod snapshot_file;
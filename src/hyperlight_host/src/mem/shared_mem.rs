@@ -18,13 +18,14 @@ use std::any::type_name;
 use std::ffi::c_void;
 use std::io::Error;
 use std::ptr::null_mut;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use hyperlight_common::mem::PAGE_SIZE_USIZE;
 use tracing::{instrument, Span};
 #[cfg(target_os = "windows")]
 use windows::Win32::System::Memory::{VirtualAlloc, MEM_COMMIT, PAGE_EXECUTE_READWRITE};
 
+use super::dirty_pages::DirtyPages;
 #[cfg(target_os = "windows")]
 use crate::HyperlightError::{MemoryRequestTooBig, WindowsAPIError};
 use crate::{log_then_return, new_error, Result};
@@ -127,6 +128,7 @@ pub struct GuestSharedMemory {
     region: Arc<HostMapping>,
     /// The lock that indicates this shared memory is being used by non-Rust code
     pub lock: Arc<RwLock<()>>,
+    dirty_pages: Arc<Mutex<DirtyPages>>,
 }
 unsafe impl Send for GuestSharedMemory {}
 
@@ -268,6 +270,7 @@ unsafe impl Send for GuestSharedMemory {}
 pub struct HostSharedMemory {
     region: Arc<HostMapping>,
     lock: Arc<RwLock<()>>,
+    dirty_pages: Arc<Mutex<DirtyPages>>,
 }
 unsafe impl Send for HostSharedMemory {}
 
@@ -353,6 +356,78 @@ impl ExclusiveSharedMemory {
         })
     }
 
+    /// Create a new region of shared memory of `size` bytes that is a
+    /// private, copy-on-write mapping of the start of `file`, so its pages
+    /// are shared with `file` until they are written to. The region will be
+    /// surrounded by guard pages.
+    ///
+    /// Return `Err` if `size` is not a multiple of the page size, or the
+    /// file could not be mapped.
+    #[cfg(target_os = "linux")]
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn new_copy_on_write(file: &std::fs::File, size: usize) -> Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        use libc::{
+            c_int, mmap, off_t, size_t, MAP_ANONYMOUS, MAP_FAILED, MAP_FIXED, MAP_NORESERVE,
+            MAP_PRIVATE, PROT_NONE, PROT_READ, PROT_WRITE,
+        };
+
+        use crate::error::HyperlightError::{MemoryRequestTooBig, MmapFailed};
+
+        if size == 0 || size % PAGE_SIZE_USIZE != 0 {
+            return Err(new_error!(
+                "Cannot create copy-on-write shared memory with size {}",
+                size
+            ));
+        }
+
+        let total_size = size
+            .checked_add(2 * PAGE_SIZE_USIZE) // guard page around the memory
+            .ok_or_else(|| new_error!("Memory required for sandbox exceeded usize::MAX"))?;
+        if total_size > isize::MAX as usize {
+            return Err(MemoryRequestTooBig(total_size, isize::MAX as usize));
+        }
+
+        // reserve the address space, leaving the guard pages inaccessible
+        let addr = unsafe {
+            mmap(
+                null_mut(),
+                total_size as size_t,
+                PROT_NONE,
+                MAP_ANONYMOUS | MAP_PRIVATE | MAP_NORESERVE,
+                -1 as c_int,
+                0 as off_t,
+            )
+        };
+        if addr == MAP_FAILED {
+            log_then_return!(MmapFailed(Error::last_os_error().raw_os_error()));
+        }
+        // unmaps the whole reservation if mapping the file fails
+        #[allow(clippy::arc_with_non_send_sync)]
+        let region = Arc::new(HostMapping {
+            ptr: addr as *mut u8,
+            size: total_size,
+        });
+
+        // map the file between the guard pages
+        let res = unsafe {
+            mmap(
+                (addr as *mut u8).add(PAGE_SIZE_USIZE) as *mut c_void,
+                size as size_t,
+                PROT_READ | PROT_WRITE,
+                MAP_PRIVATE | MAP_FIXED,
+                file.as_raw_fd(),
+                0 as off_t,
+            )
+        };
+        if res == MAP_FAILED {
+            log_then_return!(MmapFailed(Error::last_os_error().raw_os_error()));
+        }
+
+        Ok(Self { region })
+    }
+
     /// Create a new region of shared memory with the given minimum
     /// size in bytes. The region will be surrounded by guard pages.
     ///
@@ -565,14 +640,17 @@ impl ExclusiveSharedMemory {
     /// the GuestSharedMemory.
     pub fn build(self) -> (HostSharedMemory, GuestSharedMemory) {
         let lock = Arc::new(RwLock::new(()));
+        let dirty_pages = Arc::new(Mutex::new(DirtyPages::new(self.mem_size())));
         (
             HostSharedMemory {
                 region: self.region.clone(),
                 lock: lock.clone(),
+                dirty_pages: dirty_pages.clone(),
             },
             GuestSharedMemory {
                 region: self.region.clone(),
                 lock: lock.clone(),
+                dirty_pages,
             },
         )
     }
@@ -631,6 +709,20 @@ pub trait SharedMemory {
         &mut self,
         f: F,
     ) -> Result<T>;
+
+    /// Get the indices of the pages that have been written to since this
+    /// was last called, or `None` if they aren't known and all pages must
+    /// be assumed to have been written to. Writes made with
+    /// `with_exclusivity` are not tracked.
+    fn take_dirty_pages(&mut self) -> Result<Option<Vec<usize>>> {
+        Ok(None)
+    }
+
+    /// Mark all pages as written to, so that the next call to
+    /// `take_dirty_pages` returns `None`.
+    fn mark_all_dirty(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl SharedMemory for ExclusiveSharedMemory {
@@ -665,6 +757,44 @@ impl SharedMemory for GuestSharedMemory {
         drop(guard);
         Ok(ret)
     }
+    fn take_dirty_pages(&mut self) -> Result<Option<Vec<usize>>> {
+        Ok(lock_dirty_pages(&self.dirty_pages)?.take())
+    }
+    fn mark_all_dirty(&mut self) -> Result<()> {
+        lock_dirty_pages(&self.dirty_pages)?.mark_all();
+        Ok(())
+    }
+}
+
+impl GuestSharedMemory {
+    /// Mark the pages at `host_addrs`, which the hypervisor reported the
+    /// guest as having written to, as dirty. If `host_addrs` is `None`
+    /// because the hypervisor doesn't track which pages the guest writes
+    /// to, all pages are marked as dirty.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn mark_guest_dirty_pages(&self, host_addrs: Option<Vec<usize>>) -> Result<()> {
+        let mut dirty_pages = lock_dirty_pages(&self.dirty_pages)?;
+        match host_addrs {
+            Some(host_addrs) => {
+                let base_addr = self.base_addr();
+                for addr in host_addrs {
+                    if let Some(offset) = addr.checked_sub(base_addr) {
+                        dirty_pages.mark(offset, PAGE_SIZE_USIZE);
+                    }
+                }
+            }
+            None => dirty_pages.mark_all(),
+        }
+        Ok(())
+    }
+}
+
+fn lock_dirty_pages(
+    dirty_pages: &Mutex<DirtyPages>,
+) -> Result<std::sync::MutexGuard<'_, DirtyPages>> {
+    dirty_pages
+        .lock()
+        .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))
 }
 
 /// An unsafe marker trait for types for which all bit patterns are valid.
@@ -756,6 +886,7 @@ impl HostSharedMemory {
             }
         }
         drop(guard);
+        lock_dirty_pages(&self.dirty_pages)?.mark(offset, slice.len());
         Ok(())
     }
 
@@ -773,9 +904,33 @@ impl HostSharedMemory {
             unsafe { base.wrapping_add(i).write_volatile(value) };
         }
         drop(guard);
+        lock_dirty_pages(&self.dirty_pages)?.mark(offset, len);
         Ok(())
     }
 
+    /// Returns the size of the largest element that can be pushed onto the
+    /// buffer at the given offset.
+    /// NOTE! buffer_start_offset must point to the beginning of the buffer
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub fn buffer_space_available(
+        &self,
+        buffer_start_offset: usize,
+        buffer_size: usize,
+    ) -> Result<usize> {
+        let stack_pointer_rel = self.read::<u64>(buffer_start_offset)? as usize;
+
+        if stack_pointer_rel > buffer_size || stack_pointer_rel < 8 {
+            return Err(new_error!(
+                "Unable to get space available in buffer: Stack pointer is out of bounds. Stack pointer: {}, Buffer size: {}",
+                stack_pointer_rel,
+                buffer_size
+            ));
+        }
+
+        // every element is followed by an 8 byte offset back to its start
+        Ok((buffer_size - stack_pointer_rel).saturating_sub(8))
+    }
+
     /// Pushes the given data onto shared memory to the buffer at the given offset.
     /// NOTE! buffer_start_offset must point to the beginning of the buffer
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
@@ -908,6 +1063,13 @@ impl SharedMemory for HostSharedMemory {
         drop(guard);
         Ok(ret)
     }
+    fn take_dirty_pages(&mut self) -> Result<Option<Vec<usize>>> {
+        Ok(lock_dirty_pages(&self.dirty_pages)?.take())
+    }
+    fn mark_all_dirty(&mut self) -> Result<()> {
+        lock_dirty_pages(&self.dirty_pages)?.mark_all();
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -1085,6 +1247,34 @@ mod tests {
         assert_eq!(data, ret_vec);
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn copy_on_write() {
+        use std::os::unix::fs::FileExt;
+
+        let data = vec![b'a'; 2 * PAGE_SIZE_USIZE];
+        let file = tempfile::tempfile().unwrap();
+        file.write_all_at(&data, 0).unwrap();
+
+        // both mappings start with the contents of the file
+        let mut eshm1 = ExclusiveSharedMemory::new_copy_on_write(&file, data.len()).unwrap();
+        let eshm2 = ExclusiveSharedMemory::new_copy_on_write(&file, data.len()).unwrap();
+        assert_eq!(data, eshm1.copy_all_to_vec().unwrap());
+
+        // writes to one mapping are visible in neither the file nor the
+        // other mapping
+        eshm1.copy_from_slice(b"b", PAGE_SIZE_USIZE).unwrap();
+        assert_eq!(eshm1.read_u8(PAGE_SIZE_USIZE).unwrap(), b'b');
+        assert_eq!(data, eshm2.copy_all_to_vec().unwrap());
+        let mut contents = vec![0; data.len()];
+        file.read_exact_at(&mut contents, 0).unwrap();
+        assert_eq!(data, contents);
+
+        // the mapping must be a whole number of pages
+        assert!(ExclusiveSharedMemory::new_copy_on_write(&file, 0).is_err());
+        assert!(ExclusiveSharedMemory::new_copy_on_write(&file, PAGE_SIZE_USIZE + 1).is_err());
+    }
+
     /// A test to ensure that, if a `SharedMem` instance is cloned
     /// and _all_ clones are dropped, the memory region will no longer
     /// be valid.
@@ -14,6 +14,11 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+#[cfg(target_os = "linux")]
+use std::fs::File;
+use std::sync::Arc;
+
+use hyperlight_common::mem::PAGE_SIZE_USIZE;
 use tracing::{instrument, Span};
 
 use super::shared_mem::SharedMemory;
@@ -21,9 +26,15 @@ use crate::Result;
 
 /// A wrapper around a `SharedMemory` reference and a snapshot
 /// of the memory therein
+///
+/// Clones share the snapshotted memory, which is never modified.
 #[derive(Clone)]
 pub(super) struct SharedMemorySnapshot {
-    snapshot: Vec<u8>,
+    snapshot: Arc<[u8]>,
+    /// A memfd holding a copy of `snapshot`, created the first time a
+    /// copy-on-write mapping of the snapshot is needed
+    #[cfg(target_os = "linux")]
+    memfd: Option<Arc<File>>,
 }
 
 impl SharedMemorySnapshot {
@@ -31,9 +42,15 @@ impl SharedMemorySnapshot {
     /// instance of `Self` with the snapshot stored therein.
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
     pub(super) fn new<S: SharedMemory>(shared_mem: &mut S) -> Result<Self> {
-        // TODO: Track dirty pages instead of copying entire memory
         let snapshot = shared_mem.with_exclusivity(|e| e.copy_all_to_vec())??;
-        Ok(Self { snapshot })
+        // the memory now matches the snapshot, so only pages written to from
+        // now on need to be restored
+        shared_mem.take_dirty_pages()?;
+        Ok(Self {
+            snapshot: snapshot.into(),
+            #[cfg(target_os = "linux")]
+            memfd: None,
+        })
     }
 
     /// Take another snapshot of the internally-stored `SharedMemory`,
@@ -41,18 +58,71 @@ impl SharedMemorySnapshot {
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
 
     pub(super) fn replace_snapshot<S: SharedMemory>(&mut self, shared_mem: &mut S) -> Result<()> {
-        self.snapshot = shared_mem.with_exclusivity(|e| e.copy_all_to_vec())??;
+        self.snapshot = shared_mem
+            .with_exclusivity(|e| e.copy_all_to_vec())??
+            .into();
+        shared_mem.take_dirty_pages()?;
+        #[cfg(target_os = "linux")]
+        {
+            self.memfd = None;
+        }
         Ok(())
     }
 
+    /// Get the contents of the memory as of the snapshot
+    pub(super) fn as_slice(&self) -> &[u8] {
+        &self.snapshot
+    }
+
+    /// Get a memfd holding a copy of the snapshotted memory, which can be
+    /// mapped copy-on-write with `ExclusiveSharedMemory::new_copy_on_write`.
+    /// The memfd is created the first time this is called.
+    #[cfg(target_os = "linux")]
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(super) fn memfd(&mut self) -> Result<Arc<File>> {
+        use std::os::fd::{FromRawFd, OwnedFd};
+        use std::os::unix::fs::FileExt;
+
+        if let Some(memfd) = &self.memfd {
+            return Ok(memfd.clone());
+        }
+        let fd = unsafe { libc::memfd_create(c"hyperlight-snapshot".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: `fd` was just created, and nothing else owns it
+        let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+        file.set_len(self.snapshot.len() as u64)?;
+        file.write_all_at(&self.snapshot, 0)?;
+        let memfd = Arc::new(file);
+        self.memfd = Some(memfd.clone());
+        Ok(memfd)
+    }
+
     /// Copy the memory from the internally-stored memory snapshot
-    /// into the internally-stored `SharedMemory`
+    /// into the internally-stored `SharedMemory`. Only the pages that have
+    /// been written to since the snapshot was taken or last restored are
+    /// copied, if they are known. Returns the number of pages copied.
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
     pub(super) fn restore_from_snapshot<S: SharedMemory>(
         &mut self,
         shared_mem: &mut S,
-    ) -> Result<()> {
-        shared_mem.with_exclusivity(|e| e.copy_from_slice(self.snapshot.as_slice(), 0))?
+    ) -> Result<usize> {
+        let dirty_pages = shared_mem.take_dirty_pages()?;
+        shared_mem.with_exclusivity(|e| match dirty_pages {
+            Some(pages) => {
+                for page in &pages {
+                    let offset = page * PAGE_SIZE_USIZE;
+                    let end = (offset + PAGE_SIZE_USIZE).min(self.snapshot.len());
+                    e.copy_from_slice(&self.snapshot[offset..end], offset)?;
+                }
+                Ok(pages.len())
+            }
+            None => {
+                e.copy_from_slice(&self.snapshot, 0)?;
+                Ok(self.snapshot.len().div_ceil(PAGE_SIZE_USIZE))
+            }
+        })?
     }
 }
 
@@ -60,7 +130,7 @@ impl SharedMemorySnapshot {
 mod tests {
     use hyperlight_common::mem::PAGE_SIZE_USIZE;
 
-    use crate::mem::shared_mem::ExclusiveSharedMemory;
+    use crate::mem::shared_mem::{ExclusiveSharedMemory, SharedMemory};
 
     #[test]
     fn restore_replace() {
@@ -95,4 +165,42 @@ mod tests {
             assert_eq!(data2, gm.copy_all_to_vec().unwrap());
         }
     }
+
+    #[test]
+    fn restore_dirty_pages() {
+        let data = vec![b'a'; 4 * PAGE_SIZE_USIZE];
+        let mut gm = ExclusiveSharedMemory::new(4 * PAGE_SIZE_USIZE).unwrap();
+        gm.copy_from_slice(data.as_slice(), 0).unwrap();
+        let (mut hshm, gshm) = gm.build();
+        let mut snap = super::SharedMemorySnapshot::new(&mut hshm).unwrap();
+
+        // pages written to by the host are restored
+        hshm.fill(b'b', PAGE_SIZE_USIZE - 1, 2).unwrap();
+        assert_eq!(snap.restore_from_snapshot(&mut hshm).unwrap(), 2);
+        assert_eq!(
+            data,
+            hshm.with_exclusivity(|e| e.copy_all_to_vec())
+                .unwrap()
+                .unwrap()
+        );
+
+        // pages the guest is reported to have written to are restored
+        hshm.with_exclusivity(|e| e.copy_from_slice(b"c", 3 * PAGE_SIZE_USIZE))
+            .unwrap()
+            .unwrap();
+        gshm.mark_guest_dirty_pages(Some(vec![gshm.base_addr() + 3 * PAGE_SIZE_USIZE]))
+            .unwrap();
+        assert_eq!(snap.restore_from_snapshot(&mut hshm).unwrap(), 1);
+        assert_eq!(
+            data,
+            hshm.with_exclusivity(|e| e.copy_all_to_vec())
+                .unwrap()
+                .unwrap()
+        );
+
+        // all pages are restored if the guest's writes aren't known
+        gshm.mark_guest_dirty_pages(None).unwrap();
+        assert_eq!(snap.restore_from_snapshot(&mut hshm).unwrap(), 4);
+        assert_eq!(snap.restore_from_snapshot(&mut hshm).unwrap(), 0);
+    }
 }
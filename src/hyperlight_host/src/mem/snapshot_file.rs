@@ -0,0 +1,270 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::fs;
+use std::mem::size_of;
+use std::path::Path;
+
+use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
+use serde::{Deserialize, Serialize};
+use tracing::{instrument, Span};
+
+use super::mgr::STACK_COOKIE_LEN;
+use crate::error::HyperlightError::InvalidSnapshotFile;
+use crate::sandbox::SandboxConfiguration;
+use crate::{log_then_return, Result};
+
+/// The bytes every snapshot file starts with
+const SNAPSHOT_FILE_MAGIC: &[u8; 8] = b"HLSNAPSH";
+/// The version of the snapshot file format, which must be incremented
+/// whenever the format changes
+pub(crate) const SNAPSHOT_FILE_VERSION: u32 = 1;
+/// The length of the hex encoded SHA-256 digest at the end of the file
+const DIGEST_LEN: usize = 64;
+
+/// Everything about a snapshotted sandbox other than its memory and the
+/// functions its guest exposes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SnapshotHeader {
+    /// The version of Hyperlight that wrote the snapshot. The memory
+    /// layout of a sandbox may differ between versions, so a snapshot can
+    /// only be loaded by the same version.
+    pub(crate) hyperlight_version: String,
+    /// The configuration the sandbox was created with
+    pub(crate) config: SandboxConfiguration,
+    /// The size of the guest binary loaded into memory
+    pub(crate) code_size: usize,
+    /// The size of the guest's stack
+    pub(crate) stack_size: usize,
+    /// The size of the guest's heap
+    pub(crate) heap_size: usize,
+    /// The guest address the guest binary was loaded at
+    pub(crate) load_addr: u64,
+    /// The offset of the guest binary's entrypoint from `load_addr`
+    pub(crate) entrypoint_offset: u64,
+    /// The guest's stack pointer between calls into the guest, which is
+    /// the only register state that is kept between them
+    pub(crate) stack_pointer: u64,
+    /// The stack guard cookie written to the guest's memory
+    pub(crate) stack_guard: [u8; STACK_COOKIE_LEN],
+}
+
+/// A snapshot of an initialised sandbox, which can be written to a file
+/// and read back to create a new sandbox in the same state.
+///
+/// The file starts with `SNAPSHOT_FILE_MAGIC` and the little-endian
+/// `SNAPSHOT_FILE_VERSION`, followed by the JSON encoded header, the
+/// flatbuffer encoded guest function details and the sandbox's memory,
+/// each prefixed with its little-endian `u64` length, and ends with the
+/// hex encoded SHA-256 digest of everything before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SnapshotFile {
+    pub(crate) header: SnapshotHeader,
+    /// The functions the guest exposes, if it reported them
+    pub(crate) guest_functions: Option<GuestFunctionDetails>,
+    /// The contents of the sandbox's memory
+    pub(crate) memory: Vec<u8>,
+}
+
+impl SnapshotFile {
+    /// Write `self` to the file at `path`, replacing it if it exists
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn write(&self, path: &Path) -> Result<()> {
+        let header = serde_json::to_vec(&self.header)?;
+        let guest_functions = match &self.guest_functions {
+            Some(guest_functions) => Vec::<u8>::try_from(guest_functions)?,
+            None => Vec::new(),
+        };
+
+        let mut bytes = Vec::with_capacity(
+            SNAPSHOT_FILE_MAGIC.len()
+                + size_of::<u32>()
+                + 3 * size_of::<u64>()
+                + header.len()
+                + guest_functions.len()
+                + self.memory.len()
+                + DIGEST_LEN,
+        );
+        bytes.extend_from_slice(SNAPSHOT_FILE_MAGIC);
+        bytes.extend_from_slice(&SNAPSHOT_FILE_VERSION.to_le_bytes());
+        write_section(&mut bytes, &header);
+        write_section(&mut bytes, &guest_functions);
+        write_section(&mut bytes, &self.memory);
+        let digest = sha256::digest(bytes.as_slice());
+        bytes.extend_from_slice(digest.as_bytes());
+
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Read a `SnapshotFile` from the file at `path`, checking that it was
+    /// written by this version of Hyperlight and has not been corrupted
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let prefix_len = SNAPSHOT_FILE_MAGIC.len() + size_of::<u32>();
+        if bytes.len() < prefix_len + DIGEST_LEN || !bytes.starts_with(SNAPSHOT_FILE_MAGIC) {
+            log_then_return!(InvalidSnapshotFile(
+                "not a Hyperlight snapshot file".to_string()
+            ));
+        }
+        let version = u32::from_le_bytes(bytes[SNAPSHOT_FILE_MAGIC.len()..prefix_len].try_into()?);
+        if version != SNAPSHOT_FILE_VERSION {
+            log_then_return!(InvalidSnapshotFile(format!(
+                "unsupported file format version {}, expected {}",
+                version, SNAPSHOT_FILE_VERSION
+            )));
+        }
+
+        let (contents, digest) = bytes.split_at(bytes.len() - DIGEST_LEN);
+        if sha256::digest(contents).as_bytes() != digest {
+            log_then_return!(InvalidSnapshotFile(
+                "the file's digest does not match its contents".to_string()
+            ));
+        }
+
+        let mut sections = &contents[prefix_len..];
+        let header: SnapshotHeader = serde_json::from_slice(read_section(&mut sections)?)?;
+        let guest_functions = match read_section(&mut sections)? {
+            [] => None,
+            guest_functions => Some(GuestFunctionDetails::try_from(guest_functions)?),
+        };
+        let memory = read_section(&mut sections)?.to_vec();
+        if !sections.is_empty() {
+            log_then_return!(InvalidSnapshotFile(
+                "unexpected data after the sandbox's memory".to_string()
+            ));
+        }
+
+        if header.hyperlight_version != env!("CARGO_PKG_VERSION") {
+            log_then_return!(InvalidSnapshotFile(format!(
+                "written by Hyperlight {}, but this is Hyperlight {}",
+                header.hyperlight_version,
+                env!("CARGO_PKG_VERSION")
+            )));
+        }
+
+        Ok(Self {
+            header,
+            guest_functions,
+            memory,
+        })
+    }
+}
+
+/// Append `section` to `bytes`, prefixed with its length
+fn write_section(bytes: &mut Vec<u8>, section: &[u8]) {
+    bytes.extend_from_slice(&(section.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(section);
+}
+
+/// Read a section written by `write_section` from the start of `bytes`,
+/// and advance `bytes` past it
+fn read_section<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let Some((len, rest)) = bytes.split_first_chunk::<{ size_of::<u64>() }>() else {
+        log_then_return!(InvalidSnapshotFile("the file is truncated".to_string()));
+    };
+    let len = usize::try_from(u64::from_le_bytes(*len))?;
+    if rest.len() < len {
+        log_then_return!(InvalidSnapshotFile("the file is truncated".to_string()));
+    }
+    let (section, rest) = rest.split_at(len);
+    *bytes = rest;
+    Ok(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterType, ReturnType};
+    use hyperlight_common::flatbuffer_wrappers::guest_function_definition::GuestFunctionDefinition;
+    use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
+
+    use super::{SnapshotFile, SnapshotHeader, SNAPSHOT_FILE_VERSION};
+    use crate::sandbox::SandboxConfiguration;
+    use crate::HyperlightError;
+
+    fn snapshot_file() -> SnapshotFile {
+        SnapshotFile {
+            header: SnapshotHeader {
+                hyperlight_version: env!("CARGO_PKG_VERSION").to_string(),
+                config: SandboxConfiguration::default(),
+                code_size: 0x1000,
+                stack_size: 0x2000,
+                heap_size: 0x3000,
+                load_addr: 0x230000,
+                entrypoint_offset: 0x40,
+                stack_pointer: 0x250000,
+                stack_guard: [7; 16],
+            },
+            guest_functions: Some(GuestFunctionDetails::new(vec![
+                GuestFunctionDefinition::new(
+                    "Echo".to_string(),
+                    vec![ParameterType::String],
+                    ReturnType::String,
+                ),
+            ])),
+            memory: (0..=255).cycle().take(0x5000).collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot");
+
+        let file = snapshot_file();
+        file.write(&path).unwrap();
+        assert_eq!(SnapshotFile::read(&path).unwrap(), file);
+
+        let file = SnapshotFile {
+            guest_functions: None,
+            ..snapshot_file()
+        };
+        file.write(&path).unwrap();
+        assert_eq!(SnapshotFile::read(&path).unwrap(), file);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot");
+        snapshot_file().write(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        let assert_invalid = |bytes: &[u8], msg: &str| {
+            fs::write(&path, bytes).unwrap();
+            match SnapshotFile::read(&path) {
+                Err(HyperlightError::InvalidSnapshotFile(e)) => assert!(e.contains(msg), "{}", e),
+                other => panic!("expected InvalidSnapshotFile, got {:?}", other),
+            }
+        };
+
+        assert_invalid(b"not a snapshot", "not a Hyperlight snapshot file");
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[8..12].copy_from_slice(&(SNAPSHOT_FILE_VERSION + 1).to_le_bytes());
+        assert_invalid(&wrong_version, "unsupported file format version");
+
+        let mut corrupted = bytes.clone();
+        let len = corrupted.len();
+        corrupted[len / 2] ^= 0xff;
+        assert_invalid(&corrupted, "digest does not match");
+
+        assert_invalid(&bytes[..len - 1], "digest does not match");
+    }
+}
@@ -88,11 +88,7 @@ impl From<Histogram> for HyperlightMetric {
 macro_rules! histogram_observe {
     ($metric:expr, $val:expr) => {{
         match $crate::metrics::GetHyperlightMetric::<$crate::metrics::Histogram>::metric($metric) {
-            Ok(val) => {
-                if let Err(e) = val.observe($val) {
-                    log::error!("error calling observe with value {} on metric {} ", $val, e,)
-                }
-            }
+            Ok(val) => val.observe($val),
             Err(e) => log::error!("error getting metric: {}", e),
         };
     }};
@@ -104,14 +100,7 @@ macro_rules! histogram_observe {
 macro_rules! histogram_sample_sum {
     ($metric:expr) => {{
         match $crate::metrics::GetHyperlightMetric::<$crate::metrics::Histogram>::metric($metric) {
-            Ok(val) => match val.get_sample_sum() {
-                Ok(val) => val,
-                Err(e) => {
-                    log::error!("error getting samples sum of metric {}", e,);
-                    0.0
-                }
-            },
-
+            Ok(val) => val.get_sample_sum(),
             Err(e) => {
                 log::error!("error getting metric: {}", e);
                 0.0
@@ -126,14 +115,7 @@ macro_rules! histogram_sample_sum {
 macro_rules! histogram_sample_count {
     ($metric:expr) => {{
         match $crate::metrics::GetHyperlightMetric::<$crate::metrics::Histogram>::metric($metric) {
-            Ok(val) => match val.get_sample_count() {
-                Ok(val) => val,
-                Err(e) => {
-                    log::error!("error getting samples count of metric {}", e,);
-                    0
-                }
-            },
-
+            Ok(val) => val.get_sample_count(),
             Err(e) => {
                 log::error!("error getting metric: {}", e);
                 0
@@ -341,6 +341,16 @@ pub mod tests {
                 .try_into()
         }
 
+        /// Gets a named histogram metric
+        fn get_histogram_metric(name: &str) -> Result<&Histogram> {
+            Self::get_metrics()
+                .get()
+                .ok_or_else(|| new_error!("metrics hashmap not initialized"))?
+                .get(name)
+                .ok_or_else(|| new_error!("metric not found : {0:?}", name))?
+                .try_into()
+        }
+
         /// Gets a named histogram vec metric
         fn get_histogramvec_metric(name: &str) -> Result<&HistogramVec> {
             Self::get_metrics()
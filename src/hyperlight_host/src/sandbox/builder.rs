@@ -0,0 +1,461 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use log::LevelFilter;
+use tracing::{instrument, Span};
+
+use super::{
+    ExecutionTimeBudget, ExtraAllowedSyscall, FilesystemAccess, GuestBinary, SandboxConfiguration,
+    SandboxRunOptions,
+};
+use crate::func::{HostFunction1, RegisterableHostFunction};
+use crate::{log_then_return, HyperlightError, Result, UninitializedSandbox};
+
+/// The name under which the host print function is registered.
+const HOST_PRINT_FUNCTION_NAME: &str = "HostPrint";
+
+type RegisterFn<'a> = Box<dyn FnOnce(&mut UninitializedSandbox) -> Result<()> + 'a>;
+
+/// A host function waiting to be registered when the sandbox is built.
+struct PendingHostFunction<'a> {
+    name: String,
+    register: RegisterFn<'a>,
+}
+
+/// A builder for `UninitializedSandbox`es.
+///
+/// All of the options needed to create an `UninitializedSandbox` (the guest
+/// binary, the `SandboxConfiguration`, the `SandboxRunOptions`, the host
+/// print writer, host functions and the guest log level) are collected
+/// first and validated together when `build` is called, so that
+/// misconfiguration is reported in one place rather than partway through
+/// registering host functions.
+///
+/// ```no_run
+/// use std::sync::{Arc, Mutex};
+///
+/// use hyperlight_host::sandbox::SandboxBuilder;
+/// use hyperlight_host::{GuestBinary, Result};
+///
+/// let add = Arc::new(Mutex::new(|a: i32, b: i32| -> Result<i32> { Ok(a + b) }));
+///
+/// let u_sbox = SandboxBuilder::new(GuestBinary::FilePath("some_guest_binary".to_string()))
+///     .with_input_data_size(0x8000)
+///     .with_host_function("Add", add)
+///     .build()
+///     .unwrap();
+/// ```
+pub struct SandboxBuilder<'a> {
+    guest_binary: GuestBinary,
+    config: SandboxConfiguration,
+    run_options: Option<SandboxRunOptions>,
+    host_print_writer: Option<&'a dyn HostFunction1<'a, String, i32>>,
+    host_functions: Vec<PendingHostFunction<'a>>,
+    max_guest_log_level: Option<LevelFilter>,
+}
+
+impl<'a> SandboxBuilder<'a> {
+    /// Create a new builder for a sandbox that will run `guest_binary`,
+    /// using the default `SandboxConfiguration` and `SandboxRunOptions`.
+    #[instrument(skip_all, parent = Span::current(), level = "Trace")]
+    pub fn new(guest_binary: GuestBinary) -> Self {
+        Self {
+            guest_binary,
+            config: SandboxConfiguration::default(),
+            run_options: None,
+            host_print_writer: None,
+            host_functions: Vec::new(),
+            max_guest_log_level: None,
+        }
+    }
+
+    /// Replace the whole `SandboxConfiguration`. Any individual
+    /// configuration values set on this builder after this call are
+    /// applied on top of `config`.
+    pub fn with_config(mut self, config: SandboxConfiguration) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set the size of the memory buffer that is made available for input to the guest.
+    /// See `SandboxConfiguration::set_input_data_size`.
+    pub fn with_input_data_size(mut self, input_data_size: usize) -> Self {
+        self.config.set_input_data_size(input_data_size);
+        self
+    }
+
+    /// Set the size of the memory buffer that is made available for output from the guest.
+    /// See `SandboxConfiguration::set_output_data_size`.
+    pub fn with_output_data_size(mut self, output_data_size: usize) -> Self {
+        self.config.set_output_data_size(output_data_size);
+        self
+    }
+
+    /// Set the size of the memory buffer that is made available for serialising host function definitions.
+    /// See `SandboxConfiguration::set_host_function_definition_size`.
+    pub fn with_host_function_definition_size(mut self, size: usize) -> Self {
+        self.config.set_host_function_definition_size(size);
+        self
+    }
+
+    /// Set the size of the memory buffer that is made available for serialising host exceptions.
+    /// See `SandboxConfiguration::set_host_exception_size`.
+    pub fn with_host_exception_size(mut self, size: usize) -> Self {
+        self.config.set_host_exception_size(size);
+        self
+    }
+
+    /// Set the size of the memory buffer that is made available for serialising guest error messages.
+    /// See `SandboxConfiguration::set_guest_error_buffer_size`.
+    pub fn with_guest_error_buffer_size(mut self, size: usize) -> Self {
+        self.config.set_guest_error_buffer_size(size);
+        self
+    }
+
+    /// Set the size of the memory buffer that is made available for serializing guest panic context.
+    /// See `SandboxConfiguration::set_guest_panic_context_buffer_size`.
+    pub fn with_guest_panic_context_buffer_size(mut self, size: usize) -> Self {
+        self.config.set_guest_panic_context_buffer_size(size);
+        self
+    }
+
+    /// Set the stack size to use in the guest sandbox.
+    /// See `SandboxConfiguration::set_stack_size`.
+    pub fn with_stack_size(mut self, stack_size: u64) -> Self {
+        self.config.set_stack_size(stack_size);
+        self
+    }
+
+    /// Set the heap size to use in the guest sandbox.
+    /// See `SandboxConfiguration::set_heap_size`.
+    pub fn with_heap_size(mut self, heap_size: u64) -> Self {
+        self.config.set_heap_size(heap_size);
+        self
+    }
+
+    /// Set the most bytes the guest heap may grow to on demand.
+    /// See `SandboxConfiguration::set_max_heap_size`.
+    pub fn with_max_heap_size(mut self, max_heap_size: u64) -> Self {
+        self.config.set_max_heap_size(max_heap_size);
+        self
+    }
+
+    /// Set whether the guest's memory statistics are collected at the end of
+    /// every guest function call.
+    /// See `SandboxConfiguration::set_collect_memory_stats`.
+    pub fn with_collect_memory_stats(mut self, collect_memory_stats: bool) -> Self {
+        self.config.set_collect_memory_stats(collect_memory_stats);
+        self
+    }
+
+    /// Set whether the seccomp filters of host functions are in audit mode.
+    /// See `SandboxConfiguration::set_seccomp_audit_mode`.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub fn with_seccomp_audit_mode(mut self, seccomp_audit_mode: bool) -> Self {
+        self.config.set_seccomp_audit_mode(seccomp_audit_mode);
+        self
+    }
+
+    /// Set whether a seccomp filter is applied to the Hypervisor Handler thread.
+    /// See `SandboxConfiguration::set_hypervisor_handler_seccomp_filter`.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub fn with_hypervisor_handler_seccomp_filter(mut self, enabled: bool) -> Self {
+        self.config.set_hypervisor_handler_seccomp_filter(enabled);
+        self
+    }
+
+    /// Set the kernel stack size to use in the guest sandbox.
+    /// See `SandboxConfiguration::set_kernel_stack_size`.
+    pub fn with_kernel_stack_size(mut self, kernel_stack_size: usize) -> Self {
+        self.config.set_kernel_stack_size(kernel_stack_size);
+        self
+    }
+
+    /// Set the maximum execution time of a guest function execution.
+    /// See `SandboxConfiguration::set_max_execution_time`.
+    pub fn with_max_execution_time(mut self, max_execution_time: Duration) -> Self {
+        self.config.set_max_execution_time(max_execution_time);
+        self
+    }
+
+    /// Set how the execution time of a guest function call is measured.
+    /// See `SandboxConfiguration::set_execution_time_budget`.
+    pub fn with_execution_time_budget(
+        mut self,
+        execution_time_budget: ExecutionTimeBudget,
+    ) -> Self {
+        self.config.set_execution_time_budget(execution_time_budget);
+        self
+    }
+
+    /// Set the maximum time a single host function call may take.
+    /// See `SandboxConfiguration::set_max_host_function_time`.
+    pub fn with_max_host_function_time(mut self, max_host_function_time: Duration) -> Self {
+        self.config
+            .set_max_host_function_time(max_host_function_time);
+        self
+    }
+
+    /// Set how many guest function calls host functions may nest inside
+    /// each other.
+    /// See `SandboxConfiguration::set_max_nested_guest_call_depth`.
+    pub fn with_max_nested_guest_call_depth(mut self, max_nested_guest_call_depth: u8) -> Self {
+        self.config
+            .set_max_nested_guest_call_depth(max_nested_guest_call_depth);
+        self
+    }
+
+    /// Set the maximum time to wait for guest execution cancellation.
+    /// See `SandboxConfiguration::set_max_execution_cancel_wait_time`.
+    pub fn with_max_execution_cancel_wait_time(mut self, max_wait: Duration) -> Self {
+        self.config.set_max_execution_cancel_wait_time(max_wait);
+        self
+    }
+
+    /// Set the maximum time to wait for guest initialization.
+    /// See `SandboxConfiguration::set_max_initialization_time`.
+    pub fn with_max_initialization_time(mut self, max_initialization_time: Duration) -> Self {
+        self.config
+            .set_max_initialization_time(max_initialization_time);
+        self
+    }
+
+    /// Set the `SandboxRunOptions` for the sandbox.
+    pub fn with_run_options(mut self, run_options: SandboxRunOptions) -> Self {
+        self.run_options = Some(run_options);
+        self
+    }
+
+    /// Set the writer used for the `HostPrint` host function. If this is not
+    /// set, output from the guest is written to stdout.
+    pub fn with_host_print_fn(mut self, writer: &'a dyn HostFunction1<'a, String, i32>) -> Self {
+        self.host_print_writer = Some(writer);
+        self
+    }
+
+    /// Set the max log level to be used by the guest.
+    /// See `UninitializedSandbox::set_max_guest_log_level`.
+    pub fn with_guest_log_level(mut self, log_level: LevelFilter) -> Self {
+        self.max_guest_log_level = Some(log_level);
+        self
+    }
+
+    /// Add a host function, to be registered under `name` when the sandbox
+    /// is built.
+    ///
+    /// `func` can be any `Arc<Mutex<T>>` that implements one of the
+    /// `HostFunction0` to `HostFunction10` traits.
+    pub fn with_host_function<Args, R>(
+        self,
+        name: &str,
+        func: impl RegisterableHostFunction<Args, R> + 'a,
+    ) -> Self {
+        self.add_host_function(name, func, None, None)
+    }
+
+    /// Add a host function, to be registered under `name` when the sandbox
+    /// is built, that is allowed to make the given extra syscalls.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub fn with_host_function_and_extra_allowed_syscalls<Args, R>(
+        self,
+        name: &str,
+        func: impl RegisterableHostFunction<Args, R> + 'a,
+        extra_allowed_syscalls: Vec<ExtraAllowedSyscall>,
+    ) -> Self {
+        self.add_host_function(name, func, Some(extra_allowed_syscalls), None)
+    }
+
+    /// Add a host function, to be registered under `name` when the sandbox
+    /// is built, that is allowed to make the given extra syscalls and may
+    /// only access the files `filesystem_access` allows.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub fn with_host_function_and_filesystem_access<Args, R>(
+        self,
+        name: &str,
+        func: impl RegisterableHostFunction<Args, R> + 'a,
+        extra_allowed_syscalls: Vec<ExtraAllowedSyscall>,
+        filesystem_access: FilesystemAccess,
+    ) -> Self {
+        self.add_host_function(
+            name,
+            func,
+            Some(extra_allowed_syscalls),
+            Some(filesystem_access),
+        )
+    }
+
+    fn add_host_function<Args, R>(
+        mut self,
+        name: &str,
+        func: impl RegisterableHostFunction<Args, R> + 'a,
+        extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+        filesystem_access: Option<FilesystemAccess>,
+    ) -> Self {
+        let name = name.to_string();
+        let register_name = name.clone();
+        self.host_functions.push(PendingHostFunction {
+            name,
+            register: Box::new(move |sandbox| {
+                func.register_with_sandbox(
+                    sandbox,
+                    &register_name,
+                    extra_allowed_syscalls,
+                    filesystem_access,
+                )
+            }),
+        });
+        self
+    }
+
+    /// Check the options collected so far, returning every problem found
+    /// rather than only the first.
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let GuestBinary::FilePath(path) = &self.guest_binary {
+            if !Path::new(path).exists() {
+                problems.push(format!("guest binary {} does not exist", path));
+            }
+        }
+
+        if let Some(run_options) = &self.run_options {
+            if run_options.in_process() && cfg!(not(inprocess)) {
+                problems.push("in-process mode is only available in debug builds, and also requires cargo feature 'inprocess'".to_string());
+            }
+            if run_options.use_loadlib() {
+                if cfg!(not(all(inprocess, target_os = "windows"))) {
+                    problems.push(
+                        "in-process mode with LoadLibrary is only available on Windows".to_string(),
+                    );
+                }
+                if matches!(self.guest_binary, GuestBinary::Buffer(_)) {
+                    problems.push(HyperlightError::GuestBinaryShouldBeAFile().to_string());
+                }
+            }
+        }
+
+        let mut names = HashSet::new();
+        for host_function in &self.host_functions {
+            if host_function.name.is_empty() {
+                problems.push("host function names must not be empty".to_string());
+            } else if host_function.name == HOST_PRINT_FUNCTION_NAME {
+                problems.push(format!(
+                    "host function name {} is reserved, use with_host_print_fn instead",
+                    HOST_PRINT_FUNCTION_NAME
+                ));
+            } else if !names.insert(host_function.name.as_str()) {
+                problems.push(format!(
+                    "host function {} is registered more than once",
+                    host_function.name
+                ));
+            }
+        }
+
+        problems
+    }
+
+    /// Validate all the options and create the `UninitializedSandbox`,
+    /// registering every host function with it.
+    ///
+    /// Returns `HyperlightError::InvalidSandboxConfiguration` listing every
+    /// problem found if validation fails.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn build(self) -> Result<UninitializedSandbox> {
+        let problems = self.validate();
+        if !problems.is_empty() {
+            let err = HyperlightError::InvalidSandboxConfiguration(problems);
+            log_then_return!(err);
+        }
+
+        let mut sandbox = UninitializedSandbox::new(
+            self.guest_binary,
+            Some(self.config),
+            self.run_options,
+            self.host_print_writer,
+        )?;
+
+        if let Some(log_level) = self.max_guest_log_level {
+            sandbox.set_max_guest_log_level(log_level);
+        }
+
+        for host_function in self.host_functions {
+            (host_function.register)(&mut sandbox)?;
+        }
+
+        Ok(sandbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use hyperlight_common::flatbuffer_wrappers::function_types::{
+        ParameterValue, ReturnType, ReturnValue,
+    };
+    use hyperlight_testing::simple_guest_as_string;
+
+    use super::SandboxBuilder;
+    use crate::sandbox_state::sandbox::EvolvableSandbox;
+    use crate::sandbox_state::transition::Noop;
+    use crate::{GuestBinary, HyperlightError, MultiUseSandbox, Result};
+
+    #[test]
+    fn build_with_host_functions() {
+        let add = Arc::new(Mutex::new(|a: i32, b: i32| -> Result<i32> { Ok(a + b) }));
+        let zero = Arc::new(Mutex::new(|| -> Result<i32> { Ok(0) }));
+
+        let u_sbox = SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+            .with_input_data_size(0x8000)
+            .with_guest_log_level(log::LevelFilter::Error)
+            .with_host_function("HostAdd", add)
+            .with_host_function("Zero", zero)
+            .build()
+            .unwrap();
+
+        let mut sbox: MultiUseSandbox = u_sbox.evolve(Noop::default()).unwrap();
+        let res = sbox
+            .call_guest_function_by_name(
+                "Add",
+                ReturnType::Int,
+                Some(vec![ParameterValue::Int(1), ParameterValue::Int(41)]),
+            )
+            .unwrap();
+        assert_eq!(res, ReturnValue::Int(42));
+    }
+
+    #[test]
+    fn build_reports_all_problems() {
+        let f = Arc::new(Mutex::new(|| -> Result<i32> { Ok(0) }));
+
+        let res = SandboxBuilder::new(GuestBinary::FilePath("does_not_exist".to_string()))
+            .with_host_function("Dup", f.clone())
+            .with_host_function("Dup", f.clone())
+            .with_host_function("HostPrint", f)
+            .build();
+
+        match res {
+            Err(HyperlightError::InvalidSandboxConfiguration(problems)) => {
+                assert_eq!(problems.len(), 3, "{:?}", problems);
+            }
+            other => panic!("expected InvalidSandboxConfiguration, got {:?}", other),
+        }
+    }
+}
@@ -17,12 +17,28 @@ limitations under the License.
 use std::cmp::{max, min};
 use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
 use tracing::{instrument, Span};
 
 use crate::mem::exe::ExeInfo;
 
+/// How the execution time of a guest function call is measured against its
+/// maximum execution time
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
+pub enum ExecutionTimeBudget {
+    /// All the time the call takes counts, including the time spent in the
+    /// host functions the guest calls
+    #[default]
+    WallClock,
+    /// Only the time the guest spends running counts, not the time spent in
+    /// the host functions it calls. Each host function call is instead
+    /// limited to the sandbox's max_host_function_time.
+    GuestOnly,
+}
+
 /// The complete set of configuration needed to create a Sandbox
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct SandboxConfiguration {
     /// The maximum size of the guest error buffer.
@@ -53,6 +69,10 @@ pub struct SandboxConfiguration {
     /// field should be represented as an `Option`, that type is not
     /// FFI-safe, so it cannot be.
     heap_size_override: u64,
+    /// The most bytes the guest heap may grow to, by asking the host for more
+    /// memory when it runs out. If this is not more than the heap size, the
+    /// heap can't grow.
+    max_heap_size: u64,
     /// The kernel_stack_size to use in the guest sandbox. If set to 0, the default kernel stack size will be used.
     /// The value will be increased to a multiple page size when memory is allocated if necessary.
     ///
@@ -85,6 +105,33 @@ pub struct SandboxConfiguration {
     /// The size of the memory buffer that is made available for serializing
     /// guest panic context
     guest_panic_context_buffer_size: usize,
+    /// How the execution time of a guest function call is measured against
+    /// max_execution_time
+    execution_time_budget: ExecutionTimeBudget,
+    /// The max_host_function_time of a single host function call in milliseconds, which
+    /// only applies if execution_time_budget is GuestOnly. If set to 0, the max_host_function_time
+    /// will be set to the default value of 1000ms, the minimum value is 1ms.
+    ///
+    /// Note: this is a C-compatible struct, so even though this optional
+    /// field should be represented as an `Option`, that type is not
+    /// FFI-safe, so it cannot be.
+    max_host_function_time: u16,
+    /// The maximum number of guest function calls that host functions may
+    /// make back into the guest while it is waiting for them to return, one
+    /// inside the other. If set to 0, host functions can't call into the guest.
+    max_nested_guest_call_depth: u8,
+    /// Whether the guest's memory statistics are collected at the end of
+    /// every guest function call, and published as metrics
+    collect_memory_stats: bool,
+    /// Whether syscalls that host functions make that their seccomp filters
+    /// don't allow are recorded and allowed, rather than stopping the host
+    /// function
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    seccomp_audit_mode: bool,
+    /// Whether a seccomp filter is applied to the Hypervisor Handler thread,
+    /// which runs the vCPU
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    hypervisor_handler_seccomp_filter: bool,
 }
 
 impl SandboxConfiguration {
@@ -122,6 +169,14 @@ impl SandboxConfiguration {
     pub const MIN_MAX_EXECUTION_TIME: u16 = 1;
     /// The maximum value for max execution time (in milliseconds)
     pub const MAX_MAX_EXECUTION_TIME: u16 = u16::MAX;
+    /// The default value for max host function time (in milliseconds)
+    pub const DEFAULT_MAX_HOST_FUNCTION_TIME: u16 = 1000;
+    /// The minimum value for max host function time (in milliseconds)
+    pub const MIN_MAX_HOST_FUNCTION_TIME: u16 = 1;
+    /// The maximum value for max host function time (in milliseconds)
+    pub const MAX_MAX_HOST_FUNCTION_TIME: u16 = u16::MAX;
+    /// The default value for max nested guest call depth
+    pub const DEFAULT_MAX_NESTED_GUEST_CALL_DEPTH: u8 = 8;
     /// The default and minimum values for max wait for cancellation (in milliseconds)
     pub const DEFAULT_MAX_WAIT_FOR_CANCELLATION: u8 = 100;
     /// The minimum value for max wait for cancellation (in milliseconds)
@@ -168,6 +223,7 @@ impl SandboxConfiguration {
             ),
             stack_size_override: stack_size_override.unwrap_or(0),
             heap_size_override: heap_size_override.unwrap_or(0),
+            max_heap_size: 0,
             kernel_stack_size: max(kernel_stack_size, Self::MIN_KERNEL_STACK_SIZE),
             max_execution_time: {
                 match max_execution_time {
@@ -220,6 +276,14 @@ impl SandboxConfiguration {
                 guest_panic_context_buffer_size,
                 Self::MIN_GUEST_PANIC_CONTEXT_BUFFER_SIZE,
             ),
+            execution_time_budget: ExecutionTimeBudget::default(),
+            max_host_function_time: Self::DEFAULT_MAX_HOST_FUNCTION_TIME,
+            max_nested_guest_call_depth: Self::DEFAULT_MAX_NESTED_GUEST_CALL_DEPTH,
+            collect_memory_stats: false,
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            seccomp_audit_mode: false,
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            hypervisor_handler_seccomp_filter: false,
         }
     }
 
@@ -274,6 +338,61 @@ impl SandboxConfiguration {
         self.heap_size_override = heap_size;
     }
 
+    /// Set the most bytes the guest heap may grow to. When this is more than
+    /// the heap size, the difference is reserved in the guest's address space
+    /// but not mapped into it, and the guest allocator asks the host to map
+    /// more of it when it runs out of memory, rather than failing. If set to
+    /// 0, the heap can't grow.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub fn set_max_heap_size(&mut self, max_heap_size: u64) {
+        self.max_heap_size = max_heap_size;
+    }
+
+    /// Set whether the guest's memory statistics are collected at the end of
+    /// every guest function call, so that `MultiUseSandbox::memory_stats`
+    /// includes the peaks the calls reach, and they are published as metrics
+    /// labelled with the sandbox's `MultiUseSandbox::id`.
+    ///
+    /// This is off by default, as it makes every guest function call slower:
+    /// the guest counts the free blocks in its heap, and the host reads the
+    /// part of the guest's stack that hasn't been used.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub fn set_collect_memory_stats(&mut self, collect_memory_stats: bool) {
+        self.collect_memory_stats = collect_memory_stats;
+    }
+
+    /// Set whether the seccomp filters of host functions are in audit mode. In
+    /// audit mode, a syscall a host function makes that its filter doesn't
+    /// allow is recorded, along with its arguments, and then allowed, rather
+    /// than stopping the host function. The recorded syscalls can be read
+    /// with `MultiUseSandbox::audited_syscalls` to find the extra syscalls
+    /// each host function needs.
+    ///
+    /// Audit mode doesn't sandbox host functions, so it should only be used
+    /// during development.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub fn set_seccomp_audit_mode(&mut self, seccomp_audit_mode: bool) {
+        self.seccomp_audit_mode = seccomp_audit_mode;
+    }
+
+    /// Set whether a seccomp filter is applied to the Hypervisor Handler
+    /// thread, which runs the vCPU and handles its exits, including parsing
+    /// the buffers the guest writes to. The filter only allows the hypervisor
+    /// ioctls and the few other syscalls that thread needs, so that a bug in
+    /// handling the guest can't be used to make arbitrary syscalls. A
+    /// disallowed syscall is reported like one made by a host function, and
+    /// fails the guest function call with `HyperlightError::DisallowedSyscall`,
+    /// after which the sandbox can't be used.
+    ///
+    /// Host functions run on threads that are spawned from a thread without
+    /// the filter, so it doesn't allow any of the syscalls they may make.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub fn set_hypervisor_handler_seccomp_filter(&mut self, enabled: bool) {
+        self.hypervisor_handler_seccomp_filter = enabled;
+    }
+
     /// Set the kernel stack size to use in the guest sandbox. If less than the minimum value of MIN_KERNEL_STACK_SIZE, the minimum value will be used.
     /// If its not a multiple of the page size, it will be increased to the a multiple of the page size when memory is allocated.
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
@@ -284,6 +403,10 @@ impl SandboxConfiguration {
     /// Set the maximum execution time of a guest function execution. If set to 0, the max_execution_time
     /// will be set to the default value of DEFAULT_MAX_EXECUTION_TIME if the guest execution does not complete within the time specified
     /// then the execution will be cancelled, the minimum value is MIN_MAX_EXECUTION_TIME
+    ///
+    /// This is only the default for the sandbox's guest function calls: individual calls can
+    /// be given a longer or shorter timeout, a deadline, or no timeout at all with
+    /// `call_guest_function_by_name_with_timeout`.
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
     pub fn set_max_execution_time(&mut self, max_execution_time: Duration) {
         match max_execution_time.as_millis() {
@@ -300,6 +423,43 @@ impl SandboxConfiguration {
         }
     }
 
+    /// Set how the execution time of a guest function call is measured against its maximum
+    /// execution time. With `ExecutionTimeBudget::GuestOnly`, a guest that spends a long time
+    /// waiting for slow host functions is not cancelled, but each host function call is
+    /// limited to max_host_function_time instead.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub fn set_execution_time_budget(&mut self, execution_time_budget: ExecutionTimeBudget) {
+        self.execution_time_budget = execution_time_budget;
+    }
+
+    /// Set the maximum time a single host function call may take when the execution time budget
+    /// is `ExecutionTimeBudget::GuestOnly`. If set to 0, the max_host_function_time will be set to
+    /// the default value of DEFAULT_MAX_HOST_FUNCTION_TIME, the minimum value is
+    /// MIN_MAX_HOST_FUNCTION_TIME
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub fn set_max_host_function_time(&mut self, max_host_function_time: Duration) {
+        match max_host_function_time.as_millis() {
+            0 => self.max_host_function_time = Self::DEFAULT_MAX_HOST_FUNCTION_TIME,
+            1.. => {
+                self.max_host_function_time = min(
+                    Self::MAX_MAX_HOST_FUNCTION_TIME.into(),
+                    max(
+                        max_host_function_time.as_millis(),
+                        Self::MIN_MAX_HOST_FUNCTION_TIME.into(),
+                    ),
+                ) as u16
+            }
+        }
+    }
+
+    /// Set how many guest function calls host functions may make back into the guest while it is
+    /// waiting for them to return, one inside the other. If set to 0, host functions can't call
+    /// into the guest at all.
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub fn set_max_nested_guest_call_depth(&mut self, max_nested_guest_call_depth: u8) {
+        self.max_nested_guest_call_depth = max_nested_guest_call_depth;
+    }
+
     /// Set the maximum time to wait for guest execution calculation. If set to 0, the maximum cancellation time
     /// will be set to the default value of DEFAULT_MAX_WAIT_FOR_CANCELLATION if the guest execution cancellation does not complete within the time specified
     /// then an error will be returned, the minimum value is MIN_MAX_WAIT_FOR_CANCELLATION
@@ -390,6 +550,43 @@ impl SandboxConfiguration {
         self.max_initialization_time
     }
 
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_execution_time_budget(&self) -> ExecutionTimeBudget {
+        self.execution_time_budget
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_max_host_function_time(&self) -> u16 {
+        self.max_host_function_time
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_max_nested_guest_call_depth(&self) -> u8 {
+        self.max_nested_guest_call_depth
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_max_heap_size(&self) -> u64 {
+        self.max_heap_size
+    }
+
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_collect_memory_stats(&self) -> bool {
+        self.collect_memory_stats
+    }
+
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_seccomp_audit_mode(&self) -> bool {
+        self.seccomp_audit_mode
+    }
+
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_hypervisor_handler_seccomp_filter(&self) -> bool {
+        self.hypervisor_handler_seccomp_filter
+    }
+
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
     fn stack_size_override_opt(&self) -> Option<u64> {
         (self.stack_size_override > 0).then_some(self.stack_size_override)
@@ -600,6 +797,7 @@ mod tests {
         cfg.set_guest_panic_context_buffer_size(
             SandboxConfiguration::MIN_GUEST_PANIC_CONTEXT_BUFFER_SIZE - 1,
         );
+        cfg.set_max_host_function_time(Duration::from_micros(1));
 
         assert_eq!(SandboxConfiguration::MIN_INPUT_SIZE, cfg.input_data_size);
         assert_eq!(SandboxConfiguration::MIN_OUTPUT_SIZE, cfg.output_data_size);
@@ -627,6 +825,10 @@ mod tests {
             SandboxConfiguration::MIN_GUEST_PANIC_CONTEXT_BUFFER_SIZE,
             cfg.guest_panic_context_buffer_size
         );
+        assert_eq!(
+            SandboxConfiguration::DEFAULT_MAX_HOST_FUNCTION_TIME,
+            cfg.max_host_function_time
+        );
     }
 
     mod proptests {
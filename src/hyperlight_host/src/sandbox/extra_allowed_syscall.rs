@@ -0,0 +1,85 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+/// Re-export of the `seccompiler` types needed to build the rules of an
+/// `ExtraAllowedSyscall`
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+pub use seccompiler::{SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompRule};
+
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use crate::{log_then_return, Result};
+
+/// A syscall that a host function is allowed to make, in addition to those
+/// that all host functions are allowed to make.
+///
+/// A syscall number converts into an `ExtraAllowedSyscall` that allows the
+/// syscall with any arguments. On Linux with the `seccomp` feature, the
+/// syscall can instead be allowed only with certain arguments, such as
+/// `openat` only with `O_RDONLY`, with `ExtraAllowedSyscall::with_rules`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtraAllowedSyscall {
+    syscall: i64,
+    /// The syscall is allowed if its arguments match any of these, or with
+    /// any arguments if there are none
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    rules: Vec<SeccompRule>,
+}
+
+impl ExtraAllowedSyscall {
+    /// Allow `syscall` with any arguments
+    pub fn new(syscall: i64) -> Self {
+        Self {
+            syscall,
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            rules: vec![],
+        }
+    }
+
+    /// Allow `syscall` only if its arguments match any of `rules`, each of
+    /// which matches if all of its `SeccompCondition`s do. The `and!` and
+    /// `or!` macros are shorthands for building these.
+    ///
+    /// Returns an error if `rules` is empty, as it wouldn't allow the syscall
+    /// with any arguments.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub fn with_rules(syscall: i64, rules: Vec<SeccompRule>) -> Result<Self> {
+        if rules.is_empty() {
+            log_then_return!(
+                "No rules given for syscall {}, use ExtraAllowedSyscall::new to allow it with any arguments",
+                syscall
+            );
+        }
+        Ok(Self { syscall, rules })
+    }
+
+    /// The number of the syscall that is allowed
+    pub fn syscall(&self) -> i64 {
+        self.syscall
+    }
+
+    /// The syscall number and the rules for its arguments, in the form a
+    /// `seccompiler::SeccompFilter` is built from
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub(crate) fn into_seccomp_rules(self) -> (i64, Vec<SeccompRule>) {
+        (self.syscall, self.rules)
+    }
+}
+
+impl From<i64> for ExtraAllowedSyscall {
+    fn from(syscall: i64) -> Self {
+        Self::new(syscall)
+    }
+}
@@ -0,0 +1,68 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::path::{Path, PathBuf};
+
+/// The files a host function may access, enforced with a Landlock ruleset on
+/// the thread the host function runs on.
+///
+/// A host function that is registered with a `FilesystemAccess` can access
+/// nothing on the filesystem except the files and directories added to it,
+/// and what is beneath those directories. Opening those files still needs
+/// the syscalls to be allowed by the host function's seccomp filter.
+///
+/// ```
+/// use hyperlight_host::sandbox::FilesystemAccess;
+///
+/// let access = FilesystemAccess::new()
+///     .allow_read("/etc/my-service")
+///     .allow_read_write("/var/lib/my-service");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilesystemAccess {
+    read_only: Vec<PathBuf>,
+    read_write: Vec<PathBuf>,
+}
+
+impl FilesystemAccess {
+    /// Allow access to nothing on the filesystem
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow reading `path`, and everything beneath it if it is a directory
+    pub fn allow_read(mut self, path: impl Into<PathBuf>) -> Self {
+        self.read_only.push(path.into());
+        self
+    }
+
+    /// Allow reading, writing, creating and removing `path`, and everything
+    /// beneath it if it is a directory
+    pub fn allow_read_write(mut self, path: impl Into<PathBuf>) -> Self {
+        self.read_write.push(path.into());
+        self
+    }
+
+    /// The paths that may only be read
+    pub fn read_only_paths(&self) -> impl Iterator<Item = &Path> {
+        self.read_only.iter().map(PathBuf::as_path)
+    }
+
+    /// The paths that may be read and written
+    pub fn read_write_paths(&self) -> impl Iterator<Item = &Path> {
+        self.read_write.iter().map(PathBuf::as_path)
+    }
+}
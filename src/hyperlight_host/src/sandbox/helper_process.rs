@@ -0,0 +1,559 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use hyperlight_common::flatbuffer_wrappers::function_call::{FunctionCall, FunctionCallType};
+use hyperlight_common::flatbuffer_wrappers::function_types::{
+    ParameterType, ParameterValue, ReturnType, ReturnValue,
+};
+use hyperlight_common::flatbuffer_wrappers::host_function_definition::HostFunctionDefinition;
+use lazy_static::lazy_static;
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use seccompiler::{
+    SeccompCmpArgLen as ArgLen, SeccompCmpOp::Eq, SeccompCondition as Cond, SeccompRule,
+};
+use tracing::{instrument, Span};
+
+use super::host_funcs::panic_message;
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use super::ExtraAllowedSyscall;
+use crate::func::HyperlightFunction;
+use crate::HyperlightError::{
+    HostFunctionNotRegisteredForHelperProcesses, HostFunctionPanicked, HostFunctionProcessExited,
+    UnexpectedNoOfArguments, UnexpectedReturnValueType,
+};
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use crate::{and, or};
+use crate::{log_then_return, new_error, Result};
+
+/// The largest message either process accepts from the other, so that a
+/// crashed or compromised helper can't make the host allocate without bound
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// A `FunctionCall` sent to the helper process
+const MESSAGE_CALL: u8 = 0;
+/// The `ReturnValue` of a call the helper process made
+const MESSAGE_RETURN: u8 = 1;
+/// The message of an error a call the helper process made returned
+const MESSAGE_ERROR: u8 = 2;
+/// The message of a panic in a call the helper process made
+const MESSAGE_PANIC: u8 = 3;
+
+/// The seccomp filter a new helper process applies before serving calls
+const MESSAGE_START: u8 = 4;
+
+/// The fd the helper process's end of the socket is passed to it as
+const HELPER_SOCKET_FD: RawFd = 3;
+
+/// Set in the environment of a helper process to the name of the host
+/// function it serves calls to
+const HELPER_PROCESS_ENV: &str = "HYPERLIGHT_HOST_FUNCTION_HELPER_PROCESS";
+
+lazy_static! {
+    /// The host functions that can run in helper processes, by name
+    static ref HELPER_PROCESS_FUNCTIONS: Mutex<HashMap<String, HyperlightFunction>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Add `func` to the table of host functions that can run in helper
+/// processes, as `name`, replacing any function already added as `name`.
+pub(crate) fn add_helper_process_function(name: &str, func: HyperlightFunction) -> Result<()> {
+    HELPER_PROCESS_FUNCTIONS
+        .lock()
+        .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+        .insert(name.to_string(), func);
+    Ok(())
+}
+
+/// If this process is a helper process a sandbox started to run one of its
+/// host functions in, serve the calls the sandbox makes to the function and
+/// exit once the sandbox is dropped. Otherwise, return.
+///
+/// A helper process runs the host program again, with the same arguments,
+/// so a program that registers host functions to run in helper processes
+/// must call this at the start of `main`, once it has added them to the
+/// table of those that can with `register_for_helper_processes`, and before
+/// it starts any threads.
+pub fn run_helper_process_if_requested() {
+    let Some(name) = std::env::var_os(HELPER_PROCESS_ENV) else {
+        return;
+    };
+    let name = name.to_string_lossy();
+    let func = HELPER_PROCESS_FUNCTIONS
+        .lock()
+        .ok()
+        .and_then(|funcs| funcs.get(name.as_ref()).cloned());
+    let Some(func) = func else {
+        log::error!(
+            "Host function {} wasn't registered to run in helper processes before run_helper_process_if_requested was called",
+            name
+        );
+        std::process::exit(1);
+    };
+    let socket = HELPER_SOCKET_FD;
+    let Ok((MESSAGE_START, _filter)) = read_message(socket) else {
+        std::process::exit(1);
+    };
+
+    // Panics are sent back to the host, so they aren't printed here too
+    std::panic::set_hook(Box::new(|_| {}));
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    {
+        let filter: seccompiler::BpfProgram = _filter
+            .chunks_exact(8)
+            .map(|insn| seccompiler::sock_filter {
+                code: u16::from_le_bytes([insn[0], insn[1]]),
+                jt: insn[2],
+                jf: insn[3],
+                k: u32::from_le_bytes([insn[4], insn[5], insn[6], insn[7]]),
+            })
+            .collect();
+        // A disallowed syscall kills the helper process, rather than being
+        // handled by any SIGSYS handler the host program set up
+        unsafe { libc::signal(libc::SIGSYS, libc::SIG_DFL) };
+        if seccompiler::apply_filter(&filter).is_err() {
+            std::process::exit(1);
+        }
+    }
+
+    serve(socket, func);
+    // Safety: only exits, without running anything else under the filter
+    unsafe { libc::_exit(0) }
+}
+
+/// A host function that runs in a helper process, rather than on a thread of
+/// the host process, so that a memory-safety bug in the function can't
+/// compromise the host process.
+///
+/// The helper process is a new instance of the host program, which serves
+/// the calls it is sent over a socket from `run_helper_process_if_requested`
+/// one at a time, until its end of the socket is closed. If it crashes or
+/// exits, every later call fails with `HostFunctionProcessExited`.
+pub(crate) struct HelperProcess {
+    definition: HostFunctionDefinition,
+    socket: OwnedFd,
+    pid: libc::pid_t,
+    /// Why the helper process exited, once it has been reaped
+    exit_status: Option<String>,
+}
+
+impl HelperProcess {
+    /// Start a helper process that runs the host function `definition`
+    /// describes, from the table of those that can run in helper processes,
+    /// for each call it is sent.
+    ///
+    /// With the `seccomp` feature, the helper process is confined by the
+    /// filter the function would be confined by on a host function worker
+    /// thread, which also allows `extra_allowed_syscalls`, and is killed if
+    /// it makes a syscall the filter doesn't allow.
+    ///
+    /// The host program is executed again for the helper process, rather
+    /// than only forked, so that the helper doesn't start as a copy of a
+    /// process whose other threads may have held locks it needs.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    pub(crate) fn spawn(
+        definition: HostFunctionDefinition,
+        #[cfg(all(feature = "seccomp", target_os = "linux"))] extra_allowed_syscalls: Option<
+            Vec<ExtraAllowedSyscall>,
+        >,
+    ) -> Result<Self> {
+        // The helper process would fail to find the function in its table
+        // if it isn't in this one, as they are filled in by the same code
+        if !HELPER_PROCESS_FUNCTIONS
+            .lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+            .contains_key(&definition.function_name)
+        {
+            log_then_return!(HostFunctionNotRegisteredForHelperProcesses(
+                definition.function_name.clone()
+            ));
+        }
+
+        let mut fds = [0; 2];
+        if unsafe {
+            libc::socketpair(
+                libc::AF_UNIX,
+                libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+                0,
+                fds.as_mut_ptr(),
+            )
+        } != 0
+        {
+            log_then_return!(
+                "Error creating socket for host function {} helper process: {}",
+                definition.function_name,
+                io::Error::last_os_error()
+            );
+        }
+        // Safety: the fds were just created and nothing else owns them
+        let (socket, helper_socket) =
+            unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+        let helper_fd = helper_socket.as_raw_fd();
+        let mut args = std::env::args_os();
+        let mut command = Command::new("/proc/self/exe");
+        if let Some(arg0) = args.next() {
+            command.arg0(arg0);
+        }
+        command
+            .args(args)
+            .env(HELPER_PROCESS_ENV, &definition.function_name)
+            .stdin(Stdio::null());
+        // Safety: this only makes syscalls, which is all that is safe in a
+        // process forked from one that may have other threads
+        unsafe {
+            command.pre_exec(move || {
+                if helper_fd == HELPER_SOCKET_FD {
+                    // dup2 would leave it close-on-exec
+                    if libc::fcntl(helper_fd, libc::F_SETFD, 0) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                } else if libc::dup2(helper_fd, HELPER_SOCKET_FD) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // Don't pass on any other fds the host process didn't make
+                // close-on-exec, such as those of its sandboxes' VMs. They
+                // are only closed on exec, as `Command` reports exec errors
+                // over one of them.
+                libc::syscall(
+                    libc::SYS_close_range,
+                    HELPER_SOCKET_FD + 1,
+                    u32::MAX,
+                    libc::CLOSE_RANGE_CLOEXEC,
+                );
+                Ok(())
+            })
+        };
+        let pid = match command.spawn() {
+            Ok(child) => child.id() as libc::pid_t,
+            Err(e) => {
+                log_then_return!(
+                    "Error starting helper process for host function {}: {}",
+                    definition.function_name,
+                    e
+                );
+            }
+        };
+        drop(helper_socket);
+        let helper = Self {
+            definition,
+            socket,
+            pid,
+            exit_status: None,
+        };
+
+        #[allow(unused_mut)]
+        let mut start = Vec::new();
+        #[cfg(all(feature = "seccomp", target_os = "linux"))]
+        {
+            let mut syscalls = extra_allowed_syscalls.unwrap_or_default();
+            syscalls.extend(helper_syscalls()?);
+            let filter = crate::seccomp::guest::get_seccomp_filter_for_host_function_worker_thread(
+                Some(syscalls),
+                false,
+            )?;
+            for insn in filter {
+                start.extend_from_slice(&insn.code.to_le_bytes());
+                start.extend_from_slice(&[insn.jt, insn.jf]);
+                start.extend_from_slice(&insn.k.to_le_bytes());
+            }
+        }
+        if let Err(e) = write_message(helper.socket.as_raw_fd(), MESSAGE_START, &start) {
+            log_then_return!(
+                "Error starting helper process for host function {}: {}",
+                helper.definition.function_name,
+                e
+            );
+        }
+        Ok(helper)
+    }
+
+    /// The syscalls a host function worker thread makes to call the host
+    /// function in this helper process.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub(crate) fn syscalls_to_call(&self) -> Result<Vec<ExtraAllowedSyscall>> {
+        let fd = self.socket.as_raw_fd() as u64;
+        let pid = self.pid as u64;
+        let on_socket =
+            || -> Result<Vec<SeccompRule>> { Ok(or![and![Cond::new(0, ArgLen::Dword, Eq, fd)?]]) };
+        let on_pid =
+            || -> Result<Vec<SeccompRule>> { Ok(or![and![Cond::new(0, ArgLen::Dword, Eq, pid)?]]) };
+        Ok(vec![
+            ExtraAllowedSyscall::with_rules(libc::SYS_read, on_socket()?)?,
+            ExtraAllowedSyscall::with_rules(libc::SYS_sendto, on_socket()?)?,
+            // Stopping and reaping the helper process once a call fails
+            ExtraAllowedSyscall::with_rules(libc::SYS_kill, on_pid()?)?,
+            ExtraAllowedSyscall::with_rules(libc::SYS_wait4, on_pid()?)?,
+        ])
+    }
+
+    /// Call the host function in the helper process with `args`, checking
+    /// them against its definition, and checking the value it returns the
+    /// same way.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    pub(crate) fn call(&mut self, args: Vec<ParameterValue>) -> Result<ReturnValue> {
+        let name = self.definition.function_name.clone();
+        if let Some(status) = &self.exit_status {
+            return Err(HostFunctionProcessExited(name, status.clone()));
+        }
+
+        let parameter_types = self.definition.parameter_types.as_deref().unwrap_or(&[]);
+        if args.len() != parameter_types.len() {
+            log_then_return!(UnexpectedNoOfArguments(args.len(), parameter_types.len()));
+        }
+        let arg_types: Vec<ParameterType> = args.iter().map(ParameterType::from).collect();
+        self.definition.verify_equal_parameter_types(&arg_types)?;
+
+        let call: Vec<u8> = FunctionCall::new(
+            name.clone(),
+            Some(args),
+            FunctionCallType::Host,
+            self.definition.return_type,
+        )
+        .try_into()?;
+        let (kind, payload) = match write_message(self.socket.as_raw_fd(), MESSAGE_CALL, &call)
+            .and_then(|_| read_message(self.socket.as_raw_fd()))
+        {
+            Ok(response) => response,
+            Err(e) => {
+                // If the helper process is still running, it sent something
+                // that wasn't a message, so it can't be trusted any more
+                unsafe { libc::kill(self.pid, libc::SIGKILL) };
+                let status = self.reap().unwrap_or_else(|| e.to_string());
+                log::error!(
+                    "Helper process for host function {} exited: {}",
+                    name,
+                    status
+                );
+                self.exit_status = Some(status.clone());
+                return Err(HostFunctionProcessExited(name, status));
+            }
+        };
+
+        match kind {
+            MESSAGE_RETURN => {
+                let value = ReturnValue::try_from(payload.as_slice())?;
+                if return_type(&value) != self.definition.return_type {
+                    let err = UnexpectedReturnValueType(
+                        value,
+                        format!("{:?}", self.definition.return_type),
+                    );
+                    log::error!("{}", err);
+                    return Err(err);
+                }
+                Ok(value)
+            }
+            MESSAGE_ERROR => Err(new_error!("{}", String::from_utf8_lossy(&payload))),
+            MESSAGE_PANIC => Err(HostFunctionPanicked(
+                name,
+                String::from_utf8_lossy(&payload).into_owned(),
+            )),
+            other => Err(new_error!(
+                "Unexpected message {} from helper process for host function {}",
+                other,
+                name
+            )),
+        }
+    }
+
+    /// Wait for the helper process to exit, and describe why it did.
+    fn reap(&mut self) -> Option<String> {
+        let mut status = 0;
+        if unsafe { libc::waitpid(self.pid, &mut status, 0) } != self.pid {
+            return None;
+        }
+        // Reaped, so the pid mustn't be waited for or killed again
+        self.pid = 0;
+        Some(if libc::WIFSIGNALED(status) {
+            format!("killed by signal {}", libc::WTERMSIG(status))
+        } else {
+            format!("exited with status {}", libc::WEXITSTATUS(status))
+        })
+    }
+
+    /// Turn this into a `HyperlightFunction` that calls the host function in
+    /// the helper process.
+    pub(crate) fn into_host_function(mut self) -> HyperlightFunction {
+        HyperlightFunction::new(move |args| self.call(args))
+    }
+}
+
+impl Drop for HelperProcess {
+    fn drop(&mut self) {
+        if self.pid > 0 {
+            // The helper process may be in the middle of a call that was
+            // abandoned, so it is killed rather than asked to exit
+            unsafe { libc::kill(self.pid, libc::SIGKILL) };
+            self.reap();
+        }
+    }
+}
+
+/// The syscalls the helper process makes to serve calls, in addition to
+/// those allowed on every host function worker thread
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+fn helper_syscalls() -> Result<Vec<ExtraAllowedSyscall>> {
+    let on_socket = || -> Result<Vec<SeccompRule>> {
+        Ok(or![and![Cond::new(
+            0,
+            ArgLen::Dword,
+            Eq,
+            HELPER_SOCKET_FD as u64
+        )?]])
+    };
+    Ok(vec![
+        ExtraAllowedSyscall::with_rules(libc::SYS_read, on_socket()?)?,
+        ExtraAllowedSyscall::with_rules(libc::SYS_sendto, on_socket()?)?,
+        // Allocating the arguments and results of calls, which the host
+        // function worker threads do from memory their parent allocated
+        libc::SYS_brk.into(),
+        libc::SYS_mmap.into(),
+        libc::SYS_mremap.into(),
+        libc::SYS_exit_group.into(),
+    ])
+}
+
+/// Serve the calls sent over `socket` until it is closed, in the helper
+/// process.
+fn serve(socket: RawFd, func: HyperlightFunction) {
+    while let Ok((MESSAGE_CALL, payload)) = read_message(socket) {
+        let result = FunctionCall::try_from(payload.as_slice())
+            .map_err(crate::HyperlightError::from)
+            .and_then(|call| {
+                // A panic is sent back for the host to report to the guest,
+                // as it would be on a host function worker thread
+                catch_unwind(AssertUnwindSafe(|| {
+                    func.call(call.parameters.unwrap_or_default())
+                }))
+                .unwrap_or_else(|payload| {
+                    Err(HostFunctionPanicked(
+                        call.function_name,
+                        panic_message(payload.as_ref()),
+                    ))
+                })
+            });
+        let sent = match result.and_then(|value| Ok(Vec::<u8>::try_from(&value)?)) {
+            Ok(value) => write_message(socket, MESSAGE_RETURN, &value),
+            Err(HostFunctionPanicked(_, message)) => {
+                write_message(socket, MESSAGE_PANIC, message.as_bytes())
+            }
+            Err(e) => write_message(socket, MESSAGE_ERROR, e.to_string().as_bytes()),
+        };
+        if sent.is_err() {
+            return;
+        }
+    }
+}
+
+/// The `ReturnType` of `value`
+fn return_type(value: &ReturnValue) -> ReturnType {
+    match value {
+        ReturnValue::Int(_) => ReturnType::Int,
+        ReturnValue::UInt(_) => ReturnType::UInt,
+        ReturnValue::Long(_) => ReturnType::Long,
+        ReturnValue::ULong(_) => ReturnType::ULong,
+        ReturnValue::Float(_) => ReturnType::Float,
+        ReturnValue::Double(_) => ReturnType::Double,
+        ReturnValue::String(_) => ReturnType::String,
+        ReturnValue::Bool(_) => ReturnType::Bool,
+        ReturnValue::Void => ReturnType::Void,
+        ReturnValue::VecBytes(_) => ReturnType::VecBytes,
+        ReturnValue::List(_) => ReturnType::List,
+        ReturnValue::Optional(_) => ReturnType::Optional,
+        ReturnValue::Map(_) => ReturnType::Map,
+        ReturnValue::Record(_) => ReturnType::Record,
+    }
+}
+
+/// Write a message of `kind` to `fd`, as the kind, the length of the payload
+/// as a little-endian `u32`, then the payload.
+///
+/// `sendto` is used rather than `write`, so that writing to a socket the
+/// other process closed fails rather than raising `SIGPIPE`.
+fn write_message(fd: RawFd, kind: u8, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("message of {} bytes is too large", payload.len()),
+        ));
+    }
+    let mut header = [0; 5];
+    header[0] = kind;
+    header[1..].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    for mut buf in [&header[..], payload] {
+        while !buf.is_empty() {
+            let sent = unsafe {
+                libc::sendto(
+                    fd,
+                    buf.as_ptr().cast(),
+                    buf.len(),
+                    libc::MSG_NOSIGNAL,
+                    std::ptr::null(),
+                    0,
+                )
+            };
+            if sent < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e);
+            }
+            buf = &buf[sent as usize..];
+        }
+    }
+    Ok(())
+}
+
+/// Read a message written by `write_message` from `fd`
+fn read_message(fd: RawFd) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 5];
+    read_exact(fd, &mut header)?;
+    let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes is too large", len),
+        ));
+    }
+    let mut payload = vec![0; len];
+    read_exact(fd, &mut payload)?;
+    Ok((header[0], payload))
+}
+
+fn read_exact(fd: RawFd, mut buf: &mut [u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        let read = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+        match read {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n if n < 0 => {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+            n => buf = &mut buf[n as usize..],
+        }
+    }
+    Ok(())
+}
@@ -14,7 +14,10 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::any::Any;
 use std::io::{IsTerminal, Write};
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use std::sync::{Arc, Mutex};
 
 use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterValue, ReturnValue};
 use hyperlight_common::flatbuffer_wrappers::host_function_definition::HostFunctionDefinition;
@@ -22,11 +25,17 @@ use hyperlight_common::flatbuffer_wrappers::host_function_details::HostFunctionD
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use tracing::{instrument, Span};
 
-use super::{ExtraAllowedSyscall, FunctionsMap};
+#[cfg(target_os = "linux")]
+use super::helper_process::HelperProcess;
+use super::{ExtraAllowedSyscall, FilesystemAccess, FunctionsMap};
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use crate::func::nested_call::NestedCallRequest;
 use crate::func::HyperlightFunction;
 use crate::mem::mgr::SandboxMemoryManager;
 use crate::mem::shared_mem::ExclusiveSharedMemory;
-use crate::HyperlightError::HostFunctionNotFound;
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use crate::seccomp::audit::{SyscallAuditBuffer, SyscallAuditGuard, SyscallAuditLog};
+use crate::HyperlightError::{HostFunctionNotFound, HostFunctionPanicked};
 use crate::{new_error, Result};
 
 #[derive(Default, Clone)]
@@ -34,6 +43,10 @@ use crate::{new_error, Result};
 pub struct HostFuncsWrapper {
     functions_map: FunctionsMap,
     function_details: HostFunctionDetails,
+    /// The disallowed syscalls the host functions have made, if their
+    /// seccomp filters are in audit mode
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    syscall_audit_log: Option<Arc<Mutex<SyscallAuditLog>>>,
 }
 
 impl HostFuncsWrapper {
@@ -54,6 +67,23 @@ impl HostFuncsWrapper {
         &mut self.function_details
     }
 
+    /// Put the seccomp filters of the host functions in audit mode, recording
+    /// and allowing the syscalls they make that the filters don't allow.
+    #[instrument(skip_all, parent = Span::current(), level = "Trace")]
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub(crate) fn enable_seccomp_audit_mode(&mut self) {
+        self.syscall_audit_log
+            .get_or_insert_with(|| Arc::new(Mutex::new(SyscallAuditLog::default())));
+    }
+
+    /// The disallowed syscalls the host functions have made, if their seccomp
+    /// filters are in audit mode
+    #[instrument(skip_all, parent = Span::current(), level = "Trace")]
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub(crate) fn get_syscall_audit_log(&self) -> Option<&Arc<Mutex<SyscallAuditLog>>> {
+        self.syscall_audit_log.as_ref()
+    }
+
     /// Register a host function with the sandbox.
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     pub(crate) fn register_host_function(
@@ -62,11 +92,12 @@ impl HostFuncsWrapper {
         hfd: &HostFunctionDefinition,
         func: HyperlightFunction,
     ) -> Result<()> {
-        register_host_function_helper(self, mgr, hfd, func, None)
+        register_host_function_helper(self, mgr, hfd, func, None, None)
     }
 
     /// Register a host function with the sandbox, with a list of extra syscalls
-    /// that the function is allowed to make.
+    /// that the function is allowed to make, and optionally the only files it
+    /// may access.
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     #[cfg(all(feature = "seccomp", target_os = "linux"))]
     pub(crate) fn register_host_function_with_syscalls(
@@ -75,8 +106,41 @@ impl HostFuncsWrapper {
         hfd: &HostFunctionDefinition,
         func: HyperlightFunction,
         extra_allowed_syscalls: Vec<ExtraAllowedSyscall>,
+        filesystem_access: Option<FilesystemAccess>,
     ) -> Result<()> {
-        register_host_function_helper(self, mgr, hfd, func, Some(extra_allowed_syscalls))
+        register_host_function_helper(
+            self,
+            mgr,
+            hfd,
+            func,
+            Some(extra_allowed_syscalls),
+            filesystem_access,
+        )
+    }
+
+    /// Register a host function with the sandbox that runs in a helper
+    /// process, which is confined by a seccomp filter that also allows
+    /// `extra_allowed_syscalls` when the `seccomp` feature is enabled.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    #[cfg(target_os = "linux")]
+    pub(crate) fn register_host_function_in_helper_process(
+        &mut self,
+        mgr: &mut SandboxMemoryManager<ExclusiveSharedMemory>,
+        hfd: &HostFunctionDefinition,
+        #[cfg(feature = "seccomp")] extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+    ) -> Result<()> {
+        let helper = HelperProcess::spawn(
+            hfd.clone(),
+            #[cfg(feature = "seccomp")]
+            extra_allowed_syscalls,
+        )?;
+        // The worker thread the function is called on only talks to the
+        // helper process, so it is only allowed the syscalls that does
+        #[cfg(feature = "seccomp")]
+        let syscalls = Some(helper.syscalls_to_call()?);
+        #[cfg(not(feature = "seccomp"))]
+        let syscalls = None;
+        register_host_function_helper(self, mgr, hfd, helper.into_host_function(), syscalls, None)
     }
 
     /// Assuming a host function called `"HostPrint"` exists, and takes a
@@ -86,11 +150,7 @@ impl HostFuncsWrapper {
     /// and `Err` otherwise.
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     pub(super) fn host_print(&mut self, msg: String) -> Result<i32> {
-        let res = call_host_func_impl(
-            self.get_host_funcs(),
-            "HostPrint",
-            vec![ParameterValue::String(msg)],
-        )?;
+        let res = call_host_func_impl(self, "HostPrint", vec![ParameterValue::String(msg)])?;
         res.try_into()
             .map_err(|_| HostFunctionNotFound("HostPrint".to_string()))
     }
@@ -107,7 +167,7 @@ impl HostFuncsWrapper {
         name: &str,
         args: Vec<ParameterValue>,
     ) -> Result<ReturnValue> {
-        call_host_func_impl(self.get_host_funcs(), name, args)
+        call_host_func_impl(self, name, args)
     }
 }
 
@@ -117,21 +177,29 @@ fn register_host_function_helper(
     hfd: &HostFunctionDefinition,
     func: HyperlightFunction,
     extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+    filesystem_access: Option<FilesystemAccess>,
 ) -> Result<()> {
     if let Some(_syscalls) = extra_allowed_syscalls {
         #[cfg(all(feature = "seccomp", target_os = "linux"))]
-        self_
-            .get_host_funcs_mut()
-            .insert(hfd.function_name.to_string(), func, Some(_syscalls));
+        self_.get_host_funcs_mut().insert(
+            hfd.function_name.to_string(),
+            func,
+            Some(_syscalls),
+            filesystem_access,
+        );
 
         #[cfg(not(all(feature = "seccomp", target_os = "linux")))]
         return Err(new_error!(
             "Extra syscalls are only supported on Linux with seccomp"
         ));
+    } else if filesystem_access.is_some() {
+        return Err(new_error!(
+            "Restricting the files a host function may access is only supported on Linux with seccomp"
+        ));
     } else {
         self_
             .get_host_funcs_mut()
-            .insert(hfd.function_name.to_string(), func, None);
+            .insert(hfd.function_name.to_string(), func, None, None);
     }
     self_
         .get_host_func_details_mut()
@@ -153,88 +221,269 @@ fn register_host_function_helper(
     Ok(())
 }
 
-#[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
-fn call_host_func_impl(
+/// Call the host function `name` with `args` on this thread, first applying
+/// the function's Landlock ruleset and seccomp filter to it.
+fn call_func(
     host_funcs: &FunctionsMap,
     name: &str,
     args: Vec<ParameterValue>,
+    #[cfg(all(feature = "seccomp", target_os = "linux"))] audit_mode: bool,
 ) -> Result<ReturnValue> {
-    // Inner function containing the common logic
-    fn call_func(
-        host_funcs: &FunctionsMap,
-        name: &str,
-        args: Vec<ParameterValue>,
-    ) -> Result<ReturnValue> {
-        let func_with_syscalls = host_funcs
-            .get(name)
-            .ok_or_else(|| HostFunctionNotFound(name.to_string()))?;
+    let func_with_syscalls = host_funcs
+        .get(name)
+        .ok_or_else(|| HostFunctionNotFound(name.to_string()))?;
 
-        let func = func_with_syscalls.0.clone();
+    let func = func_with_syscalls.0.clone();
 
-        #[cfg(all(feature = "seccomp", target_os = "linux"))]
-        {
-            let syscalls = func_with_syscalls.1.clone();
-            let seccomp_filter =
-                crate::seccomp::guest::get_seccomp_filter_for_host_function_worker_thread(
-                    syscalls,
-                )?;
-            seccompiler::apply_filter(&seccomp_filter)?;
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    {
+        // The Landlock ruleset is applied first, as the seccomp filter
+        // doesn't allow the syscalls that apply it
+        if let Some(filesystem_access) = &func_with_syscalls.2 {
+            crate::seccomp::landlock::restrict_filesystem_access(filesystem_access)?;
         }
 
-        #[cfg(feature = "function_call_metrics")]
-        {
-            let start = std::time::Instant::now();
-            let result = func.call(args.clone());
-            crate::histogram_vec_observe!(
-                &crate::sandbox::metrics::SandboxMetric::HostFunctionCallsDurationMicroseconds,
-                &[name],
-                start.elapsed().as_micros() as f64
-            );
-            result
-        }
+        let syscalls = func_with_syscalls.1.clone();
+        let seccomp_filter =
+            crate::seccomp::guest::get_seccomp_filter_for_host_function_worker_thread(
+                syscalls, audit_mode,
+            )?;
+        crate::signal_handlers::report_panics_on_this_thread();
+        seccompiler::apply_filter(&seccomp_filter)?;
+    }
 
-        #[cfg(not(feature = "function_call_metrics"))]
-        func.call(args)
+    #[cfg(feature = "function_call_metrics")]
+    {
+        let start = std::time::Instant::now();
+        let result = call_catching_panics(&func, name, args.clone());
+        crate::histogram_vec_observe!(
+            &crate::sandbox::metrics::SandboxMetric::HostFunctionCallsDurationMicroseconds,
+            &[name],
+            start.elapsed().as_micros() as f64
+        );
+        result
     }
 
+    #[cfg(not(feature = "function_call_metrics"))]
+    call_catching_panics(&func, name, args)
+}
+
+/// Call `func`, turning a panic in it into a `HostFunctionPanicked`
+/// error, so that the guest that called it can be told.
+fn call_catching_panics(
+    func: &HyperlightFunction,
+    name: &str,
+    args: Vec<ParameterValue>,
+) -> Result<ReturnValue> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| func.call(args))).unwrap_or_else(
+        |payload| {
+            // A disallowed syscall is handled by panicking, which the host
+            // function worker thread turns back into an error
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            if let Some(crate::HyperlightError::DisallowedSyscall) =
+                payload.downcast_ref::<crate::HyperlightError>()
+            {
+                std::panic::resume_unwind(payload);
+            }
+            Err(HostFunctionPanicked(
+                name.to_string(),
+                panic_message(payload.as_ref()),
+            ))
+        },
+    )
+}
+
+#[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+fn call_host_func_impl(
+    host_funcs: &HostFuncsWrapper,
+    name: &str,
+    args: Vec<ParameterValue>,
+) -> Result<ReturnValue> {
     cfg_if::cfg_if! {
         if #[cfg(all(feature = "seccomp", target_os = "linux"))] {
-            // Clone variables for the thread
-            let host_funcs_cloned = host_funcs.clone();
-            let name_cloned = name.to_string();
-            let args_cloned = args.clone();
+            // Guest function calls the host function makes are sent back to this thread, which
+            // is running the vCPU
+            let (nested_call_tx, nested_call_rx) = crossbeam_channel::unbounded();
+            let (result_tx, result_rx) = crossbeam_channel::bounded(1);
+            let request = HostFunctionWorkerRequest {
+                name: name.to_string(),
+                args,
+                nested_call_tx,
+                result_tx,
+            };
 
             // Create a new thread when seccomp is enabled on Linux
-            let join_handle = std::thread::Builder::new()
-                .name(format!("Host Function Worker Thread for: {:?}", name_cloned))
-                .spawn(move || {
-                    // We have a `catch_unwind` here because, if a disallowed syscall is issued,
-                    // we handle it by panicking. This is to avoid returning execution to the
-                    // offending host function—for two reasons: (1) if a host function is issuing
-                    // disallowed syscalls, it could be unsafe to return to, and (2) returning
-                    // execution after trapping the disallowed syscall can lead to UB (e.g., try
-                    // running a host function that attempts to sleep without `SYS_clock_nanosleep`,
-                    // you'll block the syscall but panic in the aftermath).
-                    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| call_func(&host_funcs_cloned, &name_cloned, args_cloned))) {
-                        Ok(val) => val,
-                        Err(err) => {
-                            if let Some(crate::HyperlightError::DisallowedSyscall) = err.downcast_ref::<crate::HyperlightError>() {
-                                return Err(crate::HyperlightError::DisallowedSyscall)
-                            }
-
-                            crate::log_then_return!("Host function {} panicked", name_cloned);
-                        }
-                    }
-                })?;
+            match HOST_FUNCTION_WORKER_SPAWNER.with(|spawner| spawner.get().cloned()) {
+                Some(spawner) => spawner
+                    .0
+                    .send(request)
+                    .map_err(|_| new_error!("Host function worker spawner thread has exited"))?,
+                None => spawn_host_function_worker(
+                    host_funcs.get_host_funcs(),
+                    host_funcs.syscall_audit_log.is_some(),
+                    request,
+                )?,
+            }
+
+            // the channel is disconnected once the host function has returned
+            for request in nested_call_rx {
+                request.handle();
+            }
 
-            join_handle.join().map_err(|_| new_error!("Error joining thread executing host function"))?
+            let (result, audited) = result_rx
+                .recv()
+                .map_err(|_| new_error!("Error joining thread executing host function"))?;
+            if let (Some(log), Some(audited)) = (host_funcs.get_syscall_audit_log(), audited) {
+                let extra_allowed_syscalls = host_funcs
+                    .get_host_funcs()
+                    .get(name)
+                    .and_then(|func| func.1.clone());
+                let compared_args =
+                    crate::seccomp::guest::compared_args_for_host_function_worker_thread(
+                        extra_allowed_syscalls,
+                    )?;
+                log.lock()
+                    .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+                    .record(name, &audited, &compared_args);
+            }
+            result
         } else {
             // Directly call the function without creating a new thread
-            call_func(host_funcs, name, args)
+            call_func(host_funcs.get_host_funcs(), name, args)
         }
     }
 }
 
+/// A call of the host function `name` with `args` on a new host function
+/// worker thread, which sends back its result on `result_tx`, along with the
+/// disallowed syscalls it made if they are audited.
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+struct HostFunctionWorkerRequest {
+    name: String,
+    args: Vec<ParameterValue>,
+    nested_call_tx: crossbeam_channel::Sender<NestedCallRequest>,
+    result_tx: crossbeam_channel::Sender<(Result<ReturnValue>, Option<Box<SyscallAuditBuffer>>)>,
+}
+
+/// Spawns the host function worker threads of the host functions called on
+/// another thread, which can then apply a seccomp filter to itself without
+/// the worker threads, which only have their own filters applied,
+/// inheriting it.
+///
+/// The spawner thread has its own copy of the sandbox's host functions, and
+/// is only sent which of them to call and the arguments, so the thread using
+/// it can't make it run anything else.
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+#[derive(Clone)]
+pub(crate) struct HostFunctionWorkerSpawner(crossbeam_channel::Sender<HostFunctionWorkerRequest>);
+
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+thread_local! {
+    /// Where the host function worker threads of the host functions called
+    /// on this thread are spawned from, if not from this thread itself
+    static HOST_FUNCTION_WORKER_SPAWNER: std::cell::OnceCell<HostFunctionWorkerSpawner> =
+        const { std::cell::OnceCell::new() };
+}
+
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+impl HostFunctionWorkerSpawner {
+    /// Start a thread that spawns the worker threads of `host_funcs`. It
+    /// exits once every `HostFunctionWorkerSpawner` for it has been dropped.
+    pub(crate) fn start(host_funcs: &HostFuncsWrapper) -> Result<Self> {
+        let functions_map = host_funcs.get_host_funcs().clone();
+        let audit_mode = host_funcs.syscall_audit_log.is_some();
+        let (spawner_tx, spawner_rx) = crossbeam_channel::unbounded::<HostFunctionWorkerRequest>();
+        std::thread::Builder::new()
+            .name("Host Function Worker Spawner".to_string())
+            .spawn(move || {
+                for request in spawner_rx {
+                    if let Err(e) = spawn_host_function_worker(&functions_map, audit_mode, request)
+                    {
+                        log::error!("Error spawning host function worker thread: {}", e);
+                    }
+                }
+            })?;
+        Ok(Self(spawner_tx))
+    }
+
+    /// Spawn the host function worker threads of the host functions called
+    /// on this thread from the spawner thread, rather than from this one.
+    /// This must be called before this thread applies a seccomp filter to
+    /// itself.
+    pub(crate) fn spawn_for_this_thread(self) -> Result<()> {
+        HOST_FUNCTION_WORKER_SPAWNER.with(|spawner| {
+            spawner.set(self).map_err(|_| {
+                new_error!("Host function worker threads are already spawned from another thread")
+            })
+        })
+    }
+}
+
+/// Spawn a host function worker thread that makes the call `request` to one
+/// of `host_funcs`.
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+fn spawn_host_function_worker(
+    host_funcs: &FunctionsMap,
+    audit_mode: bool,
+    request: HostFunctionWorkerRequest,
+) -> Result<()> {
+    let host_funcs = host_funcs.clone();
+    let HostFunctionWorkerRequest {
+        name,
+        args,
+        nested_call_tx,
+        result_tx,
+    } = request;
+    std::thread::Builder::new()
+        .name(format!("Host Function Worker Thread for: {:?}", name))
+        .spawn(move || {
+            let result = {
+                let _vcpu_thread = crate::func::nested_call::VcpuThreadGuard::enter(nested_call_tx);
+                // The audited syscalls are returned to this thread's parent to be logged, as
+                // doing it here could make syscalls the filter doesn't allow.
+                let audit = audit_mode.then(SyscallAuditBuffer::start);
+                // We have a `catch_unwind` here because, if a disallowed syscall is issued,
+                // we handle it by panicking. This is to avoid returning execution to the
+                // offending host function—for two reasons: (1) if a host function is issuing
+                // disallowed syscalls, it could be unsafe to return to, and (2) returning
+                // execution after trapping the disallowed syscall can lead to UB (e.g., try
+                // running a host function that attempts to sleep without `SYS_clock_nanosleep`,
+                // you'll block the syscall but panic in the aftermath).
+                let result = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    call_func(&host_funcs, &name, args, audit_mode)
+                })) {
+                    Ok(val) => val,
+                    Err(err) => {
+                        if let Some(crate::HyperlightError::DisallowedSyscall) =
+                            err.downcast_ref::<crate::HyperlightError>()
+                        {
+                            Err(crate::HyperlightError::DisallowedSyscall)
+                        } else {
+                            Err(HostFunctionPanicked(name, panic_message(err.as_ref())))
+                        }
+                    }
+                };
+                (result, audit.map(SyscallAuditGuard::finish))
+            };
+            // Sent once the nested call channel is disconnected, so that the
+            // thread waiting for this stops handling nested calls first
+            let _ = result_tx.send(result);
+        })?;
+    Ok(())
+}
+
+/// The message a panic was started with, if it was started with one, as
+/// `panic!` does.
+pub(super) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
 /// The default writer function is to write to stdout with green text.
 #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
 pub(super) fn default_writer_func(s: String) -> Result<i32> {
@@ -14,22 +14,34 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 use hyperlight_common::flatbuffer_wrappers::function_types::{
-    ParameterValue, ReturnType, ReturnValue,
+    ParameterType, ParameterValue, ReturnType, ReturnValue,
 };
+use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
 use tracing::{instrument, Span};
 
 use super::host_funcs::HostFuncsWrapper;
-use super::{MemMgrWrapper, WrapperGetter};
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use super::AuditedSyscall;
+use super::{MemMgrWrapper, MemoryStats, WrapperGetter};
+#[cfg(feature = "async")]
+use crate::func::async_call::GuestFunctionCallFuture;
 use crate::func::call_ctx::MultiUseGuestCallContext;
 use crate::func::guest_dispatch::call_function_on_guest;
-use crate::hypervisor::hypervisor_handler::HypervisorHandler;
+use crate::func::{CallTimeout, SupportedParameterTuple, SupportedReturnType};
+use crate::hypervisor::hypervisor_handler::{HypervisorHandler, HypervisorHandlerAction};
+use crate::mem::host_mapped_region::{layout_host_mapped_regions, HostMappedRegion};
+use crate::mem::memory_region::MemoryRegion;
 use crate::mem::shared_mem::HostSharedMemory;
 use crate::sandbox_state::sandbox::{DevolvableSandbox, EvolvableSandbox, Sandbox};
 use crate::sandbox_state::transition::{MultiUseContextCallback, Noop};
-use crate::Result;
+use crate::{log_then_return, HyperlightError, InterruptHandle, Result, UninitializedSandbox};
 
 /// A sandbox that supports being used Multiple times.
 /// The implication of being used multiple times is two-fold:
@@ -44,6 +56,7 @@ pub struct MultiUseSandbox {
     pub(super) _host_funcs: Arc<Mutex<HostFuncsWrapper>>,
     pub(crate) mem_mgr: MemMgrWrapper<HostSharedMemory>,
     hv_handler: HypervisorHandler,
+    guest_functions: Option<GuestFunctionDetails>,
 }
 
 // We need to implement drop to join the
@@ -76,14 +89,190 @@ impl MultiUseSandbox {
         host_funcs: Arc<Mutex<HostFuncsWrapper>>,
         mgr: MemMgrWrapper<HostSharedMemory>,
         hv_handler: HypervisorHandler,
+        guest_functions: Option<GuestFunctionDetails>,
     ) -> MultiUseSandbox {
         Self {
             _host_funcs: host_funcs,
             mem_mgr: mgr,
             hv_handler,
+            guest_functions,
         }
     }
 
+    /// Get the signatures of the functions that the guest registered while
+    /// it was initialising.
+    ///
+    /// Returns `None` if the guest did not report its functions to the host,
+    /// for example because it was built against an older version of the
+    /// Hyperlight guest library, or because they didn't fit in its output
+    /// buffer.
+    #[instrument(skip_all, parent = Span::current(), level = "Trace")]
+    pub fn guest_functions(&self) -> Option<&GuestFunctionDetails> {
+        self.guest_functions.as_ref()
+    }
+
+    /// Get how much of its heap and stack the guest is using, to help
+    /// choose the sandbox's `heap_size_override` and `stack_size_override`.
+    ///
+    /// Heap usage and fragmentation are of the state that guest function
+    /// calls start from, while the heap high-water mark and peak stack depth
+    /// are the highest reached by the guest's initialisation or, if the
+    /// sandbox was configured with
+    /// `SandboxConfiguration::set_collect_memory_stats`, any guest function
+    /// call made with this sandbox.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn memory_stats(&self) -> Result<MemoryStats> {
+        self.mem_mgr.get_memory_stats()
+    }
+
+    /// Get the id of this sandbox, which is unique among the sandboxes
+    /// created by this process, and which the `sandbox` label of its
+    /// metrics is set to.
+    #[instrument(skip_all, parent = Span::current(), level = "Trace")]
+    pub fn id(&self) -> u64 {
+        self.mem_mgr.sandbox_id()
+    }
+
+    /// Get the syscalls that each host function has made that its seccomp
+    /// filter didn't allow, with the arguments they were made with, by host
+    /// function name. Each syscall and set of arguments appears once.
+    ///
+    /// These are only recorded if the sandbox was created with
+    /// `SandboxConfiguration::set_seccomp_audit_mode`, and are otherwise
+    /// empty. They can be turned into the extra allowed syscalls for each
+    /// host function with `ExtraAllowedSyscall::from`.
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn audited_syscalls(&self) -> Result<HashMap<String, Vec<AuditedSyscall>>> {
+        let host_funcs = self
+            ._host_funcs
+            .try_lock()
+            .map_err(|e| crate::new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?;
+        match host_funcs.get_syscall_audit_log() {
+            Some(log) => Ok(log
+                .try_lock()
+                .map_err(|e| crate::new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+                .syscalls()),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// Create a new `MultiUseSandbox` from the snapshot of an initialised
+    /// sandbox in the file at `path`, which was written by `save_snapshot`,
+    /// without initialising its guest again.
+    ///
+    /// No host functions other than the default `HostPrint` are registered
+    /// with the sandbox. To register others, create an `UninitializedSandbox`
+    /// with `UninitializedSandbox::from_snapshot` and evolve it instead.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn from_snapshot(path: impl AsRef<Path>) -> Result<MultiUseSandbox> {
+        UninitializedSandbox::from_snapshot(path, None)?.evolve(Noop::default())
+    }
+
+    /// Write a snapshot of this sandbox to the file at `path`, replacing it
+    /// if it exists, so that new sandboxes can be created in the same state
+    /// with `from_snapshot`, in this or another process, without
+    /// initialising their guests.
+    ///
+    /// The snapshot is of the sandbox's state as of its last evolution,
+    /// which is the state every guest function call starts from. It
+    /// includes the sandbox's configuration, memory and the vCPU's register
+    /// state, but not its host functions.
+    ///
+    /// Snapshots of in-process sandboxes are not supported, as their memory
+    /// contains addresses in the host process.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mgr = self.mem_mgr.unwrap_mgr();
+        if mgr.is_in_process() {
+            log_then_return!("Saving a snapshot of an in-process sandbox is not supported");
+        }
+        mgr.to_snapshot_file(
+            *self.mem_mgr.get_stack_cookie(),
+            self.guest_functions.clone(),
+        )?
+        .write(path.as_ref())
+    }
+
+    /// Create a new `MultiUseSandbox` in the same state as this one, without
+    /// initialising its guest again.
+    ///
+    /// The new sandbox runs in its own VM, on its own hypervisor handler
+    /// thread, so it can be called concurrently with this one. Its memory is
+    /// a private copy-on-write mapping of this sandbox's state as of its last
+    /// evolution, so pages are only copied once either sandbox writes to
+    /// them, and forking is much cheaper than creating and initialising a new
+    /// sandbox. Forks outlive the sandbox they were forked from, and can be
+    /// forked themselves.
+    ///
+    /// The new sandbox shares this sandbox's host functions, so a host
+    /// function called by both sandboxes at the same time runs for one of
+    /// them after the other.
+    ///
+    /// Forking in-process sandboxes is not supported, and forking is only
+    /// supported on Linux.
+    #[cfg(target_os = "linux")]
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn fork(&self) -> Result<MultiUseSandbox> {
+        let mgr = self.mem_mgr.unwrap_mgr();
+        if mgr.is_in_process() {
+            log_then_return!("Forking an in-process sandbox is not supported");
+        }
+        let host_funcs = self
+            ._host_funcs
+            .try_lock()
+            .map_err(|e| crate::new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
+            .clone();
+        let config = self.hv_handler.configuration();
+        let u_sbox = UninitializedSandbox {
+            host_funcs: Arc::new(Mutex::new(host_funcs)),
+            mgr: MemMgrWrapper::new(
+                mgr.fork()?,
+                *self.mem_mgr.get_stack_cookie(),
+                self.mem_mgr.collects_memory_stats(),
+            ),
+            run_inprocess: false,
+            max_initialization_time: config.max_init_time,
+            max_execution_time: config.max_exec_time,
+            max_wait_for_cancellation: config.max_wait_for_cancellation,
+            max_guest_log_level: config.max_guest_log_level,
+            execution_time_budget: config.execution_time_budget,
+            max_host_function_time: config.max_host_function_time,
+            max_nested_guest_call_depth: config.max_nested_guest_call_depth,
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            hypervisor_handler_seccomp_filter: config.seccomp_filter.is_some(),
+            from_snapshot: true,
+            snapshot_guest_functions: self.guest_functions.clone(),
+        };
+        super::uninitialized_evolve::evolve_impl_fork(u_sbox)
+    }
+
+    /// Take a checkpoint named `name` of the state that guest function calls
+    /// start from, replacing any checkpoint with the same name.
+    ///
+    /// A `MultiUseGuestCallContext` created from this sandbox, or from a
+    /// sandbox it's evolved into, can roll back to the checkpoint with
+    /// `MultiUseGuestCallContext::rollback`. Each checkpoint holds a copy of
+    /// the sandbox's memory until it is removed with `remove_checkpoint`, or
+    /// discarded by rolling back to an earlier one.
+    #[instrument(err(Debug), skip(self, name), parent = Span::current())]
+    pub fn checkpoint(&mut self, name: impl Into<String>) -> Result<()> {
+        self.mem_mgr.unwrap_mgr_mut().checkpoint(name.into())
+    }
+
+    /// Discard the checkpoint named `name`, returning whether there was one
+    #[instrument(err(Debug), skip(self), parent = Span::current())]
+    pub fn remove_checkpoint(&mut self, name: &str) -> Result<bool> {
+        self.mem_mgr.unwrap_mgr_mut().remove_checkpoint(name)
+    }
+
+    /// Get the names of this sandbox's checkpoints, in the order they were
+    /// taken
+    #[instrument(err(Debug), skip(self), parent = Span::current())]
+    pub fn checkpoints(&self) -> Result<Vec<String>> {
+        self.mem_mgr.unwrap_mgr().checkpoint_names()
+    }
+
     /// Create a new `MultiUseCallContext` suitable for making 0 or more
     /// calls to guest functions within the same context.
     ///
@@ -124,7 +313,7 @@ impl MultiUseSandbox {
     /// // Do a guest call with the context. Assues that the loaded binary
     /// // ("some_guest_binary") has a function therein called "SomeGuestFunc"
     /// // that takes a single integer argument and returns an integer.
-    /// match ctx.call(
+    /// match ctx.call_guest_function_by_name(
     ///     "SomeGuestFunc",
     ///     ReturnType::Int,
     ///     Some(vec![ParameterValue::Int(1)])
@@ -155,6 +344,33 @@ impl MultiUseSandbox {
         MultiUseGuestCallContext::start(self)
     }
 
+    /// Get a handle that can be used from another thread to cancel the
+    /// guest function calls made with this sandbox, or with the sandboxes and
+    /// call contexts it's evolved into.
+    ///
+    /// ```no_run
+    /// # use hyperlight_host::func::{ReturnType, ReturnValue};
+    /// # use hyperlight_host::sandbox_state::sandbox::EvolvableSandbox;
+    /// # use hyperlight_host::sandbox_state::transition::Noop;
+    /// # use hyperlight_host::{GuestBinary, MultiUseSandbox, UninitializedSandbox};
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let u_sbox = UninitializedSandbox::new(GuestBinary::FilePath("guest".to_string()), None, None, None)?;
+    /// let mut sbox: MultiUseSandbox = u_sbox.evolve(Noop::default())?;
+    /// let interrupt_handle = sbox.interrupt_handle();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_millis(100));
+    ///     interrupt_handle.kill();
+    /// });
+    /// // fails with `HyperlightError::ExecutionCanceledByHost` if it's still
+    /// // running when it's interrupted
+    /// let res = sbox.call_guest_function_by_name("Spin", ReturnType::Void, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.hv_handler.interrupt_handle()
+    }
+
     /// Call a guest function by name, with the given return type and arguments.
     #[instrument(err(Debug), skip(self, args), parent = Span::current())]
     pub fn call_guest_function_by_name(
@@ -163,17 +379,236 @@ impl MultiUseSandbox {
         func_ret_type: ReturnType,
         args: Option<Vec<ParameterValue>>,
     ) -> Result<ReturnValue> {
-        let res = call_function_on_guest(self, func_name, func_ret_type, args)?;
+        let res = call_function_on_guest(self, func_name, func_ret_type, args, None)?;
+        self.restore_state()?;
+        Ok(res)
+    }
+
+    /// Call a guest function by name, with the given return type and
+    /// arguments, cancelling it once `timeout` is reached instead of once it
+    /// has run for the sandbox's `max_execution_time`.
+    ///
+    /// `timeout` can be a `Duration`, an `Instant` deadline, or
+    /// `CallTimeout::Never` to let the call run for as long as it takes.
+    #[instrument(err(Debug), skip(self, args, timeout), parent = Span::current())]
+    pub fn call_guest_function_by_name_with_timeout(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        timeout: impl Into<CallTimeout>,
+    ) -> Result<ReturnValue> {
+        let res =
+            call_function_on_guest(self, func_name, func_ret_type, args, Some(timeout.into()))?;
+        self.restore_state()?;
+        Ok(res)
+    }
+
+    /// Call a guest function by name, with the given return type and
+    /// arguments, and with `input` and `output` as its input and output
+    /// streams.
+    ///
+    /// Unlike arguments and return values, which must fit in the sandbox's
+    /// input and output data buffers, streams can be of any size: the guest
+    /// reads `input` and writes `output` a chunk at a time, using the
+    /// `StreamReader` and `StreamWriter` in `hyperlight_guest::stream`.
+    /// Each chunk is at most as large as the sandbox's input or output data
+    /// buffer.
+    ///
+    /// Once the guest function returns, `output` is flushed and returned
+    /// along with the function's return value. Guest functions called
+    /// without streams read an empty input stream, and fail if they write to
+    /// their output stream.
+    #[instrument(err(Debug), skip(self, args, input, output), parent = Span::current())]
+    pub fn call_guest_function_with_streams<R, W>(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        input: R,
+        output: W,
+    ) -> Result<(ReturnValue, W)>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        self.mem_mgr.lock_streams()?.set(input, output);
+        let res = call_function_on_guest(self, func_name, func_ret_type, args, None);
+        let output = self.mem_mgr.lock_streams()?.take::<W>();
+        let res = res?;
+        self.restore_state()?;
+        Ok((res, output?))
+    }
+
+    /// Call a guest function by name, with the given return type and
+    /// arguments, and with `regions` of host memory mapped into the guest
+    /// for the duration of the call instead of copied into the sandbox.
+    ///
+    /// The guest address and length of each region are appended to `args`
+    /// as two `ParameterValue::ULong`s, so the guest function takes them as
+    /// its last parameters and accesses the regions with
+    /// `hyperlight_guest::host_mapped_region`. The regions are unmapped
+    /// before this returns.
+    ///
+    /// Mapping host memory into the guest is supported with KVM and MSHV,
+    /// but not with Windows Hypervisor Platform or in-process sandboxes.
+    #[instrument(err(Debug), skip(self, args, regions), parent = Span::current())]
+    pub fn call_guest_function_with_mapped_regions(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        regions: &[HostMappedRegion<'_>],
+    ) -> Result<ReturnValue> {
+        let guest_addr = self
+            .mem_mgr
+            .unwrap_mgr()
+            .layout
+            .get_host_mapped_regions_address()?;
+        let memory_regions = layout_host_mapped_regions(regions, guest_addr)?;
+        let mut args = args.unwrap_or_default();
+        for region in &memory_regions {
+            args.push(ParameterValue::ULong(region.guest_region.start as u64));
+            args.push(ParameterValue::ULong(region.guest_region.len() as u64));
+        }
+
+        let mut mapped = 0;
+        let res = self
+            .map_host_regions(&memory_regions, &mut mapped)
+            .and_then(|()| {
+                call_function_on_guest(self, func_name, func_ret_type, Some(args), None)
+            });
+        let unmapped = self.unmap_host_regions(&memory_regions[..mapped]);
+        let res = res?;
+        unmapped?;
         self.restore_state()?;
         Ok(res)
     }
 
+    /// Call a guest function by name, with the given return type and
+    /// arguments, without blocking the calling thread.
+    ///
+    /// The returned future resolves once the guest function finishes, or
+    /// fails with `ExecutionCanceledByHost` if it exceeds the sandbox's
+    /// `max_execution_time`. If the future is dropped before it resolves,
+    /// the guest function's execution is cancelled. Either way, the state of
+    /// the sandbox is reset afterwards, as with `call_guest_function_by_name`.
+    ///
+    /// The future does not depend on any particular async runtime.
+    #[cfg(feature = "async")]
+    #[instrument(skip(self, args), parent = Span::current())]
+    pub fn call_guest_function_async(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+    ) -> GuestFunctionCallFuture<'_> {
+        GuestFunctionCallFuture::new(self, func_name, func_ret_type, args)
+    }
+
+    /// Call a guest function by name with the arguments `args`, and convert
+    /// its return value to `R`.
+    ///
+    /// This is a typed equivalent of `call_guest_function_by_name`: `Args`
+    /// is a tuple of `SupportedParameterType`s (use `()` for a function that
+    /// takes no arguments) and `R` is a `SupportedReturnType`, so the
+    /// `ReturnType` passed to the guest is derived from `R`. For example:
+    ///
+    /// `let sum: i32 = sbox.call("Add", (1_i32, 41_i32))?;`
+    #[instrument(err(Debug), skip(self, args), parent = Span::current())]
+    pub fn call<Args: SupportedParameterTuple, R: SupportedReturnType<R>>(
+        &mut self,
+        func_name: &str,
+        args: Args,
+    ) -> Result<R> {
+        let args = args.get_hyperlight_values();
+        self.check_guest_function_signature(func_name, R::get_hyperlight_type(), &args)?;
+        let ret = self.call_guest_function_by_name(func_name, R::get_hyperlight_type(), args)?;
+        R::get_inner(ret)
+    }
+
+    /// Check that the guest has a function called `func_name` that takes
+    /// arguments of the same types as `args` and returns `func_ret_type`.
+    ///
+    /// Nothing is checked if the guest did not report its functions.
+    #[instrument(err(Debug), skip(self, args), parent = Span::current(), level = "Trace")]
+    pub(crate) fn check_guest_function_signature(
+        &self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: &Option<Vec<ParameterValue>>,
+    ) -> Result<()> {
+        let Some(guest_functions) = &self.guest_functions else {
+            return Ok(());
+        };
+        let Some(definition) = guest_functions.find_by_function_name(func_name) else {
+            return Err(HyperlightError::GuestFunctionNotFound(
+                func_name.to_string(),
+            ));
+        };
+
+        let parameter_types: Vec<ParameterType> =
+            args.iter().flatten().map(ParameterType::from).collect();
+        if definition.parameter_types != parameter_types || definition.return_type != func_ret_type
+        {
+            return Err(HyperlightError::GuestFunctionSignatureMismatch(
+                func_name.to_string(),
+                format!(
+                    "{:?} -> {:?}",
+                    definition.parameter_types, definition.return_type
+                ),
+                format!("{:?} -> {:?}", parameter_types, func_ret_type),
+            ));
+        }
+        Ok(())
+    }
+
     /// Restore the Sandbox's state
     #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
     pub(crate) fn restore_state(&mut self) -> Result<()> {
         let mem_mgr = self.mem_mgr.unwrap_mgr_mut();
         mem_mgr.restore_state_from_last_snapshot()
     }
+
+    /// Map `memory_regions` into the guest, counting in `mapped` how many
+    /// were mapped by the hypervisor, and so need to be unmapped, even if
+    /// mapping a later one fails.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn map_host_regions(
+        &mut self,
+        memory_regions: &[MemoryRegion],
+        mapped: &mut usize,
+    ) -> Result<()> {
+        for region in memory_regions {
+            self.hv_handler.execute_hypervisor_handler_action(
+                HypervisorHandlerAction::MapRegion(region.clone()),
+            )?;
+            *mapped += 1;
+            self.mem_mgr
+                .unwrap_mgr_mut()
+                .map_host_region_pages(region)?;
+        }
+        Ok(())
+    }
+
+    /// Unmap `memory_regions`, which were mapped by `map_host_regions`,
+    /// from the guest.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn unmap_host_regions(&mut self, memory_regions: &[MemoryRegion]) -> Result<()> {
+        if memory_regions.is_empty() {
+            return Ok(());
+        }
+        // The hypervisor must unmap the host memory before the caller's
+        // borrow of it ends, even if the page tables can't be restored.
+        self.hv_handler
+            .execute_hypervisor_handler_action(HypervisorHandlerAction::UnmapRegions)?;
+        for region in memory_regions {
+            self.mem_mgr
+                .unwrap_mgr_mut()
+                .unmap_host_region_pages(region)?;
+        }
+        Ok(())
+    }
 }
 
 impl WrapperGetter for MultiUseSandbox {
@@ -257,16 +692,26 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier, Mutex};
+    use std::time::{Duration, Instant};
+
     use hyperlight_common::flatbuffer_wrappers::function_types::{
-        ParameterValue, ReturnType, ReturnValue,
+        ParameterType, ParameterValue, ReturnType, ReturnValue,
     };
     use hyperlight_testing::simple_guest_as_string;
 
     use crate::func::call_ctx::MultiUseGuestCallContext;
-    use crate::sandbox::SandboxConfiguration;
-    use crate::sandbox_state::sandbox::{DevolvableSandbox, EvolvableSandbox};
+    use crate::func::CallTimeout;
+    use crate::sandbox::metrics::SandboxMetric::GuestStackPeakDepthBytes;
+    use crate::sandbox::{ExecutionTimeBudget, SandboxBuilder, SandboxConfiguration};
+    use crate::sandbox_state::sandbox::{DevolvableSandbox, EvolvableSandbox, Sandbox};
     use crate::sandbox_state::transition::{MultiUseContextCallback, Noop};
-    use crate::{GuestBinary, MultiUseSandbox, UninitializedSandbox};
+    use crate::{
+        histogram_vec_sample_count, GuestBinary, HostMappedRegion, HyperlightError,
+        MultiUseSandbox, UninitializedSandbox,
+    };
 
     // Tests to ensure that many (1000) function calls can be made in a call context with a small stack (1K) and heap(14K).
     // This test effectively ensures that the stack is being properly reset after each call and we are not leaking memory in the Guest.
@@ -288,7 +733,7 @@ mod tests {
         let mut ctx = sbox1.new_call_context();
 
         for _ in 0..1000 {
-            ctx.call(
+            ctx.call_guest_function_by_name(
                 "StackAllocate",
                 ReturnType::Int,
                 Some(vec![ParameterValue::Int(1)]),
@@ -308,7 +753,7 @@ mod tests {
         let mut ctx = sbox2.new_call_context();
 
         for i in 0..1000 {
-            ctx.call(
+            ctx.call_guest_function_by_name(
                 "PrintUsingPrintf",
                 ReturnType::Int,
                 Some(vec![ParameterValue::String(
@@ -332,7 +777,7 @@ mod tests {
         .unwrap();
 
         let func = Box::new(|call_ctx: &mut MultiUseGuestCallContext| {
-            call_ctx.call(
+            call_ctx.call_guest_function_by_name(
                 "AddToStatic",
                 ReturnType::Int,
                 Some(vec![ParameterValue::Int(5)]),
@@ -351,4 +796,713 @@ mod tests {
             .unwrap();
         assert_eq!(res, ReturnValue::Int(0));
     }
+
+    #[test]
+    fn checkpoint_rollback_and_commit() {
+        let mut sbox: MultiUseSandbox = {
+            let path = simple_guest_as_string().unwrap();
+            let u_sbox =
+                UninitializedSandbox::new(GuestBinary::FilePath(path), None, None, None).unwrap();
+            u_sbox.evolve(Noop::default())
+        }
+        .unwrap();
+        sbox.checkpoint("initial").unwrap();
+
+        let add = |ctx: &mut MultiUseGuestCallContext, i: i32| -> i32 {
+            ctx.call("AddToStatic", (i,)).unwrap()
+        };
+        let get =
+            |ctx: &mut MultiUseGuestCallContext| -> i32 { ctx.call("GetStatic", ()).unwrap() };
+
+        // rolling back within a context discards the checkpoints taken after
+        // the one rolled back to, and committing keeps the state
+        let mut ctx = sbox.new_call_context();
+        assert_eq!(add(&mut ctx, 1), 1);
+        ctx.checkpoint("one").unwrap();
+        assert_eq!(add(&mut ctx, 2), 3);
+        ctx.checkpoint("three").unwrap();
+        assert_eq!(add(&mut ctx, 4), 7);
+        ctx.rollback("one").unwrap();
+        assert_eq!(get(&mut ctx), 1);
+        assert!(matches!(
+            ctx.rollback("three"),
+            Err(HyperlightError::CheckpointNotFound(name)) if name == "three"
+        ));
+        let mut sbox = ctx.commit().unwrap();
+        for _ in 0..2 {
+            let res: i32 = sbox.call("AddToStatic", (1_i32,)).unwrap();
+            assert_eq!(res, 2);
+        }
+
+        // finishing a context discards the changes made through it
+        let mut ctx = sbox.new_call_context();
+        assert_eq!(add(&mut ctx, 10), 11);
+        let sbox = ctx.finish().unwrap();
+
+        // checkpoints taken before a commit can be rolled back to after it
+        let mut ctx = sbox.new_call_context();
+        ctx.rollback("initial").unwrap();
+        assert_eq!(get(&mut ctx), 0);
+        let mut sbox = ctx.commit().unwrap();
+        let res: i32 = sbox.call("GetStatic", ()).unwrap();
+        assert_eq!(res, 0);
+        assert_eq!(sbox.checkpoints().unwrap(), vec!["initial"]);
+
+        // committing replaces the state the sandbox was evolved to, so
+        // devolving goes back to the state from before it was evolved
+        let func = Box::new(|ctx: &mut MultiUseGuestCallContext| {
+            ctx.call::<_, i32>("AddToStatic", (5_i32,))?;
+            Ok(())
+        });
+        let sbox = sbox.evolve(MultiUseContextCallback::from(func)).unwrap();
+        let mut ctx = sbox.new_call_context();
+        assert_eq!(add(&mut ctx, 1), 6);
+        let mut sbox = ctx.commit().unwrap();
+        let res: i32 = sbox.call("GetStatic", ()).unwrap();
+        assert_eq!(res, 6);
+        let mut sbox = sbox.devolve(Noop::default()).unwrap();
+        let res: i32 = sbox.call("GetStatic", ()).unwrap();
+        assert_eq!(res, 0);
+
+        assert!(sbox.remove_checkpoint("initial").unwrap());
+        assert!(!sbox.remove_checkpoint("initial").unwrap());
+        assert!(sbox.checkpoints().unwrap().is_empty());
+    }
+
+    #[test]
+    fn call_guest_function_by_name_with_timeout() {
+        // a host function that takes longer than the sandbox's
+        // max_execution_time
+        let writer = |msg: String| -> crate::Result<i32> {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(300) {}
+            Ok(msg.len() as i32)
+        };
+        let writer = Arc::new(Mutex::new(writer));
+        let mut sbox: MultiUseSandbox =
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_max_initialization_time(Duration::from_secs(10))
+                .with_max_execution_time(Duration::from_millis(100))
+                .with_host_print_fn(&writer)
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap();
+
+        let print = |sbox: &mut MultiUseSandbox, timeout: CallTimeout| {
+            sbox.call_guest_function_by_name_with_timeout(
+                "PrintOutput",
+                ReturnType::Int,
+                Some(vec![ParameterValue::String("hello".to_string())]),
+                timeout,
+            )
+        };
+        assert_eq!(
+            print(&mut sbox, Duration::from_secs(10).into()).unwrap(),
+            ReturnValue::Int(5)
+        );
+        assert_eq!(
+            print(&mut sbox, (Instant::now() + Duration::from_secs(10)).into()).unwrap(),
+            ReturnValue::Int(5)
+        );
+        assert_eq!(
+            print(&mut sbox, CallTimeout::Never).unwrap(),
+            ReturnValue::Int(5)
+        );
+
+        // calls are still cancelled once their own timeout is reached
+        let spin = |sbox: &mut MultiUseSandbox, timeout: CallTimeout| {
+            sbox.call_guest_function_by_name_with_timeout("Spin", ReturnType::Int, None, timeout)
+        };
+        let res = spin(&mut sbox, Duration::from_millis(200).into());
+        assert!(matches!(
+            res,
+            Err(HyperlightError::ExecutionCanceledByHost())
+        ));
+        let res = spin(&mut sbox, Instant::now().into());
+        assert!(matches!(
+            res,
+            Err(HyperlightError::ExecutionCanceledByHost())
+        ));
+
+        // and the sandbox's max_execution_time is still the default
+        let res = sbox.call_guest_function_by_name("Spin", ReturnType::Int, None);
+        assert!(matches!(
+            res,
+            Err(HyperlightError::ExecutionCanceledByHost())
+        ));
+        let res = sbox.call_guest_function_by_name(
+            "Echo",
+            ReturnType::String,
+            Some(vec![ParameterValue::String("hello".to_string())]),
+        );
+        assert_eq!(res.unwrap(), ReturnValue::String("hello".to_string()));
+    }
+
+    #[test]
+    fn interrupt_handle() {
+        let mut sbox: MultiUseSandbox =
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_max_initialization_time(Duration::from_secs(10))
+                .with_max_execution_time(Duration::from_secs(60))
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap();
+        let interrupt_handle = sbox.interrupt_handle();
+
+        // nothing is interrupted when no call is in flight
+        assert!(!interrupt_handle.kill());
+
+        // a running call is cancelled, long before its timeout
+        let start = Instant::now();
+        let killer = {
+            let interrupt_handle = interrupt_handle.clone();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                while start.elapsed() < Duration::from_secs(30) {
+                    std::thread::sleep(Duration::from_millis(200));
+                    if interrupt_handle.kill() {
+                        return true;
+                    }
+                }
+                false
+            })
+        };
+        let res = sbox.call_guest_function_by_name("Spin", ReturnType::Int, None);
+        assert!(killer.join().unwrap());
+        assert!(matches!(
+            res,
+            Err(HyperlightError::ExecutionCanceledByHost())
+        ));
+        assert!(start.elapsed() < Duration::from_secs(30));
+
+        // and the sandbox can still be used, through a call context too
+        let mut ctx = sbox.new_call_context();
+        let res = ctx.call_guest_function_by_name(
+            "Echo",
+            ReturnType::String,
+            Some(vec![ParameterValue::String("hello".to_string())]),
+        );
+        assert_eq!(res.unwrap(), ReturnValue::String("hello".to_string()));
+        let killer = std::thread::spawn(move || {
+            while !interrupt_handle.kill() {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let res = ctx.call_guest_function_by_name("Spin", ReturnType::Int, None);
+        killer.join().unwrap();
+        assert!(matches!(
+            res,
+            Err(HyperlightError::ExecutionCanceledByHost())
+        ));
+    }
+
+    #[test]
+    fn guest_only_execution_time_budget() {
+        // a sandbox whose host print function waits for up to `host_time`,
+        // which is longer than the sandbox's max_execution_time, or until
+        // the returned sender is dropped
+        let new_sbox = |host_time: Duration, max_host_function_time: Duration| {
+            let (release, released) = std::sync::mpsc::channel::<()>();
+            let writer = move |msg: String| -> crate::Result<i32> {
+                let _ = released.recv_timeout(host_time);
+                Ok(msg.len() as i32)
+            };
+            let writer = Arc::new(Mutex::new(writer));
+            let sbox: MultiUseSandbox =
+                SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                    .with_max_initialization_time(Duration::from_secs(10))
+                    .with_max_execution_time(Duration::from_secs(1))
+                    .with_execution_time_budget(ExecutionTimeBudget::GuestOnly)
+                    .with_max_host_function_time(max_host_function_time)
+                    .with_host_print_fn(&writer)
+                    .build()
+                    .unwrap()
+                    .evolve(Noop::default())
+                    .unwrap();
+            (sbox, release)
+        };
+        let print = |sbox: &mut MultiUseSandbox| {
+            sbox.call_guest_function_by_name(
+                "PrintOutput",
+                ReturnType::Int,
+                Some(vec![ParameterValue::String("hello".to_string())]),
+            )
+        };
+
+        // the time spent in the host function doesn't count
+        let (mut sbox, release) = new_sbox(Duration::from_millis(1500), Duration::from_secs(10));
+        assert_eq!(print(&mut sbox).unwrap(), ReturnValue::Int(5));
+        drop(release);
+
+        // but the time the guest spends running does
+        let res = sbox.call_guest_function_by_name("Spin", ReturnType::Int, None);
+        assert!(matches!(
+            res,
+            Err(HyperlightError::ExecutionCanceledByHost())
+        ));
+        assert_eq!(print(&mut sbox).unwrap(), ReturnValue::Int(5));
+
+        // and host functions are cancelled after max_host_function_time
+        let (mut sbox, release) = new_sbox(Duration::from_secs(10), Duration::from_millis(100));
+        assert!(matches!(
+            print(&mut sbox),
+            Err(HyperlightError::GuestExecutionHungOnHostFunctionCall())
+        ));
+        // let the cancelled host function return
+        drop(release);
+    }
+
+    #[test]
+    fn typed_calls() {
+        let mut sbox: MultiUseSandbox = {
+            let path = simple_guest_as_string().unwrap();
+            let u_sbox =
+                UninitializedSandbox::new(GuestBinary::FilePath(path), None, None, None).unwrap();
+            u_sbox.evolve(Noop::default())
+        }
+        .unwrap();
+
+        let res: String = sbox.call("Echo", ("hello".to_string(),)).unwrap();
+        assert_eq!(res, "hello");
+
+        let res: i32 = sbox.call("GetStatic", ()).unwrap();
+        assert_eq!(res, 0);
+
+        // a return type that doesn't match the guest function's is an error
+        let res = sbox.call::<_, String>("GetStatic", ());
+        assert!(res.is_err());
+
+        let mut ctx = sbox.new_call_context();
+        for i in 1..=3 {
+            let res: i32 = ctx.call("AddToStatic", (1_i32,)).unwrap();
+            assert_eq!(res, i);
+        }
+        let mut sbox = ctx.finish().unwrap();
+        let res: i32 = sbox.call("GetStatic", ()).unwrap();
+        assert_eq!(res, 0);
+    }
+
+    #[test]
+    fn compound_parameter_calls() {
+        let mut sbox: MultiUseSandbox = {
+            let path = simple_guest_as_string().unwrap();
+            let u_sbox =
+                UninitializedSandbox::new(GuestBinary::FilePath(path), None, None, None).unwrap();
+            u_sbox.evolve(Noop::default())
+        }
+        .unwrap();
+
+        let res: i32 = sbox.call("SumList", (vec![1_i32, 2, 3],)).unwrap();
+        assert_eq!(res, 6);
+
+        let record = vec![
+            ("name".to_string(), ParameterValue::String("hl".to_string())),
+            (
+                "tags".to_string(),
+                ParameterValue::List(vec![ParameterValue::String("a".to_string())]),
+            ),
+            ("parent".to_string(), ParameterValue::Optional(None)),
+        ];
+        let res = sbox
+            .call_guest_function_by_name(
+                "EchoRecord",
+                ReturnType::Record,
+                Some(vec![ParameterValue::Record(record.clone())]),
+            )
+            .unwrap();
+        assert_eq!(res, ReturnValue::Record(record));
+
+        // a list whose elements are not all i32s can't be summed
+        let res = sbox.call::<_, i32>("SumList", (vec!["a".to_string()],));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn guest_function_signatures() {
+        let mut sbox: MultiUseSandbox = {
+            let path = simple_guest_as_string().unwrap();
+            let u_sbox =
+                UninitializedSandbox::new(GuestBinary::FilePath(path), None, None, None).unwrap();
+            u_sbox.evolve(Noop::default())
+        }
+        .unwrap();
+
+        let echo = sbox
+            .guest_functions()
+            .unwrap()
+            .find_by_function_name("Echo")
+            .unwrap();
+        assert_eq!(echo.parameter_types, vec![ParameterType::String]);
+        assert_eq!(echo.return_type, ReturnType::String);
+
+        let res = sbox.call::<_, String>("NoSuchFunction", ());
+        assert!(
+            matches!(res, Err(HyperlightError::GuestFunctionNotFound(ref name)) if name == "NoSuchFunction"),
+            "{:?}",
+            res
+        );
+
+        let res = sbox.call::<_, i32>("Echo", ("hello".to_string(),));
+        assert!(
+            matches!(
+                res,
+                Err(HyperlightError::GuestFunctionSignatureMismatch(..))
+            ),
+            "{:?}",
+            res
+        );
+
+        let res = sbox.call::<_, String>("Echo", (1_i32,));
+        assert!(
+            matches!(
+                res,
+                Err(HyperlightError::GuestFunctionSignatureMismatch(..))
+            ),
+            "{:?}",
+            res
+        );
+
+        // the details survive state being restored after a call
+        let res: String = sbox.call("Echo", ("hello".to_string(),)).unwrap();
+        assert_eq!(res, "hello");
+        let res: String = sbox.call("Echo", ("again".to_string(),)).unwrap();
+        assert_eq!(res, "again");
+    }
+
+    #[test]
+    fn call_guest_function_with_streams() {
+        let mut sbox: MultiUseSandbox =
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_input_data_size(0x1000)
+                .with_output_data_size(0x1000)
+                .with_max_execution_time(Duration::from_secs(10))
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap();
+
+        // several times larger than the input and output data buffers
+        let input: Vec<u8> = (0..0x4000).map(|i| b"abcdefgh"[i % 8]).collect();
+        let (res, output) = sbox
+            .call_guest_function_with_streams(
+                "StreamToUppercase",
+                ReturnType::Long,
+                None,
+                Cursor::new(input.clone()),
+                Vec::new(),
+            )
+            .unwrap();
+        assert_eq!(res, ReturnValue::Long(input.len() as i64));
+        assert_eq!(output, input.to_ascii_uppercase());
+
+        // without streams, the input stream is empty
+        let res = sbox
+            .call_guest_function_by_name("StreamToUppercase", ReturnType::Long, None)
+            .unwrap();
+        assert_eq!(res, ReturnValue::Long(0));
+
+        // errors writing the output stream fail the call
+        let res = sbox.call_guest_function_with_streams(
+            "StreamToUppercase",
+            ReturnType::Long,
+            None,
+            Cursor::new(input),
+            Cursor::new([0_u8; 16]),
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn call_guest_function_with_mapped_regions() {
+        #[repr(C, align(4096))]
+        struct Page([u8; 4096]);
+
+        let mut sbox: MultiUseSandbox =
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_max_execution_time(Duration::from_secs(10))
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap();
+
+        let mut src = Page([0; 4096]);
+        for (i, b) in src.0.iter_mut().enumerate() {
+            *b = b"abcdefgh"[i % 8];
+        }
+        let mut dst = Page([0; 4096]);
+        let res = sbox
+            .call_guest_function_with_mapped_regions(
+                "UppercaseMappedRegion",
+                ReturnType::ULong,
+                None,
+                &[
+                    HostMappedRegion::read_only(&src.0).unwrap(),
+                    HostMappedRegion::read_write(&mut dst.0).unwrap(),
+                ],
+            )
+            .unwrap();
+        assert_eq!(res, ReturnValue::ULong(4096));
+        assert_eq!(dst.0.to_vec(), src.0.to_ascii_uppercase());
+
+        // the guest can't write to regions that are mapped read-only
+        let res = sbox.call_guest_function_with_mapped_regions(
+            "UppercaseMappedRegion",
+            ReturnType::ULong,
+            None,
+            &[
+                HostMappedRegion::read_only(&src.0).unwrap(),
+                HostMappedRegion::read_only(&dst.0).unwrap(),
+            ],
+        );
+        assert!(res.is_err());
+
+        // regions are unmapped after each call, so can be mapped again
+        dst.0.fill(0);
+        let res = sbox
+            .call_guest_function_with_mapped_regions(
+                "UppercaseMappedRegion",
+                ReturnType::ULong,
+                None,
+                &[
+                    HostMappedRegion::read_only(&src.0).unwrap(),
+                    HostMappedRegion::read_write(&mut dst.0).unwrap(),
+                ],
+            )
+            .unwrap();
+        assert_eq!(res, ReturnValue::ULong(4096));
+        assert_eq!(dst.0.to_vec(), src.0.to_ascii_uppercase());
+    }
+
+    #[test]
+    fn save_and_load_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot");
+
+        let sbox: MultiUseSandbox =
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_max_initialization_time(Duration::from_secs(10))
+                .with_max_execution_time(Duration::from_secs(10))
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap();
+        let func = Box::new(|call_ctx: &mut MultiUseGuestCallContext| {
+            call_ctx.call_guest_function_by_name(
+                "AddToStatic",
+                ReturnType::Int,
+                Some(vec![ParameterValue::Int(5)]),
+            )?;
+            Ok(())
+        });
+        let sbox = sbox.evolve(MultiUseContextCallback::from(func)).unwrap();
+        sbox.save_snapshot(&path).unwrap();
+
+        // the guest isn't initialised again, so keeps the state it was
+        // evolved to, and every call starts from that state
+        let mut restored = MultiUseSandbox::from_snapshot(&path).unwrap();
+        assert_eq!(restored.guest_functions(), sbox.guest_functions());
+        for _ in 0..2 {
+            let res = restored
+                .call_guest_function_by_name(
+                    "AddToStatic",
+                    ReturnType::Int,
+                    Some(vec![ParameterValue::Int(2)]),
+                )
+                .unwrap();
+            assert_eq!(res, ReturnValue::Int(7));
+        }
+        let res: String = restored.call("Echo", ("hello".to_string(),)).unwrap();
+        assert_eq!(res, "hello");
+        assert!(restored.check_stack_guard().unwrap());
+
+        // snapshots of restored sandboxes can be saved too
+        restored.save_snapshot(&path).unwrap();
+        let mut restored = MultiUseSandbox::from_snapshot(&path).unwrap();
+        let res = restored
+            .call_guest_function_by_name("GetStatic", ReturnType::Int, None)
+            .unwrap();
+        assert_eq!(res, ReturnValue::Int(5));
+
+        // corrupt files are rejected
+        let mut bytes = std::fs::read(&path).unwrap();
+        let len = bytes.len();
+        bytes[len / 2] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+        let res = MultiUseSandbox::from_snapshot(&path);
+        assert!(matches!(res, Err(HyperlightError::InvalidSnapshotFile(_))));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn fork() {
+        let sbox: MultiUseSandbox =
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_max_initialization_time(Duration::from_secs(10))
+                .with_max_execution_time(Duration::from_secs(10))
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap();
+        let func = Box::new(|call_ctx: &mut MultiUseGuestCallContext| {
+            call_ctx.call_guest_function_by_name(
+                "AddToStatic",
+                ReturnType::Int,
+                Some(vec![ParameterValue::Int(5)]),
+            )?;
+            Ok(())
+        });
+        let mut sbox = sbox.evolve(MultiUseContextCallback::from(func)).unwrap();
+
+        let add_to_static = |sbox: &mut MultiUseSandbox| {
+            sbox.call_guest_function_by_name(
+                "AddToStatic",
+                ReturnType::Int,
+                Some(vec![ParameterValue::Int(2)]),
+            )
+            .unwrap()
+        };
+
+        // forks start from the state the template was evolved to, and every
+        // call resets them to it without affecting the template
+        let mut fork1 = sbox.fork().unwrap();
+        let mut fork2 = sbox.fork().unwrap();
+        assert_eq!(fork1.guest_functions(), sbox.guest_functions());
+        for _ in 0..2 {
+            assert_eq!(add_to_static(&mut fork1), ReturnValue::Int(7));
+            assert_eq!(add_to_static(&mut fork2), ReturnValue::Int(7));
+        }
+        let res: String = fork1.call("Echo", ("hello".to_string(),)).unwrap();
+        assert_eq!(res, "hello");
+        assert!(fork1.check_stack_guard().unwrap());
+        assert_eq!(add_to_static(&mut sbox), ReturnValue::Int(7));
+
+        // forks can be forked, and outlive the sandbox they were forked from
+        let mut fork3 = fork1.fork().unwrap();
+        drop(sbox);
+        drop(fork1);
+        let res = fork3
+            .call_guest_function_by_name("GetStatic", ReturnType::Int, None)
+            .unwrap();
+        assert_eq!(res, ReturnValue::Int(5));
+        assert_eq!(add_to_static(&mut fork3), ReturnValue::Int(7));
+        assert_eq!(add_to_static(&mut fork2), ReturnValue::Int(7));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn forks_call_shared_host_function_concurrently() {
+        // a host function slow enough that calls from different forks overlap
+        let calls = Arc::new(AtomicUsize::new(0));
+        let writer = {
+            let calls = calls.clone();
+            move |msg: String| -> crate::Result<i32> {
+                let start = Instant::now();
+                while start.elapsed() < Duration::from_millis(10) {}
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(msg.len() as i32)
+            }
+        };
+        let writer = Arc::new(Mutex::new(writer));
+        let sbox: MultiUseSandbox =
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_max_initialization_time(Duration::from_secs(10))
+                .with_max_execution_time(Duration::from_secs(10))
+                .with_host_print_fn(&writer)
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap();
+
+        const FORKS: usize = 4;
+        const CALLS_PER_FORK: usize = 3;
+        let barrier = Arc::new(Barrier::new(FORKS));
+        let threads: Vec<_> = (0..FORKS)
+            .map(|_| {
+                let mut fork = sbox.fork().unwrap();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..CALLS_PER_FORK {
+                        let res = fork.call_guest_function_by_name(
+                            "PrintOutput",
+                            ReturnType::Int,
+                            Some(vec![ParameterValue::String("hello".to_string())]),
+                        );
+                        assert_eq!(res.unwrap(), ReturnValue::Int(5));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), FORKS * CALLS_PER_FORK);
+    }
+
+    #[test]
+    fn memory_stats() {
+        let new_sbox = |collect_memory_stats: bool| -> MultiUseSandbox {
+            SandboxBuilder::new(GuestBinary::FilePath(simple_guest_as_string().unwrap()))
+                .with_heap_size(1024 * 1024)
+                .with_stack_size(64 * 1024)
+                .with_collect_memory_stats(collect_memory_stats)
+                .build()
+                .unwrap()
+                .evolve(Noop::default())
+                .unwrap()
+        };
+        let call = |sbox: &mut MultiUseSandbox| {
+            sbox.call_guest_function_by_name(
+                "StackAllocate",
+                ReturnType::Int,
+                Some(vec![ParameterValue::Int(16 * 1024)]),
+            )
+            .unwrap();
+            sbox.call_guest_function_by_name(
+                "CallMalloc",
+                ReturnType::Int,
+                Some(vec![ParameterValue::Int(256 * 1024)]),
+            )
+            .unwrap();
+        };
+
+        let mut sbox = new_sbox(true);
+        let initial = sbox.memory_stats().unwrap();
+        assert_eq!(initial.heap_size, 1024 * 1024);
+        assert_eq!(initial.stack_size, 64 * 1024);
+        assert!(initial.total_mapped_size > initial.heap_size + initial.stack_size);
+        // initialising the guest registered its functions on the heap
+        assert!(initial.heap_used > 0);
+        assert!(initial.heap_high_water_mark >= initial.heap_used);
+        assert!(initial.heap_free_fragments > 0);
+        assert!(initial.stack_peak_depth > 0);
+
+        call(&mut sbox);
+
+        // the peaks are kept after the calls' state has been restored
+        let stats = sbox.memory_stats().unwrap();
+        assert!(stats.stack_peak_depth > 16 * 1024);
+        assert!(stats.heap_high_water_mark > initial.heap_used + 256 * 1024);
+        assert_eq!(stats.heap_used, initial.heap_used);
+        assert_eq!(stats.heap_free_fragments, initial.heap_free_fragments);
+        // and each call's statistics are published under the sandbox's id
+        let sandbox_id = sbox.id().to_string();
+        assert_eq!(
+            histogram_vec_sample_count!(&GuestStackPeakDepthBytes, &[sandbox_id.as_str()]),
+            2
+        );
+
+        // without collecting them, the calls' peaks are left out
+        let mut sbox = new_sbox(false);
+        let initial = sbox.memory_stats().unwrap();
+        call(&mut sbox);
+        assert_eq!(sbox.memory_stats().unwrap(), initial);
+        let sandbox_id = sbox.id().to_string();
+        assert_eq!(
+            histogram_vec_sample_count!(&GuestStackPeakDepthBytes, &[sandbox_id.as_str()]),
+            0
+        );
+    }
 }
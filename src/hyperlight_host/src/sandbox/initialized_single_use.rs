@@ -21,6 +21,7 @@ use tracing::{instrument, Span};
 
 use super::{MemMgrWrapper, WrapperGetter};
 use crate::func::call_ctx::SingleUseGuestCallContext;
+use crate::func::CallTimeout;
 use crate::hypervisor::hypervisor_handler::HypervisorHandler;
 use crate::mem::shared_mem::HostSharedMemory;
 use crate::sandbox_state::sandbox::Sandbox;
@@ -173,6 +174,24 @@ impl SingleUseSandbox {
     ) -> Result<ReturnValue> {
         self.new_call_context().call(name, ret, args)
     }
+
+    /// Convenience for the following:
+    ///
+    /// `self.new_call_context().call_with_timeout(name, ret, args, timeout)`
+    ///
+    /// The guest function is cancelled once `timeout` is reached instead of
+    /// once it has run for the sandbox's `max_execution_time`.
+    #[instrument(err(Debug), skip(self, args, timeout), parent = Span::current())]
+    pub fn call_guest_function_by_name_with_timeout(
+        self,
+        name: &str,
+        ret: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        timeout: impl Into<CallTimeout>,
+    ) -> Result<ReturnValue> {
+        self.new_call_context()
+            .call_with_timeout(name, ret, args, timeout.into())
+    }
 }
 
 impl WrapperGetter for SingleUseSandbox {
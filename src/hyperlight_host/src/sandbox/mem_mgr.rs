@@ -14,6 +14,8 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::sync::{Arc, Mutex, MutexGuard};
+
 use tracing::{instrument, Span};
 
 use crate::mem::layout::SandboxMemoryLayout;
@@ -21,7 +23,10 @@ use crate::mem::mgr::{SandboxMemoryManager, STACK_COOKIE_LEN};
 use crate::mem::shared_mem::{
     ExclusiveSharedMemory, GuestSharedMemory, HostSharedMemory, SharedMemory,
 };
-use crate::Result;
+use crate::sandbox::memory_stats::MemoryStatsRecorder;
+use crate::sandbox::streams::GuestStreams;
+use crate::sandbox::MemoryStats;
+use crate::{new_error, Result};
 
 /// StackCookie
 pub type StackCookie = [u8; STACK_COOKIE_LEN];
@@ -29,12 +34,26 @@ pub type StackCookie = [u8; STACK_COOKIE_LEN];
 /// A container with methods for accessing `SandboxMemoryManager` and other
 /// related objects
 #[derive(Clone)]
-pub(crate) struct MemMgrWrapper<S>(SandboxMemoryManager<S>, StackCookie);
+pub(crate) struct MemMgrWrapper<S>(
+    SandboxMemoryManager<S>,
+    StackCookie,
+    Arc<Mutex<GuestStreams>>,
+    Arc<MemoryStatsRecorder>,
+);
 
 impl<S: SharedMemory> MemMgrWrapper<S> {
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
-    pub(super) fn new(mgr: SandboxMemoryManager<S>, stack_cookie: StackCookie) -> Self {
-        Self(mgr, stack_cookie)
+    pub(super) fn new(
+        mgr: SandboxMemoryManager<S>,
+        stack_cookie: StackCookie,
+        collect_memory_stats: bool,
+    ) -> Self {
+        Self(
+            mgr,
+            stack_cookie,
+            Arc::default(),
+            Arc::new(MemoryStatsRecorder::new(collect_memory_stats)),
+        )
     }
 
     #[instrument(skip_all, parent = Span::current(), level= "Trace")]
@@ -51,6 +70,27 @@ impl<S: SharedMemory> MemMgrWrapper<S> {
     pub(super) fn get_stack_cookie(&self) -> &StackCookie {
         &self.1
     }
+
+    /// Lock the input and output streams of guest function calls, which are
+    /// shared between all clones of `self`
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn lock_streams(&self) -> Result<MutexGuard<'_, GuestStreams>> {
+        self.2
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))
+    }
+
+    /// The id of the sandbox, which is unique among the sandboxes created
+    /// by this process, and which its metrics are labelled with
+    pub(crate) fn sandbox_id(&self) -> u64 {
+        self.3.sandbox_id()
+    }
+
+    /// Whether the memory statistics of each guest function call are
+    /// recorded by `record_memory_stats`
+    pub(crate) fn collects_memory_stats(&self) -> bool {
+        self.3.collects()
+    }
 }
 
 impl<S: SharedMemory> AsMut<SandboxMemoryManager<S>> for MemMgrWrapper<S> {
@@ -75,7 +115,7 @@ impl MemMgrWrapper<ExclusiveSharedMemory> {
         SandboxMemoryManager<GuestSharedMemory>,
     ) {
         let (hshm, gshm) = self.0.build();
-        (MemMgrWrapper(hshm, self.1), gshm)
+        (MemMgrWrapper(hshm, self.1, self.2, self.3), gshm)
     }
 
     #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
@@ -104,4 +144,25 @@ impl MemMgrWrapper<HostSharedMemory> {
         self.unwrap_mgr()
             .check_stack_guard(*self.get_stack_cookie())
     }
+
+    /// Record how much memory the call into the guest that just finished
+    /// used, before the guest's state is restored, if the sandbox collects
+    /// the memory statistics of each call.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn record_memory_stats(&self) -> Result<()> {
+        if !self.3.collects() {
+            return Ok(());
+        }
+        let call = self.unwrap_mgr().get_memory_stats()?;
+        self.3.record_call(&call)
+    }
+
+    /// Get how much memory the guest is using in its current state, with
+    /// the peaks of all the calls into it recorded by `record_memory_stats`.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn get_memory_stats(&self) -> Result<MemoryStats> {
+        let mut stats = self.unwrap_mgr().get_memory_stats()?;
+        stats.keep_peaks(&*self.3.peaks()?);
+        Ok(stats)
+    }
 }
@@ -0,0 +1,150 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tracing::{instrument, Span};
+
+use crate::sandbox::metrics::SandboxMetric::{
+    GuestHeapFreeFragments, GuestHeapHighWaterMarkBytes, GuestHeapUsedBytes,
+    GuestStackPeakDepthBytes,
+};
+use crate::{histogram_vec_observe, new_error, Result};
+
+/// The id of the next sandbox to be created
+static NEXT_SANDBOX_ID: AtomicU64 = AtomicU64::new(0);
+
+/// How much of its memory the guest in a sandbox is using, as returned by
+/// `MultiUseSandbox::memory_stats`.
+///
+/// The heap statistics are reported by the Hyperlight guest library, and
+/// are all zero if the guest was built against a version of it that
+/// doesn't report them.
+///
+/// Unless the sandbox was configured with
+/// `SandboxConfiguration::set_collect_memory_stats`, the statistics are
+/// only those of the state that guest function calls start from, which
+/// leaves out the peaks reached by the calls themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The number of bytes allocated from the guest's heap in the state
+    /// that guest function calls start from
+    pub heap_used: u64,
+    /// The most bytes that have been allocated from the guest's heap at
+    /// once, by the guest's initialisation or any guest function call whose
+    /// statistics were collected
+    pub heap_high_water_mark: u64,
+    /// The number of free blocks the guest's heap is split into in the state
+    /// that guest function calls start from. The guest only counts them at
+    /// the end of its initialisation, and at the end of guest function calls
+    /// whose statistics are collected, as counting them takes time.
+    pub heap_free_fragments: u64,
+    /// The size of the guest's heap, including the memory it has grown
+    /// into
+    pub heap_size: u64,
+    /// The most of the guest's stack that has been used at once, by the
+    /// guest's initialisation or any guest function call whose statistics
+    /// were collected. This is found by looking for the part of the stack
+    /// that still holds the pattern it was filled with before the guest
+    /// first ran, so it may be a few bytes short.
+    pub stack_peak_depth: u64,
+    /// The size of the guest's stack
+    pub stack_size: u64,
+    /// The total size of the sandbox's memory that is mapped into the
+    /// guest, which excludes the memory reserved for the heap to grow into
+    /// that it hasn't grown into
+    pub total_mapped_size: u64,
+}
+
+impl MemoryStats {
+    /// Raise the peaks in `self` to those in `other` where they're higher
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn keep_peaks(&mut self, other: &MemoryStats) {
+        self.heap_high_water_mark = self.heap_high_water_mark.max(other.heap_high_water_mark);
+        self.stack_peak_depth = self.stack_peak_depth.max(other.stack_peak_depth);
+    }
+}
+
+/// Keeps the peaks of the memory statistics of the guest function calls
+/// made with a sandbox, if it collects them, and publishes each call's
+/// statistics as metrics labelled with the sandbox's id.
+#[derive(Debug)]
+pub(crate) struct MemoryStatsRecorder {
+    /// The id of the sandbox, which is unique among the sandboxes created
+    /// by this process
+    sandbox_id: u64,
+    /// Whether the statistics of each guest function call are recorded,
+    /// as set with `SandboxConfiguration::set_collect_memory_stats`
+    collect: bool,
+    /// The peaks of the guest function calls recorded so far
+    peaks: Mutex<MemoryStats>,
+}
+
+impl MemoryStatsRecorder {
+    /// Create a recorder for a new sandbox, giving it the next id
+    #[instrument(skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn new(collect: bool) -> Self {
+        Self {
+            sandbox_id: NEXT_SANDBOX_ID.fetch_add(1, Ordering::Relaxed),
+            collect,
+            peaks: Mutex::default(),
+        }
+    }
+
+    /// The id of the sandbox, which its metrics are labelled with
+    pub(crate) fn sandbox_id(&self) -> u64 {
+        self.sandbox_id
+    }
+
+    /// Whether the statistics of each guest function call are recorded
+    pub(crate) fn collects(&self) -> bool {
+        self.collect
+    }
+
+    /// Record the statistics taken at the end of a call into the guest,
+    /// before its state was restored, and publish them as metrics.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level= "Trace")]
+    pub(crate) fn record_call(&self, call: &MemoryStats) -> Result<()> {
+        let sandbox_id = self.sandbox_id.to_string();
+        let labels = [sandbox_id.as_str()];
+        histogram_vec_observe!(&GuestHeapUsedBytes, &labels, call.heap_used as f64);
+        histogram_vec_observe!(
+            &GuestHeapHighWaterMarkBytes,
+            &labels,
+            call.heap_high_water_mark as f64
+        );
+        histogram_vec_observe!(
+            &GuestHeapFreeFragments,
+            &labels,
+            call.heap_free_fragments as f64
+        );
+        histogram_vec_observe!(
+            &GuestStackPeakDepthBytes,
+            &labels,
+            call.stack_peak_depth as f64
+        );
+        self.peaks()?.keep_peaks(call);
+        Ok(())
+    }
+
+    /// Lock the peaks of the guest function calls recorded so far
+    pub(crate) fn peaks(&self) -> Result<std::sync::MutexGuard<'_, MemoryStats>> {
+        self.peaks
+            .try_lock()
+            .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))
+    }
+}
@@ -33,6 +33,20 @@ static INIT_METRICS: Once = Once::new();
 // This contains a hashmap of all the metrics using metric names defined below as keys
 static METRICS: OnceCell<HashMap<&'static str, HyperlightMetric>> = OnceCell::new();
 
+// The buckets of the metrics of how many bytes of the guest heap are used
+const HEAP_BYTES_BUCKETS: &[f64] = &[
+    4096.0,
+    16384.0,
+    65536.0,
+    262144.0,
+    1048576.0,
+    4194304.0,
+    16777216.0,
+    67108864.0,
+    268435456.0,
+    1073741824.0,
+];
+
 // This is the definition of all the metrics used by the sandbox module
 static SANDBOX_METRIC_DEFINITIONS: &[HyperlightMetricDefinition] = &[
     HyperlightMetricDefinition {
@@ -42,6 +56,49 @@ static SANDBOX_METRIC_DEFINITIONS: &[HyperlightMetricDefinition] = &[
         labels: &["error_code", "error_message"],
         buckets: &[],
     },
+    HyperlightMetricDefinition {
+        name: "restored_snapshot_pages",
+        help: "Number of memory pages restored from a snapshot after a guest function call",
+        metric_type: HyperlightMetricType::Histogram,
+        labels: &[],
+        buckets: &[
+            0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0, 512.0, 1024.0, 2048.0, 4096.0,
+            8192.0, 16384.0, 32768.0, 65536.0,
+        ],
+    },
+    HyperlightMetricDefinition {
+        name: "guest_heap_used_bytes",
+        help: "The bytes allocated from the guest heap at the end of a guest function call",
+        metric_type: HyperlightMetricType::HistogramVec,
+        labels: &["sandbox"],
+        buckets: HEAP_BYTES_BUCKETS,
+    },
+    HyperlightMetricDefinition {
+        name: "guest_heap_high_water_mark_bytes",
+        help: "The most bytes allocated at once from the guest heap during a guest function call",
+        metric_type: HyperlightMetricType::HistogramVec,
+        labels: &["sandbox"],
+        buckets: HEAP_BYTES_BUCKETS,
+    },
+    HyperlightMetricDefinition {
+        name: "guest_heap_free_fragments",
+        help: "The number of free blocks the guest heap is split into at the end of a guest function call",
+        metric_type: HyperlightMetricType::HistogramVec,
+        labels: &["sandbox"],
+        buckets: &[
+            1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0, 512.0, 1024.0, 4096.0, 16384.0,
+        ],
+    },
+    HyperlightMetricDefinition {
+        name: "guest_stack_peak_depth_bytes",
+        help: "The most bytes of the guest stack used at once during a guest function call",
+        metric_type: HyperlightMetricType::HistogramVec,
+        labels: &["sandbox"],
+        buckets: &[
+            1024.0, 2048.0, 4096.0, 8192.0, 16384.0, 32768.0, 65536.0, 131072.0, 262144.0,
+            524288.0, 1048576.0,
+        ],
+    },
     #[cfg(feature = "function_call_metrics")]
     HyperlightMetricDefinition {
         name: "guest_function_call_duration_microseconds",
@@ -91,6 +148,11 @@ static SANDBOX_METRIC_DEFINITIONS: &[HyperlightMetricDefinition] = &[
 #[strum(serialize_all = "snake_case")]
 pub(crate) enum SandboxMetric {
     GuestErrorCount,
+    RestoredSnapshotPages,
+    GuestHeapUsedBytes,
+    GuestHeapHighWaterMarkBytes,
+    GuestHeapFreeFragments,
+    GuestStackPeakDepthBytes,
     #[cfg(feature = "function_call_metrics")]
     GuestFunctionCallDurationMicroseconds,
     #[cfg(feature = "function_call_metrics")]
@@ -123,9 +185,10 @@ mod tests {
     use crate::metrics::get_metrics_registry;
     use crate::metrics::tests::HyperlightMetricEnumTest;
     use crate::{
-        histogram_vec_observe, histogram_vec_sample_count, histogram_vec_sample_sum,
-        int_counter_vec_get, int_counter_vec_inc, int_counter_vec_inc_by, int_counter_vec_reset,
-        int_gauge_add, int_gauge_dec, int_gauge_get, int_gauge_inc, int_gauge_set, int_gauge_sub,
+        histogram_observe, histogram_sample_count, histogram_sample_sum, histogram_vec_observe,
+        histogram_vec_sample_count, histogram_vec_sample_sum, int_counter_vec_get,
+        int_counter_vec_inc, int_counter_vec_inc_by, int_counter_vec_reset, int_gauge_add,
+        int_gauge_dec, int_gauge_get, int_gauge_inc, int_gauge_set, int_gauge_sub,
     };
 
     impl HyperlightMetricEnumTest<SandboxMetric> for SandboxMetric {
@@ -225,8 +288,23 @@ mod tests {
                         assert!(histogram.get_sample_sum(&label_vals).is_ok());
                         assert_eq!(histogram.get_sample_sum(&label_vals).unwrap(), 1.0);
                     }
+                    HyperlightMetric::Histogram(histogram) => {
+                        let histogram = <super::SandboxMetric as HyperlightMetricEnumTest<
+                            SandboxMetric,
+                        >>::get_histogram_metric(
+                            histogram.name
+                        );
+                        assert!(histogram.is_ok());
+                        let histogram = histogram.unwrap();
+                        histogram_observe!(&sandbox_metric, 1.0);
+                        let result = histogram_sample_sum!(&sandbox_metric);
+                        assert_eq!(result, 1.0);
+                        assert_eq!(histogram.get_sample_count(), 1);
+                        let result = histogram_sample_count!(&sandbox_metric);
+                        assert_eq!(result, 1);
+                    }
                     _ => {
-                        panic!("metric is not an IntGauge,IntCounterVec or HistorgamVec");
+                        panic!("metric is not an IntGauge,IntCounterVec,Histogram or HistorgamVec");
                     }
                 },
                 Err(e) => {
@@ -252,8 +330,8 @@ mod tests {
         let registry = get_metrics_registry();
         let result = registry.gather();
         #[cfg(feature = "function_call_metrics")]
-        assert_eq!(result.len(), 3);
+        assert_eq!(result.len(), 8);
         #[cfg(not(feature = "function_call_metrics"))]
-        assert_eq!(result.len(), 1);
+        assert_eq!(result.len(), 6);
     }
 }
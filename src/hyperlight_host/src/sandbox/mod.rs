@@ -14,10 +14,20 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+/// A builder for configuring and validating `UninitializedSandbox`es.
+pub mod builder;
 /// Configuration needed to establish a sandbox.
 pub mod config;
+/// The syscalls that host functions may be allowed to make, in addition to
+/// those that all host functions are allowed to make
+pub mod extra_allowed_syscall;
+/// The files that host functions may be restricted to accessing
+pub mod filesystem_access;
+/// Running host functions in helper processes
+#[cfg(target_os = "linux")]
+pub(crate) mod helper_process;
 /// Functionality for reading, but not modifying host functions
-mod host_funcs;
+pub(crate) mod host_funcs;
 /// Functionality for dealing with `Sandbox`es that contain Hypervisors
 pub(crate) mod hypervisor;
 /// Functionality for dealing with initialized sandboxes that can
@@ -38,9 +48,16 @@ pub(crate) mod mem_access;
 /// Functionality for interacting with a sandbox's internally-stored
 /// `SandboxMemoryManager`
 pub(crate) mod mem_mgr;
+/// Statistics about how much of its memory a sandbox's guest is using
+pub mod memory_stats;
 pub(crate) mod outb;
+/// A pool of pre-warmed `MultiUseSandbox`es that can be checked out and
+/// back in
+pub mod pool;
 /// Options for configuring a sandbox
 mod run_options;
+/// The input and output streams of guest function calls
+pub(crate) mod streams;
 /// Functionality for creating uninitialized sandboxes, manipulating them,
 /// and converting them to initialized sandboxes.
 pub mod uninitialized;
@@ -53,12 +70,26 @@ pub(crate) mod metrics;
 
 use std::collections::HashMap;
 
+/// Re-export for `SandboxBuilder` type
+pub use builder::SandboxBuilder;
+/// Re-export for `ExecutionTimeBudget` type
+pub use config::ExecutionTimeBudget;
 /// Re-export for `SandboxConfiguration` type
 pub use config::SandboxConfiguration;
+/// Re-export for `ExtraAllowedSyscall` type
+pub use extra_allowed_syscall::ExtraAllowedSyscall;
+/// Re-export for `FilesystemAccess` type
+pub use filesystem_access::FilesystemAccess;
 /// Re-export for the `MultiUseSandbox` type
 pub use initialized_multi_use::MultiUseSandbox;
 /// Re-export for `SingleUseSandbox` type
 pub use initialized_single_use::SingleUseSandbox;
+/// Re-export for `MemoryStats` type
+pub use memory_stats::MemoryStats;
+/// Re-export for `PooledSandbox` type
+pub use pool::PooledSandbox;
+/// Re-export for `SandboxPool` type
+pub use pool::SandboxPool;
 /// Re-export for `SandboxRunOptions` type
 pub use run_options::SandboxRunOptions;
 use tracing::{instrument, Span};
@@ -73,6 +104,9 @@ use crate::hypervisor::hypervisor_handler::HypervisorHandler;
 #[cfg(target_os = "windows")]
 use crate::hypervisor::windows_hypervisor_platform;
 use crate::mem::shared_mem::HostSharedMemory;
+/// Re-export for `AuditedSyscall` type
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+pub use crate::seccomp::audit::AuditedSyscall;
 
 // In case its not obvious why there are separate is_supported_platform and is_hypervisor_present functions its because
 // Hyperlight is designed to be able to run on a host that doesn't have a hypervisor.
@@ -91,16 +125,20 @@ pub fn is_supported_platform() -> bool {
     true
 }
 
-/// Alias for the type of extra allowed syscalls.
-pub type ExtraAllowedSyscall = i64;
-
-/// A `HashMap` to map function names to `HyperlightFunction`s and their extra allowed syscalls.
+/// A `HashMap` to map function names to `HyperlightFunction`s, their extra allowed syscalls and
+/// the files they may access.
 ///
-/// Note: you cannot add extra syscalls on Windows, but the field is still present to avoid a funky
-/// conditional compilation setup. This isn't a big deal as this struct isn't public facing.
+/// Note: you cannot add extra syscalls or restrict the files a function may access on Windows, but
+/// the fields are still present to avoid a funky conditional compilation setup. This isn't a big
+/// deal as this struct isn't public facing.
 #[derive(Clone, Default)]
-pub(super) struct FunctionsMap(
-    HashMap<String, (HyperlightFunction, Option<Vec<ExtraAllowedSyscall>>)>,
+pub(super) struct FunctionsMap(HashMap<String, HostFunctionEntry>);
+
+/// A host function, the extra syscalls it is allowed to make, and the only files it may access.
+type HostFunctionEntry = (
+    HyperlightFunction,
+    Option<Vec<ExtraAllowedSyscall>>,
+    Option<FilesystemAccess>,
 );
 
 impl FunctionsMap {
@@ -110,15 +148,14 @@ impl FunctionsMap {
         key: String,
         value: HyperlightFunction,
         extra_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+        filesystem_access: Option<FilesystemAccess>,
     ) {
-        self.0.insert(key, (value, extra_syscalls));
+        self.0
+            .insert(key, (value, extra_syscalls, filesystem_access));
     }
 
     /// Get the value associated with the given key, if it exists.
-    pub(super) fn get(
-        &self,
-        key: &str,
-    ) -> Option<&(HyperlightFunction, Option<Vec<ExtraAllowedSyscall>>)> {
+    pub(super) fn get(&self, key: &str) -> Option<&HostFunctionEntry> {
         self.0.get(key)
     }
 
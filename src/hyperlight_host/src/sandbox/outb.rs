@@ -25,7 +25,10 @@ use tracing_log::format_trace;
 
 use super::host_funcs::HostFuncsWrapper;
 use super::mem_mgr::MemMgrWrapper;
+use crate::error::HyperlightHostError;
+use crate::func::nested_call::HostCallGuard;
 use crate::hypervisor::handlers::{OutBHandler, OutBHandlerFunction, OutBHandlerWrapper};
+use crate::hypervisor::hypervisor_handler::HostFunctionTimer;
 use crate::mem::mgr::SandboxMemoryManager;
 use crate::mem::shared_mem::HostSharedMemory;
 use crate::{new_error, HyperlightError, Result};
@@ -34,6 +37,9 @@ pub(super) enum OutBAction {
     Log,
     CallFunction,
     Abort,
+    ReadStream,
+    WriteStream,
+    GrowHeap,
 }
 
 impl TryFrom<u16> for OutBAction {
@@ -44,11 +50,39 @@ impl TryFrom<u16> for OutBAction {
             99 => Ok(OutBAction::Log),
             101 => Ok(OutBAction::CallFunction),
             102 => Ok(OutBAction::Abort),
+            103 => Ok(OutBAction::ReadStream),
+            104 => Ok(OutBAction::WriteStream),
+            105 => Ok(OutBAction::GrowHeap),
             _ => Err(new_error!("Invalid OutB value: {}", val)),
         }
     }
 }
 
+/// Write the panic of the host function `name` into the guest's error and
+/// host exception buffers, where the guest checks for it once the call
+/// returns.
+fn report_host_function_panic(
+    mem_mgr: &mut MemMgrWrapper<HostSharedMemory>,
+    name: String,
+    message: String,
+) -> Result<()> {
+    let err = HyperlightError::HostFunctionPanicked(name.clone(), message);
+    log::error!("{}", err);
+    let host_err = HyperlightHostError {
+        message: err.to_string(),
+        source: name,
+    };
+    let host_err_json = serde_json::to_string(&host_err)?;
+    if let Err(e) = mem_mgr
+        .as_mut()
+        .write_outb_error(err.to_string().as_bytes(), host_err_json.as_bytes())
+    {
+        log::error!("Error reporting host function panic to the guest: {}", e);
+        return Err(err);
+    }
+    Ok(())
+}
+
 #[instrument(err(Debug), skip_all, parent = Span::current(), level="Trace")]
 pub(super) fn outb_log(mgr: &mut SandboxMemoryManager<HostSharedMemory>) -> Result<()> {
     // This code will create either a logging record or a tracing record for the GuestLogData depending on if the host has set up a tracing subscriber.
@@ -116,6 +150,7 @@ pub(super) fn outb_log(mgr: &mut SandboxMemoryManager<HostSharedMemory>) -> Resu
 fn handle_outb_impl(
     mem_mgr: &mut MemMgrWrapper<HostSharedMemory>,
     host_funcs: Arc<Mutex<HostFuncsWrapper>>,
+    host_function_timer: &HostFunctionTimer,
     port: u16,
     byte: u64,
 ) -> Result<()> {
@@ -125,10 +160,32 @@ fn handle_outb_impl(
             let call = mem_mgr.as_mut().get_host_function_call()?; // pop output buffer
             let name = call.function_name.clone();
             let args: Vec<ParameterValue> = call.parameters.unwrap_or(vec![]);
-            let res = host_funcs
+            // the lock isn't held during the call, as the host function may
+            // call back into the guest, which may call host functions again
+            let funcs = host_funcs
                 .try_lock()
                 .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
-                .call_host_function(&name, args)?;
+                .clone();
+            let res = {
+                let _host_call = HostCallGuard::enter(
+                    mem_mgr.clone(),
+                    host_funcs.clone(),
+                    host_function_timer.clone(),
+                    &name,
+                )?;
+                host_function_timer.start_host_call();
+                let res = funcs.call_host_function(&name, args);
+                host_function_timer.stop_host_call();
+                res
+            };
+            let res = match res {
+                Err(HyperlightError::HostFunctionPanicked(name, message)) => {
+                    // The guest is told the host function failed, rather than
+                    // the panic taking down the sandbox
+                    return report_host_function_panic(mem_mgr, name, message);
+                }
+                res => res?,
+            };
             mem_mgr
                 .as_mut()
                 .write_response_from_host_method_call(&res)?; // push input buffers
@@ -153,22 +210,35 @@ fn handle_outb_impl(
                 )),
             }
         }
+        OutBAction::ReadStream => {
+            let max_len = mem_mgr.as_ref().get_input_stream_chunk_capacity()?;
+            let chunk = mem_mgr.lock_streams()?.read_chunk(max_len)?;
+            mem_mgr.as_mut().write_input_stream_chunk(&chunk) // push input buffers
+        }
+        OutBAction::WriteStream => {
+            let chunk = mem_mgr.as_mut().read_output_stream_chunk()?; // pop output buffer
+            mem_mgr.lock_streams()?.write_chunk(&chunk)
+        }
+        OutBAction::GrowHeap => mem_mgr.as_mut().grow_heap(),
     }
 }
 
 /// Given a `MemMgrWrapper` and ` HostFuncsWrapper` -- both passed by _value_
 ///  -- return an `OutBHandlerWrapper` wrapping the core OUTB handler logic.
+/// The time spent in host functions is recorded with `host_function_timer`.
 ///
 /// TODO: pass at least the `host_funcs_wrapper` param by reference.
 #[instrument(skip_all, parent = Span::current(), level= "Trace")]
 pub(crate) fn outb_handler_wrapper(
     mut mem_mgr_wrapper: MemMgrWrapper<HostSharedMemory>,
     host_funcs_wrapper: Arc<Mutex<HostFuncsWrapper>>,
+    host_function_timer: HostFunctionTimer,
 ) -> OutBHandlerWrapper {
     let outb_func: OutBHandlerFunction = Box::new(move |port, payload| {
         handle_outb_impl(
             &mut mem_mgr_wrapper,
             host_funcs_wrapper.clone(),
+            &host_function_timer,
             port,
             payload,
         )
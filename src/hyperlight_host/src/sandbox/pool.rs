@@ -0,0 +1,633 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use hyperlight_common::flatbuffer_wrappers::function_types::{
+    ParameterValue, ReturnType, ReturnValue,
+};
+use tracing::{instrument, Span};
+
+use crate::func::{CallTimeout, SupportedParameterTuple, SupportedReturnType};
+use crate::hypervisor::InterruptHandle;
+use crate::sandbox_state::sandbox::EvolvableSandbox;
+use crate::sandbox_state::transition::Noop;
+use crate::{log_then_return, HyperlightError, MultiUseSandbox, Result, UninitializedSandbox};
+
+/// How long the refill thread waits before retrying after it failed to
+/// create a sandbox.
+const REFILL_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+type SandboxFactory = dyn Fn() -> Result<UninitializedSandbox> + Send + Sync;
+
+/// A pool of pre-warmed `MultiUseSandbox`es.
+///
+/// Every sandbox in the pool is created by the same factory function (which
+/// should create an `UninitializedSandbox` for one guest binary and
+/// configuration, and register any host functions it needs) and then evolved
+/// with `Noop`, so that checking out a sandbox doesn't pay the cost of
+/// loading the guest binary and initialising the vCPU.
+///
+/// The pool keeps at least `min_size` idle sandboxes ready, topping itself
+/// back up on a background thread, and never holds more than `max_size`
+/// sandboxes in total (idle and checked out).
+///
+/// Sandboxes are checked out as a `PooledSandbox` guard, and are checked
+/// back in when the guard is dropped. Checking in restores the sandbox's
+/// state, so the next user sees a clean sandbox. If a call made through the
+/// guard fails with a fatal error (such as the guest timing out, aborting
+/// or overflowing its stack), the sandbox is evicted instead of being
+/// returned to the pool, and the background thread replaces it.
+///
+/// ```no_run
+/// use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterValue, ReturnType};
+/// use hyperlight_host::sandbox::SandboxPool;
+/// use hyperlight_host::{GuestBinary, UninitializedSandbox};
+///
+/// let pool = SandboxPool::new(2, 8, || {
+///     UninitializedSandbox::new(
+///         GuestBinary::FilePath("some_guest_binary".to_string()),
+///         None,
+///         None,
+///         None,
+///     )
+/// })
+/// .unwrap();
+///
+/// let mut sbox = pool.checkout().unwrap();
+/// let res = sbox.call_guest_function_by_name(
+///     "SomeGuestFunc",
+///     ReturnType::Int,
+///     Some(vec![ParameterValue::Int(1)]),
+/// );
+/// // `sbox` is checked back into the pool when it goes out of scope
+/// ```
+pub struct SandboxPool {
+    inner: Arc<PoolInner>,
+    refill_thread: Option<JoinHandle<()>>,
+}
+
+struct PoolInner {
+    factory: Box<SandboxFactory>,
+    min_size: usize,
+    max_size: usize,
+    state: Mutex<PoolState>,
+    /// Notified when a sandbox is returned to `idle`, or `total` shrinks
+    available: Condvar,
+    /// Notified when the pool may need refilling, or is shutting down
+    refill: Condvar,
+}
+
+struct PoolState {
+    idle: Vec<MultiUseSandbox>,
+    /// The number of sandboxes that are idle, checked out, or being created
+    total: usize,
+    shutdown: bool,
+}
+
+impl PoolInner {
+    fn lock_state(&self) -> Result<MutexGuard<'_, PoolState>> {
+        Ok(self.state.lock()?)
+    }
+
+    fn create_sandbox(&self) -> Result<MultiUseSandbox> {
+        let u_sbox = (self.factory)()?;
+        u_sbox.evolve(Noop::default())
+    }
+
+    /// Create a sandbox for a slot that has already been counted in
+    /// `total`, giving the slot back if creation fails.
+    fn create_sandbox_for_reserved_slot(&self) -> Result<MultiUseSandbox> {
+        self.create_sandbox().inspect_err(|_| {
+            if let Ok(mut state) = self.lock_state() {
+                state.total -= 1;
+            }
+            self.available.notify_one();
+            self.refill.notify_one();
+        })
+    }
+
+    fn needs_refill(&self, state: &PoolState) -> bool {
+        state.idle.len() < self.min_size && state.total < self.max_size
+    }
+
+    fn checkin(&self, mut sandbox: MultiUseSandbox, evict: bool) {
+        let sandbox = if evict {
+            log::info!("Evicting sandbox from pool");
+            None
+        } else {
+            match sandbox.restore_state() {
+                Ok(()) => Some(sandbox),
+                Err(e) => {
+                    log::error!(
+                        "Evicting sandbox from pool as its state could not be restored: {:?}",
+                        e
+                    );
+                    None
+                }
+            }
+        };
+
+        let mut state = match self.lock_state() {
+            Ok(state) => state,
+            Err(e) => {
+                log::error!("Failed to check sandbox into pool: {:?}", e);
+                return;
+            }
+        };
+        match sandbox {
+            Some(sandbox) => state.idle.push(sandbox),
+            None => {
+                state.total -= 1;
+                self.refill.notify_one();
+            }
+        }
+        drop(state);
+        self.available.notify_one();
+    }
+
+    fn refill_loop(&self) {
+        loop {
+            {
+                let Ok(mut state) = self.lock_state() else {
+                    return;
+                };
+                while !state.shutdown && !self.needs_refill(&state) {
+                    state = match self.refill.wait(state) {
+                        Ok(state) => state,
+                        Err(_) => return,
+                    };
+                }
+                if state.shutdown {
+                    return;
+                }
+                state.total += 1;
+            }
+
+            match self.create_sandbox() {
+                Ok(sandbox) => {
+                    if let Ok(mut state) = self.lock_state() {
+                        state.idle.push(sandbox);
+                    }
+                    self.available.notify_one();
+                }
+                Err(e) => {
+                    log::error!("Failed to create sandbox to refill pool: {:?}", e);
+                    let Ok(mut state) = self.lock_state() else {
+                        return;
+                    };
+                    state.total -= 1;
+                    self.available.notify_one();
+                    // back off so we don't spin if sandbox creation keeps failing
+                    if self.refill.wait_timeout(state, REFILL_RETRY_DELAY).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl SandboxPool {
+    /// Create a new pool, using `factory` to create each sandbox.
+    ///
+    /// `min_size` sandboxes are created and evolved before this function
+    /// returns, so that the pool is warm from the start. Returns
+    /// `HyperlightError::InvalidSandboxConfiguration` if `max_size` is zero
+    /// or less than `min_size`.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn new<F>(min_size: usize, max_size: usize, factory: F) -> Result<Self>
+    where
+        F: Fn() -> Result<UninitializedSandbox> + Send + Sync + 'static,
+    {
+        let mut problems = Vec::new();
+        if max_size == 0 {
+            problems.push("pool max_size must be greater than zero".to_string());
+        }
+        if min_size > max_size {
+            problems.push(format!(
+                "pool min_size ({}) must not be greater than max_size ({})",
+                min_size, max_size
+            ));
+        }
+        if !problems.is_empty() {
+            let err = HyperlightError::InvalidSandboxConfiguration(problems);
+            log_then_return!(err);
+        }
+
+        let inner = Arc::new(PoolInner {
+            factory: Box::new(factory),
+            min_size,
+            max_size,
+            state: Mutex::new(PoolState {
+                idle: Vec::with_capacity(max_size),
+                total: 0,
+                shutdown: false,
+            }),
+            available: Condvar::new(),
+            refill: Condvar::new(),
+        });
+
+        {
+            let mut idle = Vec::with_capacity(min_size);
+            for _ in 0..min_size {
+                idle.push(inner.create_sandbox()?);
+            }
+            let mut state = inner.lock_state()?;
+            state.total = idle.len();
+            state.idle = idle;
+        }
+
+        let refill_inner = inner.clone();
+        let refill_thread = thread::Builder::new()
+            .name("Sandbox Pool Refill".to_string())
+            .spawn(move || refill_inner.refill_loop())?;
+
+        Ok(Self {
+            inner,
+            refill_thread: Some(refill_thread),
+        })
+    }
+
+    /// Check out a sandbox, waiting as long as necessary for one to become
+    /// available.
+    ///
+    /// If there are no idle sandboxes and the pool is below `max_size`, a
+    /// new sandbox is created on the calling thread.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn checkout(&self) -> Result<PooledSandbox> {
+        self.checkout_until(None)
+    }
+
+    /// Check out a sandbox, waiting at most `timeout` for one to become
+    /// available.
+    ///
+    /// Returns `HyperlightError::SandboxPoolCheckoutTimedOut` if no sandbox
+    /// became available in time.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn checkout_timeout(&self, timeout: Duration) -> Result<PooledSandbox> {
+        self.checkout_until(Some(Instant::now() + timeout))
+    }
+
+    fn checkout_until(&self, deadline: Option<Instant>) -> Result<PooledSandbox> {
+        let mut state = self.inner.lock_state()?;
+        loop {
+            if let Some(sandbox) = state.idle.pop() {
+                if self.inner.needs_refill(&state) {
+                    self.inner.refill.notify_one();
+                }
+                return Ok(PooledSandbox::new(self.inner.clone(), sandbox));
+            }
+
+            if state.total < self.inner.max_size {
+                state.total += 1;
+                drop(state);
+                let sandbox = self.inner.create_sandbox_for_reserved_slot()?;
+                return Ok(PooledSandbox::new(self.inner.clone(), sandbox));
+            }
+
+            state = match deadline {
+                None => self.inner.available.wait(state)?,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        log_then_return!(HyperlightError::SandboxPoolCheckoutTimedOut());
+                    }
+                    self.inner
+                        .available
+                        .wait_timeout(state, deadline - now)
+                        .map_err(|e| HyperlightError::LockAttemptFailed(e.to_string()))?
+                        .0
+                }
+            };
+        }
+    }
+
+    /// Get the number of idle sandboxes in the pool, ready to be checked out.
+    pub fn idle_count(&self) -> usize {
+        self.inner
+            .lock_state()
+            .map(|state| state.idle.len())
+            .unwrap_or(0)
+    }
+
+    /// Get the total number of sandboxes belonging to the pool, whether
+    /// they are idle, checked out, or being created.
+    pub fn size(&self) -> usize {
+        self.inner
+            .lock_state()
+            .map(|state| state.total)
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for SandboxPool {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.inner.lock_state() {
+            state.shutdown = true;
+        }
+        self.inner.refill.notify_all();
+        if let Some(refill_thread) = self.refill_thread.take() {
+            if refill_thread.join().is_err() {
+                log::error!("Sandbox pool refill thread panicked");
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for SandboxPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SandboxPool")
+            .field("min_size", &self.inner.min_size)
+            .field("max_size", &self.inner.max_size)
+            .field("idle", &self.idle_count())
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+/// A `MultiUseSandbox` checked out of a `SandboxPool`.
+///
+/// The sandbox is checked back into the pool when this guard is dropped.
+pub struct PooledSandbox {
+    pool: Arc<PoolInner>,
+    sandbox: Option<MultiUseSandbox>,
+    evict: bool,
+}
+
+impl PooledSandbox {
+    fn new(pool: Arc<PoolInner>, sandbox: MultiUseSandbox) -> Self {
+        Self {
+            pool,
+            sandbox: Some(sandbox),
+            evict: false,
+        }
+    }
+
+    /// Call a guest function by name, with the given return type and
+    /// arguments.
+    ///
+    /// This behaves exactly like
+    /// `MultiUseSandbox::call_guest_function_by_name`, except that if the
+    /// call fails with an error that may have left the sandbox unusable,
+    /// the sandbox is evicted from the pool when this guard is dropped.
+    #[instrument(err(Debug), skip(self, args), parent = Span::current())]
+    pub fn call_guest_function_by_name(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+    ) -> Result<ReturnValue> {
+        self.evict_on_fatal_error(|sandbox| {
+            sandbox.call_guest_function_by_name(func_name, func_ret_type, args)
+        })
+    }
+
+    /// Call a guest function by name, with the given return type and
+    /// arguments, cancelling it once `timeout` is reached.
+    ///
+    /// This behaves exactly like
+    /// `MultiUseSandbox::call_guest_function_by_name_with_timeout`, except
+    /// that fatal errors, including the call timing out, cause the sandbox
+    /// to be evicted, as with `call_guest_function_by_name`.
+    #[instrument(err(Debug), skip(self, args, timeout), parent = Span::current())]
+    pub fn call_guest_function_by_name_with_timeout(
+        &mut self,
+        func_name: &str,
+        func_ret_type: ReturnType,
+        args: Option<Vec<ParameterValue>>,
+        timeout: impl Into<CallTimeout>,
+    ) -> Result<ReturnValue> {
+        self.evict_on_fatal_error(|sandbox| {
+            sandbox.call_guest_function_by_name_with_timeout(
+                func_name,
+                func_ret_type,
+                args,
+                timeout,
+            )
+        })
+    }
+
+    /// Call a guest function by name with the arguments `args`, and convert
+    /// its return value to `R`.
+    ///
+    /// This behaves exactly like `MultiUseSandbox::call`, except that fatal
+    /// errors cause the sandbox to be evicted, as with
+    /// `call_guest_function_by_name`.
+    #[instrument(err(Debug), skip(self, args), parent = Span::current())]
+    pub fn call<Args: SupportedParameterTuple, R: SupportedReturnType<R>>(
+        &mut self,
+        func_name: &str,
+        args: Args,
+    ) -> Result<R> {
+        self.evict_on_fatal_error(|sandbox| sandbox.call(func_name, args))
+    }
+
+    /// Get a handle that can be used to interrupt a guest function call
+    /// running in this sandbox, from any thread. An interrupted call fails
+    /// with a fatal error, so the sandbox is evicted.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.sandbox().interrupt_handle()
+    }
+
+    /// Remove this sandbox from the pool instead of checking it back in,
+    /// for example because the caller knows it is in a bad state. The pool
+    /// creates a replacement in the background if necessary.
+    pub fn evict(mut self) {
+        self.evict = true;
+    }
+
+    fn sandbox(&self) -> &MultiUseSandbox {
+        // `sandbox` is only `None` once the guard is being dropped
+        self.sandbox.as_ref().unwrap()
+    }
+
+    /// Make a call with `f`, marking the sandbox to be evicted if it fails
+    /// with an error that may have left the sandbox unusable.
+    fn evict_on_fatal_error<T>(
+        &mut self,
+        f: impl FnOnce(&mut MultiUseSandbox) -> Result<T>,
+    ) -> Result<T> {
+        // `sandbox` is only `None` once the guard is being dropped
+        let res = f(self.sandbox.as_mut().unwrap());
+        if let Err(e) = &res {
+            if is_fatal(e) {
+                self.evict = true;
+            }
+        }
+        res
+    }
+}
+
+/// Whether `e` may have left a sandbox in a state that `restore_state`
+/// can't recover from.
+fn is_fatal(e: &HyperlightError) -> bool {
+    match e {
+        HyperlightError::ExecutionCanceledByHost()
+        | HyperlightError::GuestAborted(..)
+        | HyperlightError::StackOverflow()
+        | HyperlightError::GuestExecutionHungOnHostFunctionCall()
+        | HyperlightError::HypervisorHandlerCommunicationFailure()
+        | HyperlightError::HypervisorHandlerMessageReceiveTimedout()
+        | HyperlightError::ExecutionAccessViolation(_)
+        | HyperlightError::MemoryAccessViolation(..) => true,
+        #[cfg(all(feature = "seccomp", target_os = "linux"))]
+        HyperlightError::DisallowedSyscall => true,
+        _ => false,
+    }
+}
+
+impl Drop for PooledSandbox {
+    fn drop(&mut self) {
+        if let Some(sandbox) = self.sandbox.take() {
+            self.pool.checkin(sandbox, self.evict);
+        }
+    }
+}
+
+impl std::fmt::Debug for PooledSandbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledSandbox")
+            .field("evict", &self.evict)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use hyperlight_common::flatbuffer_wrappers::function_types::{
+        ParameterValue, ReturnType, ReturnValue,
+    };
+    use hyperlight_testing::simple_guest_as_string;
+
+    use super::SandboxPool;
+    use crate::{GuestBinary, HyperlightError, UninitializedSandbox};
+
+    fn new_pool(min_size: usize, max_size: usize) -> SandboxPool {
+        SandboxPool::new(min_size, max_size, || {
+            UninitializedSandbox::new(
+                GuestBinary::FilePath(simple_guest_as_string().unwrap()),
+                None,
+                None,
+                None,
+            )
+        })
+        .unwrap()
+    }
+
+    fn wait_for(mut cond: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !cond() {
+            assert!(
+                start.elapsed() < Duration::from_secs(30),
+                "timed out waiting for pool"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let res = SandboxPool::new(2, 1, || unreachable!());
+        assert!(matches!(
+            res,
+            Err(HyperlightError::InvalidSandboxConfiguration(_))
+        ));
+        let res = SandboxPool::new(0, 0, || unreachable!());
+        assert!(matches!(
+            res,
+            Err(HyperlightError::InvalidSandboxConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn checkout_and_checkin() {
+        let pool = new_pool(2, 3);
+        assert_eq!(pool.idle_count(), 2);
+
+        let mut sboxes = Vec::new();
+        for _ in 0..3 {
+            let mut sbox = pool.checkout().unwrap();
+            let res = sbox
+                .call_guest_function_by_name(
+                    "Echo",
+                    ReturnType::String,
+                    Some(vec![ParameterValue::String("hello".to_string())]),
+                )
+                .unwrap();
+            assert_eq!(res, ReturnValue::String("hello".to_string()));
+            sboxes.push(sbox);
+        }
+        assert_eq!(pool.size(), 3);
+
+        let res = pool.checkout_timeout(Duration::from_millis(50));
+        assert!(matches!(
+            res,
+            Err(HyperlightError::SandboxPoolCheckoutTimedOut())
+        ));
+
+        sboxes.pop();
+        let _sbox = pool.checkout_timeout(Duration::from_secs(1)).unwrap();
+
+        drop(sboxes);
+        wait_for(|| pool.idle_count() == 2);
+        assert_eq!(pool.size(), 3);
+    }
+
+    #[test]
+    fn fatal_error_evicts_and_refills() {
+        let pool = new_pool(1, 1);
+
+        {
+            let mut sbox = pool.checkout().unwrap();
+            let res = sbox.call_guest_function_by_name("Spin", ReturnType::Int, None);
+            assert!(matches!(
+                res,
+                Err(HyperlightError::ExecutionCanceledByHost())
+            ));
+        }
+
+        // the evicted sandbox is replaced in the background
+        wait_for(|| pool.idle_count() == 1);
+        assert_eq!(pool.size(), 1);
+
+        let mut sbox = pool.checkout().unwrap();
+        let res = sbox
+            .call_guest_function_by_name(
+                "Echo",
+                ReturnType::String,
+                Some(vec![ParameterValue::String("hello".to_string())]),
+            )
+            .unwrap();
+        assert_eq!(res, ReturnValue::String("hello".to_string()));
+    }
+
+    #[test]
+    fn explicit_evict() {
+        let pool = new_pool(0, 1);
+        assert_eq!(pool.idle_count(), 0);
+
+        let sbox = pool.checkout().unwrap();
+        assert_eq!(pool.size(), 1);
+        sbox.evict();
+        assert_eq!(pool.size(), 0);
+        assert_eq!(pool.idle_count(), 0);
+    }
+}
@@ -0,0 +1,142 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::any::{type_name, Any};
+use std::io::{Read, Write};
+
+use hyperlight_common::stream::StreamChunk;
+use tracing::{instrument, Span};
+
+use crate::{new_error, Result};
+
+/// A `Write` that can be turned back into its concrete type once a guest
+/// function call has finished writing to it.
+trait OutputStream: Write + Send {
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<W: Write + Send + 'static> OutputStream for W {
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// The streams that the guest function currently being called reads its
+/// input from and writes its output to.
+///
+/// These are shared between the sandbox, which sets them for the duration of
+/// a call, and the outb handler, which passes them to and from the guest a
+/// chunk at a time.
+#[derive(Default)]
+pub(crate) struct GuestStreams {
+    input: Option<Box<dyn Read + Send>>,
+    output: Option<Box<dyn OutputStream>>,
+}
+
+impl GuestStreams {
+    /// Set the streams for the next guest function call.
+    #[instrument(skip_all, parent = Span::current(), level = "Trace")]
+    pub(crate) fn set<R, W>(&mut self, input: R, output: W)
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        self.input = Some(Box::new(input));
+        self.output = Some(Box::new(output));
+    }
+
+    /// Clear the streams once a guest function call has finished, flushing
+    /// the output stream and giving it back.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    pub(crate) fn take<W: Write + Send + 'static>(&mut self) -> Result<W> {
+        self.input = None;
+        let mut output = self
+            .output
+            .take()
+            .ok_or_else(|| new_error!("No output stream was set"))?;
+        output.flush()?;
+        output
+            .into_any()
+            .downcast::<W>()
+            .map(|output| *output)
+            .map_err(|_| new_error!("Output stream is not a {}", type_name::<W>()))
+    }
+
+    /// Read the next chunk of the input stream, of at most `max_len` bytes.
+    ///
+    /// An empty chunk is returned at the end of the stream, or if no input
+    /// stream was set.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    pub(crate) fn read_chunk(&mut self, max_len: usize) -> Result<StreamChunk> {
+        let Some(input) = self.input.as_mut() else {
+            return Ok(StreamChunk::default());
+        };
+        if max_len == 0 {
+            return Err(new_error!(
+                "Not enough space in the input buffer for a chunk of the input stream"
+            ));
+        }
+        let mut data = vec![0; max_len];
+        let len = loop {
+            match input.read(&mut data) {
+                Ok(len) => break len,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        data.truncate(len);
+        Ok(StreamChunk(data))
+    }
+
+    /// Write a chunk of the output stream.
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    pub(crate) fn write_chunk(&mut self, chunk: &StreamChunk) -> Result<()> {
+        let output = self.output.as_mut().ok_or_else(|| {
+            new_error!("The guest wrote to its output stream, but no output stream was provided")
+        })?;
+        output.write_all(&chunk.0)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use hyperlight_common::stream::StreamChunk;
+
+    use super::GuestStreams;
+
+    #[test]
+    fn read_and_write_chunks() {
+        let mut streams = GuestStreams::default();
+        assert!(streams.read_chunk(4).unwrap().0.is_empty());
+        assert!(streams.write_chunk(&StreamChunk(vec![1])).is_err());
+
+        streams.set(Cursor::new(vec![1, 2, 3, 4, 5]), Vec::<u8>::new());
+        assert_eq!(streams.read_chunk(4).unwrap().0, vec![1, 2, 3, 4]);
+        assert_eq!(streams.read_chunk(4).unwrap().0, vec![5]);
+        assert!(streams.read_chunk(4).unwrap().0.is_empty());
+        streams.write_chunk(&StreamChunk(vec![6, 7])).unwrap();
+        streams.write_chunk(&StreamChunk(vec![8])).unwrap();
+
+        assert!(streams.take::<Cursor<Vec<u8>>>().is_err());
+        streams.set(Cursor::new(vec![]), Vec::<u8>::new());
+        streams.write_chunk(&StreamChunk(vec![6, 7, 8])).unwrap();
+        assert_eq!(streams.take::<Vec<u8>>().unwrap(), vec![6, 7, 8]);
+        assert!(streams.read_chunk(4).unwrap().0.is_empty());
+    }
+}
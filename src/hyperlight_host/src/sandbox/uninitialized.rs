@@ -20,6 +20,8 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
+use log::LevelFilter;
 use tracing::{instrument, Span};
 
 use super::host_funcs::{default_writer_func, HostFuncsWrapper};
@@ -31,7 +33,10 @@ use crate::func::host_functions::HostFunction1;
 use crate::mem::exe::ExeInfo;
 use crate::mem::mgr::{SandboxMemoryManager, STACK_COOKIE_LEN};
 use crate::mem::shared_mem::ExclusiveSharedMemory;
-use crate::sandbox::SandboxConfiguration;
+use crate::mem::snapshot_file::SnapshotFile;
+#[cfg(all(target_os = "linux", feature = "seccomp"))]
+use crate::sandbox::ExtraAllowedSyscall;
+use crate::sandbox::{ExecutionTimeBudget, SandboxConfiguration};
 use crate::sandbox_state::sandbox::EvolvableSandbox;
 use crate::sandbox_state::transition::Noop;
 use crate::{
@@ -54,6 +59,19 @@ pub struct UninitializedSandbox {
     pub(crate) max_initialization_time: Duration,
     pub(crate) max_execution_time: Duration,
     pub(crate) max_wait_for_cancellation: Duration,
+    pub(crate) max_guest_log_level: Option<LevelFilter>,
+    pub(crate) execution_time_budget: ExecutionTimeBudget,
+    pub(crate) max_host_function_time: Duration,
+    pub(crate) max_nested_guest_call_depth: u8,
+    /// Whether a seccomp filter is applied to the Hypervisor Handler thread
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    pub(crate) hypervisor_handler_seccomp_filter: bool,
+    /// Whether the sandbox's memory was restored from a snapshot of an
+    /// initialised sandbox, so the guest must not be initialised again
+    pub(crate) from_snapshot: bool,
+    /// The functions the guest exposes, if the sandbox was restored from a
+    /// snapshot
+    pub(crate) snapshot_guest_functions: Option<GuestFunctionDetails>,
 }
 
 impl crate::sandbox_state::sandbox::UninitializedSandbox for UninitializedSandbox {
@@ -177,13 +195,76 @@ impl UninitializedSandbox {
                 use_loadlib,
             )?;
             let stack_guard = Self::create_stack_guard();
+            mgr.write_stack_pattern()?;
             mgr.set_stack_guard(&stack_guard)?;
-            MemMgrWrapper::new(mgr, stack_guard)
+            MemMgrWrapper::new(mgr, stack_guard, sandbox_cfg.get_collect_memory_stats())
         };
 
         mem_mgr_wrapper.write_memory_layout(run_inprocess)?;
 
-        let host_funcs = Arc::new(Mutex::new(HostFuncsWrapper::default()));
+        Self::from_mem_mgr(
+            mem_mgr_wrapper,
+            sandbox_cfg,
+            run_inprocess,
+            host_print_writer,
+        )
+    }
+
+    /// Create a new sandbox from the snapshot of an initialised sandbox
+    /// in the file at `path`, which was written by
+    /// `MultiUseSandbox::save_snapshot`.
+    ///
+    /// The sandbox has the same configuration as the one the snapshot was
+    /// taken of, and evolving it doesn't initialise the guest again, so its
+    /// guest functions can be called in the state they were in when the
+    /// snapshot was taken. Host functions are not part of the snapshot, so
+    /// any that the guest calls must be registered again before evolving.
+    ///
+    /// Returns `HyperlightError::InvalidSnapshotFile` if the file is not a
+    /// snapshot, is corrupt, or was written by a different version of
+    /// Hyperlight.
+    #[instrument(err(Debug), skip_all, parent = Span::current())]
+    pub fn from_snapshot(
+        path: impl AsRef<Path>,
+        host_print_writer: Option<&dyn HostFunction1<String, i32>>,
+    ) -> Result<Self> {
+        log_build_details();
+
+        let file = SnapshotFile::read(path.as_ref())?;
+        let mgr = SandboxMemoryManager::from_snapshot_file(&file)?;
+        let mem_mgr_wrapper = MemMgrWrapper::new(
+            mgr,
+            file.header.stack_guard,
+            file.header.config.get_collect_memory_stats(),
+        );
+
+        let mut sandbox = Self::from_mem_mgr(
+            mem_mgr_wrapper,
+            file.header.config,
+            false,
+            host_print_writer,
+        )?;
+        sandbox.from_snapshot = true;
+        sandbox.snapshot_guest_functions = file.guest_functions;
+        Ok(sandbox)
+    }
+
+    /// Create a new sandbox with the memory managed by `mem_mgr_wrapper`,
+    /// and register `host_print_writer` (or the default writer) with it
+    #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+    fn from_mem_mgr(
+        mem_mgr_wrapper: MemMgrWrapper<ExclusiveSharedMemory>,
+        sandbox_cfg: SandboxConfiguration,
+        run_inprocess: bool,
+        host_print_writer: Option<&dyn HostFunction1<String, i32>>,
+    ) -> Result<Self> {
+        #[allow(unused_mut)]
+        let mut host_funcs = HostFuncsWrapper::default();
+        #[cfg(all(feature = "seccomp", target_os = "linux"))]
+        if sandbox_cfg.get_seccomp_audit_mode() {
+            host_funcs.enable_seccomp_audit_mode();
+        }
+        let host_funcs = Arc::new(Mutex::new(host_funcs));
 
         let mut sandbox = Self {
             host_funcs,
@@ -196,6 +277,16 @@ impl UninitializedSandbox {
             max_wait_for_cancellation: Duration::from_millis(
                 sandbox_cfg.get_max_wait_for_cancellation() as u64,
             ),
+            max_guest_log_level: None,
+            execution_time_budget: sandbox_cfg.get_execution_time_budget(),
+            max_host_function_time: Duration::from_millis(
+                sandbox_cfg.get_max_host_function_time() as u64
+            ),
+            max_nested_guest_call_depth: sandbox_cfg.get_max_nested_guest_call_depth(),
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            hypervisor_handler_seccomp_filter: sandbox_cfg.get_hypervisor_handler_seccomp_filter(),
+            from_snapshot: false,
+            snapshot_guest_functions: None,
         };
 
         // TODO: These only here to accommodate some writer functions.
@@ -233,10 +324,16 @@ impl UninitializedSandbox {
                 writer_func
                     .try_lock()
                     .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?
-                    .register_with_extra_allowed_syscalls(
+                    .register_with_sandbox(
                         &mut sandbox,
                         "HostPrint",
-                        extra_allowed_syscalls_for_writer_func,
+                        Some(
+                            extra_allowed_syscalls_for_writer_func
+                                .into_iter()
+                                .map(ExtraAllowedSyscall::from)
+                                .collect(),
+                        ),
+                        None,
                     )?;
             }
             None => {
@@ -259,6 +356,15 @@ impl UninitializedSandbox {
         Ok(sandbox)
     }
 
+    /// Set the max log level to be used by the guest.
+    ///
+    /// If this is not set, the guest will use the host's current max log
+    /// level (as returned by `log::max_level()`) when it is initialised.
+    #[instrument(skip_all, parent = Span::current(), level = "Trace")]
+    pub fn set_max_guest_log_level(&mut self, log_level: LevelFilter) {
+        self.max_guest_log_level = Some(log_level);
+    }
+
     #[instrument(skip_all, parent = Span::current(), level = "Trace")]
     fn create_stack_guard() -> [u8; STACK_COOKIE_LEN] {
         rand::random::<[u8; STACK_COOKIE_LEN]>()
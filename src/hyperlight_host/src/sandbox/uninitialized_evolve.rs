@@ -17,19 +17,25 @@ limitations under the License.
 use core::time::Duration;
 use std::sync::{Arc, Mutex};
 
+use hyperlight_common::flatbuffer_wrappers::guest_function_details::GuestFunctionDetails;
+use log::LevelFilter;
 use rand::Rng;
 use tracing::{instrument, Span};
 
 use crate::hypervisor::hypervisor_handler::{
-    HvHandlerConfig, HypervisorHandler, HypervisorHandlerAction,
+    HostFunctionTimer, HvHandlerConfig, HypervisorHandler, HypervisorHandlerAction,
 };
 use crate::mem::mgr::SandboxMemoryManager;
 use crate::mem::ptr::RawPtr;
 use crate::mem::shared_mem::GuestSharedMemory;
+#[cfg(target_os = "linux")]
+use crate::mem::shared_mem::SharedMemory;
 use crate::sandbox::host_funcs::HostFuncsWrapper;
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+use crate::sandbox::host_funcs::HostFunctionWorkerSpawner;
 use crate::sandbox::mem_access::mem_access_handler_wrapper;
 use crate::sandbox::outb::outb_handler_wrapper;
-use crate::sandbox::{HostSharedMemory, MemMgrWrapper};
+use crate::sandbox::{ExecutionTimeBudget, HostSharedMemory, MemMgrWrapper};
 use crate::sandbox_state::sandbox::Sandbox;
 use crate::{new_error, MultiUseSandbox, Result, SingleUseSandbox, UninitializedSandbox};
 
@@ -54,9 +60,10 @@ where
         Arc<Mutex<HostFuncsWrapper>>,
         MemMgrWrapper<HostSharedMemory>,
         HypervisorHandler,
+        Option<GuestFunctionDetails>,
     ) -> Result<ResSandbox>,
 {
-    let (hshm, gshm) = u_sbox.mgr.build();
+    let (mut hshm, gshm) = u_sbox.mgr.build();
 
     let hv_handler = {
         let mut hv_handler = hv_init(
@@ -66,6 +73,13 @@ where
             u_sbox.max_initialization_time,
             u_sbox.max_execution_time,
             u_sbox.max_wait_for_cancellation,
+            u_sbox.max_guest_log_level,
+            u_sbox.execution_time_budget,
+            u_sbox.max_host_function_time,
+            u_sbox.max_nested_guest_call_depth,
+            #[cfg(all(feature = "seccomp", target_os = "linux"))]
+            u_sbox.hypervisor_handler_seccomp_filter,
+            u_sbox.from_snapshot,
         )?;
 
         {
@@ -77,22 +91,59 @@ where
         hv_handler
     };
 
-    transform(u_sbox.host_funcs, hshm, hv_handler)
+    // Read the guest function signatures before any state is snapshotted,
+    // so that they are not left in the output buffer when state is restored.
+    // A guest restored from a snapshot isn't initialised again, so its
+    // signatures come from the snapshot instead.
+    let guest_functions = if u_sbox.from_snapshot {
+        u_sbox.snapshot_guest_functions
+    } else {
+        hshm.as_mut().read_guest_function_details()?
+    };
+
+    transform(u_sbox.host_funcs, hshm, hv_handler, guest_functions)
 }
 
 #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
 pub(super) fn evolve_impl_multi_use(u_sbox: UninitializedSandbox) -> Result<MultiUseSandbox> {
-    evolve_impl(u_sbox, |hf, mut hshm, hv_handler| {
+    evolve_impl(u_sbox, |hf, mut hshm, hv_handler, guest_functions| {
         {
             hshm.as_mut().push_state()?;
         }
-        Ok(MultiUseSandbox::from_uninit(hf, hshm, hv_handler))
+        Ok(MultiUseSandbox::from_uninit(
+            hf,
+            hshm,
+            hv_handler,
+            guest_functions,
+        ))
+    })
+}
+
+/// Evolve `u_sbox`, whose memory is a copy-on-write mapping of the last
+/// snapshot of the sandbox it was forked from, into a `MultiUseSandbox`.
+///
+/// Unlike `evolve_impl_multi_use`, no snapshot is taken, as the memory
+/// manager already has the snapshots of the sandbox it was forked from.
+#[cfg(target_os = "linux")]
+#[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
+pub(super) fn evolve_impl_fork(u_sbox: UninitializedSandbox) -> Result<MultiUseSandbox> {
+    evolve_impl(u_sbox, |hf, mut hshm, hv_handler, guest_functions| {
+        // The memory already matches the last snapshot, so no pages need to
+        // be restored until they're written to. Copying every page instead
+        // would give the fork private copies of all of them.
+        hshm.as_mut().shared_mem.take_dirty_pages()?;
+        Ok(MultiUseSandbox::from_uninit(
+            hf,
+            hshm,
+            hv_handler,
+            guest_functions,
+        ))
     })
 }
 
 #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
 pub(super) fn evolve_impl_single_use(u_sbox: UninitializedSandbox) -> Result<SingleUseSandbox> {
-    evolve_impl(u_sbox, |_hf, hshm, hv_handler| {
+    evolve_impl(u_sbox, |_hf, hshm, hv_handler, _guest_functions| {
         // Its intentional not to snapshot state here. This is because
         // single use sandboxes are not reusable and so there is no need
         // to snapshot state as they cannot be devolved back to an uninitialized sandbox.
@@ -100,6 +151,7 @@ pub(super) fn evolve_impl_single_use(u_sbox: UninitializedSandbox) -> Result<Sin
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 #[instrument(err(Debug), skip_all, parent = Span::current(), level = "Trace")]
 fn hv_init(
     hshm: &MemMgrWrapper<HostSharedMemory>,
@@ -108,8 +160,29 @@ fn hv_init(
     max_init_time: Duration,
     max_exec_time: Duration,
     max_wait_for_cancellation: Duration,
+    max_guest_log_level: Option<LevelFilter>,
+    execution_time_budget: ExecutionTimeBudget,
+    max_host_function_time: Duration,
+    max_nested_guest_call_depth: u8,
+    #[cfg(all(feature = "seccomp", target_os = "linux"))] hypervisor_handler_seccomp_filter: bool,
+    from_snapshot: bool,
 ) -> Result<HypervisorHandler> {
-    let outb_hdl = outb_handler_wrapper(hshm.clone(), host_funcs);
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    let seccomp_filter = if hypervisor_handler_seccomp_filter {
+        let host_function_worker_spawner = HostFunctionWorkerSpawner::start(
+            &*host_funcs
+                .lock()
+                .map_err(|e| new_error!("Error locking at {}:{}: {}", file!(), line!(), e))?,
+        )?;
+        Some((
+            crate::seccomp::hypervisor_handler::get_seccomp_filter_for_hypervisor_handler_thread()?,
+            host_function_worker_spawner,
+        ))
+    } else {
+        None
+    };
+    let host_function_timer = HostFunctionTimer::default();
+    let outb_hdl = outb_handler_wrapper(hshm.clone(), host_funcs, host_function_timer.clone());
     let mem_access_hdl = mem_access_handler_wrapper(hshm.clone());
     let seed = {
         let mut rng = rand::thread_rng();
@@ -130,6 +203,13 @@ fn hv_init(
         max_init_time,
         max_exec_time,
         max_wait_for_cancellation,
+        max_guest_log_level,
+        execution_time_budget,
+        max_host_function_time,
+        max_nested_guest_call_depth,
+        host_function_timer,
+        #[cfg(all(feature = "seccomp", target_os = "linux"))]
+        seccomp_filter,
     };
     // Note: `dispatch_function_addr` is set by the Hyperlight guest library, and so it isn't in
     // shared memory at this point in time. We will set it after the execution of `hv_init`.
@@ -138,8 +218,13 @@ fn hv_init(
 
     hv_handler.start_hypervisor_handler(gshm)?;
 
+    let init_action = if from_snapshot {
+        HypervisorHandlerAction::InitialiseFromSnapshot
+    } else {
+        HypervisorHandlerAction::Initialise
+    };
     hv_handler
-        .execute_hypervisor_handler_action(HypervisorHandlerAction::Initialise)
+        .execute_hypervisor_handler_action(init_action)
         .map_err(|exec_e| match hv_handler.kill_hypervisor_handler_thread() {
             Ok(_) => exec_e,
             Err(kill_e) => new_error!("{}", format!("{}, {}", exec_e, kill_e)),
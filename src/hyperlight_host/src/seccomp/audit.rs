@@ -0,0 +1,323 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::ptr::{addr_of, null_mut};
+
+use seccompiler::{sock_filter, BpfProgram, SeccompRule};
+
+use crate::sandbox::ExtraAllowedSyscall;
+
+/// A syscall that a host function made that its seccomp filter didn't allow,
+/// recorded because the sandbox's seccomp filters were in audit mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AuditedSyscall {
+    /// The syscall number
+    pub syscall: i64,
+    /// The arguments the syscall was made with. A syscall is only recorded
+    /// again if it is made with different values of the arguments the
+    /// filter compares, so these are those of the first such call.
+    pub args: [u64; 6],
+}
+
+impl AuditedSyscall {
+    /// The syscall number and the arguments in `compared_args`, with the
+    /// others zeroed, which identify the syscall as far as the filter can
+    /// tell it apart from others.
+    fn key(&self, compared_args: Option<&[bool; 6]>) -> Self {
+        let mut args = [0; 6];
+        if let Some(compared_args) = compared_args {
+            for (i, arg) in args.iter_mut().enumerate() {
+                if compared_args[i] {
+                    *arg = self.args[i];
+                }
+            }
+        }
+        Self {
+            syscall: self.syscall,
+            args,
+        }
+    }
+}
+
+impl From<AuditedSyscall> for ExtraAllowedSyscall {
+    /// Allow the audited syscall with any arguments
+    fn from(audited: AuditedSyscall) -> Self {
+        Self::new(audited.syscall)
+    }
+}
+
+/// The most disallowed syscalls that are recorded during a single host
+/// function call. Later ones are still allowed, but only counted.
+const MAX_AUDITED_SYSCALLS_PER_CALL: usize = 256;
+
+/// The most distinct disallowed syscalls that are kept in the log for a
+/// single host function, across all of its calls.
+const MAX_AUDITED_SYSCALLS_PER_FUNCTION: usize = 1024;
+
+/// The disallowed syscalls a host function made during a single call.
+///
+/// The SIGSYS handler records into this, so it is allocated up front, as the
+/// handler can't allocate.
+pub(crate) struct SyscallAuditBuffer {
+    syscalls: [AuditedSyscall; MAX_AUDITED_SYSCALLS_PER_CALL],
+    len: usize,
+    dropped: usize,
+}
+
+thread_local! {
+    /// The buffer the disallowed syscalls of the host function running on
+    /// this thread are recorded into, or null if they aren't being audited
+    static SYSCALL_AUDIT_BUFFER: Cell<*mut SyscallAuditBuffer> = const { Cell::new(null_mut()) };
+}
+
+impl SyscallAuditBuffer {
+    /// Start recording the disallowed syscalls made on this thread into a
+    /// new buffer. This must be called before the seccomp filter is applied,
+    /// as it allocates.
+    pub(crate) fn start() -> SyscallAuditGuard {
+        let mut buffer = Box::new(Self {
+            syscalls: [AuditedSyscall {
+                syscall: 0,
+                args: [0; 6],
+            }; MAX_AUDITED_SYSCALLS_PER_CALL],
+            len: 0,
+            dropped: 0,
+        });
+        SYSCALL_AUDIT_BUFFER.set(&mut *buffer);
+        SyscallAuditGuard(Some(buffer))
+    }
+
+    fn record(&mut self, syscall: AuditedSyscall) {
+        if self.syscalls[..self.len].contains(&syscall) {
+            return;
+        }
+        match self.syscalls.get_mut(self.len) {
+            Some(slot) => {
+                *slot = syscall;
+                self.len += 1;
+            }
+            None => self.dropped += 1,
+        }
+    }
+}
+
+/// Stops recording disallowed syscalls into the buffer on this thread when
+/// dropped, including when the host function panics.
+pub(crate) struct SyscallAuditGuard(Option<Box<SyscallAuditBuffer>>);
+
+impl SyscallAuditGuard {
+    /// Stop recording and take the buffer the syscalls were recorded into
+    pub(crate) fn finish(mut self) -> Box<SyscallAuditBuffer> {
+        SYSCALL_AUDIT_BUFFER.set(null_mut());
+        #[allow(clippy::unwrap_used)] // only taken here, which consumes self
+        self.0.take().unwrap()
+    }
+}
+
+impl Drop for SyscallAuditGuard {
+    fn drop(&mut self) {
+        SYSCALL_AUDIT_BUFFER.set(null_mut());
+    }
+}
+
+/// The disallowed syscalls the host functions of a sandbox have made, by
+/// host function name, without duplicates.
+#[derive(Debug, Default)]
+pub(crate) struct SyscallAuditLog(HashMap<String, AuditedSyscalls>);
+
+/// The distinct disallowed syscalls a single host function has made
+#[derive(Debug, Default)]
+struct AuditedSyscalls {
+    /// The keys of the syscalls in `syscalls`
+    seen: HashSet<AuditedSyscall>,
+    syscalls: Vec<AuditedSyscall>,
+    /// Whether a syscall wasn't recorded because there were already
+    /// `MAX_AUDITED_SYSCALLS_PER_FUNCTION`
+    full: bool,
+}
+
+impl SyscallAuditLog {
+    /// Add the syscalls the host function `name` made during a call.
+    ///
+    /// `compared_args` are the arguments the host function's filter compares
+    /// for each syscall it only allows with certain arguments. Only those
+    /// arguments tell two calls of a syscall apart, as the others, such as
+    /// pointers, can differ between calls without changing whether the
+    /// filter allows it.
+    pub(crate) fn record(
+        &mut self,
+        name: &str,
+        buffer: &SyscallAuditBuffer,
+        compared_args: &HashMap<i64, [bool; 6]>,
+    ) {
+        if buffer.dropped > 0 {
+            log::warn!(
+                "Host function {} made more than {} distinct disallowed syscalls in a single call, {} were not recorded",
+                name,
+                MAX_AUDITED_SYSCALLS_PER_CALL,
+                buffer.dropped
+            );
+        }
+        let audited = self.0.entry(name.to_string()).or_default();
+        for syscall in &buffer.syscalls[..buffer.len] {
+            let key = syscall.key(compared_args.get(&syscall.syscall));
+            if audited.seen.contains(&key) {
+                continue;
+            }
+            if audited.syscalls.len() == MAX_AUDITED_SYSCALLS_PER_FUNCTION {
+                if !audited.full {
+                    log::warn!(
+                        "Host function {} made more than {} distinct disallowed syscalls, later ones are not recorded",
+                        name,
+                        MAX_AUDITED_SYSCALLS_PER_FUNCTION
+                    );
+                    audited.full = true;
+                }
+                break;
+            }
+            log::info!(
+                "Host function {} made disallowed syscall {} with arguments {:x?}",
+                name,
+                syscall.syscall,
+                syscall.args
+            );
+            audited.seen.insert(key);
+            audited.syscalls.push(*syscall);
+        }
+    }
+
+    pub(crate) fn syscalls(&self) -> HashMap<String, Vec<AuditedSyscall>> {
+        self.0
+            .iter()
+            .map(|(name, audited)| (name.clone(), audited.syscalls.clone()))
+            .collect()
+    }
+}
+
+/// Called from the SIGSYS handler with the number and arguments of a syscall
+/// the seccomp filter trapped. If the thread is auditing its syscalls, the
+/// syscall is recorded and made, and its result is returned. Otherwise,
+/// returns `None`.
+///
+/// This is async-signal-safe.
+pub(crate) fn handle_audited_syscall(syscall: i64, args: [u64; 6]) -> Option<i64> {
+    let buffer = SYSCALL_AUDIT_BUFFER.try_with(Cell::get).ok()?;
+    if buffer.is_null() {
+        return None;
+    }
+    // Safety: the buffer is only set while the `SyscallAuditGuard` that owns
+    // it is alive on this thread, and this handler runs on the same thread.
+    unsafe { (*buffer).record(AuditedSyscall { syscall, args }) };
+
+    match syscall {
+        // A new thread or process would start in the middle of the signal
+        // handler, so these are only recorded.
+        libc::SYS_clone | libc::SYS_clone3 | libc::SYS_fork | libc::SYS_vfork => {
+            Some(-libc::ENOSYS as i64)
+        }
+        _ => Some(unsafe {
+            hyperlight_audited_syscall(
+                syscall, args[0], args[1], args[2], args[3], args[4], args[5],
+            )
+        }),
+    }
+}
+
+// Makes the syscalls allowed in audit mode. The seccomp filter allows any
+// syscall made from the instruction before `hyperlight_audited_syscall_return`,
+// so that the SIGSYS handler can make the syscall that was trapped.
+std::arch::global_asm!(
+    ".pushsection .text.hyperlight_audited_syscall, \"ax\", @progbits",
+    ".globl hyperlight_audited_syscall",
+    ".hidden hyperlight_audited_syscall",
+    ".globl hyperlight_audited_syscall_return",
+    ".hidden hyperlight_audited_syscall_return",
+    "hyperlight_audited_syscall:",
+    "mov rax, rdi",
+    "mov rdi, rsi",
+    "mov rsi, rdx",
+    "mov rdx, rcx",
+    "mov r10, r8",
+    "mov r8, r9",
+    "mov r9, [rsp + 8]",
+    "syscall",
+    "hyperlight_audited_syscall_return:",
+    "ret",
+    ".popsection",
+);
+
+extern "C" {
+    fn hyperlight_audited_syscall(
+        syscall: i64,
+        arg0: u64,
+        arg1: u64,
+        arg2: u64,
+        arg3: u64,
+        arg4: u64,
+        arg5: u64,
+    ) -> i64;
+    static hyperlight_audited_syscall_return: u8;
+}
+
+/// Which of a syscall's arguments any of `rules` compare, found from the
+/// argument loads of their BPF.
+pub(crate) fn compared_args(rules: Vec<SeccompRule>) -> [bool; 6] {
+    const BPF_LD_W_ABS: u16 = 0x20;
+    // `args` is at offset 16 in `struct seccomp_data`, and each is 64 bits
+    // wide, though BPF loads them a 32 bit half at a time
+    const ARGS_OFFSET: u32 = 16;
+    const ARG_SIZE: u32 = 8;
+
+    let mut compared = [false; 6];
+    for insn in rules.into_iter().flat_map(BpfProgram::from) {
+        if insn.code == BPF_LD_W_ABS && insn.k >= ARGS_OFFSET {
+            if let Some(arg) = compared.get_mut(((insn.k - ARGS_OFFSET) / ARG_SIZE) as usize) {
+                *arg = true;
+            }
+        }
+    }
+    compared
+}
+
+/// Prepend instructions to `filter` that allow any syscall made by
+/// `hyperlight_audited_syscall`, which are those the SIGSYS handler makes on
+/// behalf of a host function in audit mode.
+pub(crate) fn allow_audited_syscalls(filter: &mut BpfProgram) {
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    const BPF_RET_K: u16 = 0x06;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+    // `instruction_pointer` is at offset 8 in `struct seccomp_data`, and BPF
+    // loads are 32 bits wide, so its low half is at 8 and its high half at 12
+    const IP_LOW_OFFSET: u32 = 8;
+    const IP_HIGH_OFFSET: u32 = 12;
+
+    let ip = unsafe { addr_of!(hyperlight_audited_syscall_return) } as u64;
+    let insn = |code, jt, jf, k| sock_filter { code, jt, jf, k };
+    filter.splice(
+        0..0,
+        [
+            insn(BPF_LD_W_ABS, 0, 0, IP_LOW_OFFSET),
+            // if the low half doesn't match, skip to the original filter
+            insn(BPF_JMP_JEQ_K, 0, 3, ip as u32),
+            insn(BPF_LD_W_ABS, 0, 0, IP_HIGH_OFFSET),
+            insn(BPF_JMP_JEQ_K, 0, 1, (ip >> 32) as u32),
+            insn(BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW),
+        ],
+    );
+}
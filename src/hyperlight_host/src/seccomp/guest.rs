@@ -14,16 +14,20 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
 use seccompiler::SeccompCmpOp::Eq;
 use seccompiler::{
     BpfProgram, SeccompAction, SeccompCmpArgLen as ArgLen, SeccompCondition as Cond, SeccompFilter,
     SeccompRule,
 };
 
+use super::audit::{allow_audited_syscalls, compared_args};
 use crate::sandbox::ExtraAllowedSyscall;
 use crate::{and, or, Result};
 
-fn syscalls_allowlist() -> Result<Vec<(i64, Vec<SeccompRule>)>> {
+pub(super) fn syscalls_allowlist() -> Result<Vec<(i64, Vec<SeccompRule>)>> {
     Ok(vec![
         // SYS_signalstack, SYS_munmap, SYS_rt_sigprocmask, SYS_madvise, and SYS_exit
         // are minimally required syscalls to be able to setup our seccomp filter.
@@ -44,9 +48,9 @@ fn syscalls_allowlist() -> Result<Vec<(i64, Vec<SeccompRule>)>> {
         ),
         (libc::SYS_rt_sigreturn, vec![]),
         // Note: This `ioctl` is used to get information about the terminal.
-        // I believe it is used to get terminal information by our default writer function.
-        // That said, I am registering it here instead of in the function specifically
-        // because we don't currently support registering parameterized syscalls.
+        // I believe it is used to get terminal information by our default writer function,
+        // and it is allowed for every host function so that host functions that print to
+        // the terminal keep working without registering it themselves.
         (
             libc::SYS_ioctl,
             or![and![Cond::new(1, ArgLen::Dword, Eq, libc::TCGETS)?]],
@@ -60,6 +64,31 @@ fn syscalls_allowlist() -> Result<Vec<(i64, Vec<SeccompRule>)>> {
     ])
 }
 
+/// Merge the rules for each syscall that is allowed more than once, so that
+/// it is allowed if any of them match. A syscall with no rules is allowed
+/// with any arguments, so that is what it is allowed with if any of its
+/// entries have no rules.
+pub(super) fn merge_rules(
+    allowed_syscalls: Vec<(i64, Vec<SeccompRule>)>,
+) -> BTreeMap<i64, Vec<SeccompRule>> {
+    let mut merged = BTreeMap::<i64, Vec<SeccompRule>>::new();
+    for (syscall, rules) in allowed_syscalls {
+        match merged.entry(syscall) {
+            Entry::Vacant(entry) => {
+                entry.insert(rules);
+            }
+            Entry::Occupied(mut entry) => {
+                if entry.get().is_empty() || rules.is_empty() {
+                    entry.get_mut().clear();
+                } else {
+                    entry.get_mut().extend(rules);
+                }
+            }
+        }
+    }
+    merged
+}
+
 /// Creates a `BpfProgram` for a `SeccompFilter` over specific syscalls/`SeccompRule`s
 /// intended to be applied in the Hypervisor Handler thread - i.e., over untrusted guest code
 /// execution.
@@ -68,28 +97,121 @@ fn syscalls_allowlist() -> Result<Vec<(i64, Vec<SeccompRule>)>> {
 /// `SeccompRules` for operations we definitely perform but are outside the handler thread
 /// (e.g., `KVM_SET_USER_MEMORY_REGION`, `KVM_GET_API_VERSION`, `KVM_CREATE_VM`,
 /// or `KVM_CREATE_VCPU`).
+///
+/// In `audit_mode`, the filter also allows the syscalls the SIGSYS handler
+/// makes on behalf of the host function after recording them.
 pub(crate) fn get_seccomp_filter_for_host_function_worker_thread(
     extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+    audit_mode: bool,
 ) -> Result<BpfProgram> {
+    build_filter(
+        host_function_worker_thread_allowlist(extra_allowed_syscalls)?,
+        audit_mode,
+    )
+}
+
+/// For each syscall the host function worker thread's filter only allows
+/// with certain arguments, which of its arguments the filter compares.
+pub(crate) fn compared_args_for_host_function_worker_thread(
+    extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+) -> Result<HashMap<i64, [bool; 6]>> {
+    Ok(merge_rules(host_function_worker_thread_allowlist(
+        extra_allowed_syscalls,
+    )?)
+    .into_iter()
+    .filter(|(_, rules)| !rules.is_empty())
+    .map(|(syscall, rules)| (syscall, compared_args(rules)))
+    .collect())
+}
+
+fn host_function_worker_thread_allowlist(
+    extra_allowed_syscalls: Option<Vec<ExtraAllowedSyscall>>,
+) -> Result<Vec<(i64, Vec<SeccompRule>)>> {
     let mut allowed_syscalls = syscalls_allowlist()?;
 
     if let Some(extra_allowed_syscalls) = extra_allowed_syscalls {
         allowed_syscalls.extend(
             extra_allowed_syscalls
                 .into_iter()
-                .map(|syscall| (syscall, vec![])),
+                .map(ExtraAllowedSyscall::into_seccomp_rules),
         );
-
-        // Remove duplicates
-        allowed_syscalls.sort_by(|a, b| a.0.cmp(&b.0));
-        allowed_syscalls.dedup();
     }
 
-    Ok(SeccompFilter::new(
-        allowed_syscalls.into_iter().collect(),
+    Ok(allowed_syscalls)
+}
+
+/// Creates a `BpfProgram` that traps any syscall that isn't in
+/// `allowed_syscalls`, so that `handle_sigsys` can report it. In
+/// `audit_mode`, the syscalls the SIGSYS handler makes on behalf of an
+/// audited host function are also allowed.
+pub(super) fn build_filter(
+    allowed_syscalls: Vec<(i64, Vec<SeccompRule>)>,
+    audit_mode: bool,
+) -> Result<BpfProgram> {
+    let mut filter: BpfProgram = SeccompFilter::new(
+        merge_rules(allowed_syscalls),
         SeccompAction::Trap,  // non-match syscall will kill the offending thread
         SeccompAction::Allow, // match syscall will be allowed
         std::env::consts::ARCH.try_into().unwrap(),
     )
-    .and_then(|filter| filter.try_into())?)
+    .and_then(|filter| filter.try_into())?;
+
+    if audit_mode {
+        allow_audited_syscalls(&mut filter);
+    }
+
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use seccompiler::SeccompCmpOp::Eq;
+    use seccompiler::{SeccompCmpArgLen as ArgLen, SeccompCondition as Cond, SeccompRule};
+
+    use super::{compared_args_for_host_function_worker_thread, merge_rules};
+    use crate::sandbox::ExtraAllowedSyscall;
+    use crate::{and, or};
+
+    #[test]
+    fn extra_allowed_syscall_rules_are_merged() {
+        let stdout = or![and![Cond::new(0, ArgLen::Dword, Eq, 1).unwrap()]];
+        let stderr = or![and![Cond::new(0, ArgLen::Dword, Eq, 2).unwrap()]];
+        let merged = merge_rules(vec![
+            (libc::SYS_write, stdout.clone()),
+            ExtraAllowedSyscall::with_rules(libc::SYS_write, stderr.clone())
+                .unwrap()
+                .into_seccomp_rules(),
+            (libc::SYS_read, stdout.clone()),
+            ExtraAllowedSyscall::from(libc::SYS_read).into_seccomp_rules(),
+        ]);
+
+        // `write` is allowed if either rule matches
+        assert_eq!(merged[&libc::SYS_write], [stdout, stderr].concat());
+        // `read` is allowed with any arguments
+        assert!(merged[&libc::SYS_read].is_empty());
+
+        assert!(ExtraAllowedSyscall::with_rules(libc::SYS_write, vec![]).is_err());
+    }
+
+    #[test]
+    fn compared_args_are_those_of_the_rules() {
+        let read_only = or![and![Cond::new(2, ArgLen::Dword, Eq, 0).unwrap()]];
+        let compared_args = compared_args_for_host_function_worker_thread(Some(vec![
+            ExtraAllowedSyscall::with_rules(libc::SYS_openat, read_only).unwrap(),
+            ExtraAllowedSyscall::from(libc::SYS_getpid),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            compared_args[&libc::SYS_openat],
+            [false, false, true, false, false, false]
+        );
+        assert_eq!(
+            compared_args[&libc::SYS_ioctl],
+            [false, true, false, false, false, false]
+        );
+        // Syscalls allowed with any arguments don't compare any
+        assert!(!compared_args.contains_key(&libc::SYS_getpid));
+        assert!(!compared_args.contains_key(&libc::SYS_futex));
+    }
 }
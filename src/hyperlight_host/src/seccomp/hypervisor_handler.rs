@@ -0,0 +1,156 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use seccompiler::SeccompCmpOp::Eq;
+#[cfg(mshv)]
+use seccompiler::SeccompCmpOp::MaskedEq;
+use seccompiler::{BpfProgram, SeccompCmpArgLen as ArgLen, SeccompCondition as Cond, SeccompRule};
+
+use super::guest::build_filter;
+use crate::{and, or, Result};
+
+/// The ioctl request number for `nr` of type `ty` that transfers an argument
+/// of `size` bytes in `dir`ection, as built by the `_IOC` macro in
+/// `<linux/ioctl.h>`
+#[cfg(kvm)]
+const fn ioc(dir: u64, ty: u32, nr: u64, size: usize) -> u64 {
+    (dir << 30) | ((size as u64) << 16) | ((ty as u64) << 8) | nr
+}
+
+/// The KVM ioctls the Hypervisor Handler thread makes on the VM and vCPU
+/// it runs. The VM and vCPU themselves are created before the thread starts.
+#[cfg(kvm)]
+fn kvm_ioctls() -> Result<Vec<SeccompRule>> {
+    use std::mem::size_of;
+
+    use kvm_bindings::{
+        kvm_dirty_log, kvm_fpu, kvm_regs, kvm_sregs, kvm_userspace_memory_region, KVMIO,
+    };
+
+    const IOC_NONE: u64 = 0;
+    const IOC_WRITE: u64 = 1;
+    const IOC_READ: u64 = 2;
+
+    let requests = [
+        ioc(IOC_NONE, KVMIO, 0x80, 0),                           // KVM_RUN
+        ioc(IOC_READ, KVMIO, 0x81, size_of::<kvm_regs>()),       // KVM_GET_REGS
+        ioc(IOC_WRITE, KVMIO, 0x82, size_of::<kvm_regs>()),      // KVM_SET_REGS
+        ioc(IOC_READ, KVMIO, 0x83, size_of::<kvm_sregs>()),      // KVM_GET_SREGS
+        ioc(IOC_WRITE, KVMIO, 0x84, size_of::<kvm_sregs>()),     // KVM_SET_SREGS
+        ioc(IOC_READ, KVMIO, 0x8c, size_of::<kvm_fpu>()),        // KVM_GET_FPU
+        ioc(IOC_WRITE, KVMIO, 0x8d, size_of::<kvm_fpu>()),       // KVM_SET_FPU
+        ioc(IOC_WRITE, KVMIO, 0x42, size_of::<kvm_dirty_log>()), // KVM_GET_DIRTY_LOG
+        ioc(
+            IOC_WRITE,
+            KVMIO,
+            0x46,
+            size_of::<kvm_userspace_memory_region>(),
+        ), // KVM_SET_USER_MEMORY_REGION
+    ];
+    requests
+        .into_iter()
+        .map(|request| Ok(and![Cond::new(1, ArgLen::Dword, Eq, request)?]))
+        .collect()
+}
+
+/// The MSHV ioctls the Hypervisor Handler thread makes. Unlike KVM's, these
+/// are allowed by their type rather than one by one, as the registers of a
+/// vCPU are read and written with the same ioctls as any other of its state.
+#[cfg(mshv)]
+fn mshv_ioctls() -> Result<Vec<SeccompRule>> {
+    Ok(or![and![Cond::new(
+        1,
+        ArgLen::Dword,
+        MaskedEq(0xff00),
+        (mshv_bindings::MSHV_IOCTL as u64) << 8
+    )?]])
+}
+
+fn syscalls_allowlist_for_hypervisor_handler_thread() -> Result<Vec<(i64, Vec<SeccompRule>)>> {
+    let mut allowed_syscalls = vec![
+        // The channels the thread receives actions on and sends results with,
+        // and the locks around the sandbox's state
+        (libc::SYS_futex, vec![]),
+        (libc::SYS_sched_yield, vec![]),
+        // Memory allocation
+        (libc::SYS_mmap, vec![]),
+        (libc::SYS_mremap, vec![]),
+        (libc::SYS_munmap, vec![]),
+        (libc::SYS_brk, vec![]),
+        (libc::SYS_madvise, vec![]),
+        (libc::SYS_mprotect, vec![]),
+        // Returning from the handlers for the SIGRTMIN that interrupts a
+        // running vCPU and for the SIGSYS that reports a disallowed syscall,
+        // which writes the syscall to stderr, as logging may too
+        (libc::SYS_rt_sigreturn, vec![]),
+        (
+            libc::SYS_write,
+            or![
+                and![Cond::new(0, ArgLen::Dword, Eq, 1)?], // stdout
+                and![Cond::new(0, ArgLen::Dword, Eq, 2)?], // stderr
+            ],
+        ),
+        (libc::SYS_rt_sigprocmask, vec![]),
+        (libc::SYS_sigaltstack, vec![]),
+        // Reading the time, if it can't be read from the vDSO, and seeding
+        // the hash maps that are created on this thread
+        (libc::SYS_clock_gettime, vec![]),
+        (libc::SYS_getrandom, vec![]),
+        // Identifying the thread and the CPUs it may run on, which the
+        // standard library and logging do
+        (libc::SYS_gettid, vec![]),
+        (libc::SYS_sched_getaffinity, vec![]),
+        // Exiting the thread
+        (libc::SYS_exit, vec![]),
+        // Closing the VM and vCPU when the thread exits, which debug builds
+        // check are still open first
+        (libc::SYS_close, vec![]),
+        (
+            libc::SYS_fcntl,
+            or![and![Cond::new(1, ArgLen::Dword, Eq, libc::F_GETFD as u64)?]],
+        ),
+        // Writing the crash dump of a VM that exits unexpectedly
+        #[cfg(all(debug_assertions, feature = "dump_on_crash"))]
+        (libc::SYS_openat, vec![]),
+        #[cfg(all(debug_assertions, feature = "dump_on_crash"))]
+        (libc::SYS_write, vec![]),
+    ];
+
+    // Running the vCPU and mapping memory into the VM. `ioctl` must not be
+    // given an empty list of rules, as that would allow it with any request.
+    #[allow(unused_mut)]
+    let mut ioctls = vec![];
+    #[cfg(kvm)]
+    ioctls.extend(kvm_ioctls()?);
+    #[cfg(mshv)]
+    ioctls.extend(mshv_ioctls()?);
+    if !ioctls.is_empty() {
+        allowed_syscalls.push((libc::SYS_ioctl, ioctls));
+    }
+
+    Ok(allowed_syscalls)
+}
+
+/// Creates a `BpfProgram` for a `SeccompFilter` intended to be applied in
+/// the Hypervisor Handler thread, which runs the vCPU and handles its exits,
+/// parsing buffers the guest controls.
+///
+/// The host function worker threads are spawned from a thread without this
+/// filter, so this only allows what the Hypervisor Handler thread itself
+/// needs, and none of the syscalls the host functions may make.
+pub(crate) fn get_seccomp_filter_for_hypervisor_handler_thread() -> Result<BpfProgram> {
+    build_filter(syscalls_allowlist_for_hypervisor_handler_thread()?, false)
+}
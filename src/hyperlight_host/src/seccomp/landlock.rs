@@ -0,0 +1,210 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::ffi::CString;
+use std::io;
+use std::mem::{size_of, MaybeUninit};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr::null;
+
+use crate::sandbox::FilesystemAccess;
+use crate::{log_then_return, new_error, Result};
+
+// From `<linux/landlock.h>`, which the `libc` crate has the syscall numbers,
+// but not the types or constants of
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+const LANDLOCK_RULE_PATH_BENEATH: u32 = 1;
+
+const LANDLOCK_ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const LANDLOCK_ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const LANDLOCK_ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const LANDLOCK_ACCESS_FS_READ_DIR: u64 = 1 << 3;
+/// The accesses the first version of Landlock handles, up to and including
+/// `LANDLOCK_ACCESS_FS_MAKE_SYM`
+const LANDLOCK_ACCESS_FS_V1: u64 = (1 << 13) - 1;
+const LANDLOCK_ACCESS_FS_REFER: u64 = 1 << 13;
+const LANDLOCK_ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+const LANDLOCK_ACCESS_FS_IOCTL_DEV: u64 = 1 << 15;
+
+/// The accesses that may be allowed on a file, rather than a directory
+const LANDLOCK_ACCESS_FILE: u64 = LANDLOCK_ACCESS_FS_EXECUTE
+    | LANDLOCK_ACCESS_FS_WRITE_FILE
+    | LANDLOCK_ACCESS_FS_READ_FILE
+    | LANDLOCK_ACCESS_FS_TRUNCATE
+    | LANDLOCK_ACCESS_FS_IOCTL_DEV;
+/// The accesses allowed on the read-only paths of a `FilesystemAccess`
+const LANDLOCK_ACCESS_READ_ONLY: u64 = LANDLOCK_ACCESS_FS_READ_FILE | LANDLOCK_ACCESS_FS_READ_DIR;
+
+/// `struct landlock_ruleset_attr`, as of the first version of Landlock. The
+/// kernel accepts this for any later version too.
+#[repr(C)]
+struct LandlockRulesetAttr {
+    handled_access_fs: u64,
+}
+
+/// `struct landlock_path_beneath_attr`
+#[repr(C, packed)]
+struct LandlockPathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// The filesystem accesses Landlock handles in version `abi` of it, which
+/// are denied unless a rule allows them.
+fn handled_access_fs(abi: i64) -> u64 {
+    let mut access = LANDLOCK_ACCESS_FS_V1;
+    if abi >= 2 {
+        access |= LANDLOCK_ACCESS_FS_REFER;
+    }
+    if abi >= 3 {
+        access |= LANDLOCK_ACCESS_FS_TRUNCATE;
+    }
+    if abi >= 5 {
+        access |= LANDLOCK_ACCESS_FS_IOCTL_DEV;
+    }
+    access
+}
+
+/// Restrict the calling thread, and any threads it spawns, to accessing
+/// only the files `access` allows.
+///
+/// This must be called before the thread's seccomp filter is applied, as
+/// that doesn't allow the syscalls it makes. Returns an error, rather than
+/// leaving the thread unrestricted, if the kernel doesn't support Landlock.
+pub(crate) fn restrict_filesystem_access(access: &FilesystemAccess) -> Result<()> {
+    let abi = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            null::<LandlockRulesetAttr>(),
+            0usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    if abi < 0 {
+        log_then_return!(
+            "Landlock is not supported by the kernel: {}",
+            io::Error::last_os_error()
+        );
+    }
+
+    let handled_access_fs = handled_access_fs(abi);
+    let attr = LandlockRulesetAttr { handled_access_fs };
+    let ruleset = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            &attr as *const LandlockRulesetAttr,
+            size_of::<LandlockRulesetAttr>(),
+            0u32,
+        )
+    };
+    if ruleset < 0 {
+        log_then_return!(
+            "Error creating Landlock ruleset: {}",
+            io::Error::last_os_error()
+        );
+    }
+    // Safety: the fd was just created and nothing else owns it
+    let ruleset = unsafe { OwnedFd::from_raw_fd(ruleset as i32) };
+
+    for path in access.read_only_paths() {
+        add_path_rule(
+            &ruleset,
+            path,
+            handled_access_fs & LANDLOCK_ACCESS_READ_ONLY,
+        )?;
+    }
+    // Executing files isn't part of reading and writing them
+    for path in access.read_write_paths() {
+        add_path_rule(
+            &ruleset,
+            path,
+            handled_access_fs & !LANDLOCK_ACCESS_FS_EXECUTE,
+        )?;
+    }
+
+    // Landlock requires this of a thread that doesn't have `CAP_SYS_ADMIN`,
+    // and applying the seccomp filter sets it anyway
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        log_then_return!("Error setting no_new_privs: {}", io::Error::last_os_error());
+    }
+    if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0u32) } != 0 {
+        log_then_return!(
+            "Error applying Landlock ruleset: {}",
+            io::Error::last_os_error()
+        );
+    }
+
+    Ok(())
+}
+
+/// Allow `access` to `path`, and to everything beneath it if it is a
+/// directory, in `ruleset`
+fn add_path_rule(ruleset: &OwnedFd, path: &Path, access: u64) -> Result<()> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| new_error!("Invalid path {} in Landlock ruleset: {}", path.display(), e))?;
+    let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    if fd < 0 {
+        log_then_return!(
+            "Error opening {} for Landlock ruleset: {}",
+            path.display(),
+            io::Error::last_os_error()
+        );
+    }
+    // Safety: the fd was just opened and nothing else owns it
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+    if unsafe { libc::fstat(fd.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+        log_then_return!(
+            "Error getting the type of {} for Landlock ruleset: {}",
+            path.display(),
+            io::Error::last_os_error()
+        );
+    }
+    // Safety: `fstat` succeeded, so it filled in `stat`
+    let is_dir = unsafe { stat.assume_init() }.st_mode & libc::S_IFMT == libc::S_IFDIR;
+
+    let rule = LandlockPathBeneathAttr {
+        // Landlock rejects rules for files that allow accesses only
+        // directories can have
+        allowed_access: if is_dir {
+            access
+        } else {
+            access & LANDLOCK_ACCESS_FILE
+        },
+        parent_fd: fd.as_raw_fd(),
+    };
+    if unsafe {
+        libc::syscall(
+            libc::SYS_landlock_add_rule,
+            ruleset.as_raw_fd(),
+            LANDLOCK_RULE_PATH_BENEATH,
+            &rule as *const LandlockPathBeneathAttr,
+            0u32,
+        )
+    } != 0
+    {
+        log_then_return!(
+            "Error adding {} to Landlock ruleset: {}",
+            path.display(),
+            io::Error::last_os_error()
+        );
+    }
+
+    Ok(())
+}
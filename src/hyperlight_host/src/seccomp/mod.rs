@@ -21,6 +21,18 @@ limitations under the License.
 /// needed for execution of guest code within Hyperlight through a syscalls allow-list.
 pub(crate) mod guest;
 
+/// This module defines the seccomp filter that can be applied to the Hypervisor Handler thread,
+/// which runs the vCPU and handles its exits.
+pub(crate) mod hypervisor_handler;
+
+/// This module records, and allows, the syscalls that host functions make
+/// that their seccomp filters don't allow, when the filters are in audit mode.
+pub(crate) mod audit;
+
+/// This module restricts the files host functions can access with Landlock, which is applied
+/// alongside their seccomp filters on the host function worker threads.
+pub(crate) mod landlock;
+
 // The credit on the creation of the macros below goes to the cloud-hypervisor team
 // (https://github.com/cloud-hypervisor/cloud-hypervisor/blob/main/vmm/src/seccomp_filters.rs)
 
@@ -41,6 +41,12 @@ pub(crate) fn setup_signal_handlers() -> crate::Result<()> {
                 // Do nothing to avoid superfluous syscalls
                 return;
             }
+            // Panics on a thread with a seccomp filter are reported by
+            // Hyperlight, and printing them may make syscalls the filter
+            // doesn't allow
+            if PANICS_REPORTED.get() {
+                return;
+            }
             // If not "DisallowedSyscall", use the original hook
             original_hook(panic_info);
         }));
@@ -60,6 +66,20 @@ pub(crate) fn setup_signal_handlers() -> crate::Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "seccomp")]
+thread_local! {
+    /// Whether panics on this thread are caught and reported by Hyperlight,
+    /// rather than printed by the original panic hook
+    static PANICS_REPORTED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Don't print panics on this thread, which is about to have a seccomp filter
+/// applied, as it catches them and reports them as host function errors.
+#[cfg(feature = "seccomp")]
+pub(crate) fn report_panics_on_this_thread() {
+    PANICS_REPORTED.set(true);
+}
+
 extern "C" fn handle_hltimeout(_: libc::c_int, _: *mut libc::siginfo_t, _: *mut libc::c_void) {
     // Do nothing. SIGRTMIN is just used to issue a VM exit to the underlying VMM.
 }
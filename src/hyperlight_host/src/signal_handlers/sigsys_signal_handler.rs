@@ -46,13 +46,6 @@ pub(super) extern "C" fn handle_sigsys(
                 return;
             }
 
-            let err_msg = b"[ERROR][HYPERLIGHT] Handling disallowed syscall\n";
-            libc::write(
-                libc::STDERR_FILENO,
-                err_msg.as_ptr() as *const _,
-                err_msg.len(),
-            );
-
             // We get the syscall number by accessing a particular offset in the `siginfo_t` struct.
             // This only works because this is handling a SIGSYS signal (i.e., the `siginfo_t` struct
             // is implemented as a union in the kernel:
@@ -61,6 +54,37 @@ pub(super) extern "C" fn handle_sigsys(
             // in the future.
             const SI_OFF_SYSCALL: isize = 6;
             let syscall = *(info as *const i32).offset(SI_OFF_SYSCALL) as usize;
+
+            // Note: This is not necessarily platform-agnostic, so we might want to be more careful here
+            // in the future.
+            let ucontext = context as *mut libc::ucontext_t;
+            let mcontext = &mut (*ucontext).uc_mcontext;
+
+            // If the host function's seccomp filter is in audit mode, the syscall is recorded
+            // and made here, and execution returns to the host function with its result.
+            let args = [
+                libc::REG_RDI,
+                libc::REG_RSI,
+                libc::REG_RDX,
+                libc::REG_R10,
+                libc::REG_R8,
+                libc::REG_R9,
+            ]
+            .map(|reg| mcontext.gregs[reg as usize] as u64);
+            if let Some(result) =
+                crate::seccomp::audit::handle_audited_syscall(syscall as i64, args)
+            {
+                mcontext.gregs[libc::REG_RAX as usize] = result as libc::greg_t;
+                return;
+            }
+
+            let err_msg = b"[ERROR][HYPERLIGHT] Handling disallowed syscall\n";
+            libc::write(
+                libc::STDERR_FILENO,
+                err_msg.as_ptr() as *const _,
+                err_msg.len(),
+            );
+
             let syscall_bytes = raw_format(b"[ERROR][HYPERLIGHT] Disallowed Syscall: ", syscall);
 
             // `write` as per https://man7.org/linux/man-pages/man7/signal-safety.7.html
@@ -71,11 +95,6 @@ pub(super) extern "C" fn handle_sigsys(
                 syscall_bytes.len(),
             );
 
-            // Note: This is not necessarily platform-agnostic, so we might want to be more careful here
-            // in the future.
-            let ucontext = context as *mut libc::ucontext_t;
-            let mcontext = &mut (*ucontext).uc_mcontext;
-
             if syscall == libc::SYS_ioctl as usize {
                 let ioctl_param = mcontext.gregs[libc::REG_EBRACE as usize] as usize;
                 let ioctl_param_bytes =
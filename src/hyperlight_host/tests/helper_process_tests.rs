@@ -0,0 +1,175 @@
+/*
+Copyright 2024 The Hyperlight Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// Helper processes are new instances of the test program, which must add the
+// host functions they run to the table of those that can before anything
+// else, so these tests have their own `main` rather than the test harness's.
+
+#[cfg(all(feature = "seccomp", target_os = "linux"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use hyperlight_host::func::{HostFunction0, ParameterValue, ReturnType, ReturnValue};
+    use hyperlight_host::sandbox_state::sandbox::EvolvableSandbox;
+    use hyperlight_host::sandbox_state::transition::Noop;
+    use hyperlight_host::{
+        GuestBinary, HyperlightError, MultiUseSandbox, Result, UninitializedSandbox,
+    };
+    use hyperlight_testing::simple_guest_as_string;
+
+    fn get_pid() -> Result<u64> {
+        let pid = unsafe { libc::syscall(libc::SYS_getpid) };
+        Ok(pid as u64)
+    }
+
+    fn make_disallowed_syscall() -> Result<u64> {
+        let pid = unsafe { libc::syscall(libc::SYS_getppid) };
+        Ok(pid as u64)
+    }
+
+    pub(super) fn register_for_helper_processes() -> Result<()> {
+        Arc::new(Mutex::new(get_pid)).register_for_helper_processes("GetPid")?;
+        Arc::new(Mutex::new(make_disallowed_syscall))
+            .register_for_helper_processes("MakeDisallowedSyscall")?;
+        let mut calls = 0;
+        Arc::new(Mutex::new(move || -> Result<u64> {
+            calls += 1;
+            Ok(calls)
+        }))
+        .register_for_helper_processes("CountCalls")?;
+        Ok(())
+    }
+
+    fn new_uninit() -> Result<UninitializedSandbox> {
+        UninitializedSandbox::new(
+            GuestBinary::FilePath(simple_guest_as_string().expect("Guest Binary Missing")),
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn call_host_function(sbox: &mut MultiUseSandbox, name: &str) -> Result<ReturnValue> {
+        sbox.call_guest_function_by_name(
+            "CallHostFunctionByName",
+            ReturnType::ULong,
+            Some(vec![ParameterValue::String(name.to_string())]),
+        )
+    }
+
+    fn host_function_runs_in_helper_process() -> Result<()> {
+        let mut usbox = new_uninit()?;
+        Arc::new(Mutex::new(get_pid)).register_in_helper_process_with_extra_allowed_syscalls(
+            &mut usbox,
+            "GetPid",
+            vec![libc::SYS_getpid],
+        )?;
+        let mut sbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
+
+        // The host function runs in another process, which serves every call
+        let res = call_host_function(&mut sbox, "GetPid")?;
+        let ReturnValue::ULong(helper_pid) = res else {
+            panic!("Expected a ULong, got {:?}", res);
+        };
+        assert_ne!(helper_pid, std::process::id() as u64);
+        let res = call_host_function(&mut sbox, "GetPid")?;
+        assert_eq!(res, ReturnValue::ULong(helper_pid));
+        Ok(())
+    }
+
+    fn helper_process_keeps_state() -> Result<()> {
+        let mut usbox = new_uninit()?;
+        Arc::new(Mutex::new(|| -> Result<u64> { Ok(0) }))
+            .register_in_helper_process(&mut usbox, "CountCalls")?;
+        let mut sbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
+
+        // The helper process calls its own instance of the function, rather
+        // than the one registered with the sandbox
+        assert_eq!(
+            call_host_function(&mut sbox, "CountCalls")?,
+            ReturnValue::ULong(1)
+        );
+        assert_eq!(
+            call_host_function(&mut sbox, "CountCalls")?,
+            ReturnValue::ULong(2)
+        );
+        Ok(())
+    }
+
+    fn helper_process_crash_is_reported() -> Result<()> {
+        let mut usbox = new_uninit()?;
+        Arc::new(Mutex::new(make_disallowed_syscall))
+            .register_in_helper_process(&mut usbox, "MakeDisallowedSyscall")?;
+        let mut sbox: MultiUseSandbox = usbox.evolve(Noop::default())?;
+
+        // A helper process that crashes is reported, rather than taking the
+        // host process with it
+        match call_host_function(&mut sbox, "MakeDisallowedSyscall") {
+            Err(HyperlightError::HostFunctionProcessExited(name, _)) => {
+                assert_eq!(name, "MakeDisallowedSyscall")
+            }
+            other => panic!("Expected HostFunctionProcessExited error, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    fn unregistered_function_is_rejected() -> Result<()> {
+        let mut usbox = new_uninit()?;
+        match Arc::new(Mutex::new(get_pid)).register_in_helper_process(&mut usbox, "GetOtherPid") {
+            Err(HyperlightError::HostFunctionNotRegisteredForHelperProcesses(name)) => {
+                assert_eq!(name, "GetOtherPid")
+            }
+            other => panic!(
+                "Expected HostFunctionNotRegisteredForHelperProcesses error, got {:?}",
+                other
+            ),
+        }
+        Ok(())
+    }
+
+    type Test = fn() -> Result<()>;
+
+    pub(super) fn run() {
+        let tests: [(&str, Test); 4] = [
+            (
+                "host_function_runs_in_helper_process",
+                host_function_runs_in_helper_process,
+            ),
+            ("helper_process_keeps_state", helper_process_keeps_state),
+            (
+                "helper_process_crash_is_reported",
+                helper_process_crash_is_reported,
+            ),
+            (
+                "unregistered_function_is_rejected",
+                unregistered_function_is_rejected,
+            ),
+        ];
+        for (name, test) in tests {
+            println!("test {} ...", name);
+            test().unwrap();
+        }
+    }
+}
+
+fn main() {
+    #[cfg(all(feature = "seccomp", target_os = "linux"))]
+    {
+        tests::register_for_helper_processes().unwrap();
+        hyperlight_host::run_helper_process_if_requested();
+        tests::run();
+    }
+}
@@ -22,7 +22,9 @@ use hyperlight_host::mem::memory_region::MemoryRegionFlags;
 use hyperlight_host::sandbox::SandboxConfiguration;
 use hyperlight_host::sandbox_state::sandbox::EvolvableSandbox;
 use hyperlight_host::sandbox_state::transition::Noop;
-use hyperlight_host::{GuestBinary, HyperlightError, SingleUseSandbox, UninitializedSandbox};
+use hyperlight_host::{
+    GuestBinary, HyperlightError, MultiUseSandbox, SingleUseSandbox, UninitializedSandbox,
+};
 use hyperlight_testing::{c_simple_guest_as_string, simple_guest_as_string};
 
 pub mod common; // pub to disable dead_code warning
@@ -266,6 +268,47 @@ fn guest_hlmalloc_abort() {
     ));
 }
 
+// checks that the heap grows into the memory reserved for it, but no further
+#[test]
+fn guest_heap_growth() {
+    let heap_size = 0x4000;
+    let max_heap_size = 0x100000;
+
+    let mut cfg = SandboxConfiguration::default();
+    cfg.set_heap_size(heap_size);
+    cfg.set_max_heap_size(max_heap_size);
+    let uninit = UninitializedSandbox::new(
+        GuestBinary::FilePath(simple_guest_as_string().unwrap()),
+        Some(cfg),
+        None,
+        None,
+    )
+    .unwrap();
+    let mut sbox: MultiUseSandbox = uninit.evolve(Noop::default()).unwrap();
+
+    // bigger than the heap, but small enough to grow into
+    let size_to_allocate = 0x10000;
+    let res = sbox
+        .call_guest_function_by_name(
+            "CallMalloc",
+            ReturnType::Int,
+            Some(vec![ParameterValue::Int(size_to_allocate)]),
+        )
+        .unwrap();
+    assert!(matches!(res, ReturnValue::Int(returned_size) if returned_size == size_to_allocate));
+
+    // bigger than the heap can grow to
+    let res = sbox.call_guest_function_by_name(
+        "CallMalloc",
+        ReturnType::Int,
+        Some(vec![ParameterValue::Int(max_heap_size as i32)]),
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        HyperlightError::GuestAborted(code, msg) if code == ErrorCode::UnknownError as u8 && msg.contains("memory allocation of ")
+    ));
+}
+
 // checks that alloca works
 #[test]
 fn dynamic_stack_allocate() {
@@ -39,7 +39,7 @@ fn pass_byte_array() {
         let mut ctx = sandbox.new_call_context();
         const LEN: usize = 10;
         let bytes = vec![1u8; LEN];
-        let res = ctx.call(
+        let res = ctx.call_guest_function_by_name(
             "SetByteArrayToZero",
             ReturnType::VecBytes,
             Some(vec![ParameterValue::VecBytes(bytes.clone())]),
@@ -53,7 +53,7 @@ fn pass_byte_array() {
             _ => panic!("Expected VecBytes"),
         }
 
-        let res = ctx.call(
+        let res = ctx.call_guest_function_by_name(
             "SetByteArrayToZeroNoLength",
             ReturnType::Int,
             Some(vec![ParameterValue::VecBytes(bytes.clone())]),
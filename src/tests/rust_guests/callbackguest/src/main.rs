@@ -21,146 +21,87 @@ extern crate alloc;
 extern crate hyperlight_guest;
 
 use alloc::format;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
 use hyperlight_common::flatbuffer_wrappers::function_call::FunctionCall;
-use hyperlight_common::flatbuffer_wrappers::function_types::{
-    ParameterType, ParameterValue, ReturnType,
-};
+use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterType, ReturnType};
 use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
 use hyperlight_common::flatbuffer_wrappers::guest_log_level::LogLevel;
-use hyperlight_common::flatbuffer_wrappers::util::{
-    get_flatbuffer_result_from_int, get_flatbuffer_result_from_void,
-};
 use hyperlight_guest::error::{HyperlightGuestError, Result};
 use hyperlight_guest::guest_function_definition::GuestFunctionDefinition;
 use hyperlight_guest::guest_function_register::register_function;
-use hyperlight_guest::host_function_call::{
-    call_host_function, get_host_value_return_as_int, print_output_as_guest_function,
-};
+use hyperlight_guest::host_function_call::print_output_as_guest_function;
 use hyperlight_guest::logging::log_message;
+use hyperlight_guest_macros::{guest_function, host_import};
 
-fn send_message_to_host_method(
-    method_name: &str,
-    guest_message: &str,
-    message: &str,
-) -> Result<Vec<u8>> {
-    let message = format!("{}{}", guest_message, message);
-    call_host_function(
-        method_name,
-        Some(Vec::from(&[ParameterValue::String(message.to_string())])),
-        ReturnType::Int,
-    )?;
+#[host_import("HostMethod")]
+fn host_method(message: String) -> Result<i32>;
 
-    let result = get_host_value_return_as_int()?;
+#[host_import("HostMethod1")]
+fn host_method1(message: String) -> Result<i32>;
 
-    Ok(get_flatbuffer_result_from_int(result))
-}
+#[host_import("HostMethod4")]
+fn host_method4(message: String) -> Result<()>;
 
-fn guest_function(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::String(message) = &function_call.parameters.as_ref().unwrap()[0] {
-        send_message_to_host_method("HostMethod", "Hello from GuestFunction, ", message)
-    } else {
-        return Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to guest_function".to_string(),
-        ));
-    }
-}
+#[host_import("ErrorMethod")]
+fn error_method(message: String) -> Result<i32>;
 
-fn guest_function1(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::String(message) = &function_call.parameters.as_ref().unwrap()[0] {
-        send_message_to_host_method("HostMethod1", "Hello from GuestFunction1, ", message)
-    } else {
-        return Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to guest_function1".to_string(),
-        ));
-    }
+#[host_import("Spin")]
+fn host_spin() -> Result<()>;
+
+#[guest_function("GuestMethod")]
+fn guest_function(message: String) -> Result<i32> {
+    host_method(format!("Hello from GuestFunction, {}", message))
 }
 
-fn guest_function2(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::String(message) = &function_call.parameters.as_ref().unwrap()[0] {
-        send_message_to_host_method("HostMethod1", "Hello from GuestFunction2, ", message)
-    } else {
-        return Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to guest_function2".to_string(),
-        ));
-    }
+#[guest_function("GuestMethod1")]
+fn guest_function1(message: String) -> Result<i32> {
+    host_method1(format!("Hello from GuestFunction1, {}", message))
 }
 
-fn guest_function3(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::String(message) = &function_call.parameters.as_ref().unwrap()[0] {
-        send_message_to_host_method("HostMethod1", "Hello from GuestFunction3, ", message)
-    } else {
-        return Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to guest_function3".to_string(),
-        ));
-    }
+#[guest_function("GuestMethod2")]
+fn guest_function2(message: String) -> Result<i32> {
+    host_method1(format!("Hello from GuestFunction2, {}", message))
 }
 
-fn guest_function4() -> Result<Vec<u8>> {
-    call_host_function(
-        "HostMethod4",
-        Some(Vec::from(&[ParameterValue::String(
-            "Hello from GuestFunction4".to_string(),
-        )])),
-        ReturnType::Void,
-    )?;
+#[guest_function("GuestMethod3")]
+fn guest_function3(message: String) -> Result<i32> {
+    host_method1(format!("Hello from GuestFunction3, {}", message))
+}
 
-    Ok(get_flatbuffer_result_from_void())
+#[guest_function("GuestMethod4")]
+fn guest_function4() -> Result<()> {
+    host_method4("Hello from GuestFunction4".to_string())
 }
 
-fn guest_log_message(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let (
-        ParameterValue::String(message),
-        ParameterValue::String(source),
-        ParameterValue::Int(level),
-    ) = (
-        &function_call.parameters.as_ref().unwrap()[0],
-        &function_call.parameters.as_ref().unwrap()[1],
-        &function_call.parameters.as_ref().unwrap()[2],
-    ) {
-        let mut log_level = *level;
-        if log_level < 0 || log_level > 6 {
-            log_level = 0;
-        }
-
-        log_message(
-            LogLevel::from(log_level as u8),
-            message,
-            source,
-            "guest_log_message",
-            file!(),
-            line!(),
-        );
-
-        Ok(get_flatbuffer_result_from_int(message.len() as i32))
-    } else {
-        return Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to guest_log_message".to_string(),
-        ));
+#[guest_function("LogMessage")]
+fn guest_log_message(message: String, source: String, level: i32) -> Result<i32> {
+    let mut log_level = level;
+    if !(0..=6).contains(&log_level) {
+        log_level = 0;
     }
+
+    log_message(
+        LogLevel::from(log_level as u8),
+        &message,
+        &source,
+        "guest_log_message",
+        file!(),
+        line!(),
+    );
+
+    Ok(message.len() as i32)
 }
 
-fn call_error_method(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::String(message) = &function_call.parameters.as_ref().unwrap()[0] {
-        send_message_to_host_method("ErrorMethod", "Error From Host: ", message)
-    } else {
-        return Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to call_error_method".to_string(),
-        ));
-    }
+#[guest_function("CallErrorMethod")]
+fn call_error_method(message: String) -> Result<i32> {
+    error_method(format!("Error From Host: {}", message))
 }
 
-fn call_host_spin() -> Result<Vec<u8>> {
-    call_host_function("Spin", None, ReturnType::Void)?;
-    Ok(get_flatbuffer_result_from_void())
+#[guest_function("CallHostSpin")]
+fn call_host_spin() -> Result<()> {
+    host_spin()
 }
 
 #[no_mangle]
@@ -173,73 +114,14 @@ pub extern "C" fn hyperlight_main() {
     );
     register_function(print_output_def);
 
-    let guest_function_def = GuestFunctionDefinition::new(
-        "GuestMethod".to_string(),
-        Vec::from(&[ParameterType::String]),
-        ReturnType::Int,
-        guest_function as i64,
-    );
-    register_function(guest_function_def);
-
-    let guest_function1_def = GuestFunctionDefinition::new(
-        "GuestMethod1".to_string(),
-        Vec::from(&[ParameterType::String]),
-        ReturnType::Int,
-        guest_function1 as i64,
-    );
-    register_function(guest_function1_def);
-
-    let guest_function2_def = GuestFunctionDefinition::new(
-        "GuestMethod2".to_string(),
-        Vec::from(&[ParameterType::String]),
-        ReturnType::Int,
-        guest_function2 as i64,
-    );
-    register_function(guest_function2_def);
-
-    let guest_function3_def = GuestFunctionDefinition::new(
-        "GuestMethod3".to_string(),
-        Vec::from(&[ParameterType::String]),
-        ReturnType::Int,
-        guest_function3 as i64,
-    );
-    register_function(guest_function3_def);
-
-    let guest_function4_def = GuestFunctionDefinition::new(
-        "GuestMethod4".to_string(),
-        Vec::new(),
-        ReturnType::Int,
-        guest_function4 as i64,
-    );
-    register_function(guest_function4_def);
-
-    let guest_log_message_def = GuestFunctionDefinition::new(
-        "LogMessage".to_string(),
-        Vec::from(&[
-            ParameterType::String,
-            ParameterType::String,
-            ParameterType::Int,
-        ]),
-        ReturnType::Int,
-        guest_log_message as i64,
-    );
-    register_function(guest_log_message_def);
-
-    let call_error_method_def = GuestFunctionDefinition::new(
-        "CallErrorMethod".to_string(),
-        Vec::from(&[ParameterType::String]),
-        ReturnType::Int,
-        call_error_method as i64,
-    );
-    register_function(call_error_method_def);
-
-    let call_host_spin_def = GuestFunctionDefinition::new(
-        "CallHostSpin".to_string(),
-        Vec::new(),
-        ReturnType::Int,
-        call_host_spin as i64,
-    );
-    register_function(call_host_spin_def);
+    guest_function::register();
+    guest_function1::register();
+    guest_function2::register();
+    guest_function3::register();
+    guest_function4::register();
+    guest_log_message::register();
+    call_error_method::register();
+    call_host_spin::register();
 }
 
 #[no_mangle]
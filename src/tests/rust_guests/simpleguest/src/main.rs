@@ -27,7 +27,7 @@ extern crate alloc;
 
 use alloc::boxed::Box;
 use alloc::format;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::ffi::c_char;
 use core::hint::black_box;
@@ -40,10 +40,9 @@ use hyperlight_common::flatbuffer_wrappers::function_types::{
 use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
 use hyperlight_common::flatbuffer_wrappers::guest_log_level::LogLevel;
 use hyperlight_common::flatbuffer_wrappers::util::{
-    get_flatbuffer_result_from_double, get_flatbuffer_result_from_float,
-    get_flatbuffer_result_from_int, get_flatbuffer_result_from_string,
-    get_flatbuffer_result_from_ulong, get_flatbuffer_result_from_vec,
-    get_flatbuffer_result_from_void,
+    get_flatbuffer_result_from_int, get_flatbuffer_result_from_record,
+    get_flatbuffer_result_from_string, get_flatbuffer_result_from_ulong,
+    get_flatbuffer_result_from_vec, get_flatbuffer_result_from_void,
 };
 use hyperlight_common::mem::PAGE_SIZE;
 use hyperlight_guest::alloca::_alloca;
@@ -54,8 +53,11 @@ use hyperlight_guest::guest_function_register::register_function;
 use hyperlight_guest::host_function_call::{
     call_host_function, get_host_value_return_as_int, get_host_value_return_as_ulong,
 };
+use hyperlight_guest::host_mapped_region::{host_mapped_region, host_mapped_region_mut};
 use hyperlight_guest::memory::hlmalloc;
+use hyperlight_guest::stream::{StreamReader, StreamWriter};
 use hyperlight_guest::{logging, MIN_STACK_ADDRESS};
+use hyperlight_guest_macros::{guest_function, host_import};
 use log::{error, LevelFilter};
 
 extern crate hyperlight_guest;
@@ -72,35 +74,21 @@ fn set_static() -> Result<Vec<u8>> {
     }
 }
 
-fn echo_double(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::Double(value) = function_call.parameters.clone().unwrap()[0].clone() {
-        Ok(get_flatbuffer_result_from_double(value))
-    } else {
-        Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to echo_double".to_string(),
-        ))
-    }
+#[guest_function("EchoDouble")]
+fn echo_double(value: f64) -> Result<f64> {
+    Ok(value)
 }
 
-fn echo_float(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::Float(value) = function_call.parameters.clone().unwrap()[0].clone() {
-        Ok(get_flatbuffer_result_from_float(value))
-    } else {
-        Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to echo_float".to_string(),
-        ))
-    }
+#[guest_function("EchoFloat")]
+fn echo_float(value: f32) -> Result<f32> {
+    Ok(value)
 }
 
+#[host_import("HostPrint")]
+fn host_print(message: String) -> Result<i32>;
+
 fn print_output(message: &str) -> Result<Vec<u8>> {
-    call_host_function(
-        "HostPrint",
-        Some(Vec::from(&[ParameterValue::String(message.to_string())])),
-        ReturnType::Int,
-    )?;
-    let result = get_host_value_return_as_int()?;
+    let result = host_print(message.to_string())?;
     Ok(get_flatbuffer_result_from_int(result))
 }
 
@@ -115,17 +103,10 @@ fn simple_print_output(function_call: &FunctionCall) -> Result<Vec<u8>> {
     }
 }
 
-fn set_byte_array_to_zero(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::VecBytes(mut vec) = function_call.parameters.clone().unwrap()[0].clone()
-    {
-        vec.fill(0);
-        Ok(get_flatbuffer_result_from_vec(&vec))
-    } else {
-        Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to set_byte_array_to_zero".to_string(),
-        ))
-    }
+#[guest_function("SetByteArrayToZero")]
+fn set_byte_array_to_zero(mut vec: Vec<u8>) -> Result<Vec<u8>> {
+    vec.fill(0);
+    Ok(vec)
 }
 
 fn print_two_args(function_call: &FunctionCall) -> Result<Vec<u8>> {
@@ -522,13 +503,51 @@ fn malloc_and_free(function_call: &FunctionCall) -> Result<Vec<u8>> {
     }
 }
 
-fn echo(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let ParameterValue::String(value) = function_call.parameters.clone().unwrap()[0].clone() {
-        Ok(get_flatbuffer_result_from_string(&value))
+#[guest_function("Echo")]
+fn echo(value: String) -> Result<String> {
+    Ok(value)
+}
+
+#[guest_function("SumList")]
+fn sum_list(values: Vec<i32>) -> Result<i32> {
+    Ok(values.iter().sum())
+}
+
+#[guest_function("StreamToUppercase")]
+fn stream_to_uppercase() -> Result<i64> {
+    let mut reader = StreamReader::new();
+    let mut writer = StreamWriter::new();
+    let mut buf = [0; 4096];
+    let mut total = 0;
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            return Ok(total);
+        }
+        buf[..len].make_ascii_uppercase();
+        writer.write_all(&buf[..len])?;
+        total += len as i64;
+    }
+}
+
+#[guest_function("UppercaseMappedRegion")]
+fn uppercase_mapped_region(src: u64, src_len: u64, dst: u64, dst_len: u64) -> Result<u64> {
+    // SAFETY: the host mapped these regions for this call, the second one read-write
+    let src = unsafe { host_mapped_region(src, src_len) };
+    let dst = unsafe { host_mapped_region_mut(dst, dst_len) };
+    let len = src.len().min(dst.len());
+    dst[..len].copy_from_slice(&src[..len]);
+    dst[..len].make_ascii_uppercase();
+    Ok(len as u64)
+}
+
+fn echo_record(function_call: &FunctionCall) -> Result<Vec<u8>> {
+    if let ParameterValue::Record(fields) = &function_call.parameters.as_ref().unwrap()[0] {
+        Ok(get_flatbuffer_result_from_record(fields))
     } else {
         Err(HyperlightGuestError::new(
             ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to echo".to_string(),
+            "Invalid parameters passed to echo_record".to_string(),
         ))
     }
 }
@@ -702,26 +721,20 @@ fn violate_seccomp_filters(function_call: &FunctionCall) -> Result<Vec<u8>> {
     }
 }
 
-fn add(function_call: &FunctionCall) -> Result<Vec<u8>> {
-    if let (ParameterValue::Int(a), ParameterValue::Int(b)) = (
-        function_call.parameters.clone().unwrap()[0].clone(),
-        function_call.parameters.clone().unwrap()[1].clone(),
-    ) {
-        call_host_function(
-            "HostAdd",
-            Some(Vec::from(&[ParameterValue::Int(a), ParameterValue::Int(b)])),
-            ReturnType::Int,
-        )?;
+/// Call the host function `name`, which takes no parameters and returns a
+/// `ULong`, so that host functions can be tested under their own names.
+#[guest_function("CallHostFunctionByName")]
+fn call_host_function_by_name(name: String) -> Result<u64> {
+    call_host_function(&name, None, ReturnType::ULong)?;
+    get_host_value_return_as_ulong()
+}
 
-        let res = get_host_value_return_as_int()?;
+#[host_import("HostAdd")]
+fn host_add(a: i32, b: i32) -> Result<i32>;
 
-        Ok(get_flatbuffer_result_from_int(res))
-    } else {
-        Err(HyperlightGuestError::new(
-            ErrorCode::GuestFunctionParameterTypeMismatch,
-            "Invalid parameters passed to add".to_string(),
-        ))
-    }
+#[guest_function("Add")]
+fn add(a: i32, b: i32) -> Result<i32> {
+    host_add(a, b)
 }
 
 #[no_mangle]
@@ -959,21 +972,23 @@ pub extern "C" fn hyperlight_main() {
     );
     register_function(print_eleven_args_def);
 
-    let set_byte_array_to_zero_def = GuestFunctionDefinition::new(
-        "SetByteArrayToZero".to_string(),
-        Vec::from(&[ParameterType::VecBytes]),
-        ReturnType::VecBytes,
-        set_byte_array_to_zero as i64,
-    );
-    register_function(set_byte_array_to_zero_def);
+    set_byte_array_to_zero::register();
 
-    let echo_def = GuestFunctionDefinition::new(
-        "Echo".to_string(),
-        Vec::from(&[ParameterType::String]),
-        ReturnType::String,
-        echo as i64,
+    echo::register();
+
+    sum_list::register();
+
+    stream_to_uppercase::register();
+
+    uppercase_mapped_region::register();
+
+    let echo_record_def = GuestFunctionDefinition::new(
+        "EchoRecord".to_string(),
+        Vec::from(&[ParameterType::Record]),
+        ReturnType::Record,
+        echo_record as i64,
     );
-    register_function(echo_def);
+    register_function(echo_record_def);
 
     let get_size_prefixed_buffer_def = GuestFunctionDefinition::new(
         "GetSizePrefixedBuffer".to_string(),
@@ -1082,29 +1097,13 @@ pub extern "C" fn hyperlight_main() {
     );
     register_function(violate_seccomp_filters_def);
 
-    let echo_float_def = GuestFunctionDefinition::new(
-        "EchoFloat".to_string(),
-        Vec::from(&[ParameterType::Float]),
-        ReturnType::Float,
-        echo_float as i64,
-    );
-    register_function(echo_float_def);
+    call_host_function_by_name::register();
 
-    let echo_double_def = GuestFunctionDefinition::new(
-        "EchoDouble".to_string(),
-        Vec::from(&[ParameterType::Double]),
-        ReturnType::Double,
-        echo_double as i64,
-    );
-    register_function(echo_double_def);
+    echo_float::register();
 
-    let add_def = GuestFunctionDefinition::new(
-        "Add".to_string(),
-        Vec::from(&[ParameterType::Int, ParameterType::Int]),
-        ReturnType::Int,
-        add as i64,
-    );
-    register_function(add_def);
+    echo_double::register();
+
+    add::register();
 }
 
 #[no_mangle]